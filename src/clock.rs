@@ -0,0 +1,58 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A source of wall-clock time, in milliseconds since the Unix epoch.
+///
+/// Expiry times are stored as absolute Unix timestamps, so they can be compared
+/// against `EXAT`/`PXAT` arguments and reported by `EXPIRETIME` directly. The
+/// store reads the time through this trait, so that a clock which only moves
+/// when it's told to can be swapped in to test expiry deterministically.
+pub trait Clock: fmt::Debug + Send {
+    fn now_millis(&self) -> u64;
+}
+
+/// Reads the time from the operating system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(| d | d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock that only moves when it's told to, so that tests can step over
+/// expiry times deterministically. Clones share the same time, so a test can
+/// keep one and hand another to the database.
+#[cfg(test)]
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new(now: u64) -> ManualClock {
+        ManualClock(Arc::new(now.into()))
+    }
+
+    pub fn set(&self, now: u64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,198 @@
+/// Matches `string` against a glob-style pattern, using the same rules as
+/// Redis's `MATCH` options and `KEYS`:
+///
+/// - `*` matches any number of bytes, including none
+/// - `?` matches exactly one byte
+/// - `[abc]` matches one of the bytes in the brackets, `[^abc]` matches one
+///   that isn't, and `[a-z]` matches a range
+/// - `\` escapes the byte after it, so that e.g. `\*` only matches `*`
+///
+/// Stars are matched by remembering where the last one was, and retrying from
+/// there with one more byte consumed when the rest of the pattern fails to
+/// match, so patterns with many stars don't backtrack exponentially.
+pub fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+
+    // the position after the last star, and the position in the string that
+    // it's currently matched up to
+    let mut retry: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+
+            retry = Some((p, s));
+
+            continue;
+        }
+
+        if let Some(next) = match_one(pattern, p, string[s]) {
+            p = next;
+            s += 1;
+
+            continue;
+        }
+
+        match retry {
+            Some((star_p, star_s)) => {
+                p = star_p;
+                s = star_s + 1;
+
+                retry = Some((star_p, s));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p ..].iter().all(| c | *c == b'*')
+}
+
+/// Matches a single byte against the part of the pattern that starts at `p`,
+/// which mustn't be a star, and returns where the rest of the pattern starts
+fn match_one(pattern: &[u8], p: usize, byte: u8) -> Option<usize> {
+    match pattern.get(p)? {
+        b'?' => Some(p + 1),
+        b'[' => {
+            let (matched, next) = match_class(pattern, p + 1, byte);
+
+            matched.then_some(next)
+        }
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == byte).then_some(p + 2),
+        c => (*c == byte).then_some(p + 1),
+    }
+}
+
+/// Matches a byte against a bracketed class whose contents start at `p`, and
+/// returns whether it matched along with the position after the closing
+/// bracket. A class that's never closed runs to the end of the pattern.
+fn match_class(pattern: &[u8], mut p: usize, byte: u8) -> (bool, usize) {
+    let negated = pattern.get(p) == Some(&b'^');
+
+    if negated {
+        p += 1;
+    }
+
+    let mut matched = false;
+
+    while let Some(&c) = pattern.get(p) {
+        match c {
+            b']' => return (matched != negated, p + 1),
+            b'\\' if p + 1 < pattern.len() => {
+                matched |= pattern[p + 1] == byte;
+                p += 2;
+            }
+            _ if pattern.get(p + 1) == Some(&b'-') && p + 2 < pattern.len() => {
+                let (start, end) = (c.min(pattern[p + 2]), c.max(pattern[p + 2]));
+
+                matched |= (start ..= end).contains(&byte);
+                p += 3;
+            }
+            _ => {
+                matched |= c == byte;
+                p += 1;
+            }
+        }
+    }
+
+    (matched != negated, p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literals() {
+        assert!(matches(b"hello", b"hello"));
+        assert!(!matches(b"hello", b"hell"));
+        assert!(!matches(b"hell", b"hello"));
+        assert!(matches(b"", b""));
+        assert!(!matches(b"", b"a"));
+    }
+
+    #[test]
+    fn stars_match_any_run_of_bytes() {
+        assert!(matches(b"*", b""));
+        assert!(matches(b"*", b"anything"));
+        assert!(matches(b"user:*", b"user:"));
+        assert!(matches(b"user:*", b"user:42"));
+        assert!(matches(b"*:42", b"user:42"));
+        assert!(matches(b"**a**", b"bab"));
+        assert!(!matches(b"user:*", b"item:42"));
+    }
+
+    #[test]
+    fn stars_backtrack_to_later_matches() {
+        assert!(matches(b"a*b*c", b"aXbYbZc"));
+        assert!(matches(b"a*b*c", b"abc"));
+        assert!(matches(b"*ab", b"aaab"));
+        assert!(!matches(b"a*b*c", b"aXbYbZ"));
+        assert!(!matches(b"a*b*c", b"aXcYc"));
+    }
+
+    #[test]
+    fn does_not_backtrack_exponentially() {
+        let string = vec![b'a'; 10_000];
+
+        assert!(!matches(b"*a*a*a*a*a*a*a*a*a*a*b", &string));
+    }
+
+    #[test]
+    fn question_marks_match_exactly_one_byte() {
+        assert!(matches(b"h?llo", b"hello"));
+        assert!(matches(b"h?llo", b"hallo"));
+        assert!(!matches(b"h?llo", b"hllo"));
+        assert!(!matches(b"h?llo", b"heello"));
+        assert!(matches(b"???", b"\x00\xff\n"));
+    }
+
+    #[test]
+    fn classes_match_one_of_their_bytes() {
+        assert!(matches(b"h[ae]llo", b"hello"));
+        assert!(matches(b"h[ae]llo", b"hallo"));
+        assert!(!matches(b"h[ae]llo", b"hillo"));
+        assert!(!matches(b"h[ae]llo", b"hllo"));
+    }
+
+    #[test]
+    fn negated_classes_match_any_other_byte() {
+        assert!(matches(b"h[^e]llo", b"hallo"));
+        assert!(!matches(b"h[^e]llo", b"hello"));
+        assert!(!matches(b"h[^e]llo", b"hllo"));
+    }
+
+    #[test]
+    fn ranges_match_in_either_order() {
+        assert!(matches(b"[a-c]", b"b"));
+        assert!(!matches(b"[a-c]", b"d"));
+        assert!(matches(b"[z-a]", b"m"));
+        assert!(!matches(b"[z-a]", b"A"));
+        assert!(matches(b"[0-9a-f]x", b"ex"));
+        assert!(matches(b"[^a-c]", b"d"));
+        assert!(!matches(b"[^c-a]", b"b"));
+    }
+
+    #[test]
+    fn backslashes_escape_special_bytes() {
+        assert!(matches(br"\*", b"*"));
+        assert!(!matches(br"\*", b"a"));
+        assert!(matches(br"a\?", b"a?"));
+        assert!(!matches(br"a\?", b"ab"));
+        assert!(matches(br"\[a]", b"[a]"));
+        assert!(matches(br"[\]]", b"]"));
+        assert!(matches(br"[a\-z]", b"-"));
+        assert!(!matches(br"[a\-z]", b"m"));
+    }
+
+    #[test]
+    fn an_unterminated_class_runs_to_the_end_of_the_pattern() {
+        assert!(matches(b"a[bc", b"ab"));
+        assert!(matches(b"a[bc", b"ac"));
+        assert!(!matches(b"a[bc", b"ad"));
+        assert!(!matches(b"a[bc", b"abc"));
+        assert!(!matches(b"a[", b"a["));
+    }
+}
@@ -4,17 +4,43 @@ use std::{thread, time::Duration};
 use std::net::{TcpListener, TcpStream};
 use std::sync::mpsc::{channel, Receiver, TryRecvError};
 
-use crate::resp::commands::get_command_from_input;
+use crate::resp::commands::{
+    get_command_from_element,
+    CommandFlag,
+    CommandHandler,
+    CommandSpec,
+    ConnectionState,
+    RespCommand,
+    RespCommandError,
+};
+use crate::resp::parser::{RespReader, RespSerialize};
 use crate::worker::{spawn_worker, WorkerMessage, WorkerResponse};
 
+mod clock;
+mod glob;
+mod random;
 mod resp;
 mod store;
 mod worker;
 
 struct Client {
+    state: ConnectionState,
     stream: TcpStream,
     read_buffer: Vec<u8>,
+    query_buffer: RespReader,
+    /// Responses that haven't been written to the socket yet, since it may
+    /// not take everything at once
+    output_buffer: Vec<u8>,
+    /// How much of the output buffer has already been written
+    output_position: usize,
     pending: VecDeque<Receiver<WorkerResponse>>,
+    /// Set once the client has sent malformed input; the connection is closed
+    /// after the responses that are still pending have been written
+    closing: bool,
+    /// Set while the client is waiting on a blocking command such as `BLPOP`;
+    /// like Redis, the commands it sends after that aren't run until the
+    /// blocking command has been replied to
+    blocked: bool,
 }
 
 fn main() -> Result<(), Error> {
@@ -28,6 +54,8 @@ fn main() -> Result<(), Error> {
 
     let worker_tx = spawn_worker();
 
+    let mut next_client_id = 1;
+
     loop {
         match listener.accept() {
             Ok((stream, _addr)) => {
@@ -36,10 +64,21 @@ fn main() -> Result<(), Error> {
                 println!("Accepting new connection");
 
                 conns.push(Client {
+                    state: ConnectionState {
+                        id: next_client_id,
+                        ..ConnectionState::default()
+                    },
                     stream,
-                    read_buffer: vec![0; 1024],
+                    read_buffer: vec![0; 16 * 1024],
+                    query_buffer: RespReader::new(),
+                    output_buffer: Vec::new(),
+                    output_position: 0,
                     pending: VecDeque::new(),
+                    closing: false,
+                    blocked: false,
                 });
+
+                next_client_id += 1;
             }
 
             Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
@@ -52,36 +91,13 @@ fn main() -> Result<(), Error> {
         let mut closed_connections = Vec::new();
 
         for (i, client) in conns.iter_mut().enumerate() {
-            let mut stream = &client.stream;
-            let mut buf = &mut client.read_buffer;
-
-            match stream.read(&mut buf) {
+            match client.stream.read(&mut client.read_buffer) {
                 Ok(0) => closed_connections.push(i),
 
                 Ok(n) => {
                     println!("Read {n} bytes");
 
-                    match get_command_from_input(&buf[..n]) {
-                        Ok(command) => {
-                            let (response_tx, response_rx) = channel::<WorkerResponse>();
-
-                            let message = WorkerMessage {
-                                op: command,
-                                reply: response_tx,
-                            };
-
-                            client.pending.push_back(response_rx);
-
-                            if let Err(e) = worker_tx.send(message) {
-                                eprintln!("Unable to send message to worker thread");
-                                dbg!(e);
-                            };
-                        }
-                        Err(_) => {
-                            // to do: better error handling
-                            stream.write_all("-ERR an error occurred\r\n".as_bytes())?;
-                        }
-                    }
+                    client.query_buffer.extend(&client.read_buffer[..n]);
                 }
 
                 Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
@@ -93,18 +109,64 @@ fn main() -> Result<(), Error> {
                 }
             }
 
+            while !client.closing && !client.blocked {
+                let (response_tx, response_rx) = channel::<WorkerResponse>();
+
+                let element = match client.query_buffer.next_element() {
+                    Ok(Some(element)) => element,
+                    Ok(None) => break,
+                    Err(e) => {
+                        // there's no way to tell where the next command starts
+                        // after malformed input, so the connection is dropped
+                        client.pending.push_back(response_rx);
+                        client.closing = true;
+
+                        let _ = response_tx.send(Err(RespCommandError::from(e)));
+                        break;
+                    }
+                };
+
+                // responses are queued even when the command couldn't be built,
+                // so that they're written back in the order they were received
+                client.pending.push_back(response_rx);
+
+                match get_command_from_element(element) {
+                    Ok(RespCommand { spec: CommandSpec { handler: CommandHandler::Connection(handler), .. }, args }) => {
+                        let response = handler(&mut client.state, args)
+                            .map(| element | Some(element.to_bytes_for(client.state.protocol)));
+
+                        let _ = response_tx.send(response);
+                    }
+                    Ok(command) => {
+                        client.blocked = command.spec.has_flag(CommandFlag::Blocking);
+
+                        let message = WorkerMessage::Command {
+                            client_id: client.state.id,
+                            op: command,
+                            protocol: client.state.protocol,
+                            reply: response_tx,
+                        };
+
+                        if let Err(e) = worker_tx.send(message) {
+                            eprintln!("Unable to send message to worker thread");
+                            dbg!(e);
+                        };
+                    }
+                    Err(e) => {
+                        let _ = response_tx.send(Err(e));
+                    }
+                }
+            }
+
             while let Some(front) = client.pending.front() {
                 match front.try_recv() {
                     Ok(response) => {
                         client.pending.pop_front();
 
-                        if let Ok(payload) = response {
-                            if let Some(data) = payload {
-                                client.stream.write_all(&data)?;
-
-                                let outgoing = data.len();
-                                println!("Wrote {outgoing} bytes");
-                            }
+                        match response {
+                            Ok(Some(data)) => client.output_buffer.extend_from_slice(&data),
+                            Ok(None) => {}
+                            Err(e) => client.output_buffer.extend_from_slice(&e.to_element().to_bytes()),
                         }
                     }
                     Err(e) => {
@@ -112,16 +174,70 @@ fn main() -> Result<(), Error> {
                             eprintln!("Error while draining pending responses");
                             dbg!(e);
                         }
+
+                        break;
                     }
                 }
             };
+
+            if !flush_output(client) {
+                closed_connections.push(i);
+            }
+
+            if client.pending.is_empty() {
+                client.blocked = false;
+
+                if client.closing && client.output_buffer.is_empty() {
+                    closed_connections.push(i);
+                }
+            }
         }
 
-        for i in closed_connections {
-            conns.swap_remove(i);
+        // removing from the back keeps the remaining indices valid
+        closed_connections.dedup();
+
+        for i in closed_connections.into_iter().rev() {
+            let client = conns.swap_remove(i);
+
+            // the worker drops the client if it's blocked, so that nothing is
+            // popped on behalf of a connection that's gone
+            let _ = worker_tx.send(WorkerMessage::Disconnected(client.state.id));
         }
 
         // 5ms backoff to prevent busy waiting
         thread::sleep(Duration::from_millis(5));
     }
 }
+
+
+/// Writes as much of the client's output buffer as the socket will take
+/// without blocking, and keeps the rest for the next iteration. Returns
+/// `false` if the connection failed, in which case it should be closed.
+fn flush_output(client: &mut Client) -> bool {
+    let start = client.output_position;
+
+    while client.output_position < client.output_buffer.len() {
+        match client.stream.write(&client.output_buffer[client.output_position..]) {
+            Ok(0) => return false,
+            Ok(n) => client.output_position += n,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return false,
+        }
+    }
+
+    let written = client.output_position - start;
+
+    if written > 0 {
+        println!("Wrote {written} bytes");
+    }
+
+    // the buffer is only emptied once all of it has been written, so that a
+    // large response isn't shifted along every time a piece of it is written
+    if client.output_position == client.output_buffer.len() {
+        client.output_buffer.clear();
+        client.output_position = 0;
+    }
+
+    true
+}
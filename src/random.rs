@@ -0,0 +1,51 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+thread_local! {
+    /// The state of the generator, which is never zero. `RandomState` is seeded
+    /// from the operating system, so hashing anything with it gives a seed that
+    /// differs between runs without needing any other source of entropy.
+    static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0_u64) | 1);
+}
+
+/// Returns a pseudo-random number from a xorshift64* generator. This is fast
+/// and good enough for picking random members (e.g. for `HRANDFIELD`), but
+/// isn't suitable for anything that needs to be unpredictable.
+pub fn next_u64() -> u64 {
+    STATE.with(| state | {
+        let mut x = state.get();
+
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+
+        state.set(x);
+
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+/// Returns a random index into a collection of `len` items, which mustn't be
+/// empty
+pub fn random_index(len: usize) -> usize {
+    (next_u64() % len as u64) as usize
+}
+
+/// Returns `count` distinct random indices into a collection of `len` items,
+/// in a random order. Returns every index if `count` is at least `len`.
+pub fn sample_indices(len: usize, count: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0 .. len).collect();
+    let count = count.min(len);
+
+    // a partial Fisher-Yates shuffle, which only shuffles as much as is needed
+    for i in 0 .. count {
+        let j = i + random_index(len - i);
+
+        indices.swap(i, j);
+    }
+
+    indices.truncate(count);
+
+    indices
+}
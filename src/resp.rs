@@ -1,20 +1,51 @@
 pub mod parser;
+pub mod inline;
 pub mod types;
 pub mod commands;
 
 use crate::resp::types::*;
+use crate::resp::types::double::format_double;
 use crate::resp::parser::{RespDeserialize, RespParseError, RespSerialize};
 
 pub const RESP_DELIMITER: &[u8] = b"\r\n";
-pub const RESP_OK: &[u8] = b"+OK\r\n";
 pub const RESP_EMPTY_STRING: &[u8; 5] = b"$-1\r\n";
+pub const RESP_EMPTY_ARRAY: &[u8; 5] = b"*-1\r\n";
 
-#[derive(Debug)]
+/// The version of the protocol that a connection speaks. Every connection
+/// starts out on RESP2, and can switch to RESP3 with `HELLO 3`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RespProtocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl RespProtocol {
+    pub fn version(&self) -> isize {
+        match self {
+            RespProtocol::Resp2 => 2,
+            RespProtocol::Resp3 => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum RespElement {
     Array(RespArray),
     SimpleString(RespSimpleString),
+    SimpleError(RespSimpleError),
     BulkString(RespBulkString),
     Integer(RespInteger),
+    Null(RespNull),
+    Boolean(RespBoolean),
+    Double(RespDouble),
+    BigNumber(RespBigNumber),
+    BulkError(RespBulkError),
+    VerbatimString(RespVerbatimString),
+    Map(RespMap),
+    Set(RespSet),
+    Attribute(RespAttribute),
+    Push(RespPush),
 }
 
 impl RespElement {
@@ -25,6 +56,92 @@ impl RespElement {
     pub fn new_bulk_string(value: &[u8]) -> RespElement {
         RespElement::BulkString(RespBulkString::new(value))
     }
+
+    pub fn new_simple_string(value: &str) -> RespElement {
+        RespElement::SimpleString(RespSimpleString { value: value.into() })
+    }
+
+    pub fn new_simple_error(value: &str) -> RespElement {
+        RespElement::SimpleError(RespSimpleError::new(value))
+    }
+
+    pub fn new_integer(value: isize) -> RespElement {
+        RespElement::Integer(RespInteger { value })
+    }
+
+    pub fn new_null() -> RespElement {
+        RespElement::Null(RespNull::default())
+    }
+
+    /// Creates a null that's sent to RESP2 clients as a null array, for
+    /// commands that reply with an array when they have something to return
+    pub fn new_null_array() -> RespElement {
+        RespElement::Null(RespNull { is_array: true })
+    }
+
+    /// Creates a double, which is sent to RESP2 clients as a bulk string
+    pub fn new_double(value: f64) -> RespElement {
+        RespElement::Double(RespDouble { value })
+    }
+
+    /// Creates a set, which is sent to RESP2 clients as an array
+    pub fn new_set(elements: Vec<RespElement>) -> RespElement {
+        RespElement::Set(RespSet { elements })
+    }
+
+    pub fn new_map(entries: Vec<(RespElement, RespElement)>) -> RespElement {
+        RespElement::Map(RespMap::new(entries))
+    }
+
+    /// Serializes the element for a connection that speaks the given version
+    /// of the protocol. RESP3 types are sent to RESP2 clients as the closest
+    /// RESP2 equivalent, the same way Redis does (e.g. maps become flat arrays
+    /// of keys and values, and doubles become bulk strings).
+    pub fn to_bytes_for(&self, protocol: RespProtocol) -> Vec<u8> {
+        if protocol == RespProtocol::Resp3 {
+            return self.to_bytes();
+        }
+
+        match self {
+            RespElement::Array(a) => aggregate_to_resp2(b'*', &a.elements),
+            RespElement::Set(s) => aggregate_to_resp2(b'*', &s.elements),
+            RespElement::Push(p) => aggregate_to_resp2(b'*', &p.elements),
+            RespElement::Map(m) => {
+                let mut acc = vec![b'*'];
+
+                acc.extend((m.entries.len() * 2).to_string().as_bytes());
+                acc.extend_from_slice(RESP_DELIMITER);
+
+                for (key, value) in &m.entries {
+                    acc.extend(key.to_bytes_for(protocol));
+                    acc.extend(value.to_bytes_for(protocol));
+                }
+
+                acc
+            }
+            // RESP2 has no way to represent attributes, so they're dropped
+            RespElement::Attribute(a) => a.element.to_bytes_for(protocol),
+            RespElement::Null(n) if n.is_array => RESP_EMPTY_ARRAY.to_vec(),
+            RespElement::Null(_) => RESP_EMPTY_STRING.to_vec(),
+            RespElement::Boolean(b) => RespInteger { value: b.value as isize }.to_bytes(),
+            RespElement::Double(d) => RespBulkString::new(format_double(d.value).as_bytes()).to_bytes(),
+            RespElement::BigNumber(n) => RespBulkString::new(n.value.as_bytes()).to_bytes(),
+            RespElement::VerbatimString(v) => RespBulkString::new(&v.value).to_bytes(),
+            RespElement::BulkError(e) => RespSimpleError::new(&String::from_utf8_lossy(&e.value)).to_bytes(),
+            _ => self.to_bytes(),
+        }
+    }
+}
+
+fn aggregate_to_resp2(prefix: u8, elements: &[RespElement]) -> Vec<u8> {
+    let mut acc = vec![prefix];
+
+    acc.extend(elements.len().to_string().as_bytes());
+    acc.extend_from_slice(RESP_DELIMITER);
+
+    acc.extend(elements.iter().flat_map(| e | e.to_bytes_for(RespProtocol::Resp2)));
+
+    acc
 }
 
 impl RespSerialize for RespElement {
@@ -34,6 +151,17 @@ impl RespSerialize for RespElement {
             RespElement::BulkString(b) => b.to_bytes(),
             RespElement::Integer(i) => i.to_bytes(),
             RespElement::SimpleString(s) => s.to_bytes(),
+            RespElement::SimpleError(e) => e.to_bytes(),
+            RespElement::Null(n) => n.to_bytes(),
+            RespElement::Boolean(b) => b.to_bytes(),
+            RespElement::Double(d) => d.to_bytes(),
+            RespElement::BigNumber(n) => n.to_bytes(),
+            RespElement::BulkError(e) => e.to_bytes(),
+            RespElement::VerbatimString(v) => v.to_bytes(),
+            RespElement::Map(m) => m.to_bytes(),
+            RespElement::Set(s) => s.to_bytes(),
+            RespElement::Attribute(a) => a.to_bytes(),
+            RespElement::Push(p) => p.to_bytes(),
         }
     }
 }
@@ -43,10 +171,21 @@ impl RespDeserialize for RespElement {
         match slice.first() {
             Some(b'*') => RespArray::from_byte_slice(slice).map(| (a, r) | (RespElement::Array(a), r)),
             Some(b'+') => RespSimpleString::from_byte_slice(slice).map(| (s, r) | (RespElement::SimpleString(s), r)),
+            Some(b'-') => RespSimpleError::from_byte_slice(slice).map(| (e, r) | (RespElement::SimpleError(e), r)),
             Some(b'$') => RespBulkString::from_byte_slice(slice).map(| (b, r) | (RespElement::BulkString(b), r)),
             Some(b':') => RespInteger::from_byte_slice(slice).map(| (i, r) | (RespElement::Integer(i), r)),
+            Some(b'_') => RespNull::from_byte_slice(slice).map(| (n, r) | (RespElement::Null(n), r)),
+            Some(b'#') => RespBoolean::from_byte_slice(slice).map(| (b, r) | (RespElement::Boolean(b), r)),
+            Some(b',') => RespDouble::from_byte_slice(slice).map(| (d, r) | (RespElement::Double(d), r)),
+            Some(b'(') => RespBigNumber::from_byte_slice(slice).map(| (n, r) | (RespElement::BigNumber(n), r)),
+            Some(b'!') => RespBulkError::from_byte_slice(slice).map(| (e, r) | (RespElement::BulkError(e), r)),
+            Some(b'=') => RespVerbatimString::from_byte_slice(slice).map(| (v, r) | (RespElement::VerbatimString(v), r)),
+            Some(b'%') => RespMap::from_byte_slice(slice).map(| (m, r) | (RespElement::Map(m), r)),
+            Some(b'~') => RespSet::from_byte_slice(slice).map(| (s, r) | (RespElement::Set(s), r)),
+            Some(b'|') => RespAttribute::from_byte_slice(slice).map(| (a, r) | (RespElement::Attribute(a), r)),
+            Some(b'>') => RespPush::from_byte_slice(slice).map(| (p, r) | (RespElement::Push(p), r)),
             Some(_) => Err(RespParseError::UnknownTypePrefix),
-            None => Err(RespParseError::UnexpectedEof),
+            None => Err(RespParseError::Incomplete),
         }
     }
 }
@@ -1,28 +1,521 @@
-use crate::resp::types::RespArray;
-use crate::resp::{RespElement, RespDeserialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
 
-pub mod echo;
-pub use echo::RespEchoCommand;
+use crate::resp::types::{RespArray, RespBulkString};
+use crate::resp::types::double::parse_double;
+use crate::resp::parser::RespParseError;
+use crate::resp::{RespElement, RespProtocol};
+use crate::store::{parse_integer, Database, HyperLogLogError, WrongTypeError};
 
+pub mod bitmap;
+pub mod command;
+pub mod echo;
+pub mod expire;
+pub mod geo;
+pub mod get;
+pub mod hash;
+pub mod hash_expire;
+pub mod hello;
+pub mod hyperloglog;
+pub mod keyspace;
+pub mod list;
+pub mod ping;
 pub mod set;
-pub use set::RespSetCommand;
+pub mod sets;
+pub mod sorted_set;
+pub mod stream;
+pub mod stream_group;
+pub mod string;
 
-pub mod get;
-pub use get::RespGetCommand;
+/// The most picks that `SRANDMEMBER` and `HRANDFIELD` make with a negative
+/// count. Redis allows up to half of `i64::MAX`, since it writes the picks out
+/// as it makes them, but replies here are built in full before they're sent.
+pub const RANDOM_MAX_REPEATED_COUNT: u64 = 1024 * 1024;
+
+/// Every command that the server supports. Adding a command only requires
+/// defining its `CommandSpec` and listing it here; dispatch, arity checks and
+/// the `COMMAND` introspection replies are all driven by this table.
+pub static COMMAND_TABLE: &[&CommandSpec] = &[
+    // bitmap
+    &bitmap::BITCOUNT,
+    &bitmap::BITFIELD,
+    &bitmap::BITFIELD_RO,
+    &bitmap::BITOP,
+    &bitmap::BITPOS,
+    &bitmap::GETBIT,
+    &bitmap::SETBIT,
+    // connection
+    &command::COMMAND,
+    &echo::ECHO,
+    &hello::HELLO,
+    &ping::PING,
+    // generic
+    &keyspace::COPY,
+    &keyspace::DEL,
+    &keyspace::EXISTS,
+    &expire::EXPIRE,
+    &expire::EXPIREAT,
+    &expire::EXPIRETIME,
+    &keyspace::KEYS,
+    &expire::PERSIST,
+    &expire::PEXPIRE,
+    &expire::PEXPIREAT,
+    &expire::PEXPIRETIME,
+    &expire::PTTL,
+    &keyspace::RANDOMKEY,
+    &keyspace::RENAME,
+    &keyspace::RENAMENX,
+    &keyspace::SCAN,
+    &keyspace::TOUCH,
+    &expire::TTL,
+    &keyspace::TYPE,
+    &keyspace::UNLINK,
+    // geo
+    &geo::GEOADD,
+    &geo::GEODIST,
+    &geo::GEOHASH,
+    &geo::GEOPOS,
+    &geo::GEOSEARCH,
+    &geo::GEOSEARCHSTORE,
+    // hash
+    &hash::HDEL,
+    &hash::HEXISTS,
+    &hash_expire::HEXPIRE,
+    &hash_expire::HEXPIREAT,
+    &hash_expire::HEXPIRETIME,
+    &hash::HGET,
+    &hash::HGETALL,
+    &hash::HINCRBY,
+    &hash::HINCRBYFLOAT,
+    &hash::HKEYS,
+    &hash::HLEN,
+    &hash::HMGET,
+    &hash_expire::HPERSIST,
+    &hash_expire::HPEXPIRE,
+    &hash_expire::HPEXPIREAT,
+    &hash_expire::HPEXPIRETIME,
+    &hash_expire::HPTTL,
+    &hash::HRANDFIELD,
+    &hash::HSCAN,
+    &hash::HSET,
+    &hash::HSETNX,
+    &hash::HSTRLEN,
+    &hash_expire::HTTL,
+    &hash::HVALS,
+    // hyperloglog
+    &hyperloglog::PFADD,
+    &hyperloglog::PFCOUNT,
+    &hyperloglog::PFDEBUG,
+    &hyperloglog::PFMERGE,
+    // list
+    &list::BLMOVE,
+    &list::BLMPOP,
+    &list::BLPOP,
+    &list::BRPOP,
+    &list::BRPOPLPUSH,
+    &list::LINDEX,
+    &list::LINSERT,
+    &list::LLEN,
+    &list::LMOVE,
+    &list::LMPOP,
+    &list::LPOP,
+    &list::LPOS,
+    &list::LPUSH,
+    &list::LPUSHX,
+    &list::LRANGE,
+    &list::LREM,
+    &list::LSET,
+    &list::LTRIM,
+    &list::RPOP,
+    &list::RPOPLPUSH,
+    &list::RPUSH,
+    &list::RPUSHX,
+    // server
+    &keyspace::DBSIZE,
+    // set
+    &sets::SADD,
+    &sets::SCARD,
+    &sets::SDIFF,
+    &sets::SDIFFSTORE,
+    &sets::SINTER,
+    &sets::SINTERCARD,
+    &sets::SINTERSTORE,
+    &sets::SISMEMBER,
+    &sets::SMEMBERS,
+    &sets::SMISMEMBER,
+    &sets::SMOVE,
+    &sets::SPOP,
+    &sets::SRANDMEMBER,
+    &sets::SREM,
+    &sets::SUNION,
+    &sets::SUNIONSTORE,
+    // sorted set
+    &sorted_set::BZMPOP,
+    &sorted_set::BZPOPMAX,
+    &sorted_set::BZPOPMIN,
+    &sorted_set::ZADD,
+    &sorted_set::ZCARD,
+    &sorted_set::ZCOUNT,
+    &sorted_set::ZDIFF,
+    &sorted_set::ZDIFFSTORE,
+    &sorted_set::ZINCRBY,
+    &sorted_set::ZINTER,
+    &sorted_set::ZINTERSTORE,
+    &sorted_set::ZMPOP,
+    &sorted_set::ZMSCORE,
+    &sorted_set::ZPOPMAX,
+    &sorted_set::ZPOPMIN,
+    &sorted_set::ZRANGE,
+    &sorted_set::ZRANGESTORE,
+    &sorted_set::ZRANK,
+    &sorted_set::ZREM,
+    &sorted_set::ZREMRANGEBYLEX,
+    &sorted_set::ZREMRANGEBYRANK,
+    &sorted_set::ZREMRANGEBYSCORE,
+    &sorted_set::ZREVRANK,
+    &sorted_set::ZSCORE,
+    &sorted_set::ZUNION,
+    &sorted_set::ZUNIONSTORE,
+    // stream
+    &stream_group::XACK,
+    &stream::XADD,
+    &stream_group::XAUTOCLAIM,
+    &stream_group::XCLAIM,
+    &stream::XDEL,
+    &stream_group::XGROUP,
+    &stream_group::XINFO,
+    &stream::XLEN,
+    &stream_group::XPENDING,
+    &stream::XRANGE,
+    &stream::XREAD,
+    &stream_group::XREADGROUP,
+    &stream::XREVRANGE,
+    &stream::XTRIM,
+    // string
+    &string::APPEND,
+    &string::DECR,
+    &string::DECRBY,
+    &get::GET,
+    &string::GETDEL,
+    &string::GETEX,
+    &string::GETRANGE,
+    &string::GETSET,
+    &string::INCR,
+    &string::INCRBY,
+    &string::INCRBYFLOAT,
+    &string::LCS,
+    &string::MGET,
+    &string::MSET,
+    &string::MSETNX,
+    &string::PSETEX,
+    &set::SET,
+    &string::SETEX,
+    &string::SETNX,
+    &string::SETRANGE,
+    &string::STRLEN,
+];
+
+/// Describes a command: how it's called, what it does, and which function
+/// runs it. The fields mirror the ones that Redis reports from `COMMAND INFO`.
+#[derive(Debug)]
+pub struct CommandSpec {
+    /// The lowercase name of the command
+    pub name: &'static str,
+    /// The number of arguments the command takes, including its name. A
+    /// negative arity means "at least" that many arguments, e.g. -3 for `SET`.
+    pub arity: isize,
+    pub flags: &'static [CommandFlag],
+    /// The position of the first key, the position of the last key (where -1
+    /// is the last argument), and the step between keys. Commands without any
+    /// keys use `(0, 0, 0)`.
+    pub keys: (isize, isize, usize),
+    /// Finds the positions of the keys in a call, for commands whose keys
+    /// can't be described by `keys` alone (e.g. ones that take a key count)
+    pub get_keys: Option<KeyFinder>,
+    pub group: &'static str,
+    pub summary: &'static str,
+    /// The version of Redis that the command was introduced in
+    pub since: &'static str,
+    pub handler: CommandHandler,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    ReadOnly,
+    Fast,
+    Blocking,
+    Admin,
+    PubSub,
+    NoScript,
+    LoadingOk,
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandFlag::Write => "write",
+            CommandFlag::ReadOnly => "readonly",
+            CommandFlag::Fast => "fast",
+            CommandFlag::Blocking => "blocking",
+            CommandFlag::Admin => "admin",
+            CommandFlag::PubSub => "pubsub",
+            CommandFlag::NoScript => "noscript",
+            CommandFlag::LoadingOk => "loading",
+        }
+    }
+}
+
+pub type CommandResult = Result<RespElement, RespCommandError>;
+
+pub type KeyFinder = fn(&RespArray) -> Result<Vec<usize>, RespCommandError>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CommandHandler {
+    /// Runs on the worker thread, with access to the store
+    Store(fn(&mut CommandContext, RespArray) -> CommandResult),
+    /// Runs on the thread that owns the connection, and can change its state
+    /// (e.g. the protocol version) without going through the worker
+    Connection(fn(&mut ConnectionState, RespArray) -> CommandResult),
+}
+
+/// What a store command has access to while it runs
+pub struct CommandContext<'a> {
+    pub db: &'a mut Database,
+    /// The protocol that the client is using, for the few replies whose shape
+    /// differs between RESP2 and RESP3 beyond how they're serialized
+    pub protocol: RespProtocol,
+    /// Set by a blocking command that can't do anything yet. When it is, the
+    /// command's reply is held back, and is only sent if the command is still
+    /// waiting once the deadline passes.
+    pub block: Option<Block>,
+}
+
+impl<'a> CommandContext<'a> {
+    pub fn new(db: &'a mut Database, protocol: RespProtocol) -> CommandContext<'a> {
+        CommandContext { db, protocol, block: None }
+    }
 
+    /// Parks the client until one of `keys` is written to and holds a value of
+    /// type `value_type`, at which point the command is run again, or until
+    /// `deadline` (a Unix time in milliseconds) passes. Without a deadline, the
+    /// client waits for as long as it takes.
+    pub fn block_on(&mut self, keys: Vec<Vec<u8>>, value_type: &'static str, deadline: Option<u64>) {
+        self.block = Some(Block { keys, value_type, deadline, retry_args: None });
+    }
+
+    /// Replaces the arguments that a blocked command is run with again once
+    /// it's woken up, for commands whose arguments depend on the state of the
+    /// store when they blocked (e.g. `XREAD` with the `$` ID, which means "the
+    /// last entry" at the time it was called). Does nothing unless the command
+    /// has called `block_on`.
+    pub fn set_retry_args(&mut self, args: RespArray) {
+        if let Some(block) = &mut self.block {
+            block.retry_args = Some(args);
+        }
+    }
+}
+
+/// The keys that a blocked command is waiting on, and when it gives up
 #[derive(Debug)]
-pub enum RespCommand {
-    Ping,
-    Echo(RespEchoCommand),
-    Set(RespSetCommand),
-    Get(RespGetCommand),
+pub struct Block {
+    pub keys: Vec<Vec<u8>>,
+    /// The type that a key has to hold for the command to be worth running
+    /// again, as returned by `Value::type_name`
+    pub value_type: &'static str,
+    pub deadline: Option<u64>,
+    /// The arguments to run the command with once it's woken up, if they
+    /// aren't the ones it was called with
+    pub retry_args: Option<RespArray>,
+}
+
+/// The state that belongs to a single connection
+#[derive(Debug, Default)]
+pub struct ConnectionState {
+    pub id: usize,
+    pub name: Option<String>,
+    pub protocol: RespProtocol,
+}
+
+/// A call to one of the commands in `COMMAND_TABLE`, whose arity has already
+/// been checked. The arguments include the command name.
+#[derive(Debug, Clone)]
+pub struct RespCommand {
+    pub spec: &'static CommandSpec,
+    pub args: RespArray,
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn accepts_arity(&self, count: usize) -> bool {
+        let count = count as isize;
+
+        if self.arity >= 0 {
+            count == self.arity
+        } else {
+            count >= -self.arity
+        }
+    }
+
+    /// Returns the positions of the keys in a call to this command
+    pub fn get_key_positions(&self, args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+        if let Some(get_keys) = self.get_keys {
+            return get_keys(args);
+        }
+
+        let (first, last, step) = self.keys;
+
+        if first <= 0 || step == 0 {
+            return Ok(Vec::new());
+        }
+
+        let last = if last < 0 { args.length as isize + last } else { last };
+
+        Ok((first ..= last).step_by(step).map(| i | i as usize).collect())
+    }
+}
+
+/// Looks up a command by name, ignoring case
+pub fn get_command_spec(name: &str) -> Option<&'static CommandSpec> {
+    static COMMANDS_BY_NAME: OnceLock<HashMap<&'static str, &'static CommandSpec>> = OnceLock::new();
+
+    let commands = COMMANDS_BY_NAME.get_or_init(|| {
+        COMMAND_TABLE.iter().map(| spec | (spec.name, *spec)).collect()
+    });
+
+    commands.get(name.to_lowercase().as_str()).copied()
 }
 
+/// The reasons that a command can be rejected. Each variant maps onto one of
+/// the error replies that Redis sends, so that clients which pattern match on
+/// them (e.g. on the `WRONGTYPE` prefix) behave the same way here.
 #[derive(Debug)]
 pub enum RespCommandError {
-    UnknownCommand,
-    InvalidArgument,
-    ParsingError,
+    /// The command name, followed by (the start of) its arguments
+    UnknownCommand(String, Vec<String>),
+    /// The subcommand, and the name of the command it was given to
+    UnknownSubcommand(String, String),
+    /// The name of the command that was given too few or too many arguments
+    WrongNumberOfArguments(String),
+    WrongType,
+    SyntaxError,
+    NotAnInteger,
+    NotAFloat,
+    /// The name of the command that was given a TTL which is out of range
+    InvalidExpireTime(String),
+    /// The client sent something that isn't a valid command
+    ProtocolError(String),
+    /// A generic `ERR` reply with the given reason
+    InvalidArgument(String),
+    NoSuchKey,
+    /// A `NOGROUP` reply for a consumer group (or its stream) that doesn't
+    /// exist, with the given reason
+    NoGroup(String),
+    BusyGroup,
+    /// A `WRONGTYPE` reply for a string that isn't a HyperLogLog
+    NotAHyperLogLog,
+    CorruptHyperLogLog,
+    UnsupportedProtocol,
+    InvalidPassword,
+}
+
+impl RespCommandError {
+    pub fn to_element(&self) -> RespElement {
+        RespElement::new_simple_error(&self.to_string())
+    }
+}
+
+impl fmt::Display for RespCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RespCommandError::UnknownCommand(name, args) => {
+                write!(f, "ERR unknown command '{}', with args beginning with: ", truncate(name, 128))?;
+
+                // like Redis, only the first 128 characters or so are repeated
+                let mut written = 0;
+
+                for arg in args {
+                    if written >= 128 {
+                        break;
+                    }
+
+                    let arg = truncate(arg, 128 - written);
+                    written += arg.len() + 3;
+
+                    write!(f, "'{arg}' ")?;
+                }
+
+                Ok(())
+            }
+            RespCommandError::UnknownSubcommand(subcommand, name) =>
+                write!(f, "ERR unknown subcommand '{}'. Try {} HELP.", truncate(subcommand, 128), name.to_uppercase()),
+            RespCommandError::WrongNumberOfArguments(name) =>
+                write!(f, "ERR wrong number of arguments for '{name}' command"),
+            RespCommandError::WrongType =>
+                write!(f, "WRONGTYPE Operation against a key holding the wrong kind of value"),
+            RespCommandError::SyntaxError =>
+                write!(f, "ERR syntax error"),
+            RespCommandError::NotAnInteger =>
+                write!(f, "ERR value is not an integer or out of range"),
+            RespCommandError::NotAFloat =>
+                write!(f, "ERR value is not a valid float"),
+            RespCommandError::InvalidExpireTime(name) =>
+                write!(f, "ERR invalid expire time in '{name}' command"),
+            RespCommandError::ProtocolError(reason) =>
+                write!(f, "ERR Protocol error: {reason}"),
+            RespCommandError::InvalidArgument(reason) =>
+                write!(f, "ERR {reason}"),
+            RespCommandError::NoSuchKey =>
+                write!(f, "ERR no such key"),
+            RespCommandError::NoGroup(reason) =>
+                write!(f, "NOGROUP {reason}"),
+            RespCommandError::BusyGroup =>
+                write!(f, "BUSYGROUP Consumer Group name already exists"),
+            RespCommandError::NotAHyperLogLog =>
+                write!(f, "WRONGTYPE Key is not a valid HyperLogLog string value."),
+            RespCommandError::CorruptHyperLogLog =>
+                write!(f, "INVALIDOBJ Corrupted HLL object detected"),
+            RespCommandError::UnsupportedProtocol =>
+                write!(f, "NOPROTO unsupported protocol version"),
+            RespCommandError::InvalidPassword =>
+                write!(f, "WRONGPASS invalid username-password pair or user is disabled."),
+        }
+    }
+}
+
+impl From<RespParseError> for RespCommandError {
+    fn from(error: RespParseError) -> RespCommandError {
+        let reason = match error {
+            RespParseError::Incomplete => "unexpected end of input".into(),
+            RespParseError::UnknownTypePrefix => "unknown type prefix".into(),
+            RespParseError::InvalidElement => "invalid element".into(),
+            RespParseError::UnbalancedQuotes => "unbalanced quotes in request".into(),
+            RespParseError::TooLarge => "too big inline request".into(),
+            RespParseError::ExpectedBulkString(prefix) => format!("expected '$', got '{}'", prefix as char),
+        };
+
+        RespCommandError::ProtocolError(reason)
+    }
+}
+
+impl From<WrongTypeError> for RespCommandError {
+    fn from(_: WrongTypeError) -> RespCommandError {
+        RespCommandError::WrongType
+    }
+}
+
+impl From<HyperLogLogError> for RespCommandError {
+    fn from(error: HyperLogLogError) -> RespCommandError {
+        match error {
+            HyperLogLogError::NotAHyperLogLog => RespCommandError::NotAHyperLogLog,
+            HyperLogLogError::Corrupt => RespCommandError::CorruptHyperLogLog,
+        }
+    }
 }
 
 pub trait RespCommandConstructor {
@@ -34,53 +527,199 @@ pub trait RespCommandConstructor {
 impl RespCommandConstructor for RespCommand {
     fn from_array(input: RespArray) -> Result<RespCommand, RespCommandError> {
         let Some(first_element) = input.elements.first() else {
-            return Err(RespCommandError::ParsingError);
+            return Err(RespCommandError::ProtocolError("empty command".into()));
         };
 
-        let command = match first_element {
-            RespElement::BulkString(b) => get_command_name(&b.value),
-            _ => Err(RespCommandError::InvalidArgument),
-        }?;
+        let command = get_argument_string(first_element)?;
 
-        let result = match command.to_lowercase().as_str() {
-            "echo" => RespCommand::Echo(RespEchoCommand::from_array(input)?),
-            "set" => RespCommand::Set(RespSetCommand::from_array(input)?),
-            "get" => RespCommand::Get(RespGetCommand::from_array(input)?),
-            _ => return Err(RespCommandError::UnknownCommand),
+        let Some(spec) = get_command_spec(&command) else {
+            let args = input.elements.iter()
+                .skip(1)
+                .filter_map(| e | get_argument_string(e).ok())
+                .collect();
+
+            return Err(RespCommandError::UnknownCommand(command, args));
         };
 
-        Ok(result)
+        if !spec.accepts_arity(input.length) {
+            return Err(RespCommandError::WrongNumberOfArguments(spec.name.into()));
+        }
+
+        Ok(RespCommand { spec, args: input })
     }
 }
 
-pub fn get_command_from_input(packet: &[u8]) -> Result<RespCommand, RespCommandError> {
-    match RespElement::from_byte_slice(packet) {
-        Ok((kind, _)) => {
-            match kind {
-                RespElement::Array(a) =>
-                    RespCommand::from_array(a),
-                RespElement::SimpleString(s) => {
-                    if s.value.to_lowercase() == "ping" {
-                        Ok(RespCommand::Ping)
-                    } else {
-                        Err(RespCommandError::UnknownCommand)
-                    }
-                },
-                _ => Err(RespCommandError::ParsingError),
-            }
-        }
-        Err(_) => Err(RespCommandError::ParsingError),
+/// A call to a command whose only argument is a single key, e.g. `TTL`
+#[derive(Debug)]
+pub struct RespKeyCommand {
+    pub key: Box<[u8]>,
+}
+
+impl RespCommandConstructor for RespKeyCommand {
+    fn from_array(input: RespArray) -> Result<RespKeyCommand, RespCommandError> {
+        let [_, key_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespKeyCommand { key })
+    }
+}
+
+pub fn get_command_from_element(element: RespElement) -> Result<RespCommand, RespCommandError> {
+    match element {
+        RespElement::Array(a) =>
+            RespCommand::from_array(a),
+        // a lone simple string is accepted as a command without arguments,
+        // e.g. `+PING\r\n`
+        RespElement::SimpleString(s) => {
+            let name = RespElement::BulkString(RespBulkString::new(s.value.as_bytes()));
+
+            RespCommand::from_array(RespArray::new(vec![name]))
+        },
+        _ => Err(RespCommandError::ProtocolError("expected an array of bulk strings".into())),
+    }
+}
+
+/// Returns the lowercase name of the command in a call, for commands that share
+/// a constructor and need to name themselves in error messages
+pub fn get_command_name(input: &RespArray) -> String {
+    input.elements.first()
+        .and_then(| e | get_argument_string(e).ok())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Returns the raw bytes of a command argument, which clients normally send
+/// as bulk strings (simple strings and integers are accepted as well)
+pub fn get_argument_bytes(element: &RespElement) -> Result<Vec<u8>, RespCommandError> {
+    match element {
+        RespElement::BulkString(b) => Ok(b.value.to_vec()),
+        RespElement::SimpleString(s) => Ok(s.value.as_bytes().to_vec()),
+        RespElement::Integer(i) => Ok(i.value.to_string().into_bytes()),
+        _ => Err(RespCommandError::ProtocolError("expected a bulk string argument".into())),
+    }
+}
+
+/// Returns a command argument as a string, replacing any invalid UTF-8 so that
+/// it can still be echoed back in an error message
+pub fn get_argument_string(element: &RespElement) -> Result<String, RespCommandError> {
+    let bytes = get_argument_bytes(element)?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Parses a command argument as a signed 64-bit integer, using the same strict
+/// rules as Redis (no leading `+`, zeros or whitespace)
+pub fn get_argument_integer(element: &RespElement) -> Result<i64, RespCommandError> {
+    if let RespElement::Integer(i) = element {
+        return Ok(i.value as i64);
     }
+
+    parse_integer(&get_argument_bytes(element)?).ok_or(RespCommandError::NotAnInteger)
+}
+
+/// Parses a command argument as a double, which can also be `inf` or `-inf`
+pub fn get_argument_float(element: &RespElement) -> Result<f64, RespCommandError> {
+    if let RespElement::Double(d) = element {
+        return Ok(d.value);
+    }
+
+    str::from_utf8(&get_argument_bytes(element)?).ok()
+        .and_then(parse_double)
+        .filter(| f | !f.is_nan())
+        .ok_or(RespCommandError::NotAFloat)
+}
+
+/// Parses the cursor given to one of the `SCAN` family of commands
+pub fn get_argument_cursor(element: &RespElement) -> Result<u64, RespCommandError> {
+    str::from_utf8(&get_argument_bytes(element)?).ok()
+        .and_then(| s | s.parse::<u64>().ok())
+        .ok_or_else(|| RespCommandError::InvalidArgument("invalid cursor".into()))
+}
+
+/// Parses the timeout of a blocking command, given in seconds, into
+/// milliseconds. A timeout of zero means waiting forever, which is `None`.
+pub fn get_argument_timeout(element: &RespElement) -> Result<Option<u64>, RespCommandError> {
+    let seconds = str::from_utf8(&get_argument_bytes(element)?).ok()
+        .and_then(parse_double)
+        .filter(| s | s.is_finite())
+        .ok_or_else(|| RespCommandError::InvalidArgument("timeout is not a float or out of range".into()))?;
+
+    if seconds < 0.0 {
+        return Err(RespCommandError::InvalidArgument("timeout is negative".into()));
+    }
+
+    match (seconds * 1000.0) as u64 {
+        0 => Ok(None),
+        millis => Ok(Some(millis)),
+    }
+}
+
+/// Converts a Redis-style index, where negative indices count back from the
+/// end, to a position in a collection of the given length
+pub fn resolve_index(index: i64, length: usize) -> Option<usize> {
+    let length = length as i64;
+    let index = if index < 0 { length + index } else { index };
+
+    (0 .. length).contains(&index).then_some(index as usize)
 }
 
-fn get_command_name(bytes: &[u8]) -> Result<String, RespCommandError> {
-    if !bytes.into_iter().all(| byte | byte.is_ascii_alphabetic()) {
-        return Err(RespCommandError::ParsingError);
+/// Converts a Redis-style inclusive range (e.g. from `LRANGE`) to the positions
+/// it covers in a collection of the given length, clamping it to the bounds of
+/// the collection. Returns `None` if the range doesn't cover anything.
+pub fn resolve_range(start: i64, stop: i64, length: usize) -> Option<Range<usize>> {
+    let length = length as i64;
+
+    let start = if start < 0 { (length + start).max(0) } else { start };
+    let stop = if stop < 0 { length + stop } else { stop.min(length - 1) };
+
+    if start > stop || start >= length {
+        return None;
     }
 
-    let Ok(command) = String::from_utf8(bytes.to_vec()) else {
-        return Err(RespCommandError::ParsingError);
+    Some(start as usize .. stop as usize + 1)
+}
+
+/// Finds the keys in a call whose arguments include a count of the keys that
+/// follow it (e.g. `LMPOP numkeys key [key ...]`), given the count's position
+pub fn get_counted_key_positions(args: &RespArray, count_position: usize) -> Result<Vec<usize>, RespCommandError> {
+    let Some(count_element) = args.elements.get(count_position) else {
+        return Err(RespCommandError::SyntaxError);
     };
 
-    Ok(command)
-}
\ No newline at end of file
+    let count = get_argument_integer(count_element)?;
+
+    if count <= 0 {
+        return Err(RespCommandError::InvalidArgument("numkeys should be greater than 0".into()));
+    }
+
+    let first = count_position + 1;
+    let last = first.saturating_add(count as usize);
+
+    if last > args.elements.len() {
+        return Err(RespCommandError::SyntaxError);
+    }
+
+    Ok((first .. last).collect())
+}
+
+/// Builds the reply for the `SCAN` family of commands: the cursor to continue
+/// from as a bulk string (`0` once the iteration is complete), followed by an
+/// array of the elements
+pub fn get_scan_reply(cursor: u64, elements: Vec<RespElement>) -> RespElement {
+    RespElement::new_array(vec![
+        RespElement::new_bulk_string(cursor.to_string().as_bytes()),
+        RespElement::new_array(elements),
+    ])
+}
+
+/// Shortens `value` to at most `length` characters, for repeating user input in
+/// error messages
+fn truncate(value: &str, length: usize) -> &str {
+    match value.char_indices().nth(length) {
+        Some((index, _)) => &value[.. index],
+        None => value,
+    }
+}
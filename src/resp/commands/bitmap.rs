@@ -0,0 +1,756 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::RespElement;
+use crate::resp::parser::RESP_MAX_BULK_LENGTH;
+use crate::resp::types::RespArray;
+use crate::store::{parse_integer, SetOptions, StringValue, Value};
+
+/// Bit offsets have to be lower than the number of bits in the longest string
+/// a client could send
+const MAX_BIT_OFFSET: usize = RESP_MAX_BULK_LENGTH * 8;
+
+pub const SETBIT: CommandSpec = CommandSpec {
+    name: "setbit",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(setbit),
+};
+
+pub const GETBIT: CommandSpec = CommandSpec {
+    name: "getbit",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Returns a bit value by offset.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(getbit),
+};
+
+pub const BITCOUNT: CommandSpec = CommandSpec {
+    name: "bitcount",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Counts the number of set bits (population counting) in a string.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(bitcount),
+};
+
+pub const BITPOS: CommandSpec = CommandSpec {
+    name: "bitpos",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Finds the first set (1) or clear (0) bit in a string.",
+    since: "2.8.7",
+    handler: CommandHandler::Store(bitpos),
+};
+
+pub const BITOP: CommandSpec = CommandSpec {
+    name: "bitop",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (2, -1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Performs bitwise operations on multiple strings, and stores the result.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(bitop),
+};
+
+pub const BITFIELD: CommandSpec = CommandSpec {
+    name: "bitfield",
+    arity: -2,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Performs arbitrary bitfield integer operations on strings.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(bitfield),
+};
+
+pub const BITFIELD_RO: CommandSpec = CommandSpec {
+    name: "bitfield_ro",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "bitmap",
+    summary: "Performs arbitrary read-only bitfield integer operations on strings.",
+    since: "6.0.0",
+    handler: CommandHandler::Store(bitfield_ro),
+};
+
+/// Parses a bit offset, which can't be negative or point past the longest
+/// string a client could send
+fn get_argument_bit_offset(element: &RespElement) -> Result<usize, RespCommandError> {
+    get_argument_integer(element).ok()
+        .filter(| offset | (0 .. MAX_BIT_OFFSET as i64).contains(offset))
+        .map(| offset | offset as usize)
+        .ok_or_else(invalid_bit_offset)
+}
+
+fn invalid_bit_offset() -> RespCommandError {
+    RespCommandError::InvalidArgument("bit offset is not an integer or out of range".into())
+}
+
+/// A call to `SETBIT`
+#[derive(Debug)]
+pub struct RespSetbitCommand {
+    pub key: Box<[u8]>,
+    pub offset: usize,
+    pub bit: bool,
+}
+
+impl RespCommandConstructor for RespSetbitCommand {
+    fn from_array(input: RespArray) -> Result<RespSetbitCommand, RespCommandError> {
+        let [_, key_element, offset_element, bit_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let offset = get_argument_bit_offset(offset_element)?;
+
+        let bit = match get_argument_integer(bit_element) {
+            Ok(0) => false,
+            Ok(1) => true,
+            _ => return Err(RespCommandError::InvalidArgument("bit is not an integer or out of range".into())),
+        };
+
+        Ok(RespSetbitCommand { key, offset, bit })
+    }
+}
+
+/// A call to `GETBIT`
+#[derive(Debug)]
+pub struct RespGetbitCommand {
+    pub key: Box<[u8]>,
+    pub offset: usize,
+}
+
+impl RespCommandConstructor for RespGetbitCommand {
+    fn from_array(input: RespArray) -> Result<RespGetbitCommand, RespCommandError> {
+        let [_, key_element, offset_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let offset = get_argument_bit_offset(offset_element)?;
+
+        Ok(RespGetbitCommand { key, offset })
+    }
+}
+
+/// A range of a string given to `BITCOUNT` or `BITPOS`, in bytes unless `BIT`
+/// is given
+#[derive(Debug, Clone, Copy)]
+pub struct BitRange {
+    pub start: i64,
+    pub end: Option<i64>,
+    pub bits: bool,
+}
+
+impl BitRange {
+    /// Resolves the range against a string of `length` bytes, into the
+    /// positions of its first and last bits. Negative offsets count back
+    /// from the end of the string, and the range is clamped to it.
+    fn resolve(&self, length: usize) -> Option<(usize, usize)> {
+        let total = if self.bits { length as i64 * 8 } else { length as i64 };
+
+        let start = if self.start < 0 { total + self.start } else { self.start }.max(0);
+        let end = match self.end.unwrap_or(-1) {
+            end if end < 0 => total + end,
+            end => end,
+        }.max(0).min(total - 1);
+
+        if start > end {
+            return None;
+        }
+
+        match self.bits {
+            true => Some((start as usize, end as usize)),
+            false => Some((start as usize * 8, end as usize * 8 + 7)),
+        }
+    }
+}
+
+/// Parses the optional `start end [BYTE | BIT]` arguments of `BITCOUNT` and
+/// `BITPOS`
+fn parse_bit_range(elements: &[RespElement]) -> Result<Option<BitRange>, RespCommandError> {
+    let [start_element, rest @ ..] = elements else {
+        return Ok(None);
+    };
+
+    let start = get_argument_integer(start_element)?;
+
+    let (end, unit) = match rest {
+        [] => (None, None),
+        [end] => (Some(get_argument_integer(end)?), None),
+        [end, unit] => (Some(get_argument_integer(end)?), Some(unit)),
+        _ => return Err(RespCommandError::SyntaxError),
+    };
+
+    let bits = match unit.map(get_argument_string).transpose()? {
+        None => false,
+        Some(unit) if unit.eq_ignore_ascii_case("BYTE") => false,
+        Some(unit) if unit.eq_ignore_ascii_case("BIT") => true,
+        Some(_) => return Err(RespCommandError::SyntaxError),
+    };
+
+    Ok(Some(BitRange { start, end, bits }))
+}
+
+/// A call to `BITCOUNT`
+#[derive(Debug)]
+pub struct RespBitcountCommand {
+    pub key: Box<[u8]>,
+    pub range: Option<BitRange>,
+}
+
+impl RespCommandConstructor for RespBitcountCommand {
+    fn from_array(input: RespArray) -> Result<RespBitcountCommand, RespCommandError> {
+        let [_, key_element, range_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let range = parse_bit_range(range_elements)?;
+
+        // unlike `BITPOS`, the end of the range can't be left out
+        if range.is_some_and(| r | r.end.is_none()) {
+            return Err(RespCommandError::SyntaxError);
+        }
+
+        Ok(RespBitcountCommand { key, range })
+    }
+}
+
+/// A call to `BITPOS`
+#[derive(Debug)]
+pub struct RespBitposCommand {
+    pub key: Box<[u8]>,
+    pub bit: bool,
+    pub range: Option<BitRange>,
+}
+
+impl RespCommandConstructor for RespBitposCommand {
+    fn from_array(input: RespArray) -> Result<RespBitposCommand, RespCommandError> {
+        let [_, key_element, bit_element, range_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let bit = match get_argument_integer(bit_element)? {
+            0 => false,
+            1 => true,
+            _ => return Err(RespCommandError::InvalidArgument("The bit argument must be 1 or 0.".into())),
+        };
+
+        let range = parse_bit_range(range_elements)?;
+
+        Ok(RespBitposCommand { key, bit, range })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+    /// The bits of the first string that aren't set in any of the others
+    Diff,
+    /// The bits of the first string that are also set in any of the others
+    AndOr,
+    /// The bits that are set in exactly one of the strings
+    One,
+}
+
+/// A call to `BITOP`
+#[derive(Debug)]
+pub struct RespBitopCommand {
+    pub operation: BitOperation,
+    pub destination: Box<[u8]>,
+    pub keys: Vec<Box<[u8]>>,
+}
+
+impl RespCommandConstructor for RespBitopCommand {
+    fn from_array(input: RespArray) -> Result<RespBitopCommand, RespCommandError> {
+        let [_, operation_element, destination_element, key_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let name = get_argument_string(operation_element)?.to_uppercase();
+
+        let operation = match name.as_str() {
+            "AND" => BitOperation::And,
+            "OR" => BitOperation::Or,
+            "XOR" => BitOperation::Xor,
+            "NOT" => BitOperation::Not,
+            "DIFF" => BitOperation::Diff,
+            "ANDOR" => BitOperation::AndOr,
+            "ONE" => BitOperation::One,
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        if operation == BitOperation::Not && key_elements.len() != 1 {
+            return Err(RespCommandError::InvalidArgument("BITOP NOT must be called with a single source key.".into()));
+        }
+
+        if matches!(operation, BitOperation::Diff | BitOperation::AndOr) && key_elements.len() < 2 {
+            return Err(RespCommandError::InvalidArgument(
+                format!("BITOP {name} must be called with at least two source keys."),
+            ));
+        }
+
+        let destination = get_argument_bytes(destination_element)?.into_boxed_slice();
+
+        let keys = key_elements.iter()
+            .map(| e | get_argument_bytes(e).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespBitopCommand { operation, destination, keys })
+    }
+}
+
+/// The type of an integer read or written by `BITFIELD`, e.g. `i16` or `u8`
+#[derive(Debug, Clone, Copy)]
+pub struct BitfieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl BitfieldType {
+    /// Parses a type, which can be a signed integer of up to 64 bits or an
+    /// unsigned integer of up to 63 bits (so that it fits in a RESP integer)
+    fn parse(element: &RespElement) -> Result<BitfieldType, RespCommandError> {
+        let bytes = get_argument_bytes(element)?;
+
+        let signed = match bytes.first() {
+            Some(b'i' | b'I') => true,
+            Some(b'u' | b'U') => false,
+            _ => return Err(invalid_bitfield_type()),
+        };
+
+        let bits = parse_integer(&bytes[1 ..])
+            .filter(| bits | (1 ..= if signed { 64 } else { 63 }).contains(bits))
+            .ok_or_else(invalid_bitfield_type)?;
+
+        Ok(BitfieldType { signed, bits: bits as u32 })
+    }
+
+    fn min(&self) -> i128 {
+        if self.signed { -(1 << (self.bits - 1)) } else { 0 }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed { (1 << (self.bits - 1)) - 1 } else { (1 << self.bits) - 1 }
+    }
+
+    /// Interprets the low bits of `raw` as an integer of this type
+    fn interpret(&self, raw: u64) -> i64 {
+        let unused = 64 - self.bits;
+
+        match self.signed {
+            // shifting back down copies the sign bit into the unused bits
+            true => ((raw << unused) as i64) >> unused,
+            false => ((raw << unused) >> unused) as i64,
+        }
+    }
+
+    fn read(&self, value: &StringValue, offset: usize) -> i64 {
+        self.interpret(value.get_bits(offset, self.bits))
+    }
+
+    /// Fits `value` into the type, handling it the way `overflow` says to if
+    /// it's out of range. Returns `None` if it's out of range and the
+    /// overflow behaviour is `FAIL`.
+    fn fit(&self, value: i128, overflow: BitfieldOverflow) -> Option<i64> {
+        if (self.min() ..= self.max()).contains(&value) {
+            return Some(value as i64);
+        }
+
+        match overflow {
+            BitfieldOverflow::Wrap => Some(self.interpret(value as u64)),
+            BitfieldOverflow::Sat => Some(value.clamp(self.min(), self.max()) as i64),
+            BitfieldOverflow::Fail => None,
+        }
+    }
+}
+
+fn invalid_bitfield_type() -> RespCommandError {
+    RespCommandError::InvalidArgument(
+        "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.".into(),
+    )
+}
+
+/// What `BITFIELD` does when `SET` or `INCRBY` produces a value that doesn't
+/// fit in its type
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BitfieldOverflow {
+    /// Keep the value's low bits, so it wraps around
+    #[default]
+    Wrap,
+    /// Clamp the value to the type's minimum or maximum
+    Sat,
+    /// Leave the integer as it is, and reply with null
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BitfieldAction {
+    Get,
+    Set(i64),
+    IncrBy(i64),
+}
+
+/// One of the `GET`, `SET` or `INCRBY` subcommands given to `BITFIELD`
+#[derive(Debug, Clone, Copy)]
+pub struct BitfieldOperation {
+    pub action: BitfieldAction,
+    pub integer_type: BitfieldType,
+    pub offset: usize,
+    pub overflow: BitfieldOverflow,
+}
+
+/// A call to `BITFIELD` or `BITFIELD_RO`
+#[derive(Debug)]
+pub struct RespBitfieldCommand {
+    pub key: Box<[u8]>,
+    pub operations: Vec<BitfieldOperation>,
+}
+
+impl RespBitfieldCommand {
+    fn is_read_only(&self) -> bool {
+        self.operations.iter().all(| op | matches!(op.action, BitfieldAction::Get))
+    }
+}
+
+impl RespCommandConstructor for RespBitfieldCommand {
+    fn from_array(input: RespArray) -> Result<RespBitfieldCommand, RespCommandError> {
+        let [_, key_element, arguments @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut operations = Vec::new();
+        let mut overflow = BitfieldOverflow::default();
+        let mut arguments = arguments;
+
+        while let [subcommand, rest @ ..] = arguments {
+            let subcommand = get_argument_string(subcommand)?.to_uppercase();
+
+            if subcommand == "OVERFLOW" {
+                let [behaviour, rest @ ..] = rest else {
+                    return Err(RespCommandError::SyntaxError);
+                };
+
+                overflow = match get_argument_string(behaviour)?.to_uppercase().as_str() {
+                    "WRAP" => BitfieldOverflow::Wrap,
+                    "SAT" => BitfieldOverflow::Sat,
+                    "FAIL" => BitfieldOverflow::Fail,
+                    _ => return Err(RespCommandError::InvalidArgument("Invalid OVERFLOW type specified".into())),
+                };
+
+                arguments = rest;
+                continue;
+            }
+
+            let (action, type_element, offset_element, rest) = match (subcommand.as_str(), rest) {
+                ("GET", [integer_type, offset, rest @ ..]) => (None, integer_type, offset, rest),
+                ("SET" | "INCRBY", [integer_type, offset, value, rest @ ..]) => (Some(value), integer_type, offset, rest),
+                _ => return Err(RespCommandError::SyntaxError),
+            };
+
+            let integer_type = BitfieldType::parse(type_element)?;
+            let offset = get_bitfield_offset(offset_element, integer_type)?;
+
+            let action = match action.map(get_argument_integer).transpose()? {
+                None => BitfieldAction::Get,
+                Some(value) if subcommand == "SET" => BitfieldAction::Set(value),
+                Some(increment) => BitfieldAction::IncrBy(increment),
+            };
+
+            operations.push(BitfieldOperation { action, integer_type, offset, overflow });
+            arguments = rest;
+        }
+
+        Ok(RespBitfieldCommand { key, operations })
+    }
+}
+
+/// Parses the offset of a `BITFIELD` integer, which is given in bits, or in
+/// multiples of the type's width if it's prefixed with `#`
+fn get_bitfield_offset(element: &RespElement, integer_type: BitfieldType) -> Result<usize, RespCommandError> {
+    let bytes = get_argument_bytes(element)?;
+
+    let offset = match bytes.strip_prefix(b"#") {
+        Some(index) => parse_integer(index).and_then(| i | i.checked_mul(integer_type.bits as i64)),
+        None => parse_integer(&bytes),
+    };
+
+    offset
+        .filter(| offset | (0 .. MAX_BIT_OFFSET as i64).contains(offset))
+        .map(| offset | offset as usize)
+        .ok_or_else(invalid_bit_offset)
+}
+
+/// Replies with the bit's previous value. The string is padded with zero
+/// bytes if it's too short, or created if the key doesn't exist.
+fn setbit(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetbitCommand::from_array(input)?;
+
+    let value = ctx.db.get_or_insert_with(&command.key, || Value::String(StringValue::default())).value.as_string_mut()?;
+
+    let previous = value.get_bits(command.offset, 1);
+    value.set_bits(command.offset, 1, command.bit as u64);
+
+    Ok(RespElement::new_integer(previous as isize))
+}
+
+fn getbit(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGetbitCommand::from_array(input)?;
+
+    let bit = ctx.db.get_string(&command.key)?.map_or(0, | value | value.get_bits(command.offset, 1));
+
+    Ok(RespElement::new_integer(bit as isize))
+}
+
+/// Masks the bits of a byte from the one at `position` onwards
+fn mask_from(position: usize) -> u8 {
+    0xff >> (position % 8)
+}
+
+/// Masks the bits of a byte up to and including the one at `position`
+fn mask_through(position: usize) -> u8 {
+    0xff << (7 - position % 8)
+}
+
+/// Returns the bytes that hold the bits from `start` to `end` (inclusive),
+/// along with the index of each byte and a mask of the bits in it that are
+/// part of the range
+fn bytes_in_range(bytes: &[u8], start: usize, end: usize) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+    let (first, last) = (start / 8, end / 8);
+
+    bytes[first ..= last].iter().enumerate().map(move | (i, &byte) | {
+        let index = first + i;
+        let mut mask = 0xff;
+
+        if index == first {
+            mask &= mask_from(start);
+        }
+
+        if index == last {
+            mask &= mask_through(end);
+        }
+
+        (index, byte, mask)
+    })
+}
+
+fn bitcount(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespBitcountCommand::from_array(input)?;
+
+    // like Redis, a range that's backwards no matter the string's length is
+    // empty, rather than being clamped
+    if command.range.is_some_and(| r | r.start < 0 && r.end.is_some_and(| end | end < 0 && r.start > end)) {
+        return Ok(RespElement::new_integer(0));
+    }
+
+    let Some(value) = ctx.db.get_string(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let bytes = value.as_bytes();
+    let range = command.range.unwrap_or(BitRange { start: 0, end: Some(-1), bits: false });
+
+    let count = match range.resolve(bytes.len()) {
+        Some((start, end)) => bytes_in_range(&bytes, start, end).map(| (_, byte, mask) | (byte & mask).count_ones() as usize).sum(),
+        None => 0,
+    };
+
+    Ok(RespElement::new_integer(count as isize))
+}
+
+/// Replies with the position of the first bit in the range that matches, or
+/// `-1` if there isn't one. When looking for a clear bit without the end of
+/// the range being given, the string is treated as if it were padded with
+/// zero bytes, so the position just past the range is returned instead.
+fn bitpos(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespBitposCommand::from_array(input)?;
+
+    let Some(value) = ctx.db.get_string(&command.key)? else {
+        return Ok(RespElement::new_integer(if command.bit { -1 } else { 0 }));
+    };
+
+    let bytes = value.as_bytes();
+    let range = command.range.unwrap_or(BitRange { start: 0, end: None, bits: false });
+
+    let Some((start, end)) = range.resolve(bytes.len()) else {
+        return Ok(RespElement::new_integer(-1));
+    };
+
+    let position = bytes_in_range(&bytes, start, end).find_map(| (index, byte, mask) | {
+        // clear bits are looked for as set bits of the inverted byte
+        let candidates = if command.bit { byte } else { !byte } & mask;
+
+        (candidates != 0).then(|| index * 8 + candidates.leading_zeros() as usize)
+    });
+
+    let position = match position {
+        Some(position) => position as isize,
+        None if !command.bit && range.end.is_none() => end as isize + 1,
+        None => -1,
+    };
+
+    Ok(RespElement::new_integer(position))
+}
+
+/// Stores the result of the operation in the destination key, and replies
+/// with its length. Keys that don't exist are treated as empty strings, and
+/// shorter strings are padded with zero bytes; if the result is empty, the
+/// destination key is deleted.
+fn bitop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespBitopCommand::from_array(input)?;
+
+    let sources = command.keys.iter()
+        .map(| key | Ok(ctx.db.get_string(key)?.map(| value | value.as_bytes().into_owned()).unwrap_or_default()))
+        .collect::<Result<Vec<_>, RespCommandError>>()?;
+
+    let length = sources.iter().map(Vec::len).max().unwrap_or(0);
+
+    if length == 0 {
+        ctx.db.delete(&command.destination);
+
+        return Ok(RespElement::new_integer(0));
+    }
+
+    let result = (0 .. length)
+        .map(| i | {
+            let mut bytes = sources.iter().map(| source | source.get(i).copied().unwrap_or(0));
+            let first = bytes.next().unwrap_or(0);
+
+            match command.operation {
+                BitOperation::And => bytes.fold(first, | a, b | a & b),
+                BitOperation::Or => bytes.fold(first, | a, b | a | b),
+                BitOperation::Xor => bytes.fold(first, | a, b | a ^ b),
+                BitOperation::Not => !first,
+                BitOperation::Diff => first & !bytes.fold(0, | a, b | a | b),
+                BitOperation::AndOr => first & bytes.fold(0, | a, b | a | b),
+                BitOperation::One => {
+                    // bits that have been seen at least once, and more than once
+                    let (once, more) = bytes.fold((first, 0), | (once, more), b | (once | b, more | (once & b)));
+
+                    once & !more
+                }
+            }
+        })
+        .collect();
+
+    ctx.db.set(&command.destination, Value::String(StringValue::new(result)), SetOptions::default());
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+/// Replies with an array of the result of each `GET`, `SET` or `INCRBY`:
+/// the integer that was read, the integer's previous value, or its new value
+/// respectively. Writes that overflow with `FAIL` leave the integer as it is,
+/// and reply with null.
+fn bitfield(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespBitfieldCommand::from_array(input)?;
+
+    if command.is_read_only() {
+        return get_bitfields(ctx, command);
+    }
+
+    // like Redis, the string is created (or padded) to hold every integer
+    // that could be written before any of them are, even if none of them
+    // end up being written because they overflow
+    let length = command.operations.iter()
+        .filter(| op | !matches!(op.action, BitfieldAction::Get))
+        .map(| op | (op.offset + op.integer_type.bits as usize).div_ceil(8))
+        .max()
+        .unwrap_or(0);
+
+    let value = ctx.db.get_or_insert_with(&command.key, || Value::String(StringValue::default())).value.as_string_mut()?;
+
+    value.grow(length);
+
+    let replies = command.operations.iter()
+        .map(| op | {
+            let current = op.integer_type.read(value, op.offset);
+
+            // like Redis, a value given for an unsigned integer is taken as
+            // the bits of the 64-bit integer, so negative values overflow
+            let updated = match op.action {
+                BitfieldAction::Get => return RespElement::new_integer(current as isize),
+                BitfieldAction::Set(v) if op.integer_type.signed => v as i128,
+                BitfieldAction::Set(v) => v as u64 as i128,
+                BitfieldAction::IncrBy(increment) => current as i128 + increment as i128,
+            };
+
+            let Some(updated) = op.integer_type.fit(updated, op.overflow) else {
+                return RespElement::new_null();
+            };
+
+            value.set_bits(op.offset, op.integer_type.bits, updated as u64);
+
+            // `SET` replies with the integer's previous value, and `INCRBY`
+            // with its new one
+            match op.action {
+                BitfieldAction::Set(_) => RespElement::new_integer(current as isize),
+                _ => RespElement::new_integer(updated as isize),
+            }
+        })
+        .collect();
+
+    Ok(RespElement::new_array(replies))
+}
+
+fn bitfield_ro(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespBitfieldCommand::from_array(input)?;
+
+    if !command.is_read_only() {
+        return Err(RespCommandError::InvalidArgument("BITFIELD_RO only supports the GET subcommand".into()));
+    }
+
+    get_bitfields(ctx, command)
+}
+
+/// Runs a `BITFIELD` call that only reads integers, which doesn't create the
+/// key if it doesn't exist
+fn get_bitfields(ctx: &mut CommandContext, command: RespBitfieldCommand) -> CommandResult {
+    let empty = StringValue::default();
+    let value = ctx.db.get_string(&command.key)?.unwrap_or(&empty);
+
+    let replies = command.operations.iter()
+        .map(| op | RespElement::new_integer(op.integer_type.read(value, op.offset) as isize))
+        .collect();
+
+    Ok(RespElement::new_array(replies))
+}
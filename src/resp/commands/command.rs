@@ -0,0 +1,260 @@
+use crate::resp::commands::{
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    ConnectionState,
+    RespCommandError,
+    COMMAND_TABLE,
+    get_argument_bytes,
+    get_argument_string,
+    get_command_spec,
+};
+use crate::resp::types::{RespArray, RespSet};
+use crate::resp::RespElement;
+
+pub const COMMAND: CommandSpec = CommandSpec {
+    name: "command",
+    arity: -1,
+    flags: &[CommandFlag::LoadingOk],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "server",
+    summary: "Returns detailed information about commands.",
+    since: "2.8.13",
+    handler: CommandHandler::Connection(command),
+};
+
+const HELP: &[&str] = &[
+    "COMMAND <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "(no subcommand)",
+    "    Return details about all commands.",
+    "COUNT",
+    "    Return the total number of commands in this server.",
+    "INFO [<command-name> ...]",
+    "    Return details about multiple commands.",
+    "    If no command names are given, documentation details for all",
+    "    commands are returned.",
+    "DOCS [<command-name> ...]",
+    "    Return documentation details about multiple commands.",
+    "    If no command names are given, documentation details for all",
+    "    commands are returned.",
+    "GETKEYS <full-command>",
+    "    Return the keys from a full command.",
+    "HELP",
+    "    Print this help.",
+];
+
+fn command(_: &mut ConnectionState, mut input: RespArray) -> CommandResult {
+    let Some(subcommand) = input.elements.get(1) else {
+        let info = COMMAND_TABLE.iter().map(| spec | get_command_info(spec)).collect();
+
+        return Ok(RespElement::new_array(info));
+    };
+
+    let subcommand = get_argument_string(subcommand)?;
+    let arguments = input.elements.split_off(2);
+
+    match subcommand.to_uppercase().as_str() {
+        "COUNT" if arguments.is_empty() => {
+            Ok(RespElement::new_integer(COMMAND_TABLE.len() as isize))
+        }
+        "INFO" if arguments.is_empty() => {
+            let info = COMMAND_TABLE.iter().map(| spec | get_command_info(spec)).collect();
+
+            Ok(RespElement::new_array(info))
+        }
+        "INFO" => {
+            let mut info = Vec::with_capacity(arguments.len());
+
+            for name in &arguments {
+                info.push(match get_command_spec(&get_argument_string(name)?) {
+                    Some(spec) => get_command_info(spec),
+                    None => RespElement::new_null(),
+                });
+            }
+
+            Ok(RespElement::new_array(info))
+        }
+        "DOCS" if arguments.is_empty() => {
+            let docs = COMMAND_TABLE.iter()
+                .map(| spec | (RespElement::new_bulk_string(spec.name.as_bytes()), get_command_docs(spec)))
+                .collect();
+
+            Ok(RespElement::new_map(docs))
+        }
+        "DOCS" => {
+            let mut docs = Vec::with_capacity(arguments.len());
+
+            // unknown commands are left out, rather than being returned as null
+            for name in &arguments {
+                if let Some(spec) = get_command_spec(&get_argument_string(name)?) {
+                    docs.push((RespElement::new_bulk_string(spec.name.as_bytes()), get_command_docs(spec)));
+                }
+            }
+
+            Ok(RespElement::new_map(docs))
+        }
+        "GETKEYS" if !arguments.is_empty() => {
+            get_keys(RespArray::new(arguments))
+        }
+        "HELP" if arguments.is_empty() => {
+            let lines = HELP.iter().map(| line | RespElement::new_simple_string(line)).collect();
+
+            Ok(RespElement::new_array(lines))
+        }
+        _ => Err(RespCommandError::UnknownSubcommand(subcommand, "command".into())),
+    }
+}
+
+/// Returns the keys that the given call would access, without running it
+fn get_keys(call: RespArray) -> CommandResult {
+    let name = get_argument_string(&call.elements[0])?;
+
+    let Some(spec) = get_command_spec(&name) else {
+        return Err(RespCommandError::InvalidArgument("Invalid command specified".into()));
+    };
+
+    if !spec.accepts_arity(call.length) {
+        return Err(RespCommandError::InvalidArgument("Invalid number of arguments specified for command".into()));
+    }
+
+    let positions = spec.get_key_positions(&call)?;
+
+    if positions.is_empty() {
+        return Err(RespCommandError::InvalidArgument("The command has no key arguments".into()));
+    }
+
+    let mut keys = Vec::with_capacity(positions.len());
+
+    for position in positions {
+        if let Some(key) = call.elements.get(position) {
+            keys.push(RespElement::new_bulk_string(&get_argument_bytes(key)?));
+        }
+    }
+
+    Ok(RespElement::new_array(keys))
+}
+
+/// Builds the reply that describes a single command, in the same layout that
+/// Redis uses: name, arity, flags, first key, last key, key step, ACL
+/// categories, tips, key specifications, and subcommands
+fn get_command_info(spec: &CommandSpec) -> RespElement {
+    let mut flags: Vec<RespElement> = spec.flags.iter()
+        .map(| flag | RespElement::new_simple_string(flag.name()))
+        .collect();
+
+    if spec.get_keys.is_some() {
+        flags.push(RespElement::new_simple_string("movablekeys"));
+    }
+
+    let categories = get_acl_categories(spec).into_iter()
+        .map(| category | RespElement::new_simple_string(&category))
+        .collect();
+
+    let (first, last, step) = spec.keys;
+
+    RespElement::new_array(vec![
+        RespElement::new_bulk_string(spec.name.as_bytes()),
+        RespElement::new_integer(spec.arity),
+        RespElement::Set(RespSet { elements: flags }),
+        RespElement::new_integer(first),
+        RespElement::new_integer(last),
+        RespElement::new_integer(step as isize),
+        RespElement::Set(RespSet { elements: categories }),
+        RespElement::new_array(Vec::new()),
+        RespElement::new_array(get_key_specs(spec)),
+        RespElement::new_array(Vec::new()),
+    ])
+}
+
+fn get_command_docs(spec: &CommandSpec) -> RespElement {
+    RespElement::new_map(vec![
+        (RespElement::new_bulk_string(b"summary"), RespElement::new_bulk_string(spec.summary.as_bytes())),
+        (RespElement::new_bulk_string(b"since"), RespElement::new_bulk_string(spec.since.as_bytes())),
+        (RespElement::new_bulk_string(b"group"), RespElement::new_bulk_string(spec.group.as_bytes())),
+    ])
+}
+
+fn get_acl_categories(spec: &CommandSpec) -> Vec<String> {
+    let mut categories = Vec::new();
+
+    match spec.group {
+        "generic" => categories.push("@keyspace".to_string()),
+        "server" => {}
+        group => categories.push(format!("@{}", group.replace('-', ""))),
+    }
+
+    if spec.has_flag(CommandFlag::Write) {
+        categories.push("@write".into());
+    }
+
+    if spec.has_flag(CommandFlag::ReadOnly) {
+        categories.push("@read".into());
+    }
+
+    if spec.has_flag(CommandFlag::Fast) {
+        categories.push("@fast".into());
+    } else {
+        categories.push("@slow".into());
+    }
+
+    if spec.has_flag(CommandFlag::Blocking) {
+        categories.push("@blocking".into());
+    }
+
+    if spec.has_flag(CommandFlag::Admin) {
+        categories.push("@admin".into());
+        categories.push("@dangerous".into());
+    }
+
+    if spec.has_flag(CommandFlag::PubSub) {
+        categories.push("@pubsub".into());
+    }
+
+    categories
+}
+
+/// Describes where a command's keys are, in the key specification format
+/// that Redis 7 introduced
+fn get_key_specs(spec: &CommandSpec) -> Vec<RespElement> {
+    let (first, last, step) = spec.keys;
+
+    if first <= 0 && spec.get_keys.is_none() {
+        return Vec::new();
+    }
+
+    let access = if spec.has_flag(CommandFlag::Write) { "RW" } else { "RO" };
+
+    let find_keys = if spec.get_keys.is_some() {
+        RespElement::new_map(vec![
+            (RespElement::new_bulk_string(b"type"), RespElement::new_bulk_string(b"unknown")),
+            (RespElement::new_bulk_string(b"spec"), RespElement::new_map(Vec::new())),
+        ])
+    } else {
+        // the last key is given relative to the first one
+        let last_key = if last < 0 { last } else { last - first };
+
+        RespElement::new_map(vec![
+            (RespElement::new_bulk_string(b"type"), RespElement::new_bulk_string(b"range")),
+            (RespElement::new_bulk_string(b"spec"), RespElement::new_map(vec![
+                (RespElement::new_bulk_string(b"lastkey"), RespElement::new_integer(last_key)),
+                (RespElement::new_bulk_string(b"keystep"), RespElement::new_integer(step as isize)),
+                (RespElement::new_bulk_string(b"limit"), RespElement::new_integer(0)),
+            ])),
+        ])
+    };
+
+    let begin_search = RespElement::new_map(vec![
+        (RespElement::new_bulk_string(b"type"), RespElement::new_bulk_string(b"index")),
+        (RespElement::new_bulk_string(b"spec"), RespElement::new_map(vec![
+            (RespElement::new_bulk_string(b"index"), RespElement::new_integer(first.max(1))),
+        ])),
+    ]);
+
+    vec![RespElement::new_map(vec![
+        (RespElement::new_bulk_string(b"flags"), RespElement::new_array(vec![RespElement::new_simple_string(access)])),
+        (RespElement::new_bulk_string(b"begin_search"), begin_search),
+        (RespElement::new_bulk_string(b"find_keys"), find_keys),
+    ])]
+}
@@ -1,29 +1,47 @@
-use crate::resp::commands::{RespCommandConstructor, RespCommandError};
-use crate::resp::RespElement;
+use crate::resp::commands::{
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    ConnectionState,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+};
 use crate::resp::types::RespArray;
+use crate::resp::RespElement;
+
+pub const ECHO: CommandSpec = CommandSpec {
+    name: "echo",
+    arity: 2,
+    flags: &[CommandFlag::Fast],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "connection",
+    summary: "Returns the given string.",
+    since: "1.0.0",
+    handler: CommandHandler::Connection(echo),
+};
 
 #[derive(Debug)]
 pub struct RespEchoCommand {
-    pub value: String,
+    pub value: Box<[u8]>,
 }
 
 impl RespCommandConstructor for RespEchoCommand {
     fn from_array(input: RespArray) -> Result<RespEchoCommand, RespCommandError> {
-        let Some(input_string) = input.elements.get(1) else {
-            return Err(RespCommandError::ParsingError);
+        let [_, input_string] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("echo".into()));
         };
 
-        let value = match input_string {
-            RespElement::SimpleString(s) => s.value.clone(),
-            RespElement::BulkString(b) => {
-                match String::from_utf8(b.value.to_vec()) {
-                    Ok(as_string) => as_string,
-                    Err(_) => return Err(RespCommandError::ParsingError),
-                }
-            }
-            _ => return Err(RespCommandError::InvalidArgument),
-        };
+        let value = get_argument_bytes(input_string)?.into_boxed_slice();
 
         Ok(RespEchoCommand { value })
     }
 }
+
+fn echo(_: &mut ConnectionState, input: RespArray) -> CommandResult {
+    let command = RespEchoCommand::from_array(input)?;
+
+    Ok(RespElement::new_bulk_string(&command.value))
+}
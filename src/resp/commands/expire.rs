@@ -0,0 +1,277 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::ExpireCondition;
+
+pub const EXPIRE: CommandSpec = CommandSpec {
+    name: "expire",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Sets the expiration time of a key in seconds.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(expire),
+};
+
+pub const PEXPIRE: CommandSpec = CommandSpec {
+    name: "pexpire",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Sets the expiration time of a key in milliseconds.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(pexpire),
+};
+
+pub const EXPIREAT: CommandSpec = CommandSpec {
+    name: "expireat",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Sets the expiration time of a key to a Unix timestamp.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(expireat),
+};
+
+pub const PEXPIREAT: CommandSpec = CommandSpec {
+    name: "pexpireat",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(pexpireat),
+};
+
+pub const TTL: CommandSpec = CommandSpec {
+    name: "ttl",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns the expiration time in seconds of a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(ttl),
+};
+
+pub const PTTL: CommandSpec = CommandSpec {
+    name: "pttl",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns the expiration time in milliseconds of a key.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(pttl),
+};
+
+pub const EXPIRETIME: CommandSpec = CommandSpec {
+    name: "expiretime",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns the expiration time of a key as a Unix timestamp.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(expiretime),
+};
+
+pub const PEXPIRETIME: CommandSpec = CommandSpec {
+    name: "pexpiretime",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns the expiration time of a key as a Unix milliseconds timestamp.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(pexpiretime),
+};
+
+pub const PERSIST: CommandSpec = CommandSpec {
+    name: "persist",
+    arity: 2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Removes the expiration time of a key.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(persist),
+};
+
+/// The units that a TTL is given in, and whether it's relative to the current
+/// time or an absolute Unix timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireUnit {
+    Seconds,
+    Milliseconds,
+    UnixSeconds,
+    UnixMilliseconds,
+}
+
+/// A call to `EXPIRE`, `PEXPIRE`, `EXPIREAT` or `PEXPIREAT`
+#[derive(Debug)]
+pub struct RespExpireCommand {
+    pub key: Box<[u8]>,
+    pub time: i64,
+    pub condition: ExpireCondition,
+}
+
+impl RespCommandConstructor for RespExpireCommand {
+    fn from_array(input: RespArray) -> Result<RespExpireCommand, RespCommandError> {
+        let [_, key_element, time_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut condition = ExpireCondition::default();
+
+        for option in options {
+            let option_name = get_argument_string(option)?;
+
+            match option_name.to_uppercase().as_str() {
+                "NX" => condition.if_none = true,
+                "XX" => condition.if_some = true,
+                "GT" => condition.if_later = true,
+                "LT" => condition.if_earlier = true,
+                _ => return Err(RespCommandError::InvalidArgument(format!("Unsupported option {option_name}"))),
+            }
+        }
+
+        if condition.if_none && (condition.if_some || condition.if_later || condition.if_earlier) {
+            return Err(RespCommandError::InvalidArgument("NX and XX, GT or LT options at the same time are not compatible".into()));
+        }
+
+        if condition.if_later && condition.if_earlier {
+            return Err(RespCommandError::InvalidArgument("GT and LT options at the same time are not compatible".into()));
+        }
+
+        // the options are checked before the time, the same way Redis does
+        let time = get_argument_integer(time_element)?;
+
+        Ok(RespExpireCommand { key, time, condition })
+    }
+}
+
+fn expire(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_expiry(ctx, input, "expire", ExpireUnit::Seconds)
+}
+
+fn pexpire(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_expiry(ctx, input, "pexpire", ExpireUnit::Milliseconds)
+}
+
+fn expireat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_expiry(ctx, input, "expireat", ExpireUnit::UnixSeconds)
+}
+
+fn pexpireat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_expiry(ctx, input, "pexpireat", ExpireUnit::UnixMilliseconds)
+}
+
+fn set_expiry(ctx: &mut CommandContext, input: RespArray, name: &str, unit: ExpireUnit) -> CommandResult {
+    let command = RespExpireCommand::from_array(input)?;
+
+    let timestamp = to_unix_millis(command.time, unit, ctx.db.now_millis())
+        .ok_or_else(|| RespCommandError::InvalidExpireTime(name.into()))?;
+
+    // times in the past are clamped to the epoch, which deletes the key
+    let when = timestamp.max(0) as u64;
+
+    let updated = ctx.db.expire(&command.key, when, command.condition);
+
+    Ok(RespElement::new_integer(updated as isize))
+}
+
+/// Converts a TTL given in `unit` into a Unix time in milliseconds, or returns
+/// `None` if it would overflow
+pub fn to_unix_millis(time: i64, unit: ExpireUnit, now: u64) -> Option<i64> {
+    let time = match unit {
+        ExpireUnit::Seconds | ExpireUnit::UnixSeconds => time.checked_mul(1000)?,
+        ExpireUnit::Milliseconds | ExpireUnit::UnixMilliseconds => time,
+    };
+
+    match unit {
+        ExpireUnit::Seconds | ExpireUnit::Milliseconds => time.checked_add(now as i64),
+        ExpireUnit::UnixSeconds | ExpireUnit::UnixMilliseconds => Some(time),
+    }
+}
+
+fn ttl(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_remaining_time(ctx, input, | millis | (millis + 500) / 1000)
+}
+
+fn pttl(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_remaining_time(ctx, input, | millis | millis)
+}
+
+fn expiretime(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_expiry_timestamp(ctx, input, | millis | millis / 1000)
+}
+
+fn pexpiretime(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_expiry_timestamp(ctx, input, | millis | millis)
+}
+
+/// Replies with how long the key has left to live, converted from milliseconds
+/// by `convert`, or -2 if the key doesn't exist and -1 if it has no TTL
+fn get_remaining_time(ctx: &mut CommandContext, input: RespArray, convert: fn(u64) -> u64) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let reply = match ctx.db.get_expiry(&command.key) {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(when)) => convert(when.saturating_sub(ctx.db.now_millis())) as isize,
+    };
+
+    Ok(RespElement::new_integer(reply))
+}
+
+/// Replies with the Unix timestamp that the key expires at, converted from
+/// milliseconds by `convert`, or -2 if the key doesn't exist and -1 if it has
+/// no TTL
+fn get_expiry_timestamp(ctx: &mut CommandContext, input: RespArray, convert: fn(u64) -> u64) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let reply = match ctx.db.get_expiry(&command.key) {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(when)) => convert(when) as isize,
+    };
+
+    Ok(RespElement::new_integer(reply))
+}
+
+fn persist(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let removed = ctx.db.persist(&command.key);
+
+    Ok(RespElement::new_integer(removed as isize))
+}
@@ -0,0 +1,610 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_float,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::commands::sorted_set::{RespZaddCommand, ZaddOptions, add_members, store_sorted_set};
+use crate::resp::{RespElement, RespProtocol};
+use crate::resp::types::RespArray;
+use crate::store::{Coordinates, GeoShape, ScoreBound, ScoreRange, SortedSet};
+
+pub const GEOADD: CommandSpec = CommandSpec {
+    name: "geoadd",
+    arity: -5,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Adds one or more members to a geospatial index. The key is created if it doesn't exist.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(geoadd),
+};
+
+pub const GEODIST: CommandSpec = CommandSpec {
+    name: "geodist",
+    arity: -4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Returns the distance between two members of a geospatial index.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(geodist),
+};
+
+pub const GEOHASH: CommandSpec = CommandSpec {
+    name: "geohash",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Returns members from a geospatial index as geohash strings.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(geohash),
+};
+
+pub const GEOPOS: CommandSpec = CommandSpec {
+    name: "geopos",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Returns the longitude and latitude of members from a geospatial index.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(geopos),
+};
+
+pub const GEOSEARCH: CommandSpec = CommandSpec {
+    name: "geosearch",
+    arity: -7,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Queries a geospatial index for members inside an area of a box or a circle.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(geosearch),
+};
+
+pub const GEOSEARCHSTORE: CommandSpec = CommandSpec {
+    name: "geosearchstore",
+    arity: -8,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "geo",
+    summary: "Queries a geospatial index for members inside an area of a box or a circle, optionally stores the result.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(geosearchstore),
+};
+
+/// A call to `GEOADD`
+#[derive(Debug)]
+pub struct RespGeoaddCommand {
+    pub key: Box<[u8]>,
+    /// Only `NX`, `XX` and `CH` can be given
+    pub options: ZaddOptions,
+    pub members: Vec<(Coordinates, Vec<u8>)>,
+}
+
+impl RespCommandConstructor for RespGeoaddCommand {
+    fn from_array(input: RespArray) -> Result<RespGeoaddCommand, RespCommandError> {
+        let [_, key_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut options = ZaddOptions::default();
+        let mut triples = rest;
+
+        while let [option, remaining @ ..] = triples {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "NX" => options.nx = true,
+                "XX" => options.xx = true,
+                "CH" => options.ch = true,
+                _ => break,
+            }
+
+            triples = remaining;
+        }
+
+        if triples.is_empty() || triples.len() % 3 != 0 || (options.nx && options.xx) {
+            return Err(RespCommandError::SyntaxError);
+        }
+
+        let members = triples.chunks(3)
+            .map(| triple | Ok((get_coordinates(&triple[0], &triple[1])?, get_argument_bytes(&triple[2])?)))
+            .collect::<Result<_, RespCommandError>>()?;
+
+        Ok(RespGeoaddCommand { key, options, members })
+    }
+}
+
+/// A call to `GEODIST`
+#[derive(Debug)]
+pub struct RespGeodistCommand {
+    pub key: Box<[u8]>,
+    pub members: (Vec<u8>, Vec<u8>),
+    /// The number of meters in the unit that the distance is given in
+    pub unit: f64,
+}
+
+impl RespCommandConstructor for RespGeodistCommand {
+    fn from_array(input: RespArray) -> Result<RespGeodistCommand, RespCommandError> {
+        let [_, key_element, member1_element, member2_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let unit = match rest {
+            [] => 1.0,
+            [unit_element] => get_argument_unit(unit_element)?,
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let members = (get_argument_bytes(member1_element)?, get_argument_bytes(member2_element)?);
+
+        Ok(RespGeodistCommand { key, members, unit })
+    }
+}
+
+/// A call to `GEOHASH` or `GEOPOS`, which take a key and any number of members
+#[derive(Debug)]
+pub struct RespGeoMembersCommand {
+    pub key: Box<[u8]>,
+    pub members: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespGeoMembersCommand {
+    fn from_array(input: RespArray) -> Result<RespGeoMembersCommand, RespCommandError> {
+        let [_, key_element, member_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let members = member_elements.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespGeoMembersCommand { key, members })
+    }
+}
+
+/// Where a search is centered
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    /// `FROMMEMBER`, the position of a member of the sorted set being searched
+    Member(Vec<u8>),
+    /// `FROMLONLAT`
+    Coordinates(Coordinates),
+}
+
+/// The order that a search's results are sorted in, by their distance from
+/// the center
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSortOrder {
+    Ascending,
+    Descending,
+}
+
+/// A call to `GEOSEARCH` or `GEOSEARCHSTORE`
+#[derive(Debug)]
+pub struct RespGeosearchCommand {
+    /// Where `GEOSEARCHSTORE` stores the result
+    pub destination: Option<Box<[u8]>>,
+    pub key: Box<[u8]>,
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    /// The number of meters in the unit that the shape is given in, which
+    /// distances are also given in
+    pub unit: f64,
+    pub order: Option<GeoSortOrder>,
+    pub count: Option<usize>,
+    /// Whether to stop searching once `count` members are found, rather than
+    /// finding the closest ones
+    pub any: bool,
+    pub with_coord: bool,
+    pub with_dist: bool,
+    pub with_hash: bool,
+    /// Whether `GEOSEARCHSTORE` stores the members with their distances as
+    /// scores, rather than their positions
+    pub store_dist: bool,
+}
+
+impl RespCommandConstructor for RespGeosearchCommand {
+    fn from_array(input: RespArray) -> Result<RespGeosearchCommand, RespCommandError> {
+        let [_, key_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("geosearch".into()));
+        };
+
+        RespGeosearchCommand::from_arguments(None, key_element, rest)
+    }
+}
+
+impl RespGeosearchCommand {
+    /// Parses the options that follow the key, where `WITHCOORD`, `WITHDIST`
+    /// and `WITHHASH` are only allowed if there's no destination, and
+    /// `STOREDIST` is only allowed if there is one
+    fn from_arguments(
+        destination: Option<Box<[u8]>>,
+        key_element: &RespElement,
+        elements: &[RespElement],
+    ) -> Result<RespGeosearchCommand, RespCommandError> {
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let name = if destination.is_some() { "GEOSEARCHSTORE" } else { "GEOSEARCH" };
+
+        let mut origin = None;
+        let mut area = None;
+        let mut order = None;
+        let mut count = None;
+        let mut any = false;
+        let mut with_coord = false;
+        let mut with_dist = false;
+        let mut with_hash = false;
+        let mut store_dist = false;
+
+        let mut options = elements;
+
+        while let [option, rest @ ..] = options {
+            options = rest;
+
+            match (get_argument_string(option)?.to_uppercase().as_str(), rest) {
+                ("FROMMEMBER", [member, ..]) if origin.is_none() => {
+                    origin = Some(GeoOrigin::Member(get_argument_bytes(member)?));
+                    options = &rest[1..];
+                }
+                ("FROMLONLAT", [longitude, latitude, ..]) if origin.is_none() => {
+                    origin = Some(GeoOrigin::Coordinates(get_coordinates(longitude, latitude)?));
+                    options = &rest[2..];
+                }
+                ("BYRADIUS", [radius, unit, ..]) if area.is_none() => {
+                    let radius = get_argument_distance(radius, "radius")?;
+
+                    if radius < 0.0 {
+                        return Err(RespCommandError::InvalidArgument("radius cannot be negative".into()));
+                    }
+
+                    let unit = get_argument_unit(unit)?;
+
+                    area = Some((GeoShape::Radius(radius * unit), unit));
+                    options = &rest[2..];
+                }
+                ("BYBOX", [width, height, unit, ..]) if area.is_none() => {
+                    let width = get_argument_distance(width, "width")?;
+                    let height = get_argument_distance(height, "height")?;
+
+                    if width < 0.0 || height < 0.0 {
+                        return Err(RespCommandError::InvalidArgument("height or width cannot be negative".into()));
+                    }
+
+                    let unit = get_argument_unit(unit)?;
+
+                    area = Some((GeoShape::Box { width: width * unit, height: height * unit }, unit));
+                    options = &rest[3..];
+                }
+                ("ASC", _) => order = Some(GeoSortOrder::Ascending),
+                ("DESC", _) => order = Some(GeoSortOrder::Descending),
+                ("COUNT", [value, ..]) => {
+                    let value = get_argument_integer(value)?;
+
+                    if value <= 0 {
+                        return Err(RespCommandError::InvalidArgument("COUNT must be > 0".into()));
+                    }
+
+                    count = Some(value as usize);
+                    options = &rest[1..];
+                }
+                ("ANY", _) => any = true,
+                ("WITHCOORD", _) => with_coord = true,
+                ("WITHDIST", _) => with_dist = true,
+                ("WITHHASH", _) => with_hash = true,
+                ("STOREDIST", _) if destination.is_some() => store_dist = true,
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        if destination.is_some() && (with_coord || with_dist || with_hash) {
+            return Err(RespCommandError::InvalidArgument(
+                format!("{name} is not compatible with WITHDIST, WITHHASH and WITHCOORD options")
+            ));
+        }
+
+        let Some(origin) = origin else {
+            return Err(RespCommandError::InvalidArgument(
+                format!("exactly one of FROMMEMBER or FROMLONLAT can be specified for {name}")
+            ));
+        };
+
+        let Some((shape, unit)) = area else {
+            return Err(RespCommandError::InvalidArgument(
+                format!("exactly one of BYRADIUS and BYBOX can be specified for {name}")
+            ));
+        };
+
+        if any && count.is_none() {
+            return Err(RespCommandError::InvalidArgument("the ANY argument requires COUNT argument".into()));
+        }
+
+        Ok(RespGeosearchCommand {
+            destination,
+            key,
+            origin,
+            shape,
+            unit,
+            order,
+            count,
+            any,
+            with_coord,
+            with_dist,
+            with_hash,
+            store_dist,
+        })
+    }
+}
+
+/// A member that was found by a search
+#[derive(Debug)]
+struct GeoMatch {
+    member: Vec<u8>,
+    score: f64,
+    coordinates: Coordinates,
+    /// The distance from the center in meters
+    distance: f64,
+}
+
+/// Parses a longitude and latitude, which must be within the range that can
+/// be indexed
+fn get_coordinates(longitude_element: &RespElement, latitude_element: &RespElement) -> Result<Coordinates, RespCommandError> {
+    let longitude = get_argument_float(longitude_element)?;
+    let latitude = get_argument_float(latitude_element)?;
+
+    Coordinates::new(longitude, latitude).ok_or_else(|| {
+        RespCommandError::InvalidArgument(format!("invalid longitude,latitude pair {longitude:.6},{latitude:.6}"))
+    })
+}
+
+/// Parses a unit of distance, returning the number of meters in it
+fn get_argument_unit(element: &RespElement) -> Result<f64, RespCommandError> {
+    match get_argument_string(element)?.to_lowercase().as_str() {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err(RespCommandError::InvalidArgument("unsupported unit provided. please use M, KM, FT, MI".into())),
+    }
+}
+
+/// Parses the size of a search's shape, where `what` names it in the error
+fn get_argument_distance(element: &RespElement, what: &str) -> Result<f64, RespCommandError> {
+    get_argument_float(element).map_err(| _ | RespCommandError::InvalidArgument(format!("need numeric {what}")))
+}
+
+/// Distances are always replied with as bulk strings with 4 decimal places
+fn get_distance_reply(distance: f64) -> RespElement {
+    RespElement::new_bulk_string(format!("{distance:.4}").as_bytes())
+}
+
+/// Coordinates are replied with as doubles in RESP3, but in RESP2 they're
+/// given with more decimal places than other doubles, like in Redis
+fn get_coordinates_reply(coordinates: Coordinates, protocol: RespProtocol) -> RespElement {
+    let get_reply = | value: f64 | match protocol {
+        RespProtocol::Resp3 => RespElement::new_double(value),
+        _ => {
+            let formatted = format!("{value:.17}");
+            let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+            RespElement::new_bulk_string(trimmed.as_bytes())
+        }
+    };
+
+    RespElement::new_array(vec![get_reply(coordinates.longitude), get_reply(coordinates.latitude)])
+}
+
+/// Replies with the number of members that were added, or with `CH`, the
+/// number that were added or moved
+fn geoadd(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGeoaddCommand::from_array(input)?;
+
+    let members = command.members.into_iter()
+        .map(| (coordinates, member) | (coordinates.score(), member))
+        .collect();
+
+    add_members(ctx.db, RespZaddCommand { key: command.key, options: command.options, members })
+}
+
+/// Replies with the distance between the members, or null if either of them
+/// doesn't exist
+fn geodist(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGeodistCommand::from_array(input)?;
+
+    let Some(set) = ctx.db.get_sorted_set(&command.key)? else {
+        return Ok(RespElement::new_null());
+    };
+
+    let (Some(score1), Some(score2)) = (set.score(&command.members.0), set.score(&command.members.1)) else {
+        return Ok(RespElement::new_null());
+    };
+
+    let distance = Coordinates::from_score(score1).distance_to(Coordinates::from_score(score2));
+
+    Ok(get_distance_reply(distance / command.unit))
+}
+
+/// Replies with the geohash string of each member, or null for members that
+/// don't exist
+fn geohash(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGeoMembersCommand::from_array(input)?;
+
+    let set = ctx.db.get_sorted_set(&command.key)?;
+
+    let hashes = command.members.iter()
+        .map(| member | match set.and_then(| s | s.score(member)) {
+            Some(score) => RespElement::new_bulk_string(Coordinates::from_score(score).geohash().as_bytes()),
+            None => RespElement::new_null(),
+        })
+        .collect();
+
+    Ok(RespElement::new_array(hashes))
+}
+
+/// Replies with the longitude and latitude of each member, or a null array
+/// for members that don't exist. These are decoded from the members' scores,
+/// so they can differ slightly from the ones that were added.
+fn geopos(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGeoMembersCommand::from_array(input)?;
+
+    let set = ctx.db.get_sorted_set(&command.key)?;
+
+    let positions = command.members.iter()
+        .map(| member | match set.and_then(| s | s.score(member)) {
+            Some(score) => get_coordinates_reply(Coordinates::from_score(score), ctx.protocol),
+            None => RespElement::new_null_array(),
+        })
+        .collect();
+
+    Ok(RespElement::new_array(positions))
+}
+
+/// Replies with the members within the shape, each of which is an array with
+/// the requested details if `WITHCOORD`, `WITHDIST` or `WITHHASH` were given
+fn geosearch(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGeosearchCommand::from_array(input)?;
+
+    let Some(matches) = search(ctx, &command)? else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    let reply = matches.into_iter()
+        .map(| m | {
+            if !command.with_coord && !command.with_dist && !command.with_hash {
+                return RespElement::new_bulk_string(&m.member);
+            }
+
+            let mut details = vec![RespElement::new_bulk_string(&m.member)];
+
+            if command.with_dist {
+                details.push(get_distance_reply(m.distance / command.unit));
+            }
+
+            if command.with_hash {
+                details.push(RespElement::new_integer(m.score as isize));
+            }
+
+            if command.with_coord {
+                details.push(get_coordinates_reply(m.coordinates, ctx.protocol));
+            }
+
+            RespElement::new_array(details)
+        })
+        .collect();
+
+    Ok(RespElement::new_array(reply))
+}
+
+/// Stores the members within the shape at the destination, replacing
+/// whatever was there and dropping its TTL, or deletes the destination if
+/// none were found. Replies with the number of members that were stored.
+fn geosearchstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, destination_element, key_element, rest @ ..] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("geosearchstore".into()));
+    };
+
+    let destination = get_argument_bytes(destination_element)?.into_boxed_slice();
+    let command = RespGeosearchCommand::from_arguments(Some(destination), key_element, rest)?;
+
+    let mut result = SortedSet::new();
+
+    for m in search(ctx, &command)?.unwrap_or_default() {
+        let score = if command.store_dist { m.distance / command.unit } else { m.score };
+
+        result.insert(m.member, score);
+    }
+
+    let destination = command.destination.as_deref().expect("STORE variants always have a destination");
+
+    Ok(store_sorted_set(ctx.db, destination, result))
+}
+
+/// Finds the members within the shape, or returns `None` if the key doesn't
+/// exist. Only the cells that cover the shape are searched, which are ranges
+/// of scores in the sorted set, and then the members in them are checked
+/// against the shape.
+fn search(ctx: &mut CommandContext, command: &RespGeosearchCommand) -> Result<Option<Vec<GeoMatch>>, RespCommandError> {
+    let set = ctx.db.get_sorted_set(&command.key)?;
+
+    let center = match &command.origin {
+        GeoOrigin::Coordinates(coordinates) => *coordinates,
+        GeoOrigin::Member(member) => set.and_then(| s | s.score(member))
+            .map(Coordinates::from_score)
+            .ok_or_else(|| RespCommandError::InvalidArgument("could not decode requested zset member".into()))?,
+    };
+
+    let Some(set) = set else {
+        return Ok(None);
+    };
+
+    // without `ANY`, every match has to be found to know which are closest
+    let limit = match command.any {
+        true => command.count,
+        false => None,
+    };
+
+    let mut matches = Vec::new();
+
+    for scores in command.shape.score_ranges(center) {
+        if limit.is_some_and(| l | matches.len() >= l) {
+            break;
+        }
+
+        let range = ScoreRange {
+            min: ScoreBound { value: scores.start as f64, exclusive: false },
+            max: ScoreBound { value: scores.end as f64, exclusive: true },
+        };
+
+        let ranks = set.ranks_by_score(&range);
+
+        for (member, score) in set.iter_from(ranks.start, false).take(ranks.len()) {
+            let coordinates = Coordinates::from_score(score);
+
+            if let Some(distance) = command.shape.distance_within(center, coordinates) {
+                matches.push(GeoMatch { member: member.to_vec(), score, coordinates, distance });
+
+                if limit.is_some_and(| l | matches.len() >= l) {
+                    break;
+                }
+            }
+        }
+    }
+
+    // the closest members are returned when there's a count, so they have to
+    // be sorted even if no order was given
+    let order = match (command.order, command.count) {
+        (None, Some(_)) if !command.any => Some(GeoSortOrder::Ascending),
+        (order, _) => order,
+    };
+
+    match order {
+        Some(GeoSortOrder::Ascending) => matches.sort_by(| a, b | a.distance.total_cmp(&b.distance)),
+        Some(GeoSortOrder::Descending) => matches.sort_by(| a, b | b.distance.total_cmp(&a.distance)),
+        None => (),
+    }
+
+    if let Some(count) = command.count {
+        matches.truncate(count);
+    }
+
+    Ok(Some(matches))
+}
@@ -1,30 +1,50 @@
-use crate::resp::commands::{RespCommandConstructor, RespCommandError};
-use crate::resp::RespElement;
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+};
 use crate::resp::types::RespArray;
+use crate::resp::RespElement;
+
+pub const GET: CommandSpec = CommandSpec {
+    name: "get",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns the string value of a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(get),
+};
 
 #[derive(Debug)]
 pub struct RespGetCommand {
-    pub key: String,
+    pub key: Box<[u8]>,
 }
 
 impl RespCommandConstructor for RespGetCommand {
     fn from_array(input: RespArray) -> Result<RespGetCommand, RespCommandError> {
-        let Some(key_element) = input.elements.get(1) else {
-            return Err(RespCommandError::InvalidArgument);
+        let [_, key_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("get".into()));
         };
 
-        let key = match key_element {
-            RespElement::SimpleString(s) => s.value.clone(),
-            RespElement::BulkString(b) => {
-                let Ok(as_string) = String::from_utf8(b.value.to_vec()) else {
-                    return Err(RespCommandError::InvalidArgument);
-                };
-                
-                as_string
-            },
-            _ => return Err(RespCommandError::InvalidArgument),
-        };
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
 
         Ok(RespGetCommand { key })
     }
 }
+
+fn get(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGetCommand::from_array(input)?;
+
+    match ctx.db.get_string(&command.key)? {
+        Some(value) => Ok(RespElement::new_bulk_string(&value.as_bytes())),
+        None => Ok(RespElement::new_null()),
+    }
+}
@@ -0,0 +1,644 @@
+use crate::glob;
+use crate::random;
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RANDOM_MAX_REPEATED_COUNT,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_cursor,
+    get_argument_float,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+    get_scan_reply,
+};
+use crate::resp::{RespElement, RespProtocol};
+use crate::resp::types::RespArray;
+use crate::resp::types::double::{format_double, parse_double};
+use crate::store::{parse_integer, Hash, Value};
+
+/// How many fields `HSCAN` looks at per call when it isn't given a `COUNT`
+const HSCAN_DEFAULT_COUNT: usize = 10;
+
+pub const HSET: CommandSpec = CommandSpec {
+    name: "hset",
+    arity: -4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Creates or modifies the value of a field in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hset),
+};
+
+pub const HSETNX: CommandSpec = CommandSpec {
+    name: "hsetnx",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Sets the value of a field in a hash only when the field doesn't exist.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hsetnx),
+};
+
+pub const HGET: CommandSpec = CommandSpec {
+    name: "hget",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the value of a field in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hget),
+};
+
+pub const HMGET: CommandSpec = CommandSpec {
+    name: "hmget",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the values of all fields in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hmget),
+};
+
+pub const HDEL: CommandSpec = CommandSpec {
+    name: "hdel",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hdel),
+};
+
+pub const HEXISTS: CommandSpec = CommandSpec {
+    name: "hexists",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Determines whether a field exists in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hexists),
+};
+
+pub const HLEN: CommandSpec = CommandSpec {
+    name: "hlen",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the number of fields in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hlen),
+};
+
+pub const HSTRLEN: CommandSpec = CommandSpec {
+    name: "hstrlen",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the length of the value of a field.",
+    since: "3.2.0",
+    handler: CommandHandler::Store(hstrlen),
+};
+
+pub const HKEYS: CommandSpec = CommandSpec {
+    name: "hkeys",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns all fields in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hkeys),
+};
+
+pub const HVALS: CommandSpec = CommandSpec {
+    name: "hvals",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns all values in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hvals),
+};
+
+pub const HGETALL: CommandSpec = CommandSpec {
+    name: "hgetall",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns all fields and values in a hash.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hgetall),
+};
+
+pub const HINCRBY: CommandSpec = CommandSpec {
+    name: "hincrby",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(hincrby),
+};
+
+pub const HINCRBYFLOAT: CommandSpec = CommandSpec {
+    name: "hincrbyfloat",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(hincrbyfloat),
+};
+
+pub const HRANDFIELD: CommandSpec = CommandSpec {
+    name: "hrandfield",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns one or more random fields from a hash.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(hrandfield),
+};
+
+pub const HSCAN: CommandSpec = CommandSpec {
+    name: "hscan",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Iterates over fields and values of a hash.",
+    since: "2.8.0",
+    handler: CommandHandler::Store(hscan),
+};
+
+/// A call to `HSET`
+#[derive(Debug)]
+pub struct RespHsetCommand {
+    pub key: Box<[u8]>,
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl RespCommandConstructor for RespHsetCommand {
+    fn from_array(input: RespArray) -> Result<RespHsetCommand, RespCommandError> {
+        let [_, key_element, pairs @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if pairs.is_empty() || pairs.len() % 2 != 0 {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let fields = pairs.chunks_exact(2)
+            .map(| pair | Ok((get_argument_bytes(&pair[0])?, get_argument_bytes(&pair[1])?)))
+            .collect::<Result<_, RespCommandError>>()?;
+
+        Ok(RespHsetCommand { key, fields })
+    }
+}
+
+/// A call to a command that takes a key and a single field, e.g. `HGET`
+#[derive(Debug)]
+pub struct RespHashFieldCommand {
+    pub key: Box<[u8]>,
+    pub field: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespHashFieldCommand {
+    fn from_array(input: RespArray) -> Result<RespHashFieldCommand, RespCommandError> {
+        let [_, key_element, field_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let field = get_argument_bytes(field_element)?;
+
+        Ok(RespHashFieldCommand { key, field })
+    }
+}
+
+/// A call to a command that takes a key and one or more fields, e.g. `HMGET`
+#[derive(Debug)]
+pub struct RespHashFieldsCommand {
+    pub key: Box<[u8]>,
+    pub fields: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespHashFieldsCommand {
+    fn from_array(input: RespArray) -> Result<RespHashFieldsCommand, RespCommandError> {
+        let [_, key_element, field_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if field_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let fields = field_elements.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespHashFieldsCommand { key, fields })
+    }
+}
+
+/// A call to `HSETNX`, `HINCRBY` or `HINCRBYFLOAT`, which take a key, a field
+/// and a value (or an increment)
+#[derive(Debug)]
+pub struct RespHashFieldValueCommand {
+    pub key: Box<[u8]>,
+    pub field: Vec<u8>,
+    pub value: RespElement,
+}
+
+impl RespCommandConstructor for RespHashFieldValueCommand {
+    fn from_array(input: RespArray) -> Result<RespHashFieldValueCommand, RespCommandError> {
+        let [_, key_element, field_element, value] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let field = get_argument_bytes(field_element)?;
+
+        Ok(RespHashFieldValueCommand { key, field, value: value.clone() })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespHrandfieldCommand {
+    pub key: Box<[u8]>,
+    /// How many fields to return; if this isn't given, the reply is a single
+    /// field rather than an array. Negative counts can return the same field
+    /// more than once.
+    pub count: Option<i64>,
+    pub with_values: bool,
+}
+
+impl RespCommandConstructor for RespHrandfieldCommand {
+    fn from_array(input: RespArray) -> Result<RespHrandfieldCommand, RespCommandError> {
+        let (key_element, count, with_values) = match input.elements.as_slice() {
+            [_, key_element] => (key_element, None, false),
+            [_, key_element, count_element] => (key_element, Some(get_argument_integer(count_element)?), false),
+            [_, key_element, count_element, option] if get_argument_string(option)?.eq_ignore_ascii_case("WITHVALUES") => {
+                (key_element, Some(get_argument_integer(count_element)?), true)
+            }
+            [_, _, _, _] => return Err(RespCommandError::SyntaxError),
+            _ => return Err(RespCommandError::WrongNumberOfArguments("hrandfield".into())),
+        };
+
+        if count.is_some_and(| c | c < 0 && c.unsigned_abs() > RANDOM_MAX_REPEATED_COUNT) {
+            return Err(RespCommandError::InvalidArgument("value is out of range".into()));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespHrandfieldCommand { key, count, with_values })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespHscanCommand {
+    pub key: Box<[u8]>,
+    pub cursor: u64,
+    pub pattern: Option<Vec<u8>>,
+    pub count: usize,
+    /// Whether to leave the values out of the reply
+    pub no_values: bool,
+}
+
+impl RespCommandConstructor for RespHscanCommand {
+    fn from_array(input: RespArray) -> Result<RespHscanCommand, RespCommandError> {
+        let [_, key_element, cursor_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("hscan".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let cursor = get_argument_cursor(cursor_element)?;
+
+        let mut pattern = None;
+        let mut count = HSCAN_DEFAULT_COUNT;
+        let mut no_values = false;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "MATCH" => {
+                    let pattern_element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                    pattern = Some(get_argument_bytes(pattern_element)?);
+                }
+                "COUNT" => {
+                    let count_element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                    count = match get_argument_integer(count_element)? {
+                        c if c >= 1 => c as usize,
+                        _ => return Err(RespCommandError::SyntaxError),
+                    };
+                }
+                "NOVALUES" => no_values = true,
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespHscanCommand { key, cursor, pattern, count, no_values })
+    }
+}
+
+fn hset(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHsetCommand::from_array(input)?;
+
+    let hash = ctx.db.get_or_insert_with(&command.key, || Value::Hash(Hash::new())).value.as_hash_mut()?;
+
+    let added = command.fields.into_iter()
+        .map(| (field, value) | hash.insert(field, value))
+        .filter(Option::is_none)
+        .count();
+
+    Ok(RespElement::new_integer(added as isize))
+}
+
+fn hsetnx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldValueCommand::from_array(input)?;
+    let value = get_argument_bytes(&command.value)?;
+
+    let hash = ctx.db.get_or_insert_with(&command.key, || Value::Hash(Hash::new())).value.as_hash_mut()?;
+
+    if hash.contains_key(&command.field) {
+        return Ok(RespElement::new_integer(0));
+    }
+
+    hash.insert(command.field, value);
+
+    Ok(RespElement::new_integer(1))
+}
+
+fn hget(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldCommand::from_array(input)?;
+
+    let value = ctx.db.get_hash(&command.key)?.and_then(| hash | hash.get(&command.field));
+
+    Ok(value.map_or_else(RespElement::new_null, | v | RespElement::new_bulk_string(v)))
+}
+
+fn hmget(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldsCommand::from_array(input)?;
+
+    let hash = ctx.db.get_hash(&command.key)?;
+
+    let values = command.fields.iter()
+        .map(| field | hash.and_then(| h | h.get(field)))
+        .map(| value | value.map_or_else(RespElement::new_null, | v | RespElement::new_bulk_string(v)))
+        .collect();
+
+    Ok(RespElement::new_array(values))
+}
+
+fn hdel(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldsCommand::from_array(input)?;
+
+    let Some(hash) = ctx.db.get_hash_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let deleted = command.fields.iter()
+        .filter(| field | hash.remove(field).is_some())
+        .count();
+
+    ctx.db.delete_if_empty(&command.key);
+
+    Ok(RespElement::new_integer(deleted as isize))
+}
+
+fn hexists(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldCommand::from_array(input)?;
+
+    let exists = ctx.db.get_hash(&command.key)?.is_some_and(| hash | hash.contains_key(&command.field));
+
+    Ok(RespElement::new_integer(exists as isize))
+}
+
+fn hlen(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_hash(&command.key)?.map_or(0, | hash | hash.len());
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn hstrlen(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldCommand::from_array(input)?;
+
+    let length = ctx.db.get_hash(&command.key)?
+        .and_then(| hash | hash.get(&command.field))
+        .map_or(0, | value | value.len());
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn hkeys(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let fields = ctx.db.get_hash(&command.key)?
+        .map(| hash | hash.keys().map(RespElement::new_bulk_string).collect())
+        .unwrap_or_default();
+
+    Ok(RespElement::new_array(fields))
+}
+
+fn hvals(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let values = ctx.db.get_hash(&command.key)?
+        .map(| hash | hash.values().map(| v | RespElement::new_bulk_string(v)).collect())
+        .unwrap_or_default();
+
+    Ok(RespElement::new_array(values))
+}
+
+/// Replies with a map of the fields to their values, which is flattened into
+/// an array for RESP2 clients
+fn hgetall(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let entries = ctx.db.get_hash(&command.key)?
+        .map(| hash | {
+            hash.iter()
+                .map(| (f, v) | (RespElement::new_bulk_string(f), RespElement::new_bulk_string(v)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(RespElement::new_map(entries))
+}
+
+fn hincrby(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldValueCommand::from_array(input)?;
+    let increment = get_argument_integer(&command.value)?;
+
+    let hash = ctx.db.get_or_insert_with(&command.key, || Value::Hash(Hash::new())).value.as_hash_mut()?;
+
+    let current = match hash.get(&command.field) {
+        Some(value) => parse_integer(value)
+            .ok_or_else(|| RespCommandError::InvalidArgument("hash value is not an integer".into()))?,
+        None => 0,
+    };
+
+    let Some(updated) = current.checked_add(increment) else {
+        return Err(RespCommandError::InvalidArgument("increment or decrement would overflow".into()));
+    };
+
+    // like Redis, incrementing a field keeps its TTL
+    hash.update(command.field, updated.to_string().into_bytes());
+
+    Ok(RespElement::new_integer(updated as isize))
+}
+
+/// Replies with the new value as a bulk string, the same way it's stored
+fn hincrbyfloat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldValueCommand::from_array(input)?;
+    let increment = get_argument_float(&command.value)?;
+
+    if !increment.is_finite() {
+        return Err(RespCommandError::InvalidArgument("value is NaN or Infinity".into()));
+    }
+
+    let hash = ctx.db.get_or_insert_with(&command.key, || Value::Hash(Hash::new())).value.as_hash_mut()?;
+
+    let current = match hash.get(&command.field) {
+        Some(value) => str::from_utf8(value).ok()
+            .and_then(parse_double)
+            .filter(| f | !f.is_nan())
+            .ok_or_else(|| RespCommandError::InvalidArgument("hash value is not a float".into()))?,
+        None => 0.0,
+    };
+
+    let updated = current + increment;
+
+    if !updated.is_finite() {
+        return Err(RespCommandError::InvalidArgument("increment would produce NaN or Infinity".into()));
+    }
+
+    let formatted = format_double(updated).into_bytes();
+
+    hash.update(command.field, formatted.clone());
+
+    Ok(RespElement::new_bulk_string(&formatted))
+}
+
+fn hrandfield(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHrandfieldCommand::from_array(input)?;
+
+    let protocol = ctx.protocol;
+    let hash = ctx.db.get_hash(&command.key)?;
+
+    let Some(count) = command.count else {
+        let field = hash.and_then(Hash::random_entry).map(| (f, _) | f);
+
+        return Ok(field.map_or_else(RespElement::new_null, RespElement::new_bulk_string));
+    };
+
+    let Some(hash) = hash else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    // a negative count allows the same field to be picked more than once, so
+    // the reply can be longer than the hash
+    let picked: Vec<_> = if count < 0 {
+        (0 .. count.unsigned_abs()).filter_map(| _ | hash.random_entry()).collect()
+    } else {
+        let entries: Vec<_> = hash.iter().collect();
+
+        random::sample_indices(entries.len(), count as usize).into_iter().map(| i | entries[i]).collect()
+    };
+
+    let picked = picked.into_iter();
+
+    let reply = if command.with_values && protocol == RespProtocol::Resp3 {
+        // RESP3 clients get each field and value as a pair, rather than one
+        // flat array
+        picked.map(| (f, v) | RespElement::new_array(vec![RespElement::new_bulk_string(f), RespElement::new_bulk_string(v)])).collect()
+    } else if command.with_values {
+        picked.flat_map(| (f, v) | [RespElement::new_bulk_string(f), RespElement::new_bulk_string(v)]).collect()
+    } else {
+        picked.map(| (f, _) | RespElement::new_bulk_string(f)).collect()
+    };
+
+    Ok(RespElement::new_array(reply))
+}
+
+/// Iterates over the hash the same way `SCAN` iterates over the keyspace, so
+/// every field that exists for the whole of an iteration is returned at least
+/// once, though some fields can be returned more than once
+fn hscan(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHscanCommand::from_array(input)?;
+
+    let Some(hash) = ctx.db.get_hash(&command.key)? else {
+        return Ok(get_scan_reply(0, Vec::new()));
+    };
+
+    let mut entries = Vec::new();
+    let mut cursor = command.cursor;
+
+    for _ in 0 .. command.count.saturating_mul(10) {
+        cursor = hash.scan(cursor, | field, value | entries.push((field.to_vec(), value.clone())));
+
+        if cursor == 0 || entries.len() >= command.count {
+            break;
+        }
+    }
+
+    let mut elements = Vec::new();
+
+    for (field, value) in entries {
+        if command.pattern.as_ref().is_some_and(| p | !glob::matches(p, &field)) {
+            continue;
+        }
+
+        elements.push(RespElement::new_bulk_string(&field));
+
+        if !command.no_values {
+            elements.push(RespElement::new_bulk_string(&value));
+        }
+    }
+
+    Ok(get_scan_reply(cursor, elements))
+}
@@ -0,0 +1,323 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::commands::expire::{ExpireUnit, to_unix_millis};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::{ExpireCondition, FieldExpireOutcome};
+
+/// The latest time that a field can be set to expire at, which is the same
+/// limit that Redis has (it keeps field TTLs in 48 bits)
+const MAX_FIELD_EXPIRY: i64 = (1 << 48) - 1;
+
+pub const HEXPIRE: CommandSpec = CommandSpec {
+    name: "hexpire",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Sets the expiration time of one or more hash fields in seconds.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hexpire),
+};
+
+pub const HPEXPIRE: CommandSpec = CommandSpec {
+    name: "hpexpire",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Sets the expiration time of one or more hash fields in milliseconds.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hpexpire),
+};
+
+pub const HEXPIREAT: CommandSpec = CommandSpec {
+    name: "hexpireat",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Sets the expiration time of one or more hash fields to a Unix timestamp.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hexpireat),
+};
+
+pub const HPEXPIREAT: CommandSpec = CommandSpec {
+    name: "hpexpireat",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Sets the expiration time of one or more hash fields to a Unix milliseconds timestamp.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hpexpireat),
+};
+
+pub const HTTL: CommandSpec = CommandSpec {
+    name: "httl",
+    arity: -5,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the expiration time in seconds of one or more hash fields.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(httl),
+};
+
+pub const HPTTL: CommandSpec = CommandSpec {
+    name: "hpttl",
+    arity: -5,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the expiration time in milliseconds of one or more hash fields.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hpttl),
+};
+
+pub const HEXPIRETIME: CommandSpec = CommandSpec {
+    name: "hexpiretime",
+    arity: -5,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the expiration time of one or more hash fields as a Unix timestamp.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hexpiretime),
+};
+
+pub const HPEXPIRETIME: CommandSpec = CommandSpec {
+    name: "hpexpiretime",
+    arity: -5,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Returns the expiration time of one or more hash fields as a Unix milliseconds timestamp.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hpexpiretime),
+};
+
+pub const HPERSIST: CommandSpec = CommandSpec {
+    name: "hpersist",
+    arity: -5,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hash",
+    summary: "Removes the expiration time of one or more hash fields.",
+    since: "7.4.0",
+    handler: CommandHandler::Store(hpersist),
+};
+
+/// A call to `HEXPIRE`, `HPEXPIRE`, `HEXPIREAT` or `HPEXPIREAT`
+#[derive(Debug)]
+pub struct RespHashExpireCommand {
+    pub key: Box<[u8]>,
+    pub time: i64,
+    pub condition: ExpireCondition,
+    pub fields: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespHashExpireCommand {
+    fn from_array(input: RespArray) -> Result<RespHashExpireCommand, RespCommandError> {
+        let [_, key_element, time_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let time = get_argument_integer(time_element)?;
+
+        if time < 0 {
+            return Err(RespCommandError::InvalidArgument("invalid expire time, must be >= 0".into()));
+        }
+
+        let mut condition = ExpireCondition::default();
+
+        // unlike `EXPIRE`, only one condition can be given, right before `FIELDS`
+        let fields = match rest {
+            [option, fields @ ..] if !get_argument_string(option)?.eq_ignore_ascii_case("FIELDS") => {
+                match get_argument_string(option)?.to_uppercase().as_str() {
+                    "NX" => condition.if_none = true,
+                    "XX" => condition.if_some = true,
+                    "GT" => condition.if_later = true,
+                    "LT" => condition.if_earlier = true,
+                    _ => return Err(missing_fields_error()),
+                }
+
+                parse_fields(fields)?
+            }
+            fields => parse_fields(fields)?,
+        };
+
+        Ok(RespHashExpireCommand { key, time, condition, fields })
+    }
+}
+
+/// A call to a command that takes a key followed by `FIELDS numfields field
+/// [field ...]`, e.g. `HTTL` or `HPERSIST`
+#[derive(Debug)]
+pub struct RespHashFieldListCommand {
+    pub key: Box<[u8]>,
+    pub fields: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespHashFieldListCommand {
+    fn from_array(input: RespArray) -> Result<RespHashFieldListCommand, RespCommandError> {
+        let [_, key_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let fields = parse_fields(rest)?;
+
+        Ok(RespHashFieldListCommand { key, fields })
+    }
+}
+
+/// Parses the `FIELDS numfields field [field ...]` arguments that the hash
+/// field TTL commands end with
+fn parse_fields(elements: &[RespElement]) -> Result<Vec<Vec<u8>>, RespCommandError> {
+    let [keyword, count_element, field_elements @ ..] = elements else {
+        return Err(missing_fields_error());
+    };
+
+    if !get_argument_string(keyword)?.eq_ignore_ascii_case("FIELDS") {
+        return Err(missing_fields_error());
+    }
+
+    let count = match get_argument_integer(count_element) {
+        Ok(count) if count > 0 => count as usize,
+        _ => return Err(RespCommandError::InvalidArgument("Parameter `numFields` should be greater than 0".into())),
+    };
+
+    if count != field_elements.len() {
+        return Err(RespCommandError::InvalidArgument("The `numfields` parameter must match the number of arguments".into()));
+    }
+
+    field_elements.iter().map(get_argument_bytes).collect()
+}
+
+fn missing_fields_error() -> RespCommandError {
+    RespCommandError::InvalidArgument("Mandatory argument FIELDS is missing or not at the right position".into())
+}
+
+fn hexpire(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_field_expiry(ctx, input, "hexpire", ExpireUnit::Seconds)
+}
+
+fn hpexpire(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_field_expiry(ctx, input, "hpexpire", ExpireUnit::Milliseconds)
+}
+
+fn hexpireat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_field_expiry(ctx, input, "hexpireat", ExpireUnit::UnixSeconds)
+}
+
+fn hpexpireat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    set_field_expiry(ctx, input, "hpexpireat", ExpireUnit::UnixMilliseconds)
+}
+
+/// Replies with an array holding one of these for each field: -2 if the field
+/// doesn't exist, 0 if the condition wasn't met, 1 if the TTL was set, or 2 if
+/// the time had already passed and the field was deleted
+fn set_field_expiry(ctx: &mut CommandContext, input: RespArray, name: &str, unit: ExpireUnit) -> CommandResult {
+    let command = RespHashExpireCommand::from_array(input)?;
+
+    let when = to_unix_millis(command.time, unit, ctx.db.now_millis())
+        .filter(| when | *when <= MAX_FIELD_EXPIRY)
+        .ok_or_else(|| RespCommandError::InvalidExpireTime(name.into()))?;
+
+    // the type is checked up front, so that the reply is either an error or
+    // a result for every field
+    ctx.db.get_hash(&command.key)?;
+
+    let mut results = Vec::with_capacity(command.fields.len());
+
+    for field in &command.fields {
+        let result = match ctx.db.expire_field(&command.key, field, when as u64, command.condition)? {
+            FieldExpireOutcome::NoSuchField => -2,
+            FieldExpireOutcome::ConditionNotMet => 0,
+            FieldExpireOutcome::Set => 1,
+            FieldExpireOutcome::Deleted => 2,
+        };
+
+        results.push(RespElement::new_integer(result));
+    }
+
+    Ok(RespElement::new_array(results))
+}
+
+fn httl(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_field_expiries(ctx, input, | when, now | (when.saturating_sub(now) + 500) / 1000)
+}
+
+fn hpttl(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_field_expiries(ctx, input, | when, now | when.saturating_sub(now))
+}
+
+fn hexpiretime(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_field_expiries(ctx, input, | when, _ | when / 1000)
+}
+
+fn hpexpiretime(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_field_expiries(ctx, input, | when, _ | when)
+}
+
+/// Replies with an array holding, for each field, its expiry time converted by
+/// `convert` (which is given the expiry time and the current time, both in
+/// Unix milliseconds), or -2 if the field doesn't exist and -1 if it has no TTL
+fn get_field_expiries(ctx: &mut CommandContext, input: RespArray, convert: fn(u64, u64) -> u64) -> CommandResult {
+    let command = RespHashFieldListCommand::from_array(input)?;
+
+    let now = ctx.db.now_millis();
+    let hash = ctx.db.get_hash(&command.key)?;
+
+    let results = command.fields.iter()
+        .map(| field | match hash.and_then(| h | h.get_expiry(field)) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(when)) => convert(when, now) as isize,
+        })
+        .map(RespElement::new_integer)
+        .collect();
+
+    Ok(RespElement::new_array(results))
+}
+
+/// Replies with an array holding, for each field, -2 if the field doesn't
+/// exist, -1 if it has no TTL, or 1 if its TTL was removed
+fn hpersist(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespHashFieldListCommand::from_array(input)?;
+
+    let mut hash = ctx.db.get_hash_mut(&command.key)?;
+
+    let results = command.fields.iter()
+        .map(| field | match hash.as_mut().and_then(| h | h.persist(field)) {
+            None => -2,
+            Some(false) => -1,
+            Some(true) => 1,
+        })
+        .map(RespElement::new_integer)
+        .collect();
+
+    Ok(RespElement::new_array(results))
+}
@@ -0,0 +1,138 @@
+use crate::resp::commands::{
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    ConnectionState,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_string,
+};
+use crate::resp::types::RespArray;
+use crate::resp::{RespElement, RespProtocol};
+
+pub const HELLO: CommandSpec = CommandSpec {
+    name: "hello",
+    arity: -1,
+    flags: &[CommandFlag::Fast, CommandFlag::NoScript, CommandFlag::LoadingOk],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "connection",
+    summary: "Handshakes with the server, and optionally switches the protocol version.",
+    since: "6.0.0",
+    handler: CommandHandler::Connection(hello),
+};
+
+#[derive(Debug)]
+pub struct RespHelloCommand {
+    pub protocol: Option<RespProtocol>,
+    pub auth: Option<(String, String)>,
+    pub client_name: Option<String>,
+}
+
+impl RespCommandConstructor for RespHelloCommand {
+    fn from_array(input: RespArray) -> Result<RespHelloCommand, RespCommandError> {
+        let mut arguments = input.elements.iter().skip(1);
+
+        let protocol = match arguments.next() {
+            Some(element) => {
+                let version = get_argument_string(element)?;
+
+                match version.parse::<isize>() {
+                    Ok(2) => Some(RespProtocol::Resp2),
+                    Ok(3) => Some(RespProtocol::Resp3),
+                    Ok(_) => return Err(RespCommandError::UnsupportedProtocol),
+                    Err(_) => return Err(RespCommandError::InvalidArgument(
+                        "Protocol version is not an integer or out of range".into()
+                    )),
+                }
+            }
+            None => None,
+        };
+
+        let mut auth = None;
+        let mut client_name = None;
+
+        while let Some(option) = arguments.next() {
+            let option = get_argument_string(option)?;
+
+            match option.to_uppercase().as_str() {
+                "AUTH" => {
+                    let (Some(username), Some(password)) = (arguments.next(), arguments.next()) else {
+                        return Err(RespCommandError::InvalidArgument(
+                            format!("Syntax error in HELLO option '{option}'")
+                        ));
+                    };
+
+                    auth = Some((get_argument_string(username)?, get_argument_string(password)?));
+                }
+                "SETNAME" => {
+                    let Some(name) = arguments.next() else {
+                        return Err(RespCommandError::InvalidArgument(
+                            format!("Syntax error in HELLO option '{option}'")
+                        ));
+                    };
+
+                    let name = get_argument_string(name)?;
+
+                    // names are shown in space separated lists, e.g. by CLIENT LIST
+                    if name.bytes().any(| byte | !(b'!'..=b'~').contains(&byte)) {
+                        return Err(RespCommandError::InvalidArgument(
+                            "Client names cannot contain spaces, newlines or special characters.".into()
+                        ));
+                    }
+
+                    client_name = Some(name);
+                }
+                _ => return Err(RespCommandError::InvalidArgument(
+                    format!("Syntax error in HELLO option '{option}'")
+                )),
+            }
+        }
+
+        Ok(RespHelloCommand { protocol, auth, client_name })
+    }
+}
+
+/// The version of Redis that the server reports itself as being compatible
+/// with, since some clients enable features based on it
+pub const REDIS_COMPATIBLE_VERSION: &str = "7.4.0";
+
+impl RespHelloCommand {
+    /// Builds the reply describing the server and the connection, which is
+    /// sent as a map to RESP3 clients and a flat array to RESP2 clients
+    pub fn get_server_info(client_id: usize, protocol: RespProtocol) -> RespElement {
+        RespElement::new_map(vec![
+            (RespElement::new_bulk_string(b"server"), RespElement::new_bulk_string(b"redis")),
+            (RespElement::new_bulk_string(b"version"), RespElement::new_bulk_string(REDIS_COMPATIBLE_VERSION.as_bytes())),
+            (RespElement::new_bulk_string(b"proto"), RespElement::new_integer(protocol.version())),
+            (RespElement::new_bulk_string(b"id"), RespElement::new_integer(client_id as isize)),
+            (RespElement::new_bulk_string(b"mode"), RespElement::new_bulk_string(b"standalone")),
+            (RespElement::new_bulk_string(b"role"), RespElement::new_bulk_string(b"master")),
+            (RespElement::new_bulk_string(b"modules"), RespElement::new_array(Vec::new())),
+        ])
+    }
+}
+
+fn hello(connection: &mut ConnectionState, input: RespArray) -> CommandResult {
+    let command = RespHelloCommand::from_array(input)?;
+
+    if let Some((username, _)) = command.auth {
+        // there's no ACL support, so only the default user (which doesn't
+        // require a password) can authenticate
+        if username != "default" {
+            return Err(RespCommandError::InvalidPassword);
+        }
+    }
+
+    // the reply is sent using the newly selected protocol
+    if let Some(protocol) = command.protocol {
+        connection.protocol = protocol;
+    }
+
+    if let Some(name) = command.client_name {
+        connection.name = Some(name);
+    }
+
+    Ok(RespHelloCommand::get_server_info(connection.id, connection.protocol))
+}
@@ -0,0 +1,260 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::commands::keyspace::RespKeysCommand;
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::{HyperLogLog, HyperLogLogEncoding, SparseOpcode, StringValue, Value};
+
+pub const PFADD: CommandSpec = CommandSpec {
+    name: "pfadd",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "hyperloglog",
+    summary: "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist.",
+    since: "2.8.9",
+    handler: CommandHandler::Store(pfadd),
+};
+
+pub const PFCOUNT: CommandSpec = CommandSpec {
+    name: "pfcount",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "hyperloglog",
+    summary: "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s).",
+    since: "2.8.9",
+    handler: CommandHandler::Store(pfcount),
+};
+
+pub const PFMERGE: CommandSpec = CommandSpec {
+    name: "pfmerge",
+    arity: -2,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "hyperloglog",
+    summary: "Merges one or more HyperLogLog values into a single key.",
+    since: "2.8.9",
+    handler: CommandHandler::Store(pfmerge),
+};
+
+pub const PFDEBUG: CommandSpec = CommandSpec {
+    name: "pfdebug",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Admin],
+    keys: (2, 2, 1),
+    get_keys: None,
+    group: "hyperloglog",
+    summary: "Internal commands for debugging HyperLogLog values.",
+    since: "2.8.9",
+    handler: CommandHandler::Store(pfdebug),
+};
+
+/// A call to `PFADD`
+#[derive(Debug)]
+pub struct RespPfaddCommand {
+    pub key: Box<[u8]>,
+    pub elements: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespPfaddCommand {
+    fn from_array(input: RespArray) -> Result<RespPfaddCommand, RespCommandError> {
+        let [_, key_element, element_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let elements = element_elements.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespPfaddCommand { key, elements })
+    }
+}
+
+/// A call to `PFDEBUG`
+#[derive(Debug)]
+pub struct RespPfdebugCommand {
+    pub subcommand: String,
+    pub key: Box<[u8]>,
+}
+
+impl RespCommandConstructor for RespPfdebugCommand {
+    fn from_array(input: RespArray) -> Result<RespPfdebugCommand, RespCommandError> {
+        let [_, subcommand_element, key_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let subcommand = get_argument_string(subcommand_element)?;
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespPfdebugCommand { subcommand, key })
+    }
+}
+
+/// Looks up the HyperLogLog stored at the key, or returns an error if the key
+/// holds something else (including a string that isn't a HyperLogLog)
+fn get_hyperloglog(ctx: &mut CommandContext, key: &[u8]) -> Result<Option<HyperLogLog>, RespCommandError> {
+    match ctx.db.get_string(key)? {
+        Some(value) => Ok(Some(HyperLogLog::from_bytes(value.as_bytes().into_owned())?)),
+        None => Ok(None),
+    }
+}
+
+/// Writes the HyperLogLog back to the key, which keeps its TTL
+fn store_hyperloglog(ctx: &mut CommandContext, key: &[u8], hll: HyperLogLog) -> Result<(), RespCommandError> {
+    let value = ctx.db.get_or_insert_with(key, || Value::String(StringValue::default())).value.as_string_mut()?;
+
+    *value = StringValue::new(hll.into_bytes());
+
+    Ok(())
+}
+
+/// Replies with `1` if the key was created or any of its registers changed
+/// (meaning the estimated cardinality may have too), or `0` otherwise
+fn pfadd(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespPfaddCommand::from_array(input)?;
+
+    let (mut hll, created) = match get_hyperloglog(ctx, &command.key)? {
+        Some(hll) => (hll, false),
+        None => (HyperLogLog::new(), true),
+    };
+
+    let changed = hll.add(command.elements.iter().map(Vec::as_slice))?;
+
+    if created || changed {
+        store_hyperloglog(ctx, &command.key, hll)?;
+    }
+
+    Ok(RespElement::new_integer((created || changed) as isize))
+}
+
+/// Replies with the estimated cardinality of the union of the HyperLogLogs,
+/// where keys that don't exist are treated as empty. When there's only one
+/// key, the cardinality is cached in it until it next changes.
+fn pfcount(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeysCommand::from_array(input)?;
+
+    if let [key] = command.keys.as_slice() {
+        let Some(mut hll) = get_hyperloglog(ctx, key)? else {
+            return Ok(RespElement::new_integer(0));
+        };
+
+        let cached = hll.is_count_cached();
+        let count = hll.count()?;
+
+        if !cached {
+            store_hyperloglog(ctx, key, hll)?;
+        }
+
+        return Ok(RespElement::new_integer(count as isize));
+    }
+
+    let mut registers = vec![0; HyperLogLog::REGISTERS];
+
+    for key in &command.keys {
+        if let Some(hll) = get_hyperloglog(ctx, key)? {
+            hll.merge_into(&mut registers)?;
+        }
+    }
+
+    Ok(RespElement::new_integer(HyperLogLog::estimate(&registers) as isize))
+}
+
+/// Stores the union of the HyperLogLogs in the destination key, which is
+/// included in the union if it already exists. The result is stored densely
+/// if any of the HyperLogLogs were.
+fn pfmerge(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeysCommand::from_array(input)?;
+
+    let mut registers = vec![0; HyperLogLog::REGISTERS];
+    let mut encoding = HyperLogLogEncoding::Sparse;
+
+    for key in &command.keys {
+        if let Some(hll) = get_hyperloglog(ctx, key)? {
+            if hll.encoding() == HyperLogLogEncoding::Dense {
+                encoding = HyperLogLogEncoding::Dense;
+            }
+
+            hll.merge_into(&mut registers)?;
+        }
+    }
+
+    let destination = &command.keys[0];
+    let mut hll = get_hyperloglog(ctx, destination)?.unwrap_or_default();
+
+    hll.set_registers(&registers, encoding);
+    store_hyperloglog(ctx, destination, hll)?;
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+/// Runs one of the debugging subcommands:
+///
+/// - `GETREG` converts the HyperLogLog to the dense representation, and
+///   replies with its registers
+/// - `DECODE` replies with the opcodes of the sparse representation
+/// - `ENCODING` replies with the name of the representation
+/// - `TODENSE` converts the HyperLogLog to the dense representation, and
+///   replies with whether it wasn't already
+fn pfdebug(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespPfdebugCommand::from_array(input)?;
+
+    let Some(mut hll) = get_hyperloglog(ctx, &command.key)? else {
+        return Err(RespCommandError::InvalidArgument("The specified key does not exist".into()));
+    };
+
+    match command.subcommand.to_uppercase().as_str() {
+        "GETREG" => {
+            let converted = hll.convert_to_dense()?;
+            let registers = hll.registers()?;
+
+            if converted {
+                store_hyperloglog(ctx, &command.key, hll)?;
+            }
+
+            Ok(RespElement::new_array(registers.into_iter().map(| r | RespElement::new_integer(r as isize)).collect()))
+        }
+        "DECODE" => {
+            let Some(opcodes) = hll.sparse_opcodes() else {
+                return Err(RespCommandError::InvalidArgument("HLL encoding is not sparse".into()));
+            };
+
+            let decoded = opcodes?.iter()
+                .map(| opcode | match opcode {
+                    SparseOpcode::Zero(len) => format!("z:{len}"),
+                    SparseOpcode::XZero(len) => format!("Z:{len}"),
+                    SparseOpcode::Val(value, len) => format!("v:{value},{len}"),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            Ok(RespElement::new_simple_string(&decoded))
+        }
+        "ENCODING" => Ok(RespElement::new_simple_string(hll.encoding().name())),
+        "TODENSE" => {
+            let converted = hll.convert_to_dense()?;
+
+            if converted {
+                store_hyperloglog(ctx, &command.key, hll)?;
+            }
+
+            Ok(RespElement::new_integer(converted as isize))
+        }
+        _ => Err(RespCommandError::InvalidArgument(format!("Unknown PFDEBUG subcommand '{}'", command.subcommand))),
+    }
+}
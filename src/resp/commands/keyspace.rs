@@ -0,0 +1,448 @@
+use crate::glob;
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_cursor,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+    get_scan_reply,
+};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+
+/// How many keys `SCAN` aims to return per call when it isn't given a `COUNT`
+const SCAN_DEFAULT_COUNT: usize = 10;
+
+/// The names that `SCAN` accepts for its `TYPE` option, which are the ones
+/// that `TYPE` replies with
+const TYPE_NAMES: &[&str] = &["string", "list", "hash", "set", "zset", "stream"];
+
+pub const DEL: CommandSpec = CommandSpec {
+    name: "del",
+    arity: -2,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Deletes one or more keys.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(del),
+};
+
+pub const UNLINK: CommandSpec = CommandSpec {
+    name: "unlink",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Asynchronously deletes one or more keys.",
+    since: "4.0.0",
+    handler: CommandHandler::Store(del),
+};
+
+pub const EXISTS: CommandSpec = CommandSpec {
+    name: "exists",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Determines whether one or more keys exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(exists),
+};
+
+pub const TOUCH: CommandSpec = CommandSpec {
+    name: "touch",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns the number of existing keys out of those specified after updating the time they were last accessed.",
+    since: "3.2.1",
+    handler: CommandHandler::Store(exists),
+};
+
+pub const TYPE: CommandSpec = CommandSpec {
+    name: "type",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Determines the type of value stored at a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(get_type),
+};
+
+pub const RENAME: CommandSpec = CommandSpec {
+    name: "rename",
+    arity: 3,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Renames a key and overwrites the destination.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(rename),
+};
+
+pub const RENAMENX: CommandSpec = CommandSpec {
+    name: "renamenx",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Renames a key only when the target key name doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(renamenx),
+};
+
+pub const COPY: CommandSpec = CommandSpec {
+    name: "copy",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "generic",
+    summary: "Copies the value of a key to a new key.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(copy),
+};
+
+pub const KEYS: CommandSpec = CommandSpec {
+    name: "keys",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns all key names that match a pattern.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(keys),
+};
+
+pub const RANDOMKEY: CommandSpec = CommandSpec {
+    name: "randomkey",
+    arity: 1,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "generic",
+    summary: "Returns a random key name from the database.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(randomkey),
+};
+
+pub const SCAN: CommandSpec = CommandSpec {
+    name: "scan",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "generic",
+    summary: "Iterates over the key names in the database.",
+    since: "2.8.0",
+    handler: CommandHandler::Store(scan),
+};
+
+pub const DBSIZE: CommandSpec = CommandSpec {
+    name: "dbsize",
+    arity: 1,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "server",
+    summary: "Returns the number of keys in the database.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(dbsize),
+};
+
+/// A call to a command that takes one or more keys, e.g. `DEL` or `EXISTS`
+#[derive(Debug)]
+pub struct RespKeysCommand {
+    pub keys: Vec<Box<[u8]>>,
+}
+
+impl RespCommandConstructor for RespKeysCommand {
+    fn from_array(input: RespArray) -> Result<RespKeysCommand, RespCommandError> {
+        if input.elements.len() < 2 {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let mut keys = Vec::with_capacity(input.elements.len() - 1);
+
+        for element in &input.elements[1..] {
+            keys.push(get_argument_bytes(element)?.into_boxed_slice());
+        }
+
+        Ok(RespKeysCommand { keys })
+    }
+}
+
+/// A call to `KEYS`
+#[derive(Debug)]
+pub struct RespPatternCommand {
+    pub pattern: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespPatternCommand {
+    fn from_array(input: RespArray) -> Result<RespPatternCommand, RespCommandError> {
+        let [_, pattern_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let pattern = get_argument_bytes(pattern_element)?;
+
+        Ok(RespPatternCommand { pattern })
+    }
+}
+
+/// A call to `SCAN`
+#[derive(Debug)]
+pub struct RespScanCommand {
+    pub cursor: u64,
+    pub pattern: Option<Vec<u8>>,
+    pub count: usize,
+    /// Only return keys holding this type of value, named the way that `TYPE`
+    /// names it
+    pub type_name: Option<String>,
+}
+
+impl RespCommandConstructor for RespScanCommand {
+    fn from_array(input: RespArray) -> Result<RespScanCommand, RespCommandError> {
+        let [_, cursor_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("scan".into()));
+        };
+
+        let cursor = get_argument_cursor(cursor_element)?;
+
+        let mut pattern = None;
+        let mut count = SCAN_DEFAULT_COUNT;
+        let mut type_name = None;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "MATCH" => {
+                    let pattern_element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                    pattern = Some(get_argument_bytes(pattern_element)?);
+                }
+                "COUNT" => {
+                    let count_element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                    count = match get_argument_integer(count_element)? {
+                        c if c >= 1 => c as usize,
+                        _ => return Err(RespCommandError::SyntaxError),
+                    };
+                }
+                "TYPE" => {
+                    let type_element = options.next().ok_or(RespCommandError::SyntaxError)?;
+                    let name = get_argument_string(type_element)?;
+
+                    if !TYPE_NAMES.contains(&name.to_lowercase().as_str()) {
+                        return Err(RespCommandError::InvalidArgument(format!("unknown type name '{name}'")));
+                    }
+
+                    type_name = Some(name.to_lowercase());
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespScanCommand { cursor, pattern, count, type_name })
+    }
+}
+
+/// A call to `RENAME` or `RENAMENX`
+#[derive(Debug)]
+pub struct RespRenameCommand {
+    pub from: Box<[u8]>,
+    pub to: Box<[u8]>,
+}
+
+impl RespCommandConstructor for RespRenameCommand {
+    fn from_array(input: RespArray) -> Result<RespRenameCommand, RespCommandError> {
+        let [_, from_element, to_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let from = get_argument_bytes(from_element)?.into_boxed_slice();
+        let to = get_argument_bytes(to_element)?.into_boxed_slice();
+
+        Ok(RespRenameCommand { from, to })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespCopyCommand {
+    pub from: Box<[u8]>,
+    pub to: Box<[u8]>,
+    /// Whether to overwrite the destination if it already exists
+    pub replace: bool,
+}
+
+impl RespCommandConstructor for RespCopyCommand {
+    fn from_array(input: RespArray) -> Result<RespCopyCommand, RespCommandError> {
+        let [_, from_element, to_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("copy".into()));
+        };
+
+        let from = get_argument_bytes(from_element)?.into_boxed_slice();
+        let to = get_argument_bytes(to_element)?.into_boxed_slice();
+
+        let mut replace = false;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "REPLACE" => replace = true,
+                "DB" => {
+                    let Some(db_element) = options.next() else {
+                        return Err(RespCommandError::SyntaxError);
+                    };
+
+                    // there's only the one database, so it's the only valid
+                    // destination
+                    if get_argument_integer(db_element)? != 0 {
+                        return Err(RespCommandError::InvalidArgument("DB index is out of range".into()));
+                    }
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespCopyCommand { from, to, replace })
+    }
+}
+
+/// Handles both `DEL` and `UNLINK`, since values are always freed straight away
+fn del(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeysCommand::from_array(input)?;
+
+    let deleted = command.keys.iter()
+        .filter(| key | ctx.db.delete(key))
+        .count();
+
+    Ok(RespElement::new_integer(deleted as isize))
+}
+
+/// Handles both `EXISTS` and `TOUCH`, since access times aren't tracked. Keys
+/// that are given more than once are counted more than once.
+fn exists(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeysCommand::from_array(input)?;
+
+    let existing = command.keys.iter()
+        .filter(| key | ctx.db.exists(key))
+        .count();
+
+    Ok(RespElement::new_integer(existing as isize))
+}
+
+fn get_type(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let name = match ctx.db.get(&command.key) {
+        Some(entry) => entry.value.type_name(),
+        None => "none",
+    };
+
+    Ok(RespElement::new_simple_string(name))
+}
+
+fn rename(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespRenameCommand::from_array(input)?;
+
+    match ctx.db.rename(&command.from, &command.to, true) {
+        Some(_) => Ok(RespElement::new_simple_string("OK")),
+        None => Err(RespCommandError::NoSuchKey),
+    }
+}
+
+fn renamenx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespRenameCommand::from_array(input)?;
+
+    match ctx.db.rename(&command.from, &command.to, false) {
+        Some(renamed) => Ok(RespElement::new_integer(renamed as isize)),
+        None => Err(RespCommandError::NoSuchKey),
+    }
+}
+
+fn copy(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespCopyCommand::from_array(input)?;
+
+    if command.from == command.to {
+        return Err(RespCommandError::InvalidArgument("source and destination objects are the same".into()));
+    }
+
+    let copied = ctx.db.copy(&command.from, &command.to, command.replace);
+
+    Ok(RespElement::new_integer(copied as isize))
+}
+
+/// Replies with every key that matches the pattern, in no particular order.
+/// This looks at every key in the database, so `SCAN` is better suited to
+/// large databases.
+fn keys(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespPatternCommand::from_array(input)?;
+
+    let keys = ctx.db.keys(| key | glob::matches(&command.pattern, key));
+
+    Ok(RespElement::new_array(keys.iter().map(| k | RespElement::new_bulk_string(k)).collect()))
+}
+
+/// Replies with a random key, or null if the database is empty
+fn randomkey(ctx: &mut CommandContext, _input: RespArray) -> CommandResult {
+    match ctx.db.random_key() {
+        Some(key) => Ok(RespElement::new_bulk_string(&key)),
+        None => Ok(RespElement::new_null()),
+    }
+}
+
+/// Iterates over the keys using the store's reverse binary cursors, so every
+/// key that exists for the whole iteration is returned at least once, even if
+/// the store is resized in between calls. `COUNT` is only a hint: `MATCH` and
+/// `TYPE` are applied after the keys are collected, so fewer keys (or none)
+/// can be returned before the iteration is complete.
+fn scan(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespScanCommand::from_array(input)?;
+
+    let (cursor, keys) = ctx.db.scan(command.cursor, command.count);
+
+    let mut elements = Vec::new();
+
+    for key in keys {
+        if command.pattern.as_ref().is_some_and(| p | !glob::matches(p, &key)) {
+            continue;
+        }
+
+        let is_other_type = command.type_name.as_ref()
+            .is_some_and(| t | ctx.db.get(&key).is_none_or(| e | e.value.type_name() != t));
+
+        if is_other_type {
+            continue;
+        }
+
+        elements.push(RespElement::new_bulk_string(&key));
+    }
+
+    Ok(get_scan_reply(cursor, elements))
+}
+
+fn dbsize(ctx: &mut CommandContext, _input: RespArray) -> CommandResult {
+    Ok(RespElement::new_integer(ctx.db.key_count() as isize))
+}
@@ -0,0 +1,1052 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_argument_timeout,
+    get_command_name,
+    get_counted_key_positions,
+    resolve_index,
+    resolve_range,
+};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::{Database, List, ListEnd, Value};
+
+pub const LPUSH: CommandSpec = CommandSpec {
+    name: "lpush",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lpush),
+};
+
+pub const RPUSH: CommandSpec = CommandSpec {
+    name: "rpush",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(rpush),
+};
+
+pub const LPUSHX: CommandSpec = CommandSpec {
+    name: "lpushx",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Prepends one or more elements to a list only when the list exists.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(lpushx),
+};
+
+pub const RPUSHX: CommandSpec = CommandSpec {
+    name: "rpushx",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Appends one or more elements to a list only when the list exists.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(rpushx),
+};
+
+pub const LPOP: CommandSpec = CommandSpec {
+    name: "lpop",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lpop),
+};
+
+pub const RPOP: CommandSpec = CommandSpec {
+    name: "rpop",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(rpop),
+};
+
+pub const LMPOP: CommandSpec = CommandSpec {
+    name: "lmpop",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (0, 0, 0),
+    get_keys: Some(get_lmpop_keys),
+    group: "list",
+    summary: "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(lmpop),
+};
+
+pub const LLEN: CommandSpec = CommandSpec {
+    name: "llen",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns the length of a list.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(llen),
+};
+
+pub const LRANGE: CommandSpec = CommandSpec {
+    name: "lrange",
+    arity: 4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns a range of elements from a list.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lrange),
+};
+
+pub const LINDEX: CommandSpec = CommandSpec {
+    name: "lindex",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns an element from a list by its index.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lindex),
+};
+
+pub const LSET: CommandSpec = CommandSpec {
+    name: "lset",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Sets the value of an element in a list by its index.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lset),
+};
+
+pub const LREM: CommandSpec = CommandSpec {
+    name: "lrem",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Removes elements from a list. Deletes the list if the last element was removed.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(lrem),
+};
+
+pub const LTRIM: CommandSpec = CommandSpec {
+    name: "ltrim",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Removes elements from both ends a list. Deletes the list if all elements were trimmed.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(ltrim),
+};
+
+pub const LINSERT: CommandSpec = CommandSpec {
+    name: "linsert",
+    arity: 5,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Inserts an element before or after another element in a list.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(linsert),
+};
+
+pub const LPOS: CommandSpec = CommandSpec {
+    name: "lpos",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns the index of matching elements in a list.",
+    since: "6.0.6",
+    handler: CommandHandler::Store(lpos),
+};
+
+pub const LMOVE: CommandSpec = CommandSpec {
+    name: "lmove",
+    arity: 5,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(lmove),
+};
+
+pub const RPOPLPUSH: CommandSpec = CommandSpec {
+    name: "rpoplpush",
+    arity: 3,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(rpoplpush),
+};
+
+pub const BLPOP: CommandSpec = CommandSpec {
+    name: "blpop",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (1, -2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(blpop),
+};
+
+pub const BRPOP: CommandSpec = CommandSpec {
+    name: "brpop",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (1, -2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(brpop),
+};
+
+pub const BLMPOP: CommandSpec = CommandSpec {
+    name: "blmpop",
+    arity: -5,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (0, 0, 0),
+    get_keys: Some(get_blmpop_keys),
+    group: "list",
+    summary: "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(blmpop),
+};
+
+pub const BLMOVE: CommandSpec = CommandSpec {
+    name: "blmove",
+    arity: 6,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(blmove),
+};
+
+pub const BRPOPLPUSH: CommandSpec = CommandSpec {
+    name: "brpoplpush",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "list",
+    summary: "Pops an element from a list, pushes it to another list and returns it. Block until an element is available otherwise. Deletes the list if the last element was popped.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(brpoplpush),
+};
+
+/// A call to `LPUSH`, `RPUSH`, `LPUSHX` or `RPUSHX`
+#[derive(Debug)]
+pub struct RespPushCommand {
+    pub key: Box<[u8]>,
+    pub elements: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespPushCommand {
+    fn from_array(input: RespArray) -> Result<RespPushCommand, RespCommandError> {
+        let [_, key_element, values @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if values.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let elements = values.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespPushCommand { key, elements })
+    }
+}
+
+/// A call to `LPOP` or `RPOP`
+#[derive(Debug)]
+pub struct RespPopCommand {
+    pub key: Box<[u8]>,
+    /// How many elements to pop; if this isn't given, the reply is a single
+    /// element rather than an array
+    pub count: Option<usize>,
+}
+
+impl RespCommandConstructor for RespPopCommand {
+    fn from_array(input: RespArray) -> Result<RespPopCommand, RespCommandError> {
+        let (key_element, count) = match input.elements.as_slice() {
+            [_, key_element] => (key_element, None),
+            [_, key_element, count_element] => (key_element, Some(get_positive_count(count_element)?)),
+            _ => return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input))),
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespPopCommand { key, count })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLmpopCommand {
+    pub keys: Vec<Box<[u8]>>,
+    pub end: ListEnd,
+    pub count: usize,
+}
+
+impl RespCommandConstructor for RespLmpopCommand {
+    fn from_array(input: RespArray) -> Result<RespLmpopCommand, RespCommandError> {
+        RespLmpopCommand::from_arguments(&input, 1)
+    }
+}
+
+impl RespLmpopCommand {
+    /// Parses the `numkeys key [key ...] LEFT | RIGHT [COUNT count]` arguments
+    /// that start at `count_position`, which is 1 for `LMPOP` and 2 for `BLMPOP`
+    fn from_arguments(input: &RespArray, count_position: usize) -> Result<RespLmpopCommand, RespCommandError> {
+        let positions = get_counted_key_positions(input, count_position)?;
+        let options_start = positions.last().map_or(count_position + 1, | p | p + 1);
+
+        let keys = positions.iter()
+            .map(| p | get_argument_bytes(&input.elements[*p]).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let (end, count) = parse_multi_pop_options(&input.elements[options_start ..])?;
+
+        Ok(RespLmpopCommand { keys, end, count })
+    }
+}
+
+/// A call to `BLPOP` or `BRPOP`
+#[derive(Debug)]
+pub struct RespBlockingPopCommand {
+    pub keys: Vec<Box<[u8]>>,
+    /// How long to wait in milliseconds, or `None` to wait forever
+    pub timeout: Option<u64>,
+}
+
+impl RespCommandConstructor for RespBlockingPopCommand {
+    fn from_array(input: RespArray) -> Result<RespBlockingPopCommand, RespCommandError> {
+        let [_, key_elements @ .., timeout_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if key_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let keys = key_elements.iter()
+            .map(| e | get_argument_bytes(e).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let timeout = get_argument_timeout(timeout_element)?;
+
+        Ok(RespBlockingPopCommand { keys, timeout })
+    }
+}
+
+/// A call to `LRANGE` or `LTRIM`
+#[derive(Debug)]
+pub struct RespListRangeCommand {
+    pub key: Box<[u8]>,
+    pub start: i64,
+    pub stop: i64,
+}
+
+impl RespCommandConstructor for RespListRangeCommand {
+    fn from_array(input: RespArray) -> Result<RespListRangeCommand, RespCommandError> {
+        let [_, key_element, start_element, stop_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let start = get_argument_integer(start_element)?;
+        let stop = get_argument_integer(stop_element)?;
+
+        Ok(RespListRangeCommand { key, start, stop })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLindexCommand {
+    pub key: Box<[u8]>,
+    pub index: i64,
+}
+
+impl RespCommandConstructor for RespLindexCommand {
+    fn from_array(input: RespArray) -> Result<RespLindexCommand, RespCommandError> {
+        let [_, key_element, index_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("lindex".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let index = get_argument_integer(index_element)?;
+
+        Ok(RespLindexCommand { key, index })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLsetCommand {
+    pub key: Box<[u8]>,
+    pub index: i64,
+    pub element: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespLsetCommand {
+    fn from_array(input: RespArray) -> Result<RespLsetCommand, RespCommandError> {
+        let [_, key_element, index_element, element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("lset".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let index = get_argument_integer(index_element)?;
+        let element = get_argument_bytes(element)?;
+
+        Ok(RespLsetCommand { key, index, element })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLremCommand {
+    pub key: Box<[u8]>,
+    /// How many matching elements to remove, starting from the head if this
+    /// is positive, or the tail if it's negative. Zero removes all of them.
+    pub count: i64,
+    pub element: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespLremCommand {
+    fn from_array(input: RespArray) -> Result<RespLremCommand, RespCommandError> {
+        let [_, key_element, count_element, element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("lrem".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let count = get_argument_integer(count_element)?;
+        let element = get_argument_bytes(element)?;
+
+        Ok(RespLremCommand { key, count, element })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLinsertCommand {
+    pub key: Box<[u8]>,
+    /// Whether to insert the element before the pivot, rather than after it
+    pub before: bool,
+    pub pivot: Vec<u8>,
+    pub element: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespLinsertCommand {
+    fn from_array(input: RespArray) -> Result<RespLinsertCommand, RespCommandError> {
+        let [_, key_element, position_element, pivot, element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("linsert".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let before = match get_argument_string(position_element)?.to_uppercase().as_str() {
+            "BEFORE" => true,
+            "AFTER" => false,
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        let pivot = get_argument_bytes(pivot)?;
+        let element = get_argument_bytes(element)?;
+
+        Ok(RespLinsertCommand { key, before, pivot, element })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLposCommand {
+    pub key: Box<[u8]>,
+    pub element: Vec<u8>,
+    /// Which match to start from; negative ranks search from the tail
+    pub rank: i64,
+    /// How many matches to return (zero for all of them); if this isn't given,
+    /// the reply is a single index rather than an array
+    pub count: Option<usize>,
+    /// How many elements to compare at most (zero for no limit)
+    pub max_length: usize,
+}
+
+impl RespCommandConstructor for RespLposCommand {
+    fn from_array(input: RespArray) -> Result<RespLposCommand, RespCommandError> {
+        let [_, key_element, element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("lpos".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let element = get_argument_bytes(element)?;
+
+        let mut rank = 1;
+        let mut count = None;
+        let mut max_length = 0;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            let option_name = get_argument_string(option)?.to_uppercase();
+
+            let Some(value_element) = options.next() else {
+                return Err(RespCommandError::SyntaxError);
+            };
+
+            let value = get_argument_integer(value_element)?;
+
+            match option_name.as_str() {
+                "RANK" if value == 0 => {
+                    return Err(RespCommandError::InvalidArgument("RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".into()));
+                }
+                // the rank is negated when searching from the tail, so the
+                // smallest value can't be allowed
+                "RANK" if value == i64::MIN => {
+                    return Err(RespCommandError::InvalidArgument(format!("value is out of range, value must between {} and {}", -i64::MAX, i64::MAX)));
+                }
+                "RANK" => rank = value,
+                "COUNT" if value < 0 => {
+                    return Err(RespCommandError::InvalidArgument("COUNT can't be negative".into()));
+                }
+                "COUNT" => count = Some(value as usize),
+                "MAXLEN" if value < 0 => {
+                    return Err(RespCommandError::InvalidArgument("MAXLEN can't be negative".into()));
+                }
+                "MAXLEN" => max_length = value as usize,
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespLposCommand { key, element, rank, count, max_length })
+    }
+}
+
+#[derive(Debug)]
+pub struct RespLmoveCommand {
+    pub source: Box<[u8]>,
+    pub destination: Box<[u8]>,
+    pub from: ListEnd,
+    pub to: ListEnd,
+}
+
+impl RespCommandConstructor for RespLmoveCommand {
+    fn from_array(input: RespArray) -> Result<RespLmoveCommand, RespCommandError> {
+        let [_, source_element, destination_element, from_element, to_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("lmove".into()));
+        };
+
+        let source = get_argument_bytes(source_element)?.into_boxed_slice();
+        let destination = get_argument_bytes(destination_element)?.into_boxed_slice();
+        let from = get_list_end(from_element)?;
+        let to = get_list_end(to_element)?;
+
+        Ok(RespLmoveCommand { source, destination, from, to })
+    }
+}
+
+/// Parses a `LEFT` or `RIGHT` argument
+pub fn get_list_end(element: &RespElement) -> Result<ListEnd, RespCommandError> {
+    match get_argument_string(element)?.to_uppercase().as_str() {
+        "LEFT" => Ok(ListEnd::Left),
+        "RIGHT" => Ok(ListEnd::Right),
+        _ => Err(RespCommandError::SyntaxError),
+    }
+}
+
+/// Parses the `LEFT | RIGHT [COUNT count]` arguments that follow the keys in
+/// `LMPOP` and `BLMPOP`
+pub fn parse_multi_pop_options(options: &[RespElement]) -> Result<(ListEnd, usize), RespCommandError> {
+    let [end_element, options @ ..] = options else {
+        return Err(RespCommandError::SyntaxError);
+    };
+
+    let end = get_list_end(end_element)?;
+
+    let count = match options {
+        [] => 1,
+        [option, count_element] if get_argument_string(option)?.eq_ignore_ascii_case("COUNT") => {
+            match get_argument_integer(count_element)? {
+                count if count > 0 => count as usize,
+                _ => return Err(RespCommandError::InvalidArgument("count should be greater than 0".into())),
+            }
+        }
+        _ => return Err(RespCommandError::SyntaxError),
+    };
+
+    Ok((end, count))
+}
+
+fn get_positive_count(element: &RespElement) -> Result<usize, RespCommandError> {
+    match get_argument_integer(element) {
+        Ok(count) if count >= 0 => Ok(count as usize),
+        _ => Err(RespCommandError::InvalidArgument("value is out of range, must be positive".into())),
+    }
+}
+
+fn get_lmpop_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 1)
+}
+
+fn get_blmpop_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 2)
+}
+
+/// Tells the worker to park the client on `keys` until one of them holds a
+/// list, or the timeout passes, and returns the reply that's sent if it does
+fn block_on_lists(ctx: &mut CommandContext, keys: &[Box<[u8]>], timeout: Option<u64>) -> CommandResult {
+    let deadline = timeout.map(| t | ctx.db.now_millis().saturating_add(t));
+
+    ctx.block_on(keys.iter().map(| k | k.to_vec()).collect(), "list", deadline);
+
+    Ok(RespElement::new_null_array())
+}
+
+fn lpush(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    push(ctx, input, ListEnd::Left, false)
+}
+
+fn rpush(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    push(ctx, input, ListEnd::Right, false)
+}
+
+fn lpushx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    push(ctx, input, ListEnd::Left, true)
+}
+
+fn rpushx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    push(ctx, input, ListEnd::Right, true)
+}
+
+/// Pushes each element in turn, and replies with the length of the list. If
+/// `only_if_exists` is set, nothing happens unless the list already exists.
+fn push(ctx: &mut CommandContext, input: RespArray, end: ListEnd, only_if_exists: bool) -> CommandResult {
+    let command = RespPushCommand::from_array(input)?;
+
+    let list = if only_if_exists {
+        match ctx.db.get_list_mut(&command.key)? {
+            Some(list) => list,
+            None => return Ok(RespElement::new_integer(0)),
+        }
+    } else {
+        ctx.db.get_or_insert_with(&command.key, || Value::List(List::new())).value.as_list_mut()?
+    };
+
+    for element in command.elements {
+        list.push(end, element);
+    }
+
+    Ok(RespElement::new_integer(list.len() as isize))
+}
+
+fn lpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    pop(ctx, input, ListEnd::Left)
+}
+
+fn rpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    pop(ctx, input, ListEnd::Right)
+}
+
+fn pop(ctx: &mut CommandContext, input: RespArray, end: ListEnd) -> CommandResult {
+    let command = RespPopCommand::from_array(input)?;
+
+    let Some(elements) = pop_elements(ctx.db, &command.key, end, command.count.unwrap_or(1))? else {
+        return match command.count {
+            Some(_) => Ok(RespElement::new_null_array()),
+            None => Ok(RespElement::new_null()),
+        };
+    };
+
+    match command.count {
+        Some(_) => Ok(RespElement::new_array(elements.iter().map(| e | RespElement::new_bulk_string(e)).collect())),
+        None => Ok(elements.first().map_or_else(RespElement::new_null, | e | RespElement::new_bulk_string(e))),
+    }
+}
+
+/// Pops up to `count` elements from one end of the list stored at `key`, and
+/// deletes the key if the list ends up empty. Returns `None` if the key
+/// doesn't exist.
+pub fn pop_elements(db: &mut Database, key: &[u8], end: ListEnd, count: usize) -> Result<Option<Vec<Vec<u8>>>, RespCommandError> {
+    let Some(list) = db.get_list_mut(key)? else {
+        return Ok(None);
+    };
+
+    let elements = (0 .. count).map_while(| _ | list.pop(end)).collect();
+
+    db.delete_if_empty(key);
+
+    Ok(Some(elements))
+}
+
+fn lmpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLmpopCommand::from_array(input)?;
+
+    for key in &command.keys {
+        // like Redis, keys that hold other types are an error, even if a
+        // list is found before them
+        if let Some(elements) = pop_elements(ctx.db, key, command.end, command.count)? {
+            return Ok(get_multi_pop_reply(key, elements));
+        }
+    }
+
+    Ok(RespElement::new_null_array())
+}
+
+/// Builds the reply for `LMPOP` and `BLMPOP`: the key that was popped from,
+/// followed by an array of the elements
+pub fn get_multi_pop_reply(key: &[u8], elements: Vec<Vec<u8>>) -> RespElement {
+    let elements = elements.iter().map(| e | RespElement::new_bulk_string(e)).collect();
+
+    RespElement::new_array(vec![
+        RespElement::new_bulk_string(key),
+        RespElement::new_array(elements),
+    ])
+}
+
+fn blpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    blocking_pop(ctx, input, ListEnd::Left)
+}
+
+fn brpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    blocking_pop(ctx, input, ListEnd::Right)
+}
+
+/// Pops an element from the first of the keys that holds a list, replying with
+/// the key and the element, or blocks until one of them does
+fn blocking_pop(ctx: &mut CommandContext, input: RespArray, end: ListEnd) -> CommandResult {
+    let command = RespBlockingPopCommand::from_array(input)?;
+
+    for key in &command.keys {
+        if let Some(elements) = pop_elements(ctx.db, key, end, 1)? {
+            let element = elements.into_iter().next().unwrap_or_default();
+
+            return Ok(RespElement::new_array(vec![
+                RespElement::new_bulk_string(key),
+                RespElement::new_bulk_string(&element),
+            ]));
+        }
+    }
+
+    block_on_lists(ctx, &command.keys, command.timeout)
+}
+
+fn blmpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let timeout = get_argument_timeout(&input.elements[1])?;
+    let command = RespLmpopCommand::from_arguments(&input, 2)?;
+
+    for key in &command.keys {
+        if let Some(elements) = pop_elements(ctx.db, key, command.end, command.count)? {
+            return Ok(get_multi_pop_reply(key, elements));
+        }
+    }
+
+    block_on_lists(ctx, &command.keys, timeout)
+}
+
+fn llen(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_list(&command.key)?.map_or(0, | list | list.len());
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn lrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespListRangeCommand::from_array(input)?;
+
+    let Some(list) = ctx.db.get_list(&command.key)? else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    let elements = match resolve_range(command.start, command.stop, list.len()) {
+        Some(range) => list.iter_range(range).map(RespElement::new_bulk_string).collect(),
+        None => Vec::new(),
+    };
+
+    Ok(RespElement::new_array(elements))
+}
+
+fn lindex(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLindexCommand::from_array(input)?;
+
+    let element = ctx.db.get_list(&command.key)?.and_then(| list | {
+        resolve_index(command.index, list.len()).and_then(| index | list.get(index))
+    });
+
+    Ok(element.map_or_else(RespElement::new_null, RespElement::new_bulk_string))
+}
+
+fn lset(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLsetCommand::from_array(input)?;
+
+    let Some(list) = ctx.db.get_list_mut(&command.key)? else {
+        return Err(RespCommandError::NoSuchKey);
+    };
+
+    let Some(index) = resolve_index(command.index, list.len()) else {
+        return Err(RespCommandError::InvalidArgument("index out of range".into()));
+    };
+
+    list.set(index, command.element);
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+fn lrem(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLremCommand::from_array(input)?;
+
+    let Some(list) = ctx.db.get_list_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let matches = list.iter().enumerate()
+        .filter(| (_, element) | *element == command.element.as_slice())
+        .map(| (i, _) | i);
+
+    let limit = match command.count.unsigned_abs() {
+        0 => usize::MAX,
+        limit => limit as usize,
+    };
+
+    let mut removed: Vec<usize> = if command.count < 0 {
+        matches.rev().take(limit).collect()
+    } else {
+        matches.take(limit).collect()
+    };
+
+    removed.sort_unstable();
+
+    list.retain_indices(| i | removed.binary_search(&i).is_err());
+
+    ctx.db.delete_if_empty(&command.key);
+
+    Ok(RespElement::new_integer(removed.len() as isize))
+}
+
+fn ltrim(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespListRangeCommand::from_array(input)?;
+
+    let Some(list) = ctx.db.get_list_mut(&command.key)? else {
+        return Ok(RespElement::new_simple_string("OK"));
+    };
+
+    let range = resolve_range(command.start, command.stop, list.len()).unwrap_or(0 .. 0);
+
+    list.trim(range);
+
+    ctx.db.delete_if_empty(&command.key);
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+fn linsert(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLinsertCommand::from_array(input)?;
+
+    let Some(list) = ctx.db.get_list_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let Some(pivot) = list.iter().position(| element | element == command.pivot.as_slice()) else {
+        return Ok(RespElement::new_integer(-1));
+    };
+
+    let index = if command.before { pivot } else { pivot + 1 };
+
+    list.insert(index, command.element);
+
+    Ok(RespElement::new_integer(list.len() as isize))
+}
+
+fn lpos(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLposCommand::from_array(input)?;
+
+    let positions = match ctx.db.get_list(&command.key)? {
+        Some(list) => find_positions(list, &command),
+        None => Vec::new(),
+    };
+
+    match command.count {
+        Some(_) => {
+            let positions = positions.into_iter().map(| p | RespElement::new_integer(p as isize)).collect();
+
+            Ok(RespElement::new_array(positions))
+        }
+        None => match positions.first() {
+            Some(position) => Ok(RespElement::new_integer(*position as isize)),
+            None => Ok(RespElement::new_null()),
+        },
+    }
+}
+
+/// Returns the indices of the elements that match an `LPOS` call, in the order
+/// that they were found
+fn find_positions(list: &List, command: &RespLposCommand) -> Vec<usize> {
+    let max_length = match command.max_length {
+        0 => list.len(),
+        max_length => max_length,
+    };
+
+    let count = match command.count {
+        Some(0) => usize::MAX,
+        Some(count) => count,
+        None => 1,
+    };
+
+    let skip = (command.rank.unsigned_abs() - 1) as usize;
+
+    let candidates = list.iter().enumerate();
+
+    let matches: Box<dyn Iterator<Item = (usize, &[u8])>> = if command.rank > 0 {
+        Box::new(candidates.take(max_length))
+    } else {
+        Box::new(candidates.rev().take(max_length))
+    };
+
+    matches
+        .filter(| (_, element) | *element == command.element.as_slice())
+        .skip(skip)
+        .take(count)
+        .map(| (i, _) | i)
+        .collect()
+}
+
+fn lmove(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLmoveCommand::from_array(input)?;
+
+    move_element(ctx.db, &command)
+}
+
+fn rpoplpush(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, source_element, destination_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("rpoplpush".into()));
+    };
+
+    let command = RespLmoveCommand {
+        source: get_argument_bytes(source_element)?.into_boxed_slice(),
+        destination: get_argument_bytes(destination_element)?.into_boxed_slice(),
+        from: ListEnd::Right,
+        to: ListEnd::Left,
+    };
+
+    move_element(ctx.db, &command)
+}
+
+fn blmove(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, source_element, destination_element, from_element, to_element, timeout_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("blmove".into()));
+    };
+
+    let command = RespLmoveCommand {
+        source: get_argument_bytes(source_element)?.into_boxed_slice(),
+        destination: get_argument_bytes(destination_element)?.into_boxed_slice(),
+        from: get_list_end(from_element)?,
+        to: get_list_end(to_element)?,
+    };
+
+    let timeout = get_argument_timeout(timeout_element)?;
+
+    blocking_move(ctx, &command, timeout)
+}
+
+fn brpoplpush(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, source_element, destination_element, timeout_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("brpoplpush".into()));
+    };
+
+    let timeout = get_argument_timeout(timeout_element)?;
+
+    let command = RespLmoveCommand {
+        source: get_argument_bytes(source_element)?.into_boxed_slice(),
+        destination: get_argument_bytes(destination_element)?.into_boxed_slice(),
+        from: ListEnd::Right,
+        to: ListEnd::Left,
+    };
+
+    blocking_move(ctx, &command, timeout)
+}
+
+/// Moves an element like `LMOVE`, or blocks until the source holds a list
+fn blocking_move(ctx: &mut CommandContext, command: &RespLmoveCommand, timeout: Option<u64>) -> CommandResult {
+    if ctx.db.get_list(&command.source)?.is_none() {
+        return block_on_lists(ctx, std::slice::from_ref(&command.source), timeout);
+    }
+
+    move_element(ctx.db, command)
+}
+
+/// Pops an element from the source list and pushes it to the destination,
+/// replying with the element (or null if the source doesn't exist)
+pub fn move_element(db: &mut Database, command: &RespLmoveCommand) -> CommandResult {
+    if db.get_list(&command.source)?.is_none() {
+        return Ok(RespElement::new_null());
+    }
+
+    // the destination is checked before anything is popped, so that nothing
+    // is lost if it holds the wrong type
+    db.get_list(&command.destination)?;
+
+    let Some(element) = db.get_list_mut(&command.source)?.and_then(| source | source.pop(command.from)) else {
+        return Ok(RespElement::new_null());
+    };
+
+    let reply = RespElement::new_bulk_string(&element);
+
+    db.get_or_insert_with(&command.destination, || Value::List(List::new()))
+        .value
+        .as_list_mut()?
+        .push(command.to, element);
+
+    // the source is only deleted once the element has been pushed, so that a
+    // list which is rotated onto itself keeps its TTL
+    db.delete_if_empty(&command.source);
+
+    Ok(reply)
+}
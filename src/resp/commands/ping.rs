@@ -0,0 +1,31 @@
+use crate::resp::commands::{
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    ConnectionState,
+    RespCommandError,
+    get_argument_bytes,
+};
+use crate::resp::types::RespArray;
+use crate::resp::RespElement;
+
+pub const PING: CommandSpec = CommandSpec {
+    name: "ping",
+    arity: -1,
+    flags: &[CommandFlag::Fast],
+    keys: (0, 0, 0),
+    get_keys: None,
+    group: "connection",
+    summary: "Returns the server's liveliness response.",
+    since: "1.0.0",
+    handler: CommandHandler::Connection(ping),
+};
+
+fn ping(_: &mut ConnectionState, input: RespArray) -> CommandResult {
+    match input.elements.as_slice() {
+        [_] => Ok(RespElement::new_simple_string("PONG")),
+        [_, message] => Ok(RespElement::new_bulk_string(&get_argument_bytes(message)?)),
+        _ => Err(RespCommandError::WrongNumberOfArguments("ping".into())),
+    }
+}
@@ -1,85 +1,163 @@
-use std::time::Duration;
-
-use crate::resp::commands::{RespCommandConstructor, RespCommandError};
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+};
 use crate::resp::RespElement;
 use crate::resp::types::RespArray;
+use crate::store::{Expiry, SetCondition, SetOptions, StringValue, Value};
+
+pub const SET: CommandSpec = CommandSpec {
+    name: "set",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(set),
+};
+
+/// The TTL options that `SET` accepts, as they were given. They're resolved
+/// into an `Expiry` when the command runs, since most are relative to the
+/// current time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetExpiration {
+    Seconds(i64),
+    Milliseconds(i64),
+    UnixSeconds(i64),
+    UnixMilliseconds(i64),
+    KeepTtl,
+}
 
 #[derive(Debug)]
 pub struct RespSetCommand {
-    pub key: String,
+    pub key: Box<[u8]>,
     pub value: Box<[u8]>,
-    pub ttl: Option<Duration>,
+    pub condition: SetCondition,
+    pub expiration: Option<SetExpiration>,
+    /// Whether to reply with the key's previous value (the `GET` option)
+    pub get: bool,
 }
 
 impl RespCommandConstructor for RespSetCommand {
     fn from_array(input: RespArray) -> Result<RespSetCommand, RespCommandError> {
-        if input.length < 3 {
-            return Err(RespCommandError::InvalidArgument);
-        }
-
-        let Some(key_element) = input.elements.get(1) else {
-            return Err(RespCommandError::InvalidArgument);
+        let [_, key_element, value_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("set".into()));
         };
 
-        let key = match key_element {
-            RespElement::SimpleString(s) => s.value.clone(),
-            RespElement::BulkString(b) => {
-                match String::from_utf8(b.value.to_vec()) {
-                    Ok(as_string) => as_string,
-                    Err(_) => return Err(RespCommandError::ParsingError)
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let value = get_argument_bytes(value_element)?.into_boxed_slice();
+
+        let mut condition = SetCondition::Always;
+        let mut expiration = None;
+        let mut get = false;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            let option_name = get_argument_string(option)?.to_uppercase();
+
+            match option_name.as_str() {
+                "NX" if condition != SetCondition::IfExists => condition = SetCondition::IfMissing,
+                "XX" if condition != SetCondition::IfMissing => condition = SetCondition::IfExists,
+                "GET" => get = true,
+                "KEEPTTL" if expiration.is_none() => expiration = Some(SetExpiration::KeepTtl),
+                "EX" | "PX" | "EXAT" | "PXAT" if expiration.is_none() => {
+                    let Some(ttl_element) = options.next() else {
+                        return Err(RespCommandError::SyntaxError);
+                    };
+
+                    let ttl = get_argument_integer(ttl_element)?;
+
+                    expiration = Some(match option_name.as_str() {
+                        "EX" => SetExpiration::Seconds(ttl),
+                        "PX" => SetExpiration::Milliseconds(ttl),
+                        "EXAT" => SetExpiration::UnixSeconds(ttl),
+                        _ => SetExpiration::UnixMilliseconds(ttl),
+                    });
                 }
+                _ => return Err(RespCommandError::SyntaxError),
             }
-            _ => return Err(RespCommandError::InvalidArgument),
-        };
+        }
 
-        let Some(value_element) = input.elements.get(2) else {
-            return Err(RespCommandError::InvalidArgument);
-        };
+        Ok(RespSetCommand { key, value, condition, expiration, get })
+    }
+}
 
-        let value = match value_element {
-            RespElement::SimpleString(s) => s.value.as_bytes(),
-            RespElement::BulkString(b) => &b.value,
-            _ => return Err(RespCommandError::InvalidArgument)
-        };
+impl SetExpiration {
+    /// Works out when the key should expire, as a Unix time in milliseconds,
+    /// given the current time `now`. Non-positive values and times that are
+    /// too far in the future are rejected, with an error that names `command`.
+    pub fn to_expiry(self, now: u64, command: &str) -> Result<Expiry, RespCommandError> {
+        let invalid = || RespCommandError::InvalidExpireTime(command.into());
 
-        let value: Box<[u8]> = Box::from(value);
-
-        let ttl = match input.elements.get(3..=4) {
-            Some([option, ttl_element]) => {
-                let option_name = match option {
-                    RespElement::SimpleString(s) => s.value.as_str(),
-                    RespElement::BulkString(b) => {
-                        match str::from_utf8(&b.value) {
-                            Ok(as_string) => as_string,
-                            Err(_) => return Err(RespCommandError::ParsingError),
-                        }
-                    },
-                    _ => return Err(RespCommandError::ParsingError),
-                };
-
-                match option_name {
-                    "EX" => get_ttl_in_secs(ttl_element)?,
-                    _ => return Err(RespCommandError::InvalidArgument),
-                }
+        let expires_at = match self {
+            SetExpiration::KeepTtl => return Ok(Expiry::Keep),
+            SetExpiration::Seconds(ttl) if ttl > 0 => {
+                ttl.checked_mul(1000).and_then(| ttl | now.checked_add(ttl as u64))
+            }
+            SetExpiration::Milliseconds(ttl) if ttl > 0 => {
+                now.checked_add(ttl as u64)
+            }
+            SetExpiration::UnixSeconds(timestamp) if timestamp > 0 => {
+                timestamp.checked_mul(1000).map(| timestamp | timestamp as u64)
+            }
+            SetExpiration::UnixMilliseconds(timestamp) if timestamp > 0 => {
+                Some(timestamp as u64)
             }
             _ => None,
         };
 
-        Ok(RespSetCommand { key, value, ttl })
+        // like Redis, times must fit in a signed 64-bit integer
+        expires_at
+            .filter(| when | *when <= i64::MAX as u64)
+            .map(Expiry::At)
+            .ok_or_else(invalid)
     }
 }
 
-fn get_ttl_in_secs(element: &RespElement) -> Result<Option<Duration>, RespCommandError> {
-    let ttl_in_secs = match element {
-        RespElement::Integer(i) if i.value > 0 => {
-            if i.value > i32::MAX as isize {
-                return Err(RespCommandError::InvalidArgument);
-            }
+fn set(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetCommand::from_array(input)?;
 
-            Some(Duration::from_secs(i.value as u64))
-        }
-        _ => return Err(RespCommandError::InvalidArgument),
+    let expiry = match command.expiration {
+        Some(expiration) => expiration.to_expiry(ctx.db.now_millis(), "set")?,
+        None => Expiry::Never,
+    };
+
+    let options = SetOptions {
+        condition: command.condition,
+        expiry,
     };
 
-    Ok(ttl_in_secs)
+    // the previous value has to be a string for `GET` to return it, which is
+    // checked up front so that nothing is written if it isn't
+    if command.get {
+        ctx.db.get_string(&command.key)?;
+    }
+
+    let value = Value::String(StringValue::new(command.value.into_vec()));
+
+    let outcome = ctx.db.set(&command.key, value, options);
+
+    if command.get {
+        return match outcome.previous {
+            Some(Value::String(previous)) => Ok(RespElement::new_bulk_string(&previous.as_bytes())),
+            _ => Ok(RespElement::new_null()),
+        };
+    }
+
+    if outcome.written {
+        Ok(RespElement::new_simple_string("OK"))
+    } else {
+        Ok(RespElement::new_null())
+    }
 }
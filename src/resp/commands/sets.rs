@@ -0,0 +1,694 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::random;
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RANDOM_MAX_REPEATED_COUNT,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+    get_counted_key_positions,
+};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::{Database, Set, SetOptions, Value};
+
+pub const SADD: CommandSpec = CommandSpec {
+    name: "sadd",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sadd),
+};
+
+pub const SREM: CommandSpec = CommandSpec {
+    name: "srem",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(srem),
+};
+
+pub const SISMEMBER: CommandSpec = CommandSpec {
+    name: "sismember",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Determines whether a member belongs to a set.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sismember),
+};
+
+pub const SMISMEMBER: CommandSpec = CommandSpec {
+    name: "smismember",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Determines whether multiple members belong to a set.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(smismember),
+};
+
+pub const SMEMBERS: CommandSpec = CommandSpec {
+    name: "smembers",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns all members of a set.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(smembers),
+};
+
+pub const SCARD: CommandSpec = CommandSpec {
+    name: "scard",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns the number of members in a set.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(scard),
+};
+
+pub const SPOP: CommandSpec = CommandSpec {
+    name: "spop",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(spop),
+};
+
+pub const SRANDMEMBER: CommandSpec = CommandSpec {
+    name: "srandmember",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns one or more random members from a set.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(srandmember),
+};
+
+pub const SMOVE: CommandSpec = CommandSpec {
+    name: "smove",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Moves a member from one set to another.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(smove),
+};
+
+pub const SINTER: CommandSpec = CommandSpec {
+    name: "sinter",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns the intersect of multiple sets.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sinter),
+};
+
+pub const SINTERCARD: CommandSpec = CommandSpec {
+    name: "sintercard",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: Some(get_sintercard_keys),
+    group: "set",
+    summary: "Returns the number of members of the intersect of multiple sets.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(sintercard),
+};
+
+pub const SINTERSTORE: CommandSpec = CommandSpec {
+    name: "sinterstore",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Stores the intersect of multiple sets in a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sinterstore),
+};
+
+pub const SUNION: CommandSpec = CommandSpec {
+    name: "sunion",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns the union of multiple sets.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sunion),
+};
+
+pub const SUNIONSTORE: CommandSpec = CommandSpec {
+    name: "sunionstore",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Stores the union of multiple sets in a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sunionstore),
+};
+
+pub const SDIFF: CommandSpec = CommandSpec {
+    name: "sdiff",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Returns the difference of multiple sets.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sdiff),
+};
+
+pub const SDIFFSTORE: CommandSpec = CommandSpec {
+    name: "sdiffstore",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "set",
+    summary: "Stores the difference of multiple sets in a key.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(sdiffstore),
+};
+
+/// A call to a command that takes a key and one or more members, e.g. `SADD`
+#[derive(Debug)]
+pub struct RespSetMembersCommand {
+    pub key: Box<[u8]>,
+    pub members: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespSetMembersCommand {
+    fn from_array(input: RespArray) -> Result<RespSetMembersCommand, RespCommandError> {
+        let [_, key_element, member_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if member_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let members = member_elements.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespSetMembersCommand { key, members })
+    }
+}
+
+/// A call to `SISMEMBER`
+#[derive(Debug)]
+pub struct RespSismemberCommand {
+    pub key: Box<[u8]>,
+    pub member: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespSismemberCommand {
+    fn from_array(input: RespArray) -> Result<RespSismemberCommand, RespCommandError> {
+        let [_, key_element, member_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("sismember".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let member = get_argument_bytes(member_element)?;
+
+        Ok(RespSismemberCommand { key, member })
+    }
+}
+
+/// A call to `SPOP` or `SRANDMEMBER`
+#[derive(Debug)]
+pub struct RespRandomMemberCommand {
+    pub key: Box<[u8]>,
+    /// How many members to return; if this isn't given, the reply is a single
+    /// member rather than an array
+    pub count: Option<i64>,
+}
+
+impl RespCommandConstructor for RespRandomMemberCommand {
+    fn from_array(input: RespArray) -> Result<RespRandomMemberCommand, RespCommandError> {
+        let (key_element, count) = match input.elements.as_slice() {
+            [_, key_element] => (key_element, None),
+            [_, key_element, count_element] => (key_element, Some(get_argument_integer(count_element)?)),
+            [_, _, _, ..] => return Err(RespCommandError::SyntaxError),
+            _ => return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input))),
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespRandomMemberCommand { key, count })
+    }
+}
+
+/// A call to `SMOVE`
+#[derive(Debug)]
+pub struct RespSmoveCommand {
+    pub source: Box<[u8]>,
+    pub destination: Box<[u8]>,
+    pub member: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespSmoveCommand {
+    fn from_array(input: RespArray) -> Result<RespSmoveCommand, RespCommandError> {
+        let [_, source_element, destination_element, member_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("smove".into()));
+        };
+
+        let source = get_argument_bytes(source_element)?.into_boxed_slice();
+        let destination = get_argument_bytes(destination_element)?.into_boxed_slice();
+        let member = get_argument_bytes(member_element)?;
+
+        Ok(RespSmoveCommand { source, destination, member })
+    }
+}
+
+/// A call to `SINTER`, `SUNION` or `SDIFF`, or one of their `STORE` variants
+/// (in which case the first key is the destination)
+#[derive(Debug)]
+pub struct RespSetKeysCommand {
+    pub keys: Vec<Box<[u8]>>,
+}
+
+impl RespCommandConstructor for RespSetKeysCommand {
+    fn from_array(input: RespArray) -> Result<RespSetKeysCommand, RespCommandError> {
+        let [_, key_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if key_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let keys = key_elements.iter()
+            .map(| e | get_argument_bytes(e).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespSetKeysCommand { keys })
+    }
+}
+
+/// A call to `SINTERCARD`
+#[derive(Debug)]
+pub struct RespSintercardCommand {
+    pub keys: Vec<Box<[u8]>>,
+    /// The count to stop at, where 0 means there's no limit
+    pub limit: usize,
+}
+
+impl RespCommandConstructor for RespSintercardCommand {
+    fn from_array(input: RespArray) -> Result<RespSintercardCommand, RespCommandError> {
+        let positions = get_counted_key_positions(&input, 1)?;
+        let options_start = positions.last().map_or(2, | p | p + 1);
+
+        let keys = positions.iter()
+            .map(| p | get_argument_bytes(&input.elements[*p]).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let limit = match &input.elements[options_start ..] {
+            [] => 0,
+            [option, limit_element] if get_argument_string(option)?.eq_ignore_ascii_case("LIMIT") => {
+                match get_argument_integer(limit_element)? {
+                    l if l < 0 => return Err(RespCommandError::InvalidArgument("LIMIT can't be negative".into())),
+                    l => l as usize,
+                }
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        Ok(RespSintercardCommand { keys, limit })
+    }
+}
+
+fn get_sintercard_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 1)
+}
+
+/// The ways that `SINTER`, `SUNION` and `SDIFF` combine sets
+#[derive(Debug, Clone, Copy)]
+enum SetOperation {
+    Intersection,
+    Union,
+    Difference,
+}
+
+/// Combines the sets at the given keys, treating keys that don't exist as
+/// empty sets. Every key is type checked, even if the result is known to be
+/// empty before they've all been looked at.
+fn combine_sets(db: &mut Database, keys: &[Box<[u8]>], operation: SetOperation) -> Result<Vec<Vec<u8>>, RespCommandError> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut lengths = Vec::with_capacity(keys.len());
+
+    for key in keys {
+        lengths.push(db.get_set(key)?.map_or(0, Set::len));
+    }
+
+    match operation {
+        SetOperation::Intersection => {
+            if lengths.contains(&0) {
+                return Ok(Vec::new());
+            }
+
+            // starting from the smallest set keeps the number of lookups down
+            let mut order: Vec<usize> = (0 .. keys.len()).collect();
+            order.sort_by_key(| i | lengths[*i]);
+
+            let mut members = get_members(db, &keys[order[0]])?;
+
+            for i in &order[1 ..] {
+                let set = db.get_set(&keys[*i])?;
+
+                members.retain(| m | set.is_some_and(| s | s.contains(m)));
+            }
+
+            Ok(members)
+        }
+        SetOperation::Union => {
+            let mut members = HashSet::new();
+
+            for key in keys {
+                if let Some(set) = db.get_set(key)? {
+                    members.extend(set.iter().map(Cow::into_owned));
+                }
+            }
+
+            Ok(members.into_iter().collect())
+        }
+        SetOperation::Difference => {
+            let mut members = get_members(db, &keys[0])?;
+
+            for key in &keys[1 ..] {
+                if let Some(set) = db.get_set(key)? {
+                    members.retain(| m | !set.contains(m));
+                }
+            }
+
+            Ok(members)
+        }
+    }
+}
+
+fn get_members(db: &mut Database, key: &[u8]) -> Result<Vec<Vec<u8>>, RespCommandError> {
+    let members = db.get_set(key)?
+        .map(| set | set.iter().map(Cow::into_owned).collect())
+        .unwrap_or_default();
+
+    Ok(members)
+}
+
+/// Replies with a set of the members, which is sent to RESP2 clients as an
+/// array
+fn get_members_reply<T: AsRef<[u8]>>(members: impl IntoIterator<Item = T>) -> RespElement {
+    RespElement::new_set(members.into_iter().map(| m | RespElement::new_bulk_string(m.as_ref())).collect())
+}
+
+fn sadd(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetMembersCommand::from_array(input)?;
+
+    let set = ctx.db.get_or_insert_with(&command.key, || Value::Set(Set::new())).value.as_set_mut()?;
+
+    let added = command.members.into_iter()
+        .map(| member | set.insert(member))
+        .filter(| added | *added)
+        .count();
+
+    Ok(RespElement::new_integer(added as isize))
+}
+
+fn srem(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetMembersCommand::from_array(input)?;
+
+    let Some(set) = ctx.db.get_set_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let removed = command.members.iter()
+        .filter(| member | set.remove(member))
+        .count();
+
+    ctx.db.delete_if_empty(&command.key);
+
+    Ok(RespElement::new_integer(removed as isize))
+}
+
+fn sismember(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSismemberCommand::from_array(input)?;
+
+    let exists = ctx.db.get_set(&command.key)?.is_some_and(| set | set.contains(&command.member));
+
+    Ok(RespElement::new_integer(exists as isize))
+}
+
+fn smismember(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetMembersCommand::from_array(input)?;
+
+    let set = ctx.db.get_set(&command.key)?;
+
+    let results = command.members.iter()
+        .map(| member | set.is_some_and(| s | s.contains(member)))
+        .map(| exists | RespElement::new_integer(exists as isize))
+        .collect();
+
+    Ok(RespElement::new_array(results))
+}
+
+fn smembers(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let members = get_members(ctx.db, &command.key)?;
+
+    Ok(get_members_reply(members))
+}
+
+fn scard(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_set(&command.key)?.map_or(0, Set::len);
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn spop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespRandomMemberCommand::from_array(input)?;
+
+    let count = match command.count {
+        Some(c) if c < 0 => {
+            return Err(RespCommandError::InvalidArgument("value is out of range, must be positive".into()));
+        }
+        count => count.map(| c | c as usize),
+    };
+
+    let Some(set) = ctx.db.get_set_mut(&command.key)? else {
+        return Ok(match count {
+            Some(_) => RespElement::new_set(Vec::new()),
+            None => RespElement::new_null(),
+        });
+    };
+
+    // members are popped one at a time, so each pick is from the ones that
+    // are left
+    let mut popped = Vec::new();
+
+    for _ in 0 .. count.unwrap_or(1) {
+        let Some(member) = set.random_member().map(Cow::into_owned) else {
+            break;
+        };
+
+        set.remove(&member);
+        popped.push(member);
+    }
+
+    ctx.db.delete_if_empty(&command.key);
+
+    match count {
+        Some(_) => Ok(get_members_reply(popped)),
+        None => Ok(popped.first().map_or_else(RespElement::new_null, | m | RespElement::new_bulk_string(m))),
+    }
+}
+
+fn srandmember(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespRandomMemberCommand::from_array(input)?;
+
+    if command.count.is_some_and(| c | c < 0 && c.unsigned_abs() > RANDOM_MAX_REPEATED_COUNT) {
+        return Err(RespCommandError::InvalidArgument("value is out of range".into()));
+    }
+
+    let set = ctx.db.get_set(&command.key)?;
+
+    let Some(count) = command.count else {
+        let member = set.and_then(Set::random_member);
+
+        return Ok(member.map_or_else(RespElement::new_null, | m | RespElement::new_bulk_string(&m)));
+    };
+
+    let Some(set) = set else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    // a negative count allows the same member to be picked more than once, so
+    // the reply can be longer than the set
+    if count < 0 {
+        let picked = (0 .. count.unsigned_abs())
+            .filter_map(| _ | set.random_member())
+            .map(| m | RespElement::new_bulk_string(&m))
+            .collect();
+
+        return Ok(RespElement::new_array(picked));
+    }
+
+    let members: Vec<_> = set.iter().collect();
+
+    let picked = random::sample_indices(members.len(), count as usize).into_iter()
+        .map(| i | RespElement::new_bulk_string(&members[i]))
+        .collect();
+
+    Ok(RespElement::new_array(picked))
+}
+
+fn smove(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSmoveCommand::from_array(input)?;
+
+    let Some(source) = ctx.db.get_set(&command.source)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let is_member = source.contains(&command.member);
+
+    // the destination is checked before anything is removed, so that nothing
+    // is lost if it holds the wrong type
+    ctx.db.get_set(&command.destination)?;
+
+    // moving a member onto the same set leaves it where it is
+    if !is_member || command.source == command.destination {
+        return Ok(RespElement::new_integer(is_member as isize));
+    }
+
+    if let Some(source) = ctx.db.get_set_mut(&command.source)? {
+        source.remove(&command.member);
+    }
+
+    ctx.db.delete_if_empty(&command.source);
+
+    ctx.db.get_or_insert_with(&command.destination, || Value::Set(Set::new()))
+        .value
+        .as_set_mut()?
+        .insert(command.member);
+
+    Ok(RespElement::new_integer(1))
+}
+
+fn sinter(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, SetOperation::Intersection)
+}
+
+fn sunion(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, SetOperation::Union)
+}
+
+fn sdiff(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, SetOperation::Difference)
+}
+
+fn sinterstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, SetOperation::Intersection)
+}
+
+fn sunionstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, SetOperation::Union)
+}
+
+fn sdiffstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, SetOperation::Difference)
+}
+
+fn combine(ctx: &mut CommandContext, input: RespArray, operation: SetOperation) -> CommandResult {
+    let command = RespSetKeysCommand::from_array(input)?;
+
+    let members = combine_sets(ctx.db, &command.keys, operation)?;
+
+    Ok(get_members_reply(members))
+}
+
+/// Stores the combined sets at the destination (the first key), replacing
+/// whatever was there and dropping its TTL, or deletes the destination if the
+/// result is empty. Replies with the size of the result.
+fn combine_and_store(ctx: &mut CommandContext, input: RespArray, operation: SetOperation) -> CommandResult {
+    let command = RespSetKeysCommand::from_array(input)?;
+
+    let [destination, keys @ ..] = command.keys.as_slice() else {
+        unreachable!("the command has at least one key");
+    };
+
+    let members = combine_sets(ctx.db, keys, operation)?;
+    let count = members.len();
+
+    if members.is_empty() {
+        ctx.db.delete(destination);
+    } else {
+        ctx.db.set(destination, Value::Set(members.into_iter().collect()), SetOptions::default());
+    }
+
+    Ok(RespElement::new_integer(count as isize))
+}
+
+/// Replies with the size of the intersection, stopping once it reaches the
+/// limit (if there is one)
+fn sintercard(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSintercardCommand::from_array(input)?;
+
+    let count = combine_sets(ctx.db, &command.keys, SetOperation::Intersection)?.len();
+
+    let count = match command.limit {
+        0 => count,
+        limit => count.min(limit),
+    };
+
+    Ok(RespElement::new_integer(count as isize))
+}
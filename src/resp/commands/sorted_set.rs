@@ -0,0 +1,1464 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_float,
+    get_argument_integer,
+    get_argument_string,
+    get_argument_timeout,
+    get_command_name,
+    get_counted_key_positions,
+    resolve_range,
+};
+use crate::resp::commands::list::RespBlockingPopCommand;
+use crate::resp::{RespElement, RespProtocol};
+use crate::resp::types::RespArray;
+use crate::resp::types::double::parse_double;
+use crate::store::{
+    Database,
+    LexBound,
+    LexRange,
+    ScoreBound,
+    ScoreRange,
+    SetOptions,
+    SortedSet,
+    Value,
+    WrongTypeError,
+};
+
+pub const ZADD: CommandSpec = CommandSpec {
+    name: "zadd",
+    arity: -4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zadd),
+};
+
+pub const ZREM: CommandSpec = CommandSpec {
+    name: "zrem",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zrem),
+};
+
+pub const ZSCORE: CommandSpec = CommandSpec {
+    name: "zscore",
+    arity: 3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the score of a member in a sorted set.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zscore),
+};
+
+pub const ZMSCORE: CommandSpec = CommandSpec {
+    name: "zmscore",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the score of one or more members in a sorted set.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zmscore),
+};
+
+pub const ZINCRBY: CommandSpec = CommandSpec {
+    name: "zincrby",
+    arity: 4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Increments the score of a member in a sorted set.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zincrby),
+};
+
+pub const ZCARD: CommandSpec = CommandSpec {
+    name: "zcard",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the number of members in a sorted set.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zcard),
+};
+
+pub const ZCOUNT: CommandSpec = CommandSpec {
+    name: "zcount",
+    arity: 4,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the count of members in a sorted set that have scores within a range.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zcount),
+};
+
+pub const ZRANK: CommandSpec = CommandSpec {
+    name: "zrank",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zrank),
+};
+
+pub const ZREVRANK: CommandSpec = CommandSpec {
+    name: "zrevrank",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the index of a member in a sorted set ordered by descending scores.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zrevrank),
+};
+
+pub const ZRANGE: CommandSpec = CommandSpec {
+    name: "zrange",
+    arity: -4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns members in a sorted set within a range of indexes, scores or members.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zrange),
+};
+
+pub const ZRANGESTORE: CommandSpec = CommandSpec {
+    name: "zrangestore",
+    arity: -5,
+    flags: &[CommandFlag::Write],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Stores a range of members from sorted set in a key.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zrangestore),
+};
+
+pub const ZPOPMIN: CommandSpec = CommandSpec {
+    name: "zpopmin",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(zpopmin),
+};
+
+pub const ZPOPMAX: CommandSpec = CommandSpec {
+    name: "zpopmax",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(zpopmax),
+};
+
+pub const ZMPOP: CommandSpec = CommandSpec {
+    name: "zmpop",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (0, 0, 0),
+    get_keys: Some(get_zmpop_keys),
+    group: "sorted-set",
+    summary: "Returns the highest- or lowest-scoring members from one of multiple sorted sets after removing them. Deletes the sorted set if the last member was popped.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(zmpop),
+};
+
+pub const BZPOPMIN: CommandSpec = CommandSpec {
+    name: "bzpopmin",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast, CommandFlag::Blocking],
+    keys: (1, -2, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last member was popped.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(bzpopmin),
+};
+
+pub const BZPOPMAX: CommandSpec = CommandSpec {
+    name: "bzpopmax",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast, CommandFlag::Blocking],
+    keys: (1, -2, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last member was popped.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(bzpopmax),
+};
+
+pub const BZMPOP: CommandSpec = CommandSpec {
+    name: "bzmpop",
+    arity: -5,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (0, 0, 0),
+    get_keys: Some(get_bzmpop_keys),
+    group: "sorted-set",
+    summary: "Removes and returns a member by score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last member was popped.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(bzmpop),
+};
+
+pub const ZREMRANGEBYRANK: CommandSpec = CommandSpec {
+    name: "zremrangebyrank",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes members in a sorted set within a range of indexes. Deletes the sorted set if all members were removed.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zremrangebyrank),
+};
+
+pub const ZREMRANGEBYSCORE: CommandSpec = CommandSpec {
+    name: "zremrangebyscore",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes members in a sorted set within a range of scores. Deletes the sorted set if all members were removed.",
+    since: "1.2.0",
+    handler: CommandHandler::Store(zremrangebyscore),
+};
+
+pub const ZREMRANGEBYLEX: CommandSpec = CommandSpec {
+    name: "zremrangebylex",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "sorted-set",
+    summary: "Removes members in a sorted set within a lexicographical range. Deletes the sorted set if all members were removed.",
+    since: "2.8.9",
+    handler: CommandHandler::Store(zremrangebylex),
+};
+
+pub const ZUNION: CommandSpec = CommandSpec {
+    name: "zunion",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: Some(get_zset_operation_keys),
+    group: "sorted-set",
+    summary: "Returns the union of multiple sorted sets.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zunion),
+};
+
+pub const ZUNIONSTORE: CommandSpec = CommandSpec {
+    name: "zunionstore",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: Some(get_zset_operation_store_keys),
+    group: "sorted-set",
+    summary: "Stores the union of multiple sorted sets in a key.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zunionstore),
+};
+
+pub const ZINTER: CommandSpec = CommandSpec {
+    name: "zinter",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: Some(get_zset_operation_keys),
+    group: "sorted-set",
+    summary: "Returns the intersect of multiple sorted sets.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zinter),
+};
+
+pub const ZINTERSTORE: CommandSpec = CommandSpec {
+    name: "zinterstore",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: Some(get_zset_operation_store_keys),
+    group: "sorted-set",
+    summary: "Stores the intersect of multiple sorted sets in a key.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(zinterstore),
+};
+
+pub const ZDIFF: CommandSpec = CommandSpec {
+    name: "zdiff",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (0, 0, 0),
+    get_keys: Some(get_zset_operation_keys),
+    group: "sorted-set",
+    summary: "Returns the difference between multiple sorted sets.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zdiff),
+};
+
+pub const ZDIFFSTORE: CommandSpec = CommandSpec {
+    name: "zdiffstore",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: Some(get_zset_operation_store_keys),
+    group: "sorted-set",
+    summary: "Stores the difference of multiple sorted sets in a key.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(zdiffstore),
+};
+
+/// The options that `ZADD` takes before its score/member pairs
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ZaddOptions {
+    /// Only add new members
+    pub nx: bool,
+    /// Only update members that already exist
+    pub xx: bool,
+    /// Only update scores that are greater than the current one
+    pub gt: bool,
+    /// Only update scores that are less than the current one
+    pub lt: bool,
+    /// Reply with the number of members that were added or changed, rather
+    /// than only the ones that were added
+    pub ch: bool,
+    /// Add the score to the member's current score, like `ZINCRBY`
+    pub incr: bool,
+}
+
+/// A call to `ZADD`
+#[derive(Debug)]
+pub struct RespZaddCommand {
+    pub key: Box<[u8]>,
+    pub options: ZaddOptions,
+    pub members: Vec<(f64, Vec<u8>)>,
+}
+
+impl RespCommandConstructor for RespZaddCommand {
+    fn from_array(input: RespArray) -> Result<RespZaddCommand, RespCommandError> {
+        let [_, key_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("zadd".into()));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut options = ZaddOptions::default();
+        let mut pairs = rest;
+
+        while let [option, remaining @ ..] = pairs {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "NX" => options.nx = true,
+                "XX" => options.xx = true,
+                "GT" => options.gt = true,
+                "LT" => options.lt = true,
+                "CH" => options.ch = true,
+                "INCR" => options.incr = true,
+                _ => break,
+            }
+
+            pairs = remaining;
+        }
+
+        if pairs.is_empty() || pairs.len() % 2 != 0 {
+            return Err(RespCommandError::SyntaxError);
+        }
+
+        if options.nx && options.xx {
+            return Err(RespCommandError::InvalidArgument("XX and NX options at the same time are not compatible".into()));
+        }
+
+        if [options.gt, options.lt, options.nx].iter().filter(| o | **o).count() > 1 {
+            return Err(RespCommandError::InvalidArgument("GT, LT, and/or NX options at the same time are not compatible".into()));
+        }
+
+        if options.incr && pairs.len() > 2 {
+            return Err(RespCommandError::InvalidArgument("INCR option supports a single increment-element pair".into()));
+        }
+
+        let members = pairs.chunks_exact(2)
+            .map(| pair | Ok((get_argument_float(&pair[0])?, get_argument_bytes(&pair[1])?)))
+            .collect::<Result<_, RespCommandError>>()?;
+
+        Ok(RespZaddCommand { key, options, members })
+    }
+}
+
+/// A call to a command that takes a key and one or more members, e.g. `ZREM`
+#[derive(Debug)]
+pub struct RespZsetMembersCommand {
+    pub key: Box<[u8]>,
+    pub members: Vec<Vec<u8>>,
+}
+
+impl RespCommandConstructor for RespZsetMembersCommand {
+    fn from_array(input: RespArray) -> Result<RespZsetMembersCommand, RespCommandError> {
+        let [_, key_element, member_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if member_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let members = member_elements.iter()
+            .map(get_argument_bytes)
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespZsetMembersCommand { key, members })
+    }
+}
+
+/// A call to `ZRANK` or `ZREVRANK`
+#[derive(Debug)]
+pub struct RespZrankCommand {
+    pub key: Box<[u8]>,
+    pub member: Vec<u8>,
+    pub with_score: bool,
+}
+
+impl RespCommandConstructor for RespZrankCommand {
+    fn from_array(input: RespArray) -> Result<RespZrankCommand, RespCommandError> {
+        let (key_element, member_element, with_score) = match input.elements.as_slice() {
+            [_, key_element, member_element] => (key_element, member_element, false),
+            [_, key_element, member_element, option] if get_argument_string(option)?.eq_ignore_ascii_case("WITHSCORE") => {
+                (key_element, member_element, true)
+            }
+            [_, _, _, ..] => return Err(RespCommandError::SyntaxError),
+            _ => return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input))),
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let member = get_argument_bytes(member_element)?;
+
+        Ok(RespZrankCommand { key, member, with_score })
+    }
+}
+
+/// What a `ZRANGE` selects members by
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZrangeBy {
+    /// Inclusive ranks, where negative ranks count back from the end
+    Rank(i64, i64),
+    Score(ScoreRange),
+    Lex(LexRange),
+}
+
+/// A call to `ZRANGE` or `ZRANGESTORE`
+#[derive(Debug)]
+pub struct RespZrangeCommand {
+    /// Where `ZRANGESTORE` stores the result
+    pub destination: Option<Box<[u8]>>,
+    pub key: Box<[u8]>,
+    pub by: ZrangeBy,
+    /// Whether to order the members from the highest score to the lowest
+    pub reverse: bool,
+    /// How many members to skip, and how many to return after that (where a
+    /// negative count means all of them)
+    pub limit: Option<(i64, i64)>,
+    pub with_scores: bool,
+}
+
+impl RespCommandConstructor for RespZrangeCommand {
+    fn from_array(input: RespArray) -> Result<RespZrangeCommand, RespCommandError> {
+        let [_, key_element, rest @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments("zrange".into()));
+        };
+
+        RespZrangeCommand::from_arguments(None, key_element, rest)
+    }
+}
+
+impl RespZrangeCommand {
+    /// Parses the `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+    /// [WITHSCORES]` arguments that follow the key, where `WITHSCORES` is only
+    /// allowed if there's no destination
+    fn from_arguments(
+        destination: Option<Box<[u8]>>,
+        key_element: &RespElement,
+        elements: &[RespElement],
+    ) -> Result<RespZrangeCommand, RespCommandError> {
+        let [start_element, stop_element, options @ ..] = elements else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut by_score = false;
+        let mut by_lex = false;
+        let mut reverse = false;
+        let mut limit = None;
+        let mut with_scores = false;
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "BYSCORE" => by_score = true,
+                "BYLEX" => by_lex = true,
+                "REV" => reverse = true,
+                "WITHSCORES" if destination.is_none() => with_scores = true,
+                "LIMIT" => {
+                    let (Some(offset), Some(count)) = (options.next(), options.next()) else {
+                        return Err(RespCommandError::SyntaxError);
+                    };
+
+                    limit = Some((get_argument_integer(offset)?, get_argument_integer(count)?));
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        if by_score && by_lex {
+            return Err(RespCommandError::SyntaxError);
+        }
+
+        if limit.is_some() && !by_score && !by_lex {
+            return Err(RespCommandError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX".into()
+            ));
+        }
+
+        if with_scores && by_lex {
+            return Err(RespCommandError::InvalidArgument(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".into()
+            ));
+        }
+
+        // when ordering by score or member, `REV` expects the range to be
+        // given from the highest to the lowest
+        let (min_element, max_element) = match reverse {
+            true => (stop_element, start_element),
+            false => (start_element, stop_element),
+        };
+
+        let by = if by_score {
+            ZrangeBy::Score(ScoreRange { min: parse_score_bound(min_element)?, max: parse_score_bound(max_element)? })
+        } else if by_lex {
+            ZrangeBy::Lex(LexRange { min: parse_lex_bound(min_element)?, max: parse_lex_bound(max_element)? })
+        } else {
+            ZrangeBy::Rank(get_argument_integer(start_element)?, get_argument_integer(stop_element)?)
+        };
+
+        Ok(RespZrangeCommand { destination, key, by, reverse, limit, with_scores })
+    }
+}
+
+/// A call to `ZPOPMIN` or `ZPOPMAX`
+#[derive(Debug)]
+pub struct RespZpopCommand {
+    pub key: Box<[u8]>,
+    /// How many members to pop; if this isn't given, a single member is popped
+    /// and the reply isn't nested for RESP3 clients
+    pub count: Option<usize>,
+}
+
+impl RespCommandConstructor for RespZpopCommand {
+    fn from_array(input: RespArray) -> Result<RespZpopCommand, RespCommandError> {
+        let (key_element, count) = match input.elements.as_slice() {
+            [_, key_element] => (key_element, None),
+            [_, key_element, count_element] => (key_element, Some(get_argument_integer(count_element)?)),
+            [_, _, _, ..] => return Err(RespCommandError::SyntaxError),
+            _ => return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input))),
+        };
+
+        if count.is_some_and(| c | c < 0) {
+            return Err(RespCommandError::InvalidArgument("value is out of range, must be positive".into()));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespZpopCommand { key, count: count.map(| c | c as usize) })
+    }
+}
+
+/// A call to `ZMPOP`, or the arguments after the timeout of `BZMPOP`
+#[derive(Debug)]
+pub struct RespZmpopCommand {
+    pub keys: Vec<Box<[u8]>>,
+    /// Whether the highest-scoring members are popped (`MAX`) rather than the
+    /// lowest (`MIN`)
+    pub highest: bool,
+    pub count: usize,
+}
+
+impl RespCommandConstructor for RespZmpopCommand {
+    fn from_array(input: RespArray) -> Result<RespZmpopCommand, RespCommandError> {
+        RespZmpopCommand::from_arguments(&input, 1)
+    }
+}
+
+impl RespZmpopCommand {
+    /// Parses the `numkeys key [key ...] MIN | MAX [COUNT count]` arguments
+    /// that start at `count_position`, which is 1 for `ZMPOP` and 2 for `BZMPOP`
+    fn from_arguments(input: &RespArray, count_position: usize) -> Result<RespZmpopCommand, RespCommandError> {
+        let positions = get_counted_key_positions(input, count_position)?;
+        let options_start = positions.last().map_or(count_position + 1, | p | p + 1);
+
+        let keys = positions.iter()
+            .map(| p | get_argument_bytes(&input.elements[*p]).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let [which_element, options @ ..] = &input.elements[options_start ..] else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        let highest = match get_argument_string(which_element)?.to_ascii_uppercase().as_str() {
+            "MIN" => false,
+            "MAX" => true,
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        let count = match options {
+            [] => 1,
+            [option, count_element] if get_argument_string(option)?.eq_ignore_ascii_case("COUNT") => {
+                match get_argument_integer(count_element)? {
+                    count if count > 0 => count as usize,
+                    _ => return Err(RespCommandError::InvalidArgument("count should be greater than 0".into())),
+                }
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        Ok(RespZmpopCommand { keys, highest, count })
+    }
+}
+
+/// How `ZUNION` and `ZINTER` combine the scores of a member that's in more
+/// than one of their inputs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            // adding infinities with opposite signs gives NaN, which Redis
+            // treats as 0
+            Aggregate::Sum => zero_if_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+/// The ways that `ZUNION`, `ZINTER` and `ZDIFF` combine sorted sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ZsetOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// A call to `ZUNION`, `ZINTER` or `ZDIFF`, or one of their `STORE` variants
+#[derive(Debug)]
+pub struct RespZsetOperationCommand {
+    pub destination: Option<Box<[u8]>>,
+    pub keys: Vec<Box<[u8]>>,
+    /// What each input's scores are multiplied by
+    pub weights: Vec<f64>,
+    pub aggregate: Aggregate,
+    pub with_scores: bool,
+}
+
+impl RespZsetOperationCommand {
+    /// Parses `[destination] numkeys key [key ...]` followed by the options,
+    /// where `WEIGHTS` and `AGGREGATE` are only allowed for unions and
+    /// intersections, and `WITHSCORES` is only allowed if the result isn't
+    /// being stored
+    fn from_arguments(input: &RespArray, operation: ZsetOperation, store: bool) -> Result<RespZsetOperationCommand, RespCommandError> {
+        let count_position = if store { 2 } else { 1 };
+
+        let destination = match store {
+            true => Some(get_argument_bytes(&input.elements[1])?.into_boxed_slice()),
+            false => None,
+        };
+
+        let positions = get_counted_key_positions(input, count_position)?;
+        let options_start = positions.last().map_or(count_position + 1, | p | p + 1);
+
+        let keys: Vec<Box<[u8]>> = positions.iter()
+            .map(| p | get_argument_bytes(&input.elements[*p]).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let mut weights = vec![1.0; keys.len()];
+        let mut aggregate = Aggregate::default();
+        let mut with_scores = false;
+
+        let mut options = input.elements[options_start ..].iter();
+        let allows_weights = operation != ZsetOperation::Difference;
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "WEIGHTS" if allows_weights => {
+                    for weight in weights.iter_mut() {
+                        let element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                        *weight = get_argument_float(element)
+                            .map_err(| _ | RespCommandError::InvalidArgument("weight value is not a float".into()))?;
+                    }
+                }
+                "AGGREGATE" if allows_weights => {
+                    let element = options.next().ok_or(RespCommandError::SyntaxError)?;
+
+                    aggregate = match get_argument_string(element)?.to_uppercase().as_str() {
+                        "SUM" => Aggregate::Sum,
+                        "MIN" => Aggregate::Min,
+                        "MAX" => Aggregate::Max,
+                        _ => return Err(RespCommandError::SyntaxError),
+                    };
+                }
+                "WITHSCORES" if !store => with_scores = true,
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespZsetOperationCommand { destination, keys, weights, aggregate, with_scores })
+    }
+}
+
+fn get_zset_operation_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 1)
+}
+
+fn get_zmpop_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 1)
+}
+
+fn get_bzmpop_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    get_counted_key_positions(args, 2)
+}
+
+fn get_zset_operation_store_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    let mut positions = vec![1];
+
+    positions.extend(get_counted_key_positions(args, 2)?);
+
+    Ok(positions)
+}
+
+/// Parses one end of a score range, which is a float that can be prefixed
+/// with `(` to exclude it from the range
+fn parse_score_bound(element: &RespElement) -> Result<ScoreBound, RespCommandError> {
+    let bytes = get_argument_bytes(element)?;
+
+    let (value, exclusive) = match bytes.strip_prefix(b"(") {
+        Some(value) => (value, true),
+        None => (bytes.as_slice(), false),
+    };
+
+    let value = str::from_utf8(value).ok()
+        .and_then(parse_double)
+        .filter(| v | !v.is_nan())
+        .ok_or_else(|| RespCommandError::InvalidArgument("min or max is not a float".into()))?;
+
+    Ok(ScoreBound { value, exclusive })
+}
+
+/// Parses one end of a lexicographical range, which is either `-` or `+` for
+/// the start or end of the set, or a member prefixed with `[` to include it
+/// in the range or `(` to exclude it
+fn parse_lex_bound(element: &RespElement) -> Result<LexBound, RespCommandError> {
+    let bytes = get_argument_bytes(element)?;
+
+    match bytes.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Start),
+        Some((b'+', [])) => Ok(LexBound::End),
+        Some((b'[', member)) => Ok(LexBound::Inclusive(member.to_vec())),
+        Some((b'(', member)) => Ok(LexBound::Exclusive(member.to_vec())),
+        _ => Err(RespCommandError::InvalidArgument("min or max not valid string range item".into())),
+    }
+}
+
+fn zero_if_nan(value: f64) -> f64 {
+    if value.is_nan() { 0.0 } else { value }
+}
+
+/// Builds the reply for commands that return members along with their scores
+/// (when `with_scores` is set): a flat array alternating between members and
+/// scores for RESP2 clients, or an array of `[member, score]` pairs for RESP3
+/// clients
+fn get_members_reply<'a>(
+    members: impl IntoIterator<Item = (&'a [u8], f64)>,
+    with_scores: bool,
+    protocol: RespProtocol,
+) -> RespElement {
+    let members = members.into_iter();
+
+    let reply = if with_scores && protocol == RespProtocol::Resp3 {
+        members.map(| (m, s) | RespElement::new_array(vec![RespElement::new_bulk_string(m), RespElement::new_double(s)])).collect()
+    } else if with_scores {
+        members.flat_map(| (m, s) | [RespElement::new_bulk_string(m), RespElement::new_double(s)]).collect()
+    } else {
+        members.map(| (m, _) | RespElement::new_bulk_string(m)).collect()
+    };
+
+    RespElement::new_array(reply)
+}
+
+/// What happened to a member that was given to `ZADD` or `ZINCRBY`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ZaddOutcome {
+    Added(f64),
+    /// The member's score was changed to the given one
+    Changed(f64),
+    /// The member already had the given score
+    Unchanged(f64),
+    /// The member wasn't added or updated because of the options
+    Skipped,
+}
+
+/// Adds a member or updates its score according to the options, the same way
+/// that Redis does for `ZADD`
+fn add_member(set: &mut SortedSet, member: Vec<u8>, score: f64, options: ZaddOptions) -> Result<ZaddOutcome, RespCommandError> {
+    let Some(current) = set.score(&member) else {
+        if options.xx {
+            return Ok(ZaddOutcome::Skipped);
+        }
+
+        set.insert(member, score);
+
+        return Ok(ZaddOutcome::Added(score));
+    };
+
+    if options.nx {
+        return Ok(ZaddOutcome::Skipped);
+    }
+
+    let updated = if options.incr { current + score } else { score };
+
+    if updated.is_nan() {
+        return Err(RespCommandError::InvalidArgument("resulting score is not a number (NaN)".into()));
+    }
+
+    if (options.gt && updated <= current) || (options.lt && updated >= current) {
+        return Ok(ZaddOutcome::Skipped);
+    }
+
+    if updated == current {
+        return Ok(ZaddOutcome::Unchanged(updated));
+    }
+
+    set.insert(member, updated);
+
+    Ok(ZaddOutcome::Changed(updated))
+}
+
+fn zadd(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    add_members(ctx.db, RespZaddCommand::from_array(input)?)
+}
+
+/// Adds the members or updates their scores, and replies the way that `ZADD`
+/// does
+pub fn add_members(db: &mut Database, command: RespZaddCommand) -> CommandResult {
+    let options = command.options;
+
+    // with `XX`, nothing can be added, so a sorted set isn't created for the
+    // key if it doesn't exist
+    let exists = db.get_sorted_set(&command.key)?.is_some();
+
+    if !exists && options.xx {
+        return Ok(match options.incr {
+            true => RespElement::new_null(),
+            false => RespElement::new_integer(0),
+        });
+    }
+
+    let set = db.get_or_insert_with(&command.key, || Value::SortedSet(SortedSet::new()))
+        .value
+        .as_sorted_set_mut()?;
+
+    let mut added = 0;
+    let mut changed = 0;
+    let mut last_outcome = ZaddOutcome::Skipped;
+
+    for (score, member) in command.members {
+        last_outcome = add_member(set, member, score, options)?;
+
+        match last_outcome {
+            ZaddOutcome::Added(_) => added += 1,
+            ZaddOutcome::Changed(_) => changed += 1,
+            _ => (),
+        }
+    }
+
+    // `INCR` can skip the only member it was given, which would leave an
+    // empty sorted set behind
+    db.delete_if_empty(&command.key);
+
+    if options.incr {
+        return Ok(match last_outcome {
+            ZaddOutcome::Added(s) | ZaddOutcome::Changed(s) | ZaddOutcome::Unchanged(s) => RespElement::new_double(s),
+            ZaddOutcome::Skipped => RespElement::new_null(),
+        });
+    }
+
+    let count = if options.ch { added + changed } else { added };
+
+    Ok(RespElement::new_integer(count))
+}
+
+fn zrem(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespZsetMembersCommand::from_array(input)?;
+
+    let Some(set) = ctx.db.get_sorted_set_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let removed = command.members.iter()
+        .filter(| member | set.remove(member).is_some())
+        .count();
+
+    ctx.db.delete_if_empty(&command.key);
+
+    Ok(RespElement::new_integer(removed as isize))
+}
+
+fn zscore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, member_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zscore".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let member = get_argument_bytes(member_element)?;
+
+    let score = ctx.db.get_sorted_set(&key)?.and_then(| set | set.score(&member));
+
+    Ok(score.map_or_else(RespElement::new_null, RespElement::new_double))
+}
+
+fn zmscore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespZsetMembersCommand::from_array(input)?;
+
+    let set = ctx.db.get_sorted_set(&command.key)?;
+
+    let scores = command.members.iter()
+        .map(| member | set.and_then(| s | s.score(member)))
+        .map(| score | score.map_or_else(RespElement::new_null, RespElement::new_double))
+        .collect();
+
+    Ok(RespElement::new_array(scores))
+}
+
+fn zincrby(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, increment_element, member_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zincrby".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let increment = get_argument_float(increment_element)?;
+    let member = get_argument_bytes(member_element)?;
+
+    let set = ctx.db.get_or_insert_with(&key, || Value::SortedSet(SortedSet::new()))
+        .value
+        .as_sorted_set_mut()?;
+
+    let options = ZaddOptions { incr: true, ..ZaddOptions::default() };
+
+    let outcome = add_member(set, member, increment, options);
+
+    // the increment can fail for a new key, which would leave an empty sorted
+    // set behind
+    ctx.db.delete_if_empty(&key);
+
+    match outcome? {
+        ZaddOutcome::Added(s) | ZaddOutcome::Changed(s) | ZaddOutcome::Unchanged(s) => Ok(RespElement::new_double(s)),
+        ZaddOutcome::Skipped => unreachable!("ZINCRBY doesn't take any options that skip members"),
+    }
+}
+
+fn zcard(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_sorted_set(&command.key)?.map_or(0, SortedSet::len);
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn zcount(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, min_element, max_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zcount".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let range = ScoreRange { min: parse_score_bound(min_element)?, max: parse_score_bound(max_element)? };
+
+    let count = ctx.db.get_sorted_set(&key)?.map_or(0, | set | set.ranks_by_score(&range).len());
+
+    Ok(RespElement::new_integer(count as isize))
+}
+
+fn zrank(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_rank(ctx, input, false)
+}
+
+fn zrevrank(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_rank(ctx, input, true)
+}
+
+/// Replies with the member's rank, along with its score if `WITHSCORE` was
+/// given, or null if it isn't in the sorted set
+fn get_rank(ctx: &mut CommandContext, input: RespArray, reverse: bool) -> CommandResult {
+    let command = RespZrankCommand::from_array(input)?;
+
+    let set = ctx.db.get_sorted_set(&command.key)?;
+
+    let Some((set, rank)) = set.and_then(| s | Some((s, s.rank(&command.member)?))) else {
+        return Ok(match command.with_score {
+            true => RespElement::new_null_array(),
+            false => RespElement::new_null(),
+        });
+    };
+
+    let rank = if reverse { set.len() - 1 - rank } else { rank };
+
+    if !command.with_score {
+        return Ok(RespElement::new_integer(rank as isize));
+    }
+
+    let score = set.score(&command.member).unwrap_or_default();
+
+    Ok(RespElement::new_array(vec![RespElement::new_integer(rank as isize), RespElement::new_double(score)]))
+}
+
+fn zrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespZrangeCommand::from_array(input)?;
+
+    let Some(set) = ctx.db.get_sorted_set(&command.key)? else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    let members = get_range(set, &command);
+
+    Ok(get_members_reply(members, command.with_scores, ctx.protocol))
+}
+
+/// Stores the range at the destination, replacing whatever was there and
+/// dropping its TTL, or deletes the destination if the range is empty.
+/// Replies with the number of members that were stored.
+fn zrangestore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, destination_element, key_element, rest @ ..] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zrangestore".into()));
+    };
+
+    let destination = get_argument_bytes(destination_element)?.into_boxed_slice();
+    let command = RespZrangeCommand::from_arguments(Some(destination), key_element, rest)?;
+
+    let mut result = SortedSet::new();
+
+    if let Some(set) = ctx.db.get_sorted_set(&command.key)? {
+        for (member, score) in get_range(set, &command) {
+            result.insert(member.to_vec(), score);
+        }
+    }
+
+    let destination = command.destination.as_deref().expect("STORE variants always have a destination");
+
+    Ok(store_sorted_set(ctx.db, destination, result))
+}
+
+/// Returns the members that a `ZRANGE` selects, in the order it returns them
+fn get_range<'a>(set: &'a SortedSet, command: &RespZrangeCommand) -> Vec<(&'a [u8], f64)> {
+    let length = set.len();
+
+    let ranks: Range<usize> = match &command.by {
+        ZrangeBy::Rank(start, stop) => match resolve_range(*start, *stop, length) {
+            // with `REV`, the ranks count from the highest score, so they're
+            // flipped around to count from the lowest
+            Some(ranks) if command.reverse => length - ranks.end .. length - ranks.start,
+            Some(ranks) => ranks,
+            None => 0 .. 0,
+        },
+        ZrangeBy::Score(range) => set.ranks_by_score(range),
+        ZrangeBy::Lex(range) => set.ranks_by_lex(range),
+    };
+
+    let (offset, count) = match command.limit {
+        Some((offset, _)) if offset < 0 => return Vec::new(),
+        Some((offset, count)) if count < 0 => (offset as usize, usize::MAX),
+        Some((offset, count)) => (offset as usize, count as usize),
+        None => (0, usize::MAX),
+    };
+
+    if offset >= ranks.len() {
+        return Vec::new();
+    }
+
+    let first = if command.reverse { ranks.end - 1 - offset } else { ranks.start + offset };
+
+    set.iter_from(first, command.reverse)
+        .take(count.min(ranks.len() - offset))
+        .collect()
+}
+
+fn zpopmin(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    pop(ctx, input, false)
+}
+
+fn zpopmax(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    pop(ctx, input, true)
+}
+
+/// Pops the members with the lowest scores, or the highest if `highest` is
+/// set. Without a count, the reply is a flat `[member, score]` array (which
+/// is empty if there was nothing to pop); with one, it's the same as a
+/// `ZRANGE ... WITHSCORES` reply.
+fn pop(ctx: &mut CommandContext, input: RespArray, highest: bool) -> CommandResult {
+    let command = RespZpopCommand::from_array(input)?;
+
+    let popped = pop_members(ctx.db, &command.key, command.count.unwrap_or(1), highest)?;
+
+    let members = popped.iter().map(| (m, s) | (m.as_slice(), *s));
+
+    match command.count {
+        Some(_) => Ok(get_members_reply(members, true, ctx.protocol)),
+        // a single member isn't nested in a pair, even for RESP3 clients
+        None => Ok(get_members_reply(members, true, RespProtocol::Resp2)),
+    }
+}
+
+/// Removes up to `count` members from the start of the sorted set, or from
+/// the end if `highest` is set, and deletes the key if it ends up empty
+pub fn pop_members(db: &mut Database, key: &[u8], count: usize, highest: bool) -> Result<Vec<(Vec<u8>, f64)>, WrongTypeError> {
+    let Some(set) = db.get_sorted_set_mut(key)? else {
+        return Ok(Vec::new());
+    };
+
+    let first = if highest { set.len().saturating_sub(1) } else { 0 };
+
+    let popped: Vec<(Vec<u8>, f64)> = set.iter_from(first, highest)
+        .take(count)
+        .map(| (m, s) | (m.to_vec(), s))
+        .collect();
+
+    for (member, _) in &popped {
+        set.remove(member);
+    }
+
+    db.delete_if_empty(key);
+
+    Ok(popped)
+}
+
+fn zmpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespZmpopCommand::from_array(input)?;
+
+    for key in &command.keys {
+        let popped = pop_members(ctx.db, key, command.count, command.highest)?;
+
+        if !popped.is_empty() {
+            return Ok(get_multi_pop_reply(key, &popped));
+        }
+    }
+
+    Ok(RespElement::new_null_array())
+}
+
+/// Builds the reply for `ZMPOP` and `BZMPOP`: the key that was popped from,
+/// followed by an array of `[member, score]` pairs (which are nested for
+/// RESP2 clients too)
+fn get_multi_pop_reply(key: &[u8], popped: &[(Vec<u8>, f64)]) -> RespElement {
+    let members = popped.iter().map(| (m, s) | (m.as_slice(), *s));
+
+    RespElement::new_array(vec![
+        RespElement::new_bulk_string(key),
+        get_members_reply(members, true, RespProtocol::Resp3),
+    ])
+}
+
+fn bzpopmin(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    blocking_pop(ctx, input, false)
+}
+
+fn bzpopmax(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    blocking_pop(ctx, input, true)
+}
+
+/// Pops the member with the lowest score (or the highest, if `highest` is
+/// set) from the first of the keys that holds a sorted set, replying with the
+/// key, the member and its score, or blocks until one of them does
+fn blocking_pop(ctx: &mut CommandContext, input: RespArray, highest: bool) -> CommandResult {
+    let command = RespBlockingPopCommand::from_array(input)?;
+
+    for key in &command.keys {
+        if let Some((member, score)) = pop_members(ctx.db, key, 1, highest)?.into_iter().next() {
+            return Ok(RespElement::new_array(vec![
+                RespElement::new_bulk_string(key),
+                RespElement::new_bulk_string(&member),
+                RespElement::new_double(score),
+            ]));
+        }
+    }
+
+    block_on_sorted_sets(ctx, &command.keys, command.timeout)
+}
+
+fn bzmpop(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let timeout = get_argument_timeout(&input.elements[1])?;
+    let command = RespZmpopCommand::from_arguments(&input, 2)?;
+
+    for key in &command.keys {
+        let popped = pop_members(ctx.db, key, command.count, command.highest)?;
+
+        if !popped.is_empty() {
+            return Ok(get_multi_pop_reply(key, &popped));
+        }
+    }
+
+    block_on_sorted_sets(ctx, &command.keys, timeout)
+}
+
+/// Tells the worker to park the client on `keys` until one of them holds a
+/// sorted set, or the timeout passes, and returns the reply that's sent if it
+/// does
+fn block_on_sorted_sets(ctx: &mut CommandContext, keys: &[Box<[u8]>], timeout: Option<u64>) -> CommandResult {
+    let deadline = timeout.map(| t | ctx.db.now_millis().saturating_add(t));
+
+    ctx.block_on(keys.iter().map(| k | k.to_vec()).collect(), "zset", deadline);
+
+    Ok(RespElement::new_null_array())
+}
+
+fn zremrangebyrank(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, start_element, stop_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zremrangebyrank".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let start = get_argument_integer(start_element)?;
+    let stop = get_argument_integer(stop_element)?;
+
+    remove_range(ctx.db, &key, | set | resolve_range(start, stop, set.len()).unwrap_or(0 .. 0))
+}
+
+fn zremrangebyscore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, min_element, max_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zremrangebyscore".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let range = ScoreRange { min: parse_score_bound(min_element)?, max: parse_score_bound(max_element)? };
+
+    remove_range(ctx.db, &key, | set | set.ranks_by_score(&range))
+}
+
+fn zremrangebylex(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let [_, key_element, min_element, max_element] = input.elements.as_slice() else {
+        return Err(RespCommandError::WrongNumberOfArguments("zremrangebylex".into()));
+    };
+
+    let key = get_argument_bytes(key_element)?;
+    let range = LexRange { min: parse_lex_bound(min_element)?, max: parse_lex_bound(max_element)? };
+
+    remove_range(ctx.db, &key, | set | set.ranks_by_lex(&range))
+}
+
+/// Removes the members whose ranks are given by `get_ranks`, and replies with
+/// how many were removed
+fn remove_range(db: &mut Database, key: &[u8], get_ranks: impl FnOnce(&SortedSet) -> Range<usize>) -> CommandResult {
+    let Some(set) = db.get_sorted_set_mut(key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let ranks = get_ranks(set);
+
+    let members: Vec<Vec<u8>> = set.iter_from(ranks.start, false)
+        .take(ranks.len())
+        .map(| (m, _) | m.to_vec())
+        .collect();
+
+    for member in &members {
+        set.remove(member);
+    }
+
+    db.delete_if_empty(key);
+
+    Ok(RespElement::new_integer(members.len() as isize))
+}
+
+fn zunion(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, ZsetOperation::Union)
+}
+
+fn zinter(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, ZsetOperation::Intersection)
+}
+
+fn zdiff(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine(ctx, input, ZsetOperation::Difference)
+}
+
+fn zunionstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, ZsetOperation::Union)
+}
+
+fn zinterstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, ZsetOperation::Intersection)
+}
+
+fn zdiffstore(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    combine_and_store(ctx, input, ZsetOperation::Difference)
+}
+
+fn combine(ctx: &mut CommandContext, input: RespArray, operation: ZsetOperation) -> CommandResult {
+    let command = RespZsetOperationCommand::from_arguments(&input, operation, false)?;
+
+    let result = combine_sorted_sets(ctx.db, &command, operation)?;
+
+    Ok(get_members_reply(result.iter(), command.with_scores, ctx.protocol))
+}
+
+/// Stores the combined sorted sets at the destination, and replies with the
+/// number of members in the result
+fn combine_and_store(ctx: &mut CommandContext, input: RespArray, operation: ZsetOperation) -> CommandResult {
+    let command = RespZsetOperationCommand::from_arguments(&input, operation, true)?;
+
+    let result = combine_sorted_sets(ctx.db, &command, operation)?;
+    let destination = command.destination.as_deref().expect("STORE variants always have a destination");
+
+    Ok(store_sorted_set(ctx.db, destination, result))
+}
+
+/// Combines the inputs of a `ZUNION`, `ZINTER` or `ZDIFF`, which can be sets
+/// as well as sorted sets (in which case every member has a score of 1).
+/// Keys that don't exist are treated as empty sets, and every key is type
+/// checked before anything is combined.
+fn combine_sorted_sets(
+    db: &mut Database,
+    command: &RespZsetOperationCommand,
+    operation: ZsetOperation,
+) -> Result<SortedSet, RespCommandError> {
+    let mut all_exist = true;
+
+    for key in &command.keys {
+        all_exist &= for_each_member(db, key, | _, _ | ())?;
+    }
+
+    if operation == ZsetOperation::Intersection && !all_exist {
+        return Ok(SortedSet::new());
+    }
+
+    let mut scores: HashMap<Vec<u8>, f64> = HashMap::new();
+
+    for (i, (key, weight)) in command.keys.iter().zip(&command.weights).enumerate() {
+        let weigh = | score: f64 | zero_if_nan(score * weight);
+
+        match operation {
+            ZsetOperation::Union => {
+                for_each_member(db, key, | member, score | {
+                    let score = weigh(score);
+
+                    scores.entry(member.to_vec())
+                        .and_modify(| current | *current = command.aggregate.apply(*current, score))
+                        .or_insert(score);
+                })?;
+            }
+            ZsetOperation::Intersection if i == 0 => {
+                for_each_member(db, key, | member, score | {
+                    scores.insert(member.to_vec(), weigh(score));
+                })?;
+            }
+            ZsetOperation::Intersection => {
+                let mut intersection = HashMap::new();
+
+                for_each_member(db, key, | member, score | {
+                    if let Some(current) = scores.get(member) {
+                        intersection.insert(member.to_vec(), command.aggregate.apply(*current, weigh(score)));
+                    }
+                })?;
+
+                scores = intersection;
+            }
+            ZsetOperation::Difference if i == 0 => {
+                for_each_member(db, key, | member, score | {
+                    scores.insert(member.to_vec(), score);
+                })?;
+            }
+            ZsetOperation::Difference => {
+                for_each_member(db, key, | member, _ | {
+                    scores.remove(member);
+                })?;
+            }
+        }
+    }
+
+    let mut result = SortedSet::new();
+
+    for (member, score) in scores {
+        result.insert(member, score);
+    }
+
+    Ok(result)
+}
+
+/// Calls `f` with each member of the set or sorted set at the key, and
+/// returns whether the key exists
+fn for_each_member(db: &mut Database, key: &[u8], mut f: impl FnMut(&[u8], f64)) -> Result<bool, WrongTypeError> {
+    match db.get(key).map(| e | &e.value) {
+        Some(Value::SortedSet(set)) => set.iter().for_each(| (m, s) | f(m, s)),
+        Some(Value::Set(set)) => set.iter().for_each(| m | f(&m, 1.0)),
+        Some(_) => return Err(WrongTypeError),
+        None => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Stores the sorted set at the key, replacing whatever was there and
+/// dropping its TTL, or deletes the key if the sorted set is empty. Replies
+/// with the number of members in the sorted set.
+pub fn store_sorted_set(db: &mut Database, key: &[u8], set: SortedSet) -> RespElement {
+    let count = set.len();
+
+    if set.is_empty() {
+        db.delete(key);
+    } else {
+        db.set(key, Value::SortedSet(set), SetOptions::default());
+    }
+
+    RespElement::new_integer(count as isize)
+}
@@ -0,0 +1,851 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::{RespElement, RespProtocol};
+use crate::resp::types::RespArray;
+use crate::store::{
+    STREAM_DEFAULT_TRIM_LIMIT,
+    Stream,
+    StreamFields,
+    StreamId,
+    TrimStrategy,
+    Value,
+};
+
+pub const XADD: CommandSpec = CommandSpec {
+    name: "xadd",
+    arity: -5,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xadd),
+};
+
+pub const XLEN: CommandSpec = CommandSpec {
+    name: "xlen",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Return the number of messages in a stream.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xlen),
+};
+
+pub const XRANGE: CommandSpec = CommandSpec {
+    name: "xrange",
+    arity: -4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Returns the messages from a stream within a range of IDs.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xrange),
+};
+
+pub const XREVRANGE: CommandSpec = CommandSpec {
+    name: "xrevrange",
+    arity: -4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Returns the messages from a stream within a range of IDs in reverse order.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xrevrange),
+};
+
+pub const XDEL: CommandSpec = CommandSpec {
+    name: "xdel",
+    arity: -3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Returns the number of messages after removing them from a stream.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xdel),
+};
+
+pub const XTRIM: CommandSpec = CommandSpec {
+    name: "xtrim",
+    arity: -4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Deletes messages from the beginning of a stream.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xtrim),
+};
+
+pub const XREAD: CommandSpec = CommandSpec {
+    name: "xread",
+    arity: -4,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Blocking],
+    keys: (0, 0, 0),
+    get_keys: Some(get_xread_keys),
+    group: "stream",
+    summary: "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xread),
+};
+
+/// How the ID of an entry that's added with `XADD` is picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XaddId {
+    /// `*`, which generates the ID from the current time
+    Auto,
+    /// `<ms>-*`, which generates the sequence number for the given time
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// The `MAXLEN` or `MINID` option given to `XADD` or `XTRIM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimOptions {
+    pub strategy: TrimStrategy,
+    /// Whether `~` was given, which lets the stream be trimmed less than asked
+    pub approximate: bool,
+    /// The most entries to remove, or `None` for no limit
+    pub limit: Option<usize>,
+}
+
+/// A call to `XADD`
+#[derive(Debug)]
+pub struct RespXaddCommand {
+    pub key: Box<[u8]>,
+    /// Whether `NOMKSTREAM` was given, in which case nothing is added unless
+    /// the stream already exists
+    pub no_mkstream: bool,
+    pub trim: Option<TrimOptions>,
+    pub id: XaddId,
+    pub fields: StreamFields,
+}
+
+impl RespCommandConstructor for RespXaddCommand {
+    fn from_array(input: RespArray) -> Result<RespXaddCommand, RespCommandError> {
+        let [_, key_element, arguments @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut no_mkstream = false;
+        let mut parser = TrimOptionsParser::default();
+        let mut position = 0;
+
+        while let Some(element) = arguments.get(position) {
+            if get_argument_string(element)?.eq_ignore_ascii_case("NOMKSTREAM") {
+                no_mkstream = true;
+                position += 1;
+            } else if let Some(consumed) = parser.parse_option(&arguments[position ..])? {
+                position += consumed;
+            } else {
+                break;
+            }
+        }
+
+        let trim = parser.finish()?;
+
+        let [id_element, field_elements @ ..] = &arguments[position ..] else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        if field_elements.is_empty() || !field_elements.len().is_multiple_of(2) {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let id = match get_argument_string(id_element)?.as_str() {
+            "*" => XaddId::Auto,
+            id => match id.strip_suffix("-*") {
+                Some(ms) => XaddId::AutoSeq(ms.parse().map_err(|_| invalid_stream_id())?),
+                None => XaddId::Explicit(StreamId::parse(id, 0).ok_or_else(invalid_stream_id)?),
+            },
+        };
+
+        if id == XaddId::Explicit(StreamId::MIN) {
+            return Err(RespCommandError::InvalidArgument("The ID specified in XADD must be greater than 0-0".into()));
+        }
+
+        let fields = field_elements.chunks_exact(2)
+            .map(| pair | Ok((get_argument_bytes(&pair[0])?, get_argument_bytes(&pair[1])?)))
+            .collect::<Result<_, RespCommandError>>()?;
+
+        Ok(RespXaddCommand { key, no_mkstream, trim, id, fields })
+    }
+}
+
+/// A call to `XTRIM`
+#[derive(Debug)]
+pub struct RespXtrimCommand {
+    pub key: Box<[u8]>,
+    pub trim: TrimOptions,
+}
+
+impl RespCommandConstructor for RespXtrimCommand {
+    fn from_array(input: RespArray) -> Result<RespXtrimCommand, RespCommandError> {
+        let [_, key_element, arguments @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let mut parser = TrimOptionsParser::default();
+        let mut position = 0;
+
+        while position < arguments.len() {
+            match parser.parse_option(&arguments[position ..])? {
+                Some(consumed) => position += consumed,
+                None => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        let trim = parser.finish()?.ok_or(RespCommandError::SyntaxError)?;
+
+        Ok(RespXtrimCommand { key, trim })
+    }
+}
+
+/// Collects the `MAXLEN`, `MINID` and `LIMIT` options of `XADD` and `XTRIM`,
+/// which can be given in any order
+#[derive(Debug, Default)]
+struct TrimOptionsParser {
+    strategy: Option<TrimStrategy>,
+    approximate: bool,
+    limit: Option<usize>,
+}
+
+impl TrimOptionsParser {
+    /// Parses the option at the start of `arguments`, and returns how many
+    /// arguments it took up, or `None` if it isn't a trimming option
+    fn parse_option(&mut self, arguments: &[RespElement]) -> Result<Option<usize>, RespCommandError> {
+        let [option_element, rest @ ..] = arguments else {
+            return Ok(None);
+        };
+
+        let option = get_argument_string(option_element)?.to_ascii_uppercase();
+
+        if option == "LIMIT" {
+            let [limit_element, ..] = rest else {
+                return Err(RespCommandError::SyntaxError);
+            };
+
+            match get_argument_integer(limit_element)? {
+                limit if limit >= 0 => self.limit = Some(limit as usize),
+                _ => return Err(RespCommandError::InvalidArgument("The LIMIT argument must be >= 0.".into())),
+            }
+
+            return Ok(Some(2));
+        }
+
+        if option != "MAXLEN" && option != "MINID" {
+            return Ok(None);
+        }
+
+        if self.strategy.is_some() {
+            return Err(RespCommandError::InvalidArgument(
+                "syntax error, MAXLEN and MINID options at the same time are not compatible".into(),
+            ));
+        }
+
+        let (approximate, threshold_element, consumed) = match rest {
+            [operator, threshold, ..] if get_argument_bytes(operator)? == b"~" => (true, threshold, 3),
+            [operator, threshold, ..] if get_argument_bytes(operator)? == b"=" => (false, threshold, 3),
+            [threshold, ..] => (false, threshold, 2),
+            [] => return Err(RespCommandError::SyntaxError),
+        };
+
+        self.approximate = approximate;
+
+        self.strategy = Some(if option == "MAXLEN" {
+            match get_argument_integer(threshold_element)? {
+                max_len if max_len >= 0 => TrimStrategy::MaxLen(max_len as usize),
+                _ => return Err(RespCommandError::InvalidArgument("The MAXLEN argument must be >= 0.".into())),
+            }
+        } else {
+            TrimStrategy::MinId(parse_stream_id(threshold_element, 0)?)
+        });
+
+        Ok(Some(consumed))
+    }
+
+    fn finish(self) -> Result<Option<TrimOptions>, RespCommandError> {
+        if self.limit.is_some() && !self.approximate {
+            return Err(RespCommandError::InvalidArgument("syntax error, LIMIT cannot be used without the special ~ option".into()));
+        }
+
+        let Some(strategy) = self.strategy else {
+            return Ok(None);
+        };
+
+        let limit = match self.limit {
+            // a limit of 0 means there isn't one
+            Some(0) => None,
+            Some(limit) => Some(limit),
+            None if self.approximate => Some(STREAM_DEFAULT_TRIM_LIMIT),
+            None => None,
+        };
+
+        Ok(Some(TrimOptions { strategy, approximate: self.approximate, limit }))
+    }
+}
+
+/// A call to `XRANGE` or `XREVRANGE`
+#[derive(Debug)]
+pub struct RespXrangeCommand {
+    pub key: Box<[u8]>,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: Option<usize>,
+}
+
+impl RespXrangeCommand {
+    /// Parses the arguments, where `reverse` is set for `XREVRANGE`, which
+    /// takes the end of the range before the start
+    fn from_arguments(input: RespArray, reverse: bool) -> Result<RespXrangeCommand, RespCommandError> {
+        let [_, key_element, first_element, second_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let (start_element, end_element) = if reverse {
+            (second_element, first_element)
+        } else {
+            (first_element, second_element)
+        };
+
+        let start = parse_range_id(start_element, false)?;
+        let end = parse_range_id(end_element, true)?;
+
+        let count = match options {
+            [] => None,
+            [option, count_element] if get_argument_string(option)?.eq_ignore_ascii_case("COUNT") => {
+                Some(get_argument_integer(count_element)?.max(0) as usize)
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        Ok(RespXrangeCommand { key, start, end, count })
+    }
+}
+
+/// A call to `XDEL`
+#[derive(Debug)]
+pub struct RespXdelCommand {
+    pub key: Box<[u8]>,
+    pub ids: Vec<StreamId>,
+}
+
+impl RespCommandConstructor for RespXdelCommand {
+    fn from_array(input: RespArray) -> Result<RespXdelCommand, RespCommandError> {
+        let [_, key_element, id_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if id_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let ids = id_elements.iter()
+            .map(| e | parse_stream_id(e, 0))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespXdelCommand { key, ids })
+    }
+}
+
+/// The ID given to `XREAD` for a stream, which it reads the entries after
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XreadId {
+    /// `$`, which is the last ID in the stream when the command was called,
+    /// so that only entries added after that are read
+    Last,
+    After(StreamId),
+}
+
+/// A call to `XREAD`
+#[derive(Debug)]
+pub struct RespXreadCommand {
+    /// The most entries to read from each stream, or `None` for no limit
+    pub count: Option<usize>,
+    /// How long to block for in milliseconds if there's nothing to read yet,
+    /// where `Some(None)` blocks forever and `None` doesn't block at all
+    pub block: Option<Option<u64>>,
+    pub keys: Vec<Box<[u8]>>,
+    pub ids: Vec<XreadId>,
+}
+
+impl RespCommandConstructor for RespXreadCommand {
+    fn from_array(input: RespArray) -> Result<RespXreadCommand, RespCommandError> {
+        let XreadOptions { count, block, first_key, .. } = parse_xread_options(&input)?;
+
+        let streams = &input.elements[first_key ..];
+        let (key_elements, id_elements) = streams.split_at(streams.len() / 2);
+
+        let keys = key_elements.iter()
+            .map(| e | get_argument_bytes(e).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let ids = id_elements.iter()
+            .map(| e | match get_argument_bytes(e)?.as_slice() {
+                b"$" => Ok(XreadId::Last),
+                _ => parse_stream_id(e, 0).map(XreadId::After),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespXreadCommand { count, block, keys, ids })
+    }
+}
+
+impl RespXreadCommand {
+    /// Builds the arguments for the same call, but with the given IDs in place
+    /// of the ones it was called with
+    fn to_arguments(&self, ids: &[StreamId]) -> RespArray {
+        let mut elements = vec![RespElement::new_bulk_string(b"XREAD")];
+
+        if let Some(count) = self.count {
+            elements.push(RespElement::new_bulk_string(b"COUNT"));
+            elements.push(RespElement::new_bulk_string(count.to_string().as_bytes()));
+        }
+
+        if let Some(timeout) = self.block {
+            elements.push(RespElement::new_bulk_string(b"BLOCK"));
+            elements.push(RespElement::new_bulk_string(timeout.unwrap_or(0).to_string().as_bytes()));
+        }
+
+        elements.push(RespElement::new_bulk_string(b"STREAMS"));
+        elements.extend(self.keys.iter().map(| k | RespElement::new_bulk_string(k)));
+        elements.extend(ids.iter().map(| id | RespElement::new_bulk_string(id.to_string().as_bytes())));
+
+        RespArray::new(elements)
+    }
+}
+
+/// The options that come before `STREAMS` in a call to `XREAD` or
+/// `XREADGROUP`
+#[derive(Debug)]
+pub struct XreadOptions {
+    pub count: Option<usize>,
+    pub block: Option<Option<u64>>,
+    /// The group and consumer names given with `GROUP`, which is only taken
+    /// (and required) by `XREADGROUP`
+    pub group: Option<Box<[u8]>>,
+    pub consumer: Option<Box<[u8]>>,
+    /// Whether `NOACK` was given to `XREADGROUP`
+    pub no_ack: bool,
+    /// The position of the first key, which comes straight after `STREAMS`
+    pub first_key: usize,
+}
+
+pub fn parse_xread_options(input: &RespArray) -> Result<XreadOptions, RespCommandError> {
+    let is_group = get_command_name(input) == "xreadgroup";
+
+    let mut options = XreadOptions { count: None, block: None, group: None, consumer: None, no_ack: false, first_key: 0 };
+    let mut position = 1;
+
+    loop {
+        let Some(option_element) = input.elements.get(position) else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        let option = get_argument_string(option_element)?.to_ascii_uppercase();
+
+        match option.as_str() {
+            "STREAMS" => break,
+            "NOACK" if is_group => {
+                options.no_ack = true;
+                position += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(value_element) = input.elements.get(position + 1) else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        match option.as_str() {
+            // a count of 0 (or less) means there isn't one
+            "COUNT" => options.count = Some(get_argument_integer(value_element)?).filter(| c | *c > 0).map(| c | c as usize),
+            "BLOCK" => options.block = Some(get_block_timeout(value_element)?),
+            "GROUP" if !is_group => {
+                return Err(RespCommandError::InvalidArgument(
+                    "The GROUP option is only supported by XREADGROUP. You called XREAD instead.".into(),
+                ));
+            }
+            "GROUP" => {
+                let Some(consumer_element) = input.elements.get(position + 2) else {
+                    return Err(RespCommandError::SyntaxError);
+                };
+
+                options.group = Some(get_argument_bytes(value_element)?.into_boxed_slice());
+                options.consumer = Some(get_argument_bytes(consumer_element)?.into_boxed_slice());
+
+                position += 1;
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        }
+
+        position += 2;
+    }
+
+    options.first_key = position + 1;
+    let streams = input.elements.len() - options.first_key;
+
+    if streams == 0 || !streams.is_multiple_of(2) {
+        return Err(RespCommandError::InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '{}' must be specified.",
+            get_command_name(input),
+            if is_group { ">" } else { "$" },
+        )));
+    }
+
+    if is_group && options.group.is_none() {
+        return Err(RespCommandError::InvalidArgument("Missing GROUP option for XREADGROUP".into()));
+    }
+
+    Ok(options)
+}
+
+/// Finds the keys of `XREAD` and `XREADGROUP`, which are the first half of
+/// the arguments after `STREAMS`
+pub fn get_xread_keys(args: &RespArray) -> Result<Vec<usize>, RespCommandError> {
+    let first_key = parse_xread_options(args)?.first_key;
+    let streams = (args.elements.len() - first_key) / 2;
+
+    Ok((first_key .. first_key + streams).collect())
+}
+
+/// Parses the `BLOCK` timeout of `XREAD`, which is given in milliseconds. A
+/// timeout of zero means waiting forever, which is `None`.
+fn get_block_timeout(element: &RespElement) -> Result<Option<u64>, RespCommandError> {
+    let timeout = get_argument_integer(element)
+        .map_err(|_| RespCommandError::InvalidArgument("timeout is not an integer or out of range".into()))?;
+
+    match timeout {
+        timeout if timeout < 0 => Err(RespCommandError::InvalidArgument("timeout is negative".into())),
+        0 => Ok(None),
+        timeout => Ok(Some(timeout as u64)),
+    }
+}
+
+fn invalid_stream_id() -> RespCommandError {
+    RespCommandError::InvalidArgument("Invalid stream ID specified as stream command argument".into())
+}
+
+/// Parses a stream ID argument, which can leave out the sequence number, in
+/// which case it's `default_seq`
+pub fn parse_stream_id(element: &RespElement, default_seq: u64) -> Result<StreamId, RespCommandError> {
+    StreamId::parse(&get_argument_string(element)?, default_seq).ok_or_else(invalid_stream_id)
+}
+
+/// Parses one end of the range given to `XRANGE` or `XREVRANGE`, which can be
+/// `-` or `+` for the lowest and highest possible IDs, or prefixed with `(` to
+/// leave the ID itself out of the range. An ID without a sequence number
+/// covers every entry in that millisecond.
+pub fn parse_range_id(element: &RespElement, is_end: bool) -> Result<StreamId, RespCommandError> {
+    let bound = get_argument_string(element)?;
+
+    match bound.as_str() {
+        "-" => return Ok(StreamId::MIN),
+        "+" => return Ok(StreamId::MAX),
+        _ => {}
+    }
+
+    let default_seq = if is_end { u64::MAX } else { 0 };
+
+    let Some(id) = bound.strip_prefix('(') else {
+        return StreamId::parse(&bound, default_seq).ok_or_else(invalid_stream_id);
+    };
+
+    let id = StreamId::parse(id, default_seq).ok_or_else(invalid_stream_id)?;
+
+    if is_end {
+        id.previous().ok_or_else(|| RespCommandError::InvalidArgument("invalid end ID for the interval".into()))
+    } else {
+        id.next().ok_or_else(|| RespCommandError::InvalidArgument("invalid start ID for the interval".into()))
+    }
+}
+
+/// Builds the reply for a single entry: its ID, followed by a flat array of
+/// its fields and their values
+pub fn get_entry_reply(id: &StreamId, fields: &StreamFields) -> RespElement {
+    let fields = fields.iter()
+        .flat_map(| (f, v) | [RespElement::new_bulk_string(f), RespElement::new_bulk_string(v)])
+        .collect();
+
+    RespElement::new_array(vec![
+        RespElement::new_bulk_string(id.to_string().as_bytes()),
+        RespElement::new_array(fields),
+    ])
+}
+
+/// Builds the reply for commands that read from several streams: a map of
+/// each key to its entries for RESP3 clients, or an array of `[key, entries]`
+/// pairs for RESP2 clients
+pub fn get_streams_reply(streams: Vec<(&[u8], Vec<RespElement>)>, protocol: RespProtocol) -> RespElement {
+    let streams = streams.into_iter()
+        .map(| (key, entries) | (RespElement::new_bulk_string(key), RespElement::new_array(entries)));
+
+    match protocol {
+        RespProtocol::Resp3 => RespElement::new_map(streams.collect()),
+        _ => RespElement::new_array(streams.map(| (k, e) | RespElement::new_array(vec![k, e])).collect()),
+    }
+}
+
+fn xadd(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXaddCommand::from_array(input)?;
+
+    let now = ctx.db.now_millis();
+    let empty = Stream::new();
+
+    // the ID is worked out before the stream is created, so that a stream
+    // isn't left behind if the ID is rejected
+    let id = match ctx.db.get_stream(&command.key)? {
+        Some(stream) => get_new_entry_id(stream, command.id, now)?,
+        None if command.no_mkstream => return Ok(RespElement::new_null()),
+        None => get_new_entry_id(&empty, command.id, now)?,
+    };
+
+    let stream = ctx.db.get_or_insert_with(&command.key, || Value::Stream(Stream::new())).value.as_stream_mut()?;
+
+    stream.insert(id, command.fields);
+
+    if let Some(trim) = command.trim {
+        stream.trim(trim.strategy, trim.approximate, trim.limit);
+    }
+
+    Ok(RespElement::new_bulk_string(id.to_string().as_bytes()))
+}
+
+/// Works out the ID for an entry that's being added to the stream, which has
+/// to be greater than the stream's last ID
+fn get_new_entry_id(stream: &Stream, id: XaddId, now: u64) -> Result<StreamId, RespCommandError> {
+    let too_small = || RespCommandError::InvalidArgument(
+        "The ID specified in XADD is equal or smaller than the target stream top item".into(),
+    );
+
+    match id {
+        XaddId::Auto => stream.next_id(now).ok_or_else(|| RespCommandError::InvalidArgument(
+            "The stream has exhausted the last possible ID, unable to add more items".into(),
+        )),
+        XaddId::AutoSeq(ms) => stream.next_id_at(ms).ok_or_else(too_small),
+        XaddId::Explicit(id) if id > stream.last_id() => Ok(id),
+        XaddId::Explicit(_) => Err(too_small()),
+    }
+}
+
+fn xlen(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_stream(&command.key)?.map_or(0, Stream::len);
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+fn xrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_range(ctx, input, false)
+}
+
+fn xrevrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    get_range(ctx, input, true)
+}
+
+/// Replies with the entries whose IDs are in the range, from the lowest ID
+/// to the highest, or the other way around if `reverse` is set
+fn get_range(ctx: &mut CommandContext, input: RespArray, reverse: bool) -> CommandResult {
+    let command = RespXrangeCommand::from_arguments(input, reverse)?;
+
+    let Some(stream) = ctx.db.get_stream(&command.key)? else {
+        return Ok(RespElement::new_array(Vec::new()));
+    };
+
+    let count = command.count.unwrap_or(usize::MAX);
+    let range = stream.range(command.start, command.end);
+
+    let entries = if reverse {
+        range.rev().take(count).map(| (id, fields) | get_entry_reply(id, fields)).collect()
+    } else {
+        range.take(count).map(| (id, fields) | get_entry_reply(id, fields)).collect()
+    };
+
+    Ok(RespElement::new_array(entries))
+}
+
+fn xdel(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXdelCommand::from_array(input)?;
+
+    let Some(stream) = ctx.db.get_stream_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let deleted = command.ids.into_iter()
+        .filter(| id | stream.remove(*id))
+        .count();
+
+    Ok(RespElement::new_integer(deleted as isize))
+}
+
+fn xtrim(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXtrimCommand::from_array(input)?;
+
+    let Some(stream) = ctx.db.get_stream_mut(&command.key)? else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let trimmed = stream.trim(command.trim.strategy, command.trim.approximate, command.trim.limit);
+
+    Ok(RespElement::new_integer(trimmed as isize))
+}
+
+/// Replies with the entries after the given ID in each of the streams,
+/// leaving out the streams that don't have any. If none of them do and
+/// `BLOCK` was given, the client waits until an entry is added to one of them.
+fn xread(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXreadCommand::from_array(input)?;
+
+    // `$` is resolved up front, so that it still refers to the same entry
+    // if the command has to be run again after blocking
+    let mut ids = Vec::with_capacity(command.ids.len());
+
+    for (key, id) in command.keys.iter().zip(&command.ids) {
+        let stream = ctx.db.get_stream(key)?;
+
+        ids.push(match id {
+            XreadId::Last => stream.map_or(StreamId::MIN, Stream::last_id),
+            XreadId::After(id) => *id,
+        });
+    }
+
+    let mut streams = Vec::new();
+
+    for (key, id) in command.keys.iter().zip(&ids) {
+        let Some(start) = id.next() else {
+            continue;
+        };
+
+        let Some(stream) = ctx.db.get_stream(key)? else {
+            continue;
+        };
+
+        let entries: Vec<RespElement> = stream.range(start, StreamId::MAX)
+            .take(command.count.unwrap_or(usize::MAX))
+            .map(| (id, fields) | get_entry_reply(id, fields))
+            .collect();
+
+        if !entries.is_empty() {
+            streams.push((key.as_ref(), entries));
+        }
+    }
+
+    if !streams.is_empty() {
+        return Ok(get_streams_reply(streams, ctx.protocol));
+    }
+
+    let Some(timeout) = command.block else {
+        return Ok(RespElement::new_null_array());
+    };
+
+    let deadline = timeout.map(| t | ctx.db.now_millis().saturating_add(t));
+
+    ctx.block_on(command.keys.iter().map(| k | k.to_vec()).collect(), "stream", deadline);
+    ctx.set_retry_args(command.to_arguments(&ids));
+
+    Ok(RespElement::new_null_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range_id(bound: &str, is_end: bool) -> Result<StreamId, String> {
+        parse_range_id(&RespElement::new_bulk_string(bound.as_bytes()), is_end).map_err(| e | e.to_string())
+    }
+
+    fn xadd_id(args: &[&str]) -> Result<XaddId, String> {
+        let mut elements = vec![RespElement::new_bulk_string(b"XADD"), RespElement::new_bulk_string(b"key")];
+
+        elements.extend(args.iter().map(| a | RespElement::new_bulk_string(a.as_bytes())));
+        elements.extend([RespElement::new_bulk_string(b"field"), RespElement::new_bulk_string(b"value")]);
+
+        RespXaddCommand::from_array(RespArray::new(elements)).map(| c | c.id).map_err(| e | e.to_string())
+    }
+
+    #[test]
+    fn range_bounds_cover_whole_milliseconds() {
+        assert_eq!(range_id("-", false), Ok(StreamId::MIN));
+        assert_eq!(range_id("+", true), Ok(StreamId::MAX));
+        assert_eq!(range_id("5", false), Ok(StreamId::new(5, 0)));
+        assert_eq!(range_id("5", true), Ok(StreamId::new(5, u64::MAX)));
+        assert_eq!(range_id("5-3", true), Ok(StreamId::new(5, 3)));
+    }
+
+    #[test]
+    fn exclusive_range_bounds_skip_the_id() {
+        assert_eq!(range_id("(5-3", false), Ok(StreamId::new(5, 4)));
+        assert_eq!(range_id("(5-3", true), Ok(StreamId::new(5, 2)));
+        assert_eq!(range_id("(5", false), Ok(StreamId::new(5, 1)));
+        assert_eq!(range_id("(5-0", true), Ok(StreamId::new(4, u64::MAX)));
+
+        assert_eq!(range_id("(18446744073709551615-18446744073709551615", false), Err("ERR invalid start ID for the interval".into()));
+        assert_eq!(range_id("(0-0", true), Err("ERR invalid end ID for the interval".into()));
+        assert_eq!(range_id("(-", false), Err("ERR Invalid stream ID specified as stream command argument".into()));
+    }
+
+    #[test]
+    fn xadd_ids_can_be_generated_in_part_or_in_full() {
+        assert_eq!(xadd_id(&["*"]), Ok(XaddId::Auto));
+        assert_eq!(xadd_id(&["5-*"]), Ok(XaddId::AutoSeq(5)));
+        assert_eq!(xadd_id(&["5"]), Ok(XaddId::Explicit(StreamId::new(5, 0))));
+        assert_eq!(xadd_id(&["MAXLEN", "10", "5-1"]), Ok(XaddId::Explicit(StreamId::new(5, 1))));
+
+        assert_eq!(xadd_id(&["0-0"]), Err("ERR The ID specified in XADD must be greater than 0-0".into()));
+        assert_eq!(xadd_id(&["x-*"]), Err("ERR Invalid stream ID specified as stream command argument".into()));
+    }
+
+    #[test]
+    fn xadd_ids_have_to_move_forward() {
+        let mut stream = Stream::new();
+
+        stream.insert(StreamId::new(5, 3), Vec::new());
+
+        let too_small = Err("ERR The ID specified in XADD is equal or smaller than the target stream top item".to_string());
+        let new_id = | id | get_new_entry_id(&stream, id, 1).map_err(| e | e.to_string());
+
+        assert_eq!(new_id(XaddId::Explicit(StreamId::new(5, 3))), too_small);
+        assert_eq!(new_id(XaddId::Explicit(StreamId::new(4, 9))), too_small);
+        assert_eq!(new_id(XaddId::AutoSeq(4)), too_small);
+        assert_eq!(new_id(XaddId::Explicit(StreamId::new(5, 4))), Ok(StreamId::new(5, 4)));
+        assert_eq!(new_id(XaddId::AutoSeq(5)), Ok(StreamId::new(5, 4)));
+
+        // the clock is behind the last ID, so the sequence number is used
+        assert_eq!(new_id(XaddId::Auto), Ok(StreamId::new(5, 4)));
+
+        stream.insert(StreamId::MAX, Vec::new());
+
+        assert_eq!(
+            get_new_entry_id(&stream, XaddId::Auto, 1).map_err(| e | e.to_string()),
+            Err("ERR The stream has exhausted the last possible ID, unable to add more items".into()),
+        );
+    }
+}
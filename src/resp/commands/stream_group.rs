@@ -0,0 +1,1020 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    get_argument_bytes,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::commands::stream::{
+    XreadId,
+    XreadOptions,
+    get_entry_reply,
+    get_streams_reply,
+    get_xread_keys,
+    parse_range_id,
+    parse_stream_id,
+    parse_xread_options,
+};
+use crate::resp::RespElement;
+use crate::resp::types::RespArray;
+use crate::store::{ClaimOptions, ConsumerGroup, Stream, StreamId, Value};
+
+/// How many entries `XINFO STREAM FULL` lists by default, for the stream and
+/// for each pending entries list
+const XINFO_FULL_DEFAULT_COUNT: usize = 10;
+
+/// The most entries that `XAUTOCLAIM` looks at for each one that it's asked to
+/// claim, which limits how long a single call can take
+const XAUTOCLAIM_ATTEMPTS_FACTOR: i64 = 10;
+
+pub const XGROUP: CommandSpec = CommandSpec {
+    name: "xgroup",
+    arity: -2,
+    flags: &[CommandFlag::Write],
+    keys: (2, 2, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "A container for consumer groups commands.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xgroup),
+};
+
+pub const XREADGROUP: CommandSpec = CommandSpec {
+    name: "xreadgroup",
+    arity: -7,
+    flags: &[CommandFlag::Write, CommandFlag::Blocking],
+    keys: (0, 0, 0),
+    get_keys: Some(get_xread_keys),
+    group: "stream",
+    summary: "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xreadgroup),
+};
+
+pub const XACK: CommandSpec = CommandSpec {
+    name: "xack",
+    arity: -4,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xack),
+};
+
+pub const XPENDING: CommandSpec = CommandSpec {
+    name: "xpending",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Returns the information and entries from a stream consumer group's pending entries list.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xpending),
+};
+
+pub const XCLAIM: CommandSpec = CommandSpec {
+    name: "xclaim",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Changes, or acquires, ownership of a message in a consumer group, as if the message was delivered a consumer group member.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xclaim),
+};
+
+pub const XAUTOCLAIM: CommandSpec = CommandSpec {
+    name: "xautoclaim",
+    arity: -6,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "Changes, or acquires, ownership of messages in a consumer group, as if the messages were delivered to as consumer group member.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(xautoclaim),
+};
+
+pub const XINFO: CommandSpec = CommandSpec {
+    name: "xinfo",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (2, 2, 1),
+    get_keys: None,
+    group: "stream",
+    summary: "A container for stream introspection commands.",
+    since: "5.0.0",
+    handler: CommandHandler::Store(xinfo),
+};
+
+const XGROUP_HELP: &[&str] = &[
+    "XGROUP <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "CREATE <key> <groupname> <id|$> [option]",
+    "    Create a new consumer group. Options are:",
+    "    * MKSTREAM",
+    "      Create the empty stream if it does not exist.",
+    "    * ENTRIESREAD entries_read",
+    "      Set the group's entries_read counter (internal use).",
+    "CREATECONSUMER <key> <groupname> <consumer>",
+    "    Create a new consumer in the specified group.",
+    "DELCONSUMER <key> <groupname> <consumer>",
+    "    Remove the specified consumer.",
+    "DESTROY <key> <groupname>",
+    "    Remove the specified group.",
+    "SETID <key> <groupname> <id|$> [ENTRIESREAD entries_read]",
+    "    Set the current group ID and entries_read counter.",
+    "HELP",
+    "    Print this help.",
+];
+
+const XINFO_HELP: &[&str] = &[
+    "XINFO <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+    "CONSUMERS <key> <groupname>",
+    "    Show consumers of <groupname>.",
+    "GROUPS <key>",
+    "    Show the stream consumer groups.",
+    "STREAM <key> [FULL [COUNT <count>]",
+    "    Show information about the stream.",
+    "HELP",
+    "    Print this help.",
+];
+
+/// The ID given to `XREADGROUP` for a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XreadgroupId {
+    /// `>`, which reads entries that haven't been delivered to the group yet
+    New,
+    /// Reads the consumer's pending entries after this ID again
+    History(StreamId),
+}
+
+/// A call to `XREADGROUP`
+#[derive(Debug)]
+pub struct RespXreadgroupCommand {
+    pub group: Box<[u8]>,
+    pub consumer: Box<[u8]>,
+    /// The most entries to read from each stream, or `None` for no limit
+    pub count: Option<usize>,
+    /// How long to block for in milliseconds if there's nothing to read yet,
+    /// where `Some(None)` blocks forever and `None` doesn't block at all
+    pub block: Option<Option<u64>>,
+    /// Whether the entries that are read are left out of the pending entries
+    /// list, as though they were acknowledged straight away
+    pub no_ack: bool,
+    pub keys: Vec<Box<[u8]>>,
+    pub ids: Vec<XreadgroupId>,
+}
+
+impl RespCommandConstructor for RespXreadgroupCommand {
+    fn from_array(input: RespArray) -> Result<RespXreadgroupCommand, RespCommandError> {
+        let XreadOptions { count, block, group, consumer, no_ack, first_key } = parse_xread_options(&input)?;
+        let (Some(group), Some(consumer)) = (group, consumer) else {
+            return Err(RespCommandError::SyntaxError);
+        };
+
+        let streams = &input.elements[first_key ..];
+        let (key_elements, id_elements) = streams.split_at(streams.len() / 2);
+
+        let keys = key_elements.iter()
+            .map(| e | get_argument_bytes(e).map(Vec::into_boxed_slice))
+            .collect::<Result<_, _>>()?;
+
+        let ids = id_elements.iter()
+            .map(| e | match get_argument_bytes(e)?.as_slice() {
+                b">" => Ok(XreadgroupId::New),
+                b"$" => Err(RespCommandError::InvalidArgument(
+                    "The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by \
+                     specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set."
+                        .into(),
+                )),
+                _ => parse_stream_id(e, 0).map(XreadgroupId::History),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespXreadgroupCommand { group, consumer, count, block, no_ack, keys, ids })
+    }
+}
+
+/// A call to `XACK`
+#[derive(Debug)]
+pub struct RespXackCommand {
+    pub key: Box<[u8]>,
+    pub group: Box<[u8]>,
+    pub ids: Vec<StreamId>,
+}
+
+impl RespCommandConstructor for RespXackCommand {
+    fn from_array(input: RespArray) -> Result<RespXackCommand, RespCommandError> {
+        let [_, key_element, group_element, id_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if id_elements.is_empty() {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let group = get_argument_bytes(group_element)?.into_boxed_slice();
+
+        let ids = id_elements.iter()
+            .map(| e | parse_stream_id(e, 0))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RespXackCommand { key, group, ids })
+    }
+}
+
+/// A call to `XPENDING`
+#[derive(Debug)]
+pub struct RespXpendingCommand {
+    pub key: Box<[u8]>,
+    pub group: Box<[u8]>,
+    /// The range of pending entries to list, or `None` for a summary of them
+    pub range: Option<XpendingRange>,
+}
+
+/// The arguments of the extended form of `XPENDING`, which lists the pending
+/// entries themselves
+#[derive(Debug)]
+pub struct XpendingRange {
+    /// How long an entry has to have been pending for, in milliseconds, for
+    /// it to be listed
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    /// Only lists the entries that are pending for this consumer
+    pub consumer: Option<Box<[u8]>>,
+}
+
+impl RespCommandConstructor for RespXpendingCommand {
+    fn from_array(input: RespArray) -> Result<RespXpendingCommand, RespCommandError> {
+        let [_, key_element, group_element, arguments @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let group = get_argument_bytes(group_element)?.into_boxed_slice();
+
+        if arguments.is_empty() {
+            return Ok(RespXpendingCommand { key, group, range: None });
+        }
+
+        let (min_idle, range_elements) = match arguments {
+            [option, min_idle, rest @ ..] if get_argument_string(option)?.eq_ignore_ascii_case("IDLE") => {
+                (get_argument_integer(min_idle)?.max(0) as u64, rest)
+            }
+            _ => (0, arguments),
+        };
+
+        let (start_element, end_element, count_element, consumer) = match range_elements {
+            [start, end, count] => (start, end, count, None),
+            [start, end, count, consumer] => (start, end, count, Some(get_argument_bytes(consumer)?.into_boxed_slice())),
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        let range = XpendingRange {
+            min_idle,
+            start: parse_range_id(start_element, false)?,
+            end: parse_range_id(end_element, true)?,
+            count: get_argument_integer(count_element)?.max(0) as usize,
+            consumer,
+        };
+
+        Ok(RespXpendingCommand { key, group, range: Some(range) })
+    }
+}
+
+/// A call to `XCLAIM`
+#[derive(Debug)]
+pub struct RespXclaimCommand {
+    pub key: Box<[u8]>,
+    pub group: Box<[u8]>,
+    pub consumer: Box<[u8]>,
+    pub ids: Vec<StreamId>,
+    /// The options, apart from the delivery time, which depends on the time
+    /// that the command is run
+    pub options: ClaimOptions,
+    pub idle: Option<i64>,
+    pub time: Option<i64>,
+}
+
+impl RespCommandConstructor for RespXclaimCommand {
+    fn from_array(input: RespArray) -> Result<RespXclaimCommand, RespCommandError> {
+        let [_, key_element, group_element, consumer_element, min_idle_element, arguments @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let group = get_argument_bytes(group_element)?.into_boxed_slice();
+        let consumer = get_argument_bytes(consumer_element)?.into_boxed_slice();
+
+        let min_idle = get_argument_integer(min_idle_element)
+            .map_err(|_| RespCommandError::InvalidArgument("Invalid min-idle-time argument for XCLAIM".into()))?;
+
+        let mut options = ClaimOptions { min_idle: min_idle.max(0) as u64, ..ClaimOptions::default() };
+
+        // the IDs run up to the first argument that isn't one, which is where
+        // the options start
+        let mut ids = Vec::new();
+        let mut position = 0;
+
+        while let Some(id) = arguments.get(position).and_then(| e | parse_stream_id(e, 0).ok()) {
+            ids.push(id);
+            position += 1;
+        }
+
+        let mut idle = None;
+        let mut time = None;
+
+        while let Some(option_element) = arguments.get(position) {
+            let option = get_argument_string(option_element)?;
+            let invalid = | name: &str | RespCommandError::InvalidArgument(format!("Invalid {name} option argument for XCLAIM"));
+
+            match (option.to_ascii_uppercase().as_str(), arguments.get(position + 1)) {
+                ("FORCE", _) => {
+                    options.force = true;
+                    position += 1;
+                    continue;
+                }
+                ("JUSTID", _) => {
+                    options.just_id = true;
+                    position += 1;
+                    continue;
+                }
+                ("IDLE", Some(value)) => idle = Some(get_argument_integer(value).map_err(|_| invalid("IDLE"))?),
+                ("TIME", Some(value)) => time = Some(get_argument_integer(value).map_err(|_| invalid("TIME"))?),
+                ("RETRYCOUNT", Some(value)) => {
+                    let retry_count = get_argument_integer(value).map_err(|_| invalid("RETRYCOUNT"))?;
+
+                    // a negative count is the same as not giving one
+                    options.retry_count = (retry_count >= 0).then_some(retry_count as u64);
+                }
+                ("LASTID", Some(value)) => options.last_id = Some(parse_stream_id(value, 0)?),
+                _ => return Err(RespCommandError::InvalidArgument(format!("Unrecognized XCLAIM option '{option}'"))),
+            }
+
+            position += 2;
+        }
+
+        Ok(RespXclaimCommand { key, group, consumer, ids, options, idle, time })
+    }
+}
+
+impl RespXclaimCommand {
+    /// Works out the delivery time that claimed entries are given, which is
+    /// `now` unless `IDLE` or `TIME` set an earlier one
+    fn get_delivery_time(&self, now: u64) -> u64 {
+        let delivery_time = match (self.time, self.idle) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => (now as i64).saturating_sub(idle),
+            (None, None) => return now,
+        };
+
+        if delivery_time < 0 || delivery_time as u64 > now {
+            return now;
+        }
+
+        delivery_time as u64
+    }
+}
+
+/// A call to `XAUTOCLAIM`
+#[derive(Debug)]
+pub struct RespXautoclaimCommand {
+    pub key: Box<[u8]>,
+    pub group: Box<[u8]>,
+    pub consumer: Box<[u8]>,
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub count: usize,
+    pub just_id: bool,
+}
+
+impl RespCommandConstructor for RespXautoclaimCommand {
+    fn from_array(input: RespArray) -> Result<RespXautoclaimCommand, RespCommandError> {
+        let [_, key_element, group_element, consumer_element, min_idle_element, start_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let group = get_argument_bytes(group_element)?.into_boxed_slice();
+        let consumer = get_argument_bytes(consumer_element)?.into_boxed_slice();
+
+        let min_idle = get_argument_integer(min_idle_element)
+            .map_err(|_| RespCommandError::InvalidArgument("Invalid min-idle-time argument for XAUTOCLAIM".into()))?;
+
+        let start = parse_range_id(start_element, false)?;
+
+        let mut count = 100;
+        let mut just_id = false;
+        let mut position = 0;
+
+        while let Some(option_element) = options.get(position) {
+            let option = get_argument_string(option_element)?.to_ascii_uppercase();
+
+            match (option.as_str(), options.get(position + 1)) {
+                ("JUSTID", _) => {
+                    just_id = true;
+                    position += 1;
+                }
+                ("COUNT", Some(count_element)) => {
+                    count = match get_argument_integer(count_element) {
+                        Ok(count) if (1 ..= i64::MAX / XAUTOCLAIM_ATTEMPTS_FACTOR).contains(&count) => count as usize,
+                        _ => return Err(RespCommandError::InvalidArgument("COUNT must be > 0".into())),
+                    };
+
+                    position += 2;
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        Ok(RespXautoclaimCommand { key, group, consumer, min_idle: min_idle.max(0) as u64, start, count, just_id })
+    }
+}
+
+fn no_such_group(key: &[u8], group: &[u8]) -> RespCommandError {
+    RespCommandError::NoGroup(format!(
+        "No such consumer group '{}' for key name '{}'",
+        String::from_utf8_lossy(group),
+        String::from_utf8_lossy(key),
+    ))
+}
+
+fn no_such_key_or_group(key: &[u8], group: &[u8]) -> RespCommandError {
+    RespCommandError::NoGroup(format!(
+        "No such key '{}' or consumer group '{}'",
+        String::from_utf8_lossy(key),
+        String::from_utf8_lossy(group),
+    ))
+}
+
+/// Parses the `ENTRIESREAD` value given to `XGROUP CREATE` or `XGROUP SETID`,
+/// where -1 means that the number of entries read isn't known
+fn parse_entries_read(element: &RespElement) -> Result<Option<u64>, RespCommandError> {
+    match get_argument_integer(element)? {
+        -1 => Ok(None),
+        entries_read if entries_read >= 0 => Ok(Some(entries_read as u64)),
+        _ => Err(RespCommandError::InvalidArgument("value for ENTRIESREAD must be positive or -1".into())),
+    }
+}
+
+/// Parses the ID that a group is created with or moved to, where `$` is the
+/// last ID in the stream
+fn parse_group_id(element: &RespElement) -> Result<XreadId, RespCommandError> {
+    match get_argument_bytes(element)?.as_slice() {
+        b"$" => Ok(XreadId::Last),
+        _ => parse_stream_id(element, 0).map(XreadId::After),
+    }
+}
+
+fn resolve_group_id(id: XreadId, stream: &Stream) -> StreamId {
+    match id {
+        XreadId::Last => stream.last_id(),
+        XreadId::After(id) => id,
+    }
+}
+
+fn xgroup(ctx: &mut CommandContext, mut input: RespArray) -> CommandResult {
+    let subcommand = get_argument_string(&input.elements[1])?;
+    let arguments = input.elements.split_off(2);
+
+    match (subcommand.to_uppercase().as_str(), arguments.as_slice()) {
+        ("CREATE", [key, group, id, options @ ..]) if options.len() <= 3 => xgroup_create(ctx, key, group, id, options),
+        ("SETID", [key, group, id, options @ ..]) if options.len() <= 2 => xgroup_setid(ctx, key, group, id, options),
+        ("DESTROY", [key, group]) => {
+            let stream = get_group_stream(ctx, key)?;
+
+            Ok(RespElement::new_integer(stream.destroy_group(&get_argument_bytes(group)?) as isize))
+        }
+        ("CREATECONSUMER", [key, group, consumer]) => {
+            let now = ctx.db.now_millis();
+            let group = get_argument_bytes(group)?;
+            let stream = get_group_stream(ctx, key)?;
+
+            let Some(group_state) = stream.group_mut(&group) else {
+                return Err(no_such_group(&get_argument_bytes(key)?, &group));
+            };
+
+            Ok(RespElement::new_integer(group_state.create_consumer(&get_argument_bytes(consumer)?, now) as isize))
+        }
+        ("DELCONSUMER", [key, group, consumer]) => {
+            let group = get_argument_bytes(group)?;
+            let stream = get_group_stream(ctx, key)?;
+
+            let Some(group_state) = stream.group_mut(&group) else {
+                return Err(no_such_group(&get_argument_bytes(key)?, &group));
+            };
+
+            let pending = group_state.delete_consumer(&get_argument_bytes(consumer)?).unwrap_or(0);
+
+            Ok(RespElement::new_integer(pending as isize))
+        }
+        ("HELP", []) => {
+            let lines = XGROUP_HELP.iter().map(| line | RespElement::new_simple_string(line)).collect();
+
+            Ok(RespElement::new_array(lines))
+        }
+        ("CREATE" | "SETID" | "DESTROY" | "CREATECONSUMER" | "DELCONSUMER" | "HELP", _) => {
+            Err(RespCommandError::WrongNumberOfArguments(format!("xgroup|{}", subcommand.to_lowercase())))
+        }
+        _ => Err(RespCommandError::UnknownSubcommand(subcommand, "xgroup".into())),
+    }
+}
+
+/// Looks up the stream that an `XGROUP` subcommand works on, which has to
+/// exist
+fn get_group_stream<'a>(ctx: &'a mut CommandContext, key: &RespElement) -> Result<&'a mut Stream, RespCommandError> {
+    ctx.db.get_stream_mut(&get_argument_bytes(key)?)?.ok_or_else(|| RespCommandError::InvalidArgument(
+        "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM \
+         option to create an empty stream automatically."
+            .into(),
+    ))
+}
+
+fn xgroup_create(
+    ctx: &mut CommandContext,
+    key_element: &RespElement,
+    group_element: &RespElement,
+    id_element: &RespElement,
+    options: &[RespElement],
+) -> CommandResult {
+    let id = parse_group_id(id_element)?;
+
+    let mut mkstream = false;
+    let mut entries_read = None;
+    let mut position = 0;
+
+    while let Some(option_element) = options.get(position) {
+        let option = get_argument_string(option_element)?.to_ascii_uppercase();
+
+        match (option.as_str(), options.get(position + 1)) {
+            ("MKSTREAM", _) => {
+                mkstream = true;
+                position += 1;
+            }
+            ("ENTRIESREAD", Some(value)) => {
+                entries_read = parse_entries_read(value)?;
+                position += 2;
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        }
+    }
+
+    let key = get_argument_bytes(key_element)?;
+
+    let stream = if mkstream && ctx.db.get_stream(&key)?.is_none() {
+        ctx.db.get_or_insert_with(&key, || Value::Stream(Stream::new())).value.as_stream_mut()?
+    } else {
+        get_group_stream(ctx, key_element)?
+    };
+
+    let group = ConsumerGroup::new(resolve_group_id(id, stream), entries_read);
+
+    if !stream.create_group(&get_argument_bytes(group_element)?, group) {
+        return Err(RespCommandError::BusyGroup);
+    }
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+fn xgroup_setid(
+    ctx: &mut CommandContext,
+    key_element: &RespElement,
+    group_element: &RespElement,
+    id_element: &RespElement,
+    options: &[RespElement],
+) -> CommandResult {
+    let id = parse_group_id(id_element)?;
+
+    let entries_read = match options {
+        [] => None,
+        [option, value] if get_argument_string(option)?.eq_ignore_ascii_case("ENTRIESREAD") => parse_entries_read(value)?,
+        _ => return Err(RespCommandError::SyntaxError),
+    };
+
+    let group = get_argument_bytes(group_element)?;
+    let stream = get_group_stream(ctx, key_element)?;
+    let id = resolve_group_id(id, stream);
+
+    let Some(group_state) = stream.group_mut(&group) else {
+        return Err(no_such_group(&get_argument_bytes(key_element)?, &group));
+    };
+
+    group_state.set_last_delivered_id(id, entries_read);
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+/// Reads entries from each of the streams on behalf of a consumer in the
+/// group. With `>`, the entries that haven't been delivered to the group yet
+/// are read, leaving out the streams that don't have any, and if none of them
+/// do and `BLOCK` was given, the client waits until an entry is added to one
+/// of them. Otherwise, the consumer's pending entries are read again.
+fn xreadgroup(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXreadgroupCommand::from_array(input)?;
+
+    // every stream has to have the group before anything is read from any
+    // of them
+    for key in &command.keys {
+        if ctx.db.get_stream(key)?.and_then(| s | s.group(&command.group)).is_none() {
+            return Err(RespCommandError::NoGroup(format!(
+                "No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(&command.group),
+            )));
+        }
+    }
+
+    let now = ctx.db.now_millis();
+    let count = command.count.unwrap_or(usize::MAX);
+    let mut streams = Vec::new();
+
+    for (key, id) in command.keys.iter().zip(&command.ids) {
+        let Some(stream) = ctx.db.get_stream_mut(key)? else {
+            continue;
+        };
+
+        match id {
+            XreadgroupId::New => {
+                let ids = stream.read_group(&command.group, &command.consumer, count, command.no_ack, now).unwrap_or_default();
+
+                let entries: Vec<RespElement> = ids.iter()
+                    .filter_map(| id | stream.get(*id).map(| fields | get_entry_reply(id, fields)))
+                    .collect();
+
+                if !entries.is_empty() {
+                    streams.push((key.as_ref(), entries));
+                }
+            }
+            XreadgroupId::History(after) => {
+                let ids = stream.read_group_history(&command.group, &command.consumer, *after, count, now).unwrap_or_default();
+
+                // entries that have been deleted since they were delivered
+                // are still listed, but without their fields
+                let entries = ids.iter()
+                    .map(| id | match stream.get(*id) {
+                        Some(fields) => get_entry_reply(id, fields),
+                        None => RespElement::new_array(vec![
+                            get_id_reply(*id),
+                            RespElement::new_null_array(),
+                        ]),
+                    })
+                    .collect();
+
+                streams.push((key.as_ref(), entries));
+            }
+        }
+    }
+
+    if !streams.is_empty() {
+        return Ok(get_streams_reply(streams, ctx.protocol));
+    }
+
+    let Some(timeout) = command.block else {
+        return Ok(RespElement::new_null_array());
+    };
+
+    // `>` always refers to whatever the group hasn't read yet, so the command
+    // can be run again as it is once an entry is added
+    let deadline = timeout.map(| t | ctx.db.now_millis().saturating_add(t));
+
+    ctx.block_on(command.keys.iter().map(| k | k.to_vec()).collect(), "stream", deadline);
+
+    Ok(RespElement::new_null_array())
+}
+
+fn xack(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXackCommand::from_array(input)?;
+
+    let Some(group) = ctx.db.get_stream_mut(&command.key)?.and_then(| s | s.group_mut(&command.group)) else {
+        return Ok(RespElement::new_integer(0));
+    };
+
+    let acknowledged = command.ids.into_iter()
+        .filter(| id | group.acknowledge(*id))
+        .count();
+
+    Ok(RespElement::new_integer(acknowledged as isize))
+}
+
+fn xpending(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXpendingCommand::from_array(input)?;
+    let now = ctx.db.now_millis();
+
+    let Some(group) = ctx.db.get_stream(&command.key)?.and_then(| s | s.group(&command.group)) else {
+        return Err(no_such_key_or_group(&command.key, &command.group));
+    };
+
+    let Some(range) = command.range else {
+        return Ok(get_pending_summary(group));
+    };
+
+    let pending: Vec<(StreamId, &[u8], u64, u64)> = match &range.consumer {
+        Some(name) => match group.consumer(name) {
+            Some(consumer) => consumer.pending()
+                .filter(| id | (range.start ..= range.end).contains(id))
+                .filter_map(| id | group.pending_entry(id).map(| e | (id, name.as_ref(), e.delivery_time, e.delivery_count)))
+                .collect(),
+            None => Vec::new(),
+        },
+        None => group.pending_range(range.start, range.end)
+            .map(| (id, e) | (*id, e.consumer.as_slice(), e.delivery_time, e.delivery_count))
+            .collect(),
+    };
+
+    let entries = pending.into_iter()
+        .filter(| (_, _, delivery_time, _) | now.saturating_sub(*delivery_time) >= range.min_idle)
+        .take(range.count)
+        .map(| (id, consumer, delivery_time, delivery_count) | RespElement::new_array(vec![
+            get_id_reply(id),
+            RespElement::new_bulk_string(consumer),
+            RespElement::new_integer(now.saturating_sub(delivery_time) as isize),
+            RespElement::new_integer(delivery_count as isize),
+        ]))
+        .collect();
+
+    Ok(RespElement::new_array(entries))
+}
+
+/// Builds the reply for `XPENDING` without a range: the number of pending
+/// entries, the lowest and highest of their IDs, and how many each consumer
+/// has pending
+fn get_pending_summary(group: &ConsumerGroup) -> RespElement {
+    let mut pending = group.pending_range(StreamId::MIN, StreamId::MAX).map(| (id, _) | *id);
+
+    let Some(first) = pending.next() else {
+        return RespElement::new_array(vec![
+            RespElement::new_integer(0),
+            RespElement::new_null(),
+            RespElement::new_null(),
+            RespElement::new_null_array(),
+        ]);
+    };
+
+    let last = pending.next_back().unwrap_or(first);
+
+    let consumers = group.consumers()
+        .filter(| (_, consumer) | consumer.pending_len() > 0)
+        .map(| (name, consumer) | RespElement::new_array(vec![
+            RespElement::new_bulk_string(name),
+            RespElement::new_bulk_string(consumer.pending_len().to_string().as_bytes()),
+        ]))
+        .collect();
+
+    RespElement::new_array(vec![
+        RespElement::new_integer(group.pending_len() as isize),
+        RespElement::new_bulk_string(first.to_string().as_bytes()),
+        RespElement::new_bulk_string(last.to_string().as_bytes()),
+        RespElement::new_array(consumers),
+    ])
+}
+
+fn xclaim(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXclaimCommand::from_array(input)?;
+    let now = ctx.db.now_millis();
+
+    let Some(stream) = ctx.db.get_stream_mut(&command.key)?.filter(| s | s.group(&command.group).is_some()) else {
+        return Err(no_such_key_or_group(&command.key, &command.group));
+    };
+
+    let options = ClaimOptions { delivery_time: command.get_delivery_time(now), ..command.options };
+
+    let claimed = stream.claim(&command.group, &command.consumer, &command.ids, &options, now).unwrap_or_default();
+
+    Ok(get_claimed_reply(stream, &claimed, options.just_id))
+}
+
+fn xautoclaim(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespXautoclaimCommand::from_array(input)?;
+    let now = ctx.db.now_millis();
+
+    let Some(stream) = ctx.db.get_stream_mut(&command.key)?.filter(| s | s.group(&command.group).is_some()) else {
+        return Err(no_such_key_or_group(&command.key, &command.group));
+    };
+
+    let result = stream.auto_claim(
+        &command.group,
+        &command.consumer,
+        command.min_idle,
+        command.start,
+        command.count,
+        command.just_id,
+        now,
+    ).unwrap_or_default();
+
+    let deleted = result.deleted.iter()
+        .map(| id | get_id_reply(*id))
+        .collect();
+
+    Ok(RespElement::new_array(vec![
+        RespElement::new_bulk_string(result.next.to_string().as_bytes()),
+        get_claimed_reply(stream, &result.claimed, command.just_id),
+        RespElement::new_array(deleted),
+    ]))
+}
+
+/// Builds the list of entries that were claimed, or just their IDs if
+/// `JUSTID` was given
+fn get_claimed_reply(stream: &Stream, claimed: &[StreamId], just_id: bool) -> RespElement {
+    let entries = claimed.iter()
+        .filter_map(| id | if just_id {
+            Some(get_id_reply(*id))
+        } else {
+            stream.get(*id).map(| fields | get_entry_reply(id, fields))
+        })
+        .collect();
+
+    RespElement::new_array(entries)
+}
+
+fn xinfo(ctx: &mut CommandContext, mut input: RespArray) -> CommandResult {
+    let subcommand = get_argument_string(&input.elements[1])?;
+    let arguments = input.elements.split_off(2);
+    let now = ctx.db.now_millis();
+
+    match (subcommand.to_uppercase().as_str(), arguments.as_slice()) {
+        ("STREAM", [key, options @ ..]) => {
+            let count = match options {
+                [] => None,
+                [full] if get_argument_string(full)?.eq_ignore_ascii_case("FULL") => Some(XINFO_FULL_DEFAULT_COUNT),
+                [full, option, count]
+                    if get_argument_string(full)?.eq_ignore_ascii_case("FULL")
+                        && get_argument_string(option)?.eq_ignore_ascii_case("COUNT") =>
+                {
+                    // a count of 0 lists everything, and a negative one is
+                    // the same as leaving it out
+                    match get_argument_integer(count)? {
+                        0 => Some(usize::MAX),
+                        count if count < 0 => Some(XINFO_FULL_DEFAULT_COUNT),
+                        count => Some(count as usize),
+                    }
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            };
+
+            let stream = ctx.db.get_stream(&get_argument_bytes(key)?)?.ok_or(RespCommandError::NoSuchKey)?;
+
+            Ok(match count {
+                Some(count) => get_stream_full_info(stream, count),
+                None => get_stream_info(stream),
+            })
+        }
+        ("GROUPS", [key]) => {
+            let stream = ctx.db.get_stream(&get_argument_bytes(key)?)?.ok_or(RespCommandError::NoSuchKey)?;
+
+            let groups = stream.groups()
+                .map(| (name, group) | RespElement::new_map(vec![
+                    field("name", RespElement::new_bulk_string(name)),
+                    field("consumers", RespElement::new_integer(group.consumers().len() as isize)),
+                    field("pending", RespElement::new_integer(group.pending_len() as isize)),
+                    field("last-delivered-id", RespElement::new_bulk_string(group.last_delivered_id().to_string().as_bytes())),
+                    field("entries-read", get_optional_integer(group.entries_read())),
+                    field("lag", get_optional_integer(stream.lag(group))),
+                ]))
+                .collect();
+
+            Ok(RespElement::new_array(groups))
+        }
+        ("CONSUMERS", [key, group]) => {
+            let key = get_argument_bytes(key)?;
+            let group = get_argument_bytes(group)?;
+            let stream = ctx.db.get_stream(&key)?.ok_or(RespCommandError::NoSuchKey)?;
+
+            let Some(group_state) = stream.group(&group) else {
+                return Err(no_such_group(&key, &group));
+            };
+
+            let consumers = group_state.consumers()
+                .map(| (name, consumer) | RespElement::new_map(vec![
+                    field("name", RespElement::new_bulk_string(name)),
+                    field("pending", RespElement::new_integer(consumer.pending_len() as isize)),
+                    field("idle", RespElement::new_integer(now.saturating_sub(consumer.seen_time) as isize)),
+                    field("inactive", RespElement::new_integer(
+                        consumer.active_time.map_or(-1, | t | now.saturating_sub(t) as isize),
+                    )),
+                ]))
+                .collect();
+
+            Ok(RespElement::new_array(consumers))
+        }
+        ("HELP", []) => {
+            let lines = XINFO_HELP.iter().map(| line | RespElement::new_simple_string(line)).collect();
+
+            Ok(RespElement::new_array(lines))
+        }
+        ("STREAM" | "GROUPS" | "CONSUMERS" | "HELP", _) => {
+            Err(RespCommandError::WrongNumberOfArguments(format!("xinfo|{}", subcommand.to_lowercase())))
+        }
+        _ => Err(RespCommandError::UnknownSubcommand(subcommand, "xinfo".into())),
+    }
+}
+
+fn field(name: &str, value: RespElement) -> (RespElement, RespElement) {
+    (RespElement::new_bulk_string(name.as_bytes()), value)
+}
+
+fn get_optional_integer(value: Option<u64>) -> RespElement {
+    value.map_or_else(RespElement::new_null, | v | RespElement::new_integer(v as isize))
+}
+
+fn get_id_reply(id: StreamId) -> RespElement {
+    RespElement::new_bulk_string(id.to_string().as_bytes())
+}
+
+/// The fields that both forms of `XINFO STREAM` start with. Redis also
+/// reports the size of the radix tree that it keeps entries in, which is left
+/// out since entries are kept differently here.
+fn get_stream_summary(stream: &Stream) -> Vec<(RespElement, RespElement)> {
+    vec![
+        field("length", RespElement::new_integer(stream.len() as isize)),
+        field("last-generated-id", get_id_reply(stream.last_id())),
+        field("max-deleted-entry-id", get_id_reply(stream.max_deleted_id())),
+        field("entries-added", RespElement::new_integer(stream.entries_added() as isize)),
+        field("recorded-first-entry-id", get_id_reply(stream.first_id())),
+    ]
+}
+
+fn get_stream_info(stream: &Stream) -> RespElement {
+    let entry = | entry: Option<(&StreamId, _)> | entry.map_or_else(RespElement::new_null, | (id, fields) | get_entry_reply(id, fields));
+
+    let mut fields = get_stream_summary(stream);
+
+    fields.push(field("groups", RespElement::new_integer(stream.groups().len() as isize)));
+    fields.push(field("first-entry", entry(stream.first_entry())));
+    fields.push(field("last-entry", entry(stream.last_entry())));
+
+    RespElement::new_map(fields)
+}
+
+/// Builds the reply for `XINFO STREAM FULL`, which lists up to `count` of the
+/// stream's entries, and of each of its groups' pending entries
+fn get_stream_full_info(stream: &Stream, count: usize) -> RespElement {
+    let entries = stream.range(StreamId::MIN, StreamId::MAX)
+        .take(count)
+        .map(| (id, fields) | get_entry_reply(id, fields))
+        .collect();
+
+    let groups = stream.groups()
+        .map(| (name, group) | {
+            let pending = group.pending_range(StreamId::MIN, StreamId::MAX)
+                .take(count)
+                .map(| (id, entry) | RespElement::new_array(vec![
+                    get_id_reply(*id),
+                    RespElement::new_bulk_string(&entry.consumer),
+                    RespElement::new_integer(entry.delivery_time as isize),
+                    RespElement::new_integer(entry.delivery_count as isize),
+                ]))
+                .collect();
+
+            let consumers = group.consumers()
+                .map(| (name, consumer) | {
+                    let pending = consumer.pending()
+                        .take(count)
+                        .filter_map(| id | group.pending_entry(id).map(| entry | RespElement::new_array(vec![
+                            get_id_reply(id),
+                            RespElement::new_integer(entry.delivery_time as isize),
+                            RespElement::new_integer(entry.delivery_count as isize),
+                        ])))
+                        .collect();
+
+                    RespElement::new_map(vec![
+                        field("name", RespElement::new_bulk_string(name)),
+                        field("seen-time", RespElement::new_integer(consumer.seen_time as isize)),
+                        field("active-time", RespElement::new_integer(consumer.active_time.map_or(-1, | t | t as isize))),
+                        field("pel-count", RespElement::new_integer(consumer.pending_len() as isize)),
+                        field("pending", RespElement::new_array(pending)),
+                    ])
+                })
+                .collect();
+
+            RespElement::new_map(vec![
+                field("name", RespElement::new_bulk_string(name)),
+                field("last-delivered-id", get_id_reply(group.last_delivered_id())),
+                field("entries-read", get_optional_integer(group.entries_read())),
+                field("lag", get_optional_integer(stream.lag(group))),
+                field("pel-count", RespElement::new_integer(group.pending_len() as isize)),
+                field("pending", RespElement::new_array(pending)),
+                field("consumers", RespElement::new_array(consumers)),
+            ])
+        })
+        .collect();
+
+    let mut fields = get_stream_summary(stream);
+
+    fields.push(field("entries", RespElement::new_array(entries)));
+    fields.push(field("groups", RespElement::new_array(groups)));
+
+    RespElement::new_map(fields)
+}
@@ -0,0 +1,898 @@
+use crate::resp::commands::{
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    CommandSpec,
+    RespCommandConstructor,
+    RespCommandError,
+    RespKeyCommand,
+    get_argument_bytes,
+    get_argument_float,
+    get_argument_integer,
+    get_argument_string,
+    get_command_name,
+};
+use crate::resp::commands::keyspace::RespKeysCommand;
+use crate::resp::commands::set::SetExpiration;
+use crate::resp::RespElement;
+use crate::resp::parser::RESP_MAX_BULK_LENGTH;
+use crate::resp::types::RespArray;
+use crate::resp::types::double::{format_double, parse_double};
+use crate::store::{ExpireCondition, Expiry, SetCondition, SetOptions, StringValue, Value};
+
+pub const INCR: CommandSpec = CommandSpec {
+    name: "incr",
+    arity: 2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(incr),
+};
+
+pub const DECR: CommandSpec = CommandSpec {
+    name: "decr",
+    arity: 2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(decr),
+};
+
+pub const INCRBY: CommandSpec = CommandSpec {
+    name: "incrby",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(incrby),
+};
+
+pub const DECRBY: CommandSpec = CommandSpec {
+    name: "decrby",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(decrby),
+};
+
+pub const INCRBYFLOAT: CommandSpec = CommandSpec {
+    name: "incrbyfloat",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(incrbyfloat),
+};
+
+pub const APPEND: CommandSpec = CommandSpec {
+    name: "append",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(append),
+};
+
+pub const GETRANGE: CommandSpec = CommandSpec {
+    name: "getrange",
+    arity: 4,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns a substring of the string stored at a key.",
+    since: "2.4.0",
+    handler: CommandHandler::Store(getrange),
+};
+
+pub const SETRANGE: CommandSpec = CommandSpec {
+    name: "setrange",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(setrange),
+};
+
+pub const STRLEN: CommandSpec = CommandSpec {
+    name: "strlen",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns the length of a string value.",
+    since: "2.2.0",
+    handler: CommandHandler::Store(strlen),
+};
+
+pub const GETSET: CommandSpec = CommandSpec {
+    name: "getset",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns the previous string value of a key after setting it to a new value.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(getset),
+};
+
+pub const GETDEL: CommandSpec = CommandSpec {
+    name: "getdel",
+    arity: 2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns the string value of a key after deleting the key.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(getdel),
+};
+
+pub const GETEX: CommandSpec = CommandSpec {
+    name: "getex",
+    arity: -2,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Returns the string value of a key after setting its expiration time.",
+    since: "6.2.0",
+    handler: CommandHandler::Store(getex),
+};
+
+pub const SETNX: CommandSpec = CommandSpec {
+    name: "setnx",
+    arity: 3,
+    flags: &[CommandFlag::Write, CommandFlag::Fast],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Set the string value of a key only when the key doesn't exist.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(setnx),
+};
+
+pub const SETEX: CommandSpec = CommandSpec {
+    name: "setex",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Sets the string value and expiration time of a key. Creates the key if it doesn't exist.",
+    since: "2.0.0",
+    handler: CommandHandler::Store(setex),
+};
+
+pub const PSETEX: CommandSpec = CommandSpec {
+    name: "psetex",
+    arity: 4,
+    flags: &[CommandFlag::Write],
+    keys: (1, 1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist.",
+    since: "2.6.0",
+    handler: CommandHandler::Store(psetex),
+};
+
+pub const MSET: CommandSpec = CommandSpec {
+    name: "mset",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 2),
+    get_keys: None,
+    group: "string",
+    summary: "Atomically creates or modifies the string values of one or more keys.",
+    since: "1.0.1",
+    handler: CommandHandler::Store(mset),
+};
+
+pub const MSETNX: CommandSpec = CommandSpec {
+    name: "msetnx",
+    arity: -3,
+    flags: &[CommandFlag::Write],
+    keys: (1, -1, 2),
+    get_keys: None,
+    group: "string",
+    summary: "Atomically modifies the string values of one or more keys only when all keys don't exist.",
+    since: "1.0.1",
+    handler: CommandHandler::Store(msetnx),
+};
+
+pub const MGET: CommandSpec = CommandSpec {
+    name: "mget",
+    arity: -2,
+    flags: &[CommandFlag::ReadOnly, CommandFlag::Fast],
+    keys: (1, -1, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Atomically returns the string values of one or more keys.",
+    since: "1.0.0",
+    handler: CommandHandler::Store(mget),
+};
+
+pub const LCS: CommandSpec = CommandSpec {
+    name: "lcs",
+    arity: -3,
+    flags: &[CommandFlag::ReadOnly],
+    keys: (1, 2, 1),
+    get_keys: None,
+    group: "string",
+    summary: "Finds the longest common substring.",
+    since: "7.0.0",
+    handler: CommandHandler::Store(lcs),
+};
+
+/// A call to a command that takes a key and a value (or an amount), e.g.
+/// `APPEND` or `INCRBY`
+#[derive(Debug)]
+pub struct RespKeyValueCommand {
+    pub key: Box<[u8]>,
+    pub value: RespElement,
+}
+
+impl RespCommandConstructor for RespKeyValueCommand {
+    fn from_array(input: RespArray) -> Result<RespKeyValueCommand, RespCommandError> {
+        let [_, key_element, value] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        Ok(RespKeyValueCommand { key, value: value.clone() })
+    }
+}
+
+/// A call to `GETRANGE`
+#[derive(Debug)]
+pub struct RespGetrangeCommand {
+    pub key: Box<[u8]>,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl RespCommandConstructor for RespGetrangeCommand {
+    fn from_array(input: RespArray) -> Result<RespGetrangeCommand, RespCommandError> {
+        let [_, key_element, start_element, end_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let start = get_argument_integer(start_element)?;
+        let end = get_argument_integer(end_element)?;
+
+        Ok(RespGetrangeCommand { key, start, end })
+    }
+}
+
+/// A call to `SETRANGE`
+#[derive(Debug)]
+pub struct RespSetrangeCommand {
+    pub key: Box<[u8]>,
+    pub offset: usize,
+    pub value: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespSetrangeCommand {
+    fn from_array(input: RespArray) -> Result<RespSetrangeCommand, RespCommandError> {
+        let [_, key_element, offset_element, value_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        let offset = match get_argument_integer(offset_element)? {
+            offset if offset >= 0 => offset as usize,
+            _ => return Err(RespCommandError::InvalidArgument("offset is out of range".into())),
+        };
+
+        let value = get_argument_bytes(value_element)?;
+
+        Ok(RespSetrangeCommand { key, offset, value })
+    }
+}
+
+/// A call to `GETEX`
+#[derive(Debug)]
+pub struct RespGetexCommand {
+    pub key: Box<[u8]>,
+    pub expiration: Option<SetExpiration>,
+    /// Whether `PERSIST` was given, which removes the key's TTL
+    pub persist: bool,
+}
+
+impl RespCommandConstructor for RespGetexCommand {
+    fn from_array(input: RespArray) -> Result<RespGetexCommand, RespCommandError> {
+        let [_, key_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+
+        // only one of the options can be given
+        let (expiration, persist) = match options {
+            [] => (None, false),
+            [option] if get_argument_string(option)?.eq_ignore_ascii_case("PERSIST") => (None, true),
+            [option, ttl_element] => {
+                let ttl = get_argument_integer(ttl_element)?;
+
+                let expiration = match get_argument_string(option)?.to_uppercase().as_str() {
+                    "EX" => SetExpiration::Seconds(ttl),
+                    "PX" => SetExpiration::Milliseconds(ttl),
+                    "EXAT" => SetExpiration::UnixSeconds(ttl),
+                    "PXAT" => SetExpiration::UnixMilliseconds(ttl),
+                    _ => return Err(RespCommandError::SyntaxError),
+                };
+
+                (Some(expiration), false)
+            }
+            _ => return Err(RespCommandError::SyntaxError),
+        };
+
+        Ok(RespGetexCommand { key, expiration, persist })
+    }
+}
+
+/// A call to `SETEX` or `PSETEX`
+#[derive(Debug)]
+pub struct RespSetexCommand {
+    pub key: Box<[u8]>,
+    pub ttl: i64,
+    pub value: Vec<u8>,
+}
+
+impl RespCommandConstructor for RespSetexCommand {
+    fn from_array(input: RespArray) -> Result<RespSetexCommand, RespCommandError> {
+        let [_, key_element, ttl_element, value_element] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let key = get_argument_bytes(key_element)?.into_boxed_slice();
+        let ttl = get_argument_integer(ttl_element)?;
+        let value = get_argument_bytes(value_element)?;
+
+        Ok(RespSetexCommand { key, ttl, value })
+    }
+}
+
+/// A call to `MSET` or `MSETNX`
+#[derive(Debug)]
+pub struct RespMsetCommand {
+    pub pairs: Vec<(Box<[u8]>, Vec<u8>)>,
+}
+
+impl RespCommandConstructor for RespMsetCommand {
+    fn from_array(input: RespArray) -> Result<RespMsetCommand, RespCommandError> {
+        let [_, pair_elements @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        if pair_elements.is_empty() || !pair_elements.len().is_multiple_of(2) {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        }
+
+        let pairs = pair_elements.chunks_exact(2)
+            .map(| pair | Ok((get_argument_bytes(&pair[0])?.into_boxed_slice(), get_argument_bytes(&pair[1])?)))
+            .collect::<Result<_, RespCommandError>>()?;
+
+        Ok(RespMsetCommand { pairs })
+    }
+}
+
+/// A call to `LCS`
+#[derive(Debug)]
+pub struct RespLcsCommand {
+    pub first_key: Box<[u8]>,
+    pub second_key: Box<[u8]>,
+    /// Whether only the length of the match is returned (`LEN`)
+    pub len: bool,
+    /// Whether the positions of the matching ranges are returned (`IDX`)
+    pub idx: bool,
+    /// Ranges shorter than this are left out of the `IDX` reply
+    pub min_match_len: usize,
+    /// Whether the length of each range is included in the `IDX` reply
+    pub with_match_len: bool,
+}
+
+impl RespCommandConstructor for RespLcsCommand {
+    fn from_array(input: RespArray) -> Result<RespLcsCommand, RespCommandError> {
+        let [_, first_element, second_element, options @ ..] = input.elements.as_slice() else {
+            return Err(RespCommandError::WrongNumberOfArguments(get_command_name(&input)));
+        };
+
+        let mut command = RespLcsCommand {
+            first_key: get_argument_bytes(first_element)?.into_boxed_slice(),
+            second_key: get_argument_bytes(second_element)?.into_boxed_slice(),
+            len: false,
+            idx: false,
+            min_match_len: 0,
+            with_match_len: false,
+        };
+
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            match get_argument_string(option)?.to_uppercase().as_str() {
+                "LEN" => command.len = true,
+                "IDX" => command.idx = true,
+                "WITHMATCHLEN" => command.with_match_len = true,
+                "MINMATCHLEN" => {
+                    let Some(length_element) = options.next() else {
+                        return Err(RespCommandError::SyntaxError);
+                    };
+
+                    command.min_match_len = get_argument_integer(length_element)?.max(0) as usize;
+                }
+                _ => return Err(RespCommandError::SyntaxError),
+            }
+        }
+
+        if command.len && command.idx {
+            return Err(RespCommandError::InvalidArgument(
+                "If you want both the length and indexes, please just use IDX.".into(),
+            ));
+        }
+
+        Ok(command)
+    }
+}
+
+fn string_too_long() -> RespCommandError {
+    RespCommandError::InvalidArgument("string exceeds maximum allowed size (proto-max-bulk-len)".into())
+}
+
+fn incr(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    increment_by(ctx, &command.key, 1)
+}
+
+fn decr(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    increment_by(ctx, &command.key, -1)
+}
+
+fn incrby(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let increment = get_argument_integer(&command.value)?;
+
+    increment_by(ctx, &command.key, increment)
+}
+
+fn decrby(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let decrement = get_argument_integer(&command.value)?;
+
+    let Some(increment) = decrement.checked_neg() else {
+        return Err(RespCommandError::InvalidArgument("decrement would overflow".into()));
+    };
+
+    increment_by(ctx, &command.key, increment)
+}
+
+/// Adds `increment` to the integer stored at the key, starting from 0 if the
+/// key doesn't exist, and replies with the result
+fn increment_by(ctx: &mut CommandContext, key: &[u8], increment: i64) -> CommandResult {
+    let value = ctx.db.get_or_insert_with(key, || Value::String(StringValue::from_integer(0))).value.as_string_mut()?;
+
+    let current = value.as_integer().ok_or(RespCommandError::NotAnInteger)?;
+
+    let Some(updated) = current.checked_add(increment) else {
+        return Err(RespCommandError::InvalidArgument("increment or decrement would overflow".into()));
+    };
+
+    // like Redis, changing a counter keeps its TTL
+    *value = StringValue::from_integer(updated);
+
+    Ok(RespElement::new_integer(updated as isize))
+}
+
+/// Replies with the new value as a bulk string, the same way it's stored
+fn incrbyfloat(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let increment = get_argument_float(&command.value)?;
+
+    if !increment.is_finite() {
+        return Err(RespCommandError::InvalidArgument("increment would produce NaN or Infinity".into()));
+    }
+
+    let value = ctx.db.get_or_insert_with(&command.key, || Value::String(StringValue::from_integer(0))).value.as_string_mut()?;
+
+    let current = match value.as_integer() {
+        Some(current) => current as f64,
+        None => str::from_utf8(&value.as_bytes()).ok()
+            .and_then(parse_double)
+            .filter(| f | !f.is_nan())
+            .ok_or(RespCommandError::NotAFloat)?,
+    };
+
+    let updated = current + increment;
+
+    if !updated.is_finite() {
+        return Err(RespCommandError::InvalidArgument("increment would produce NaN or Infinity".into()));
+    }
+
+    let formatted = format_double(updated).into_bytes();
+
+    *value = StringValue::new(formatted.clone());
+
+    Ok(RespElement::new_bulk_string(&formatted))
+}
+
+fn append(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let suffix = get_argument_bytes(&command.value)?;
+
+    let value = ctx.db.get_or_insert_with(&command.key, || Value::String(StringValue::default())).value.as_string_mut()?;
+
+    if value.len() + suffix.len() > RESP_MAX_BULK_LENGTH {
+        return Err(string_too_long());
+    }
+
+    Ok(RespElement::new_integer(value.append(&suffix) as isize))
+}
+
+/// Replies with the bytes between `start` and `end`, inclusive, where
+/// negative offsets count back from the end of the string. Offsets past
+/// either end of the string are clamped to it.
+fn getrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGetrangeCommand::from_array(input)?;
+
+    let Some(value) = ctx.db.get_string(&command.key)? else {
+        return Ok(RespElement::new_bulk_string(b""));
+    };
+
+    let bytes = value.as_bytes();
+    let length = bytes.len() as i64;
+
+    if command.start < 0 && command.end < 0 && command.start > command.end {
+        return Ok(RespElement::new_bulk_string(b""));
+    }
+
+    let start = if command.start < 0 { length + command.start } else { command.start }.max(0);
+    let end = if command.end < 0 { length + command.end } else { command.end }.max(0).min(length - 1);
+
+    if length == 0 || start > end {
+        return Ok(RespElement::new_bulk_string(b""));
+    }
+
+    Ok(RespElement::new_bulk_string(&bytes[start as usize ..= end as usize]))
+}
+
+/// Replies with the string's length after it's been overwritten. Nothing is
+/// written if the value is empty, so the key isn't created in that case.
+fn setrange(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetrangeCommand::from_array(input)?;
+
+    let length = ctx.db.get_string(&command.key)?.map(StringValue::len);
+
+    if command.value.is_empty() {
+        return Ok(RespElement::new_integer(length.unwrap_or(0) as isize));
+    }
+
+    if command.offset.saturating_add(command.value.len()) > RESP_MAX_BULK_LENGTH {
+        return Err(string_too_long());
+    }
+
+    let value = ctx.db.get_or_insert_with(&command.key, || Value::String(StringValue::default())).value.as_string_mut()?;
+
+    Ok(RespElement::new_integer(value.set_range(command.offset, &command.value) as isize))
+}
+
+fn strlen(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let length = ctx.db.get_string(&command.key)?.map_or(0, StringValue::len);
+
+    Ok(RespElement::new_integer(length as isize))
+}
+
+/// Sets the key's value (removing any TTL it had), and replies with the value
+/// it had before
+fn getset(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let value = get_argument_bytes(&command.value)?;
+
+    // the previous value has to be a string, which is checked up front so
+    // that nothing is written if it isn't
+    ctx.db.get_string(&command.key)?;
+
+    let outcome = ctx.db.set(&command.key, Value::String(StringValue::new(value)), SetOptions::default());
+
+    match outcome.previous {
+        Some(Value::String(previous)) => Ok(RespElement::new_bulk_string(&previous.as_bytes())),
+        _ => Ok(RespElement::new_null()),
+    }
+}
+
+fn getdel(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyCommand::from_array(input)?;
+
+    let Some(value) = ctx.db.get_string(&command.key)? else {
+        return Ok(RespElement::new_null());
+    };
+
+    let reply = RespElement::new_bulk_string(&value.as_bytes());
+
+    ctx.db.delete(&command.key);
+
+    Ok(reply)
+}
+
+/// Replies with the key's value, and then changes its TTL. A time that has
+/// already passed deletes the key, although its value is still returned.
+fn getex(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespGetexCommand::from_array(input)?;
+
+    let expiry = match command.expiration {
+        Some(expiration) => Some(expiration.to_expiry(ctx.db.now_millis(), "getex")?),
+        None if command.persist => Some(Expiry::Never),
+        None => None,
+    };
+
+    let Some(value) = ctx.db.get_string(&command.key)? else {
+        return Ok(RespElement::new_null());
+    };
+
+    let reply = RespElement::new_bulk_string(&value.as_bytes());
+
+    match expiry {
+        Some(Expiry::At(when)) => {
+            ctx.db.expire(&command.key, when, ExpireCondition::default());
+        }
+        Some(Expiry::Never) => {
+            ctx.db.persist(&command.key);
+        }
+        _ => {}
+    }
+
+    Ok(reply)
+}
+
+fn setnx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeyValueCommand::from_array(input)?;
+    let value = get_argument_bytes(&command.value)?;
+
+    let options = SetOptions { condition: SetCondition::IfMissing, expiry: Expiry::Never };
+    let outcome = ctx.db.set(&command.key, Value::String(StringValue::new(value)), options);
+
+    Ok(RespElement::new_integer(outcome.written as isize))
+}
+
+fn setex(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetexCommand::from_array(input)?;
+
+    set_with_expiration(ctx, command.key, command.value, SetExpiration::Seconds(command.ttl), "setex")
+}
+
+fn psetex(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespSetexCommand::from_array(input)?;
+
+    set_with_expiration(ctx, command.key, command.value, SetExpiration::Milliseconds(command.ttl), "psetex")
+}
+
+fn set_with_expiration(
+    ctx: &mut CommandContext,
+    key: Box<[u8]>,
+    value: Vec<u8>,
+    expiration: SetExpiration,
+    name: &str,
+) -> CommandResult {
+    let expiry = expiration.to_expiry(ctx.db.now_millis(), name)?;
+
+    let options = SetOptions { condition: SetCondition::Always, expiry };
+
+    ctx.db.set(&key, Value::String(StringValue::new(value)), options);
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+fn mset(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespMsetCommand::from_array(input)?;
+
+    for (key, value) in command.pairs {
+        ctx.db.set(&key, Value::String(StringValue::new(value)), SetOptions::default());
+    }
+
+    Ok(RespElement::new_simple_string("OK"))
+}
+
+/// Sets every key, as long as none of them exist, and replies with whether
+/// they were set
+fn msetnx(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespMsetCommand::from_array(input)?;
+
+    if command.pairs.iter().any(| (key, _) | ctx.db.exists(key)) {
+        return Ok(RespElement::new_integer(0));
+    }
+
+    for (key, value) in command.pairs {
+        ctx.db.set(&key, Value::String(StringValue::new(value)), SetOptions::default());
+    }
+
+    Ok(RespElement::new_integer(1))
+}
+
+/// Replies with the value of each key, where keys that don't exist or don't
+/// hold a string are null
+fn mget(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespKeysCommand::from_array(input)?;
+
+    let values = command.keys.iter()
+        .map(| key | match ctx.db.get(key).and_then(| e | e.value.as_string().ok()) {
+            Some(value) => RespElement::new_bulk_string(&value.as_bytes()),
+            None => RespElement::new_null(),
+        })
+        .collect();
+
+    Ok(RespElement::new_array(values))
+}
+
+/// A range of bytes that two strings have in common, as the inclusive
+/// positions of the range in each of them
+#[derive(Debug, Clone, Copy)]
+struct LcsMatch {
+    first: (usize, usize),
+    second: (usize, usize),
+}
+
+impl LcsMatch {
+    fn len(&self) -> usize {
+        self.first.1 - self.first.0 + 1
+    }
+}
+
+fn lcs(ctx: &mut CommandContext, input: RespArray) -> CommandResult {
+    let command = RespLcsCommand::from_array(input)?;
+
+    let first = get_lcs_operand(ctx, &command.first_key)?;
+    let second = get_lcs_operand(ctx, &command.second_key)?;
+
+    let (text, matches) = find_lcs(&first, &second)?;
+
+    if command.len {
+        return Ok(RespElement::new_integer(text.len() as isize));
+    }
+
+    if !command.idx {
+        return Ok(RespElement::new_bulk_string(&text));
+    }
+
+    let position = | (start, end): (usize, usize) | RespElement::new_array(vec![
+        RespElement::new_integer(start as isize),
+        RespElement::new_integer(end as isize),
+    ]);
+
+    let matches = matches.into_iter()
+        .filter(| m | m.len() >= command.min_match_len)
+        .map(| m | {
+            let mut reply = vec![position(m.first), position(m.second)];
+
+            if command.with_match_len {
+                reply.push(RespElement::new_integer(m.len() as isize));
+            }
+
+            RespElement::new_array(reply)
+        })
+        .collect();
+
+    Ok(RespElement::new_map(vec![
+        (RespElement::new_bulk_string(b"matches"), RespElement::new_array(matches)),
+        (RespElement::new_bulk_string(b"len"), RespElement::new_integer(text.len() as isize)),
+    ]))
+}
+
+/// Looks up one of the strings given to `LCS`, where a key that doesn't exist
+/// is treated as an empty string
+fn get_lcs_operand(ctx: &mut CommandContext, key: &[u8]) -> Result<Vec<u8>, RespCommandError> {
+    match ctx.db.get(key).map(| e | e.value.as_string()) {
+        Some(Ok(value)) => Ok(value.as_bytes().into_owned()),
+        Some(Err(_)) => Err(RespCommandError::InvalidArgument("The specified keys must contain string values".into())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Finds the longest common subsequence of two strings with the usual dynamic
+/// programming table, and then walks back through the table to recover it,
+/// along with the ranges it's made up of (from the end of the strings to the
+/// start, which is the order Redis lists them in)
+fn find_lcs(first: &[u8], second: &[u8]) -> Result<(Vec<u8>, Vec<LcsMatch>), RespCommandError> {
+    let width = second.len() + 1;
+
+    // like Redis, the table isn't allowed to take up more memory than the
+    // longest string a client could send
+    let cells = (first.len() + 1).checked_mul(width)
+        .filter(| cells | cells.saturating_mul(size_of::<u32>()) <= RESP_MAX_BULK_LENGTH)
+        .ok_or_else(|| RespCommandError::InvalidArgument(
+            "Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len".into(),
+        ))?;
+
+    // `table[i * width + j]` is the length of the LCS of the first `i` bytes
+    // of `first` and the first `j` bytes of `second`
+    let mut table = vec![0u32; cells];
+
+    for i in 1 ..= first.len() {
+        for j in 1 ..= second.len() {
+            table[i * width + j] = if first[i - 1] == second[j - 1] {
+                table[(i - 1) * width + j - 1] + 1
+            } else {
+                table[(i - 1) * width + j].max(table[i * width + j - 1])
+            };
+        }
+    }
+
+    let length = table[first.len() * width + second.len()] as usize;
+    let mut text = vec![0; length];
+    let mut matches = Vec::new();
+    let mut current: Option<LcsMatch> = None;
+
+    let (mut i, mut j, mut remaining) = (first.len(), second.len(), length);
+
+    while i > 0 && j > 0 {
+        let mut emit = false;
+
+        if first[i - 1] == second[j - 1] {
+            text[remaining - 1] = first[i - 1];
+
+            match &mut current {
+                None => current = Some(LcsMatch { first: (i - 1, i - 1), second: (j - 1, j - 1) }),
+                // the range carries on backwards if it's contiguous in both
+                Some(range) if range.first.0 == i && range.second.0 == j => {
+                    range.first.0 -= 1;
+                    range.second.0 -= 1;
+                }
+                Some(_) => emit = true,
+            }
+
+            // a range that reaches the start of either string can't go on
+            if current.is_some_and(| r | r.first.0 == 0 || r.second.0 == 0) {
+                emit = true;
+            }
+
+            remaining -= 1;
+            i -= 1;
+            j -= 1;
+        } else {
+            if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+
+            emit = current.is_some();
+        }
+
+        if emit {
+            matches.extend(current.take());
+        }
+    }
+
+    Ok((text, matches))
+}
@@ -0,0 +1,212 @@
+//! Support for inline commands, i.e. commands that are sent as a plain line of
+//! text such as `SET foo "hello world"\r\n` rather than as a RESP array. These
+//! are mostly used by people typing into `nc` or telnet, and by health checks.
+
+use crate::resp::parser::{RespParseError, RESP_MAX_LINE_LENGTH};
+use crate::resp::types::RespArray;
+use crate::resp::RespElement;
+
+/// Reads one inline command from the front of `input`, and returns it as an
+/// array of bulk strings (the same shape as a RESP command) along with any
+/// remaining bytes. Lines may end with either CRLF or just LF.
+///
+/// Returns `RespParseError::Incomplete` if the line hasn't been fully received
+/// yet. A blank line produces an empty array, which callers should skip.
+pub fn parse_inline_command(input: &[u8]) -> Result<(RespArray, &[u8]), RespParseError> {
+    let Some(position_of_lf) = input.iter().position(| byte | *byte == b'\n') else {
+        if input.len() > RESP_MAX_LINE_LENGTH {
+            return Err(RespParseError::TooLarge);
+        }
+
+        return Err(RespParseError::Incomplete);
+    };
+
+    let line = &input[.. position_of_lf];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    let elements = split_arguments(line)?
+        .iter()
+        .map(| argument | RespElement::new_bulk_string(argument))
+        .collect();
+
+    Ok((RespArray::new(elements), &input[(position_of_lf + 1)..]))
+}
+
+/// Splits a line into arguments the same way Redis does (see `sdssplitargs`).
+/// Arguments are separated by whitespace, and may be wrapped in quotes:
+///
+///   - Double quoted arguments support the escapes `\n`, `\r`, `\t`, `\b`,
+///     `\a`, `\xHH` (a hex encoded byte), and a backslash before any other
+///     character stands for that character
+///   - Single quoted arguments only support `\'`
+///
+/// A closing quote must be followed by whitespace or the end of the line.
+pub fn split_arguments(line: &[u8]) -> Result<Vec<Vec<u8>>, RespParseError> {
+    let mut arguments = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        if i >= line.len() {
+            return Ok(arguments);
+        }
+
+        let mut current = Vec::new();
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+
+        loop {
+            let byte = line.get(i).copied();
+
+            if in_double_quotes {
+                match byte {
+                    None => return Err(RespParseError::UnbalancedQuotes),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'x')
+                        && is_hex_pair(line.get(i + 2), line.get(i + 3)) =>
+                    {
+                        current.push(hex_value(line[i + 2]) * 16 + hex_value(line[i + 3]));
+                        i += 3;
+                    }
+                    Some(b'\\') if i + 1 < line.len() => {
+                        i += 1;
+
+                        current.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    Some(b'"') => {
+                        if line.get(i + 1).is_some_and(| next | !next.is_ascii_whitespace()) {
+                            return Err(RespParseError::UnbalancedQuotes);
+                        }
+
+                        i += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else if in_single_quotes {
+                match byte {
+                    None => return Err(RespParseError::UnbalancedQuotes),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                        current.push(b'\'');
+                        i += 1;
+                    }
+                    Some(b'\'') => {
+                        if line.get(i + 1).is_some_and(| next | !next.is_ascii_whitespace()) {
+                            return Err(RespParseError::UnbalancedQuotes);
+                        }
+
+                        i += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else {
+                match byte {
+                    None => break,
+                    Some(b' ' | b'\n' | b'\r' | b'\t' | b'\0') => break,
+                    Some(b'"') => in_double_quotes = true,
+                    Some(b'\'') => in_single_quotes = true,
+                    Some(other) => current.push(other),
+                }
+            }
+
+            i += 1;
+        }
+
+        arguments.push(current);
+    }
+}
+
+fn is_hex_pair(a: Option<&u8>, b: Option<&u8>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a.is_ascii_hexdigit() && b.is_ascii_hexdigit())
+}
+
+fn hex_value(byte: u8) -> u8 {
+    match byte {
+        b'0'..=b'9' => byte - b'0',
+        b'a'..=b'f' => byte - b'a' + 10,
+        _ => byte - b'A' + 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::commands::RespCommandError;
+
+    fn split(line: &[u8]) -> Vec<Vec<u8>> {
+        split_arguments(line).unwrap()
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(split(b"  SET\tfoo   bar "), [b"SET".to_vec(), b"foo".to_vec(), b"bar".to_vec()]);
+        assert!(split(b"   ").is_empty());
+    }
+
+    #[test]
+    fn unescapes_double_quoted_arguments() {
+        assert_eq!(split(br#"SET k "hello world""#)[2], b"hello world");
+        assert_eq!(split(br#""a\nb\r\t\b\a""#)[0], b"a\nb\r\t\x08\x07");
+        assert_eq!(split(br#""\x41\x7a\xff""#)[0], b"Az\xff");
+        assert_eq!(split(br#""\"quoted\" \\ \q""#)[0], br#""quoted" \ q"#);
+        assert_eq!(split(br#""""#)[0], b"");
+    }
+
+    #[test]
+    fn keeps_a_malformed_hex_escape_as_written() {
+        // `\x` without two hex digits falls back to a plain escaped `x`
+        assert_eq!(split(br#""\xZZ""#)[0], b"xZZ");
+        assert_eq!(split(br#""\x4""#)[0], b"x4");
+    }
+
+    #[test]
+    fn only_unescapes_quotes_in_single_quoted_arguments() {
+        assert_eq!(split(br"'it\'s'")[0], b"it's");
+        assert_eq!(split(br"'a\nb \x41'")[0], br"a\nb \x41");
+        assert_eq!(split(br#"'say "hi"'"#)[0], br#"say "hi""#);
+    }
+
+    #[test]
+    fn quotes_can_start_partway_through_an_argument() {
+        assert_eq!(split(br#"foo"bar baz""#)[0], b"foobar baz");
+    }
+
+    #[test]
+    fn rejects_a_closing_quote_followed_by_a_non_space() {
+        assert!(matches!(split_arguments(br#""foo"bar"#), Err(RespParseError::UnbalancedQuotes)));
+        assert!(matches!(split_arguments(br"'foo'bar"), Err(RespParseError::UnbalancedQuotes)));
+        assert_eq!(split(br#""foo" bar"#), [b"foo".to_vec(), b"bar".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_unbalanced_quotes() {
+        for line in [&br#"SET k "hello"#[..], br"SET k 'hello", br#"SET k "trailing\"#] {
+            let error = split_arguments(line).unwrap_err();
+
+            assert!(matches!(error, RespParseError::UnbalancedQuotes), "{line:?}");
+            assert_eq!(RespCommandError::from(error).to_string(), "ERR Protocol error: unbalanced quotes in request");
+        }
+    }
+
+    #[test]
+    fn parses_a_line_ending_in_crlf_or_lf() {
+        let (array, remaining) = parse_inline_command(b"PING\r\nECHO 'a b'\nGET").unwrap();
+
+        assert_eq!(array.elements.len(), 1);
+
+        let (array, remaining) = parse_inline_command(remaining).unwrap();
+
+        assert_eq!(array.elements.len(), 2);
+        assert!(matches!(parse_inline_command(remaining), Err(RespParseError::Incomplete)));
+    }
+}
@@ -1,10 +1,31 @@
-use crate::resp::RESP_DELIMITER;
+use crate::resp::inline::parse_inline_command;
+use crate::resp::types::RespArray;
+use crate::resp::{RespElement, RESP_DELIMITER};
+
+/// The largest number of bytes that a single header line (e.g. `*3\r\n` or
+/// `$5\r\n`) may span before the input is considered to be malformed
+pub const RESP_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// The largest number of elements that a client may send in a single array
+pub const RESP_MAX_ARRAY_LENGTH: usize = 1024 * 1024;
+
+/// The largest bulk string that a client may send, in bytes
+pub const RESP_MAX_BULK_LENGTH: usize = 512 * 1024 * 1024;
 
 #[derive(Debug)]
 pub enum RespParseError {
-    UnexpectedEof,
+    /// The input ended before a complete element could be read; this isn't
+    /// necessarily an error, since the rest of the element may arrive later
+    Incomplete,
     UnknownTypePrefix,
     InvalidElement,
+    /// An inline command has an opening quote without a matching closing quote
+    UnbalancedQuotes,
+    /// A header line or inline command is longer than `RESP_MAX_LINE_LENGTH`
+    TooLarge,
+    /// A request contains an element with the given prefix where only a bulk
+    /// string may appear (e.g. an integer or a nested array inside a request)
+    ExpectedBulkString(u8),
 }
 
 pub trait RespSerialize {
@@ -13,42 +34,221 @@ pub trait RespSerialize {
 
 pub trait RespDeserialize {
     /// Parses the given byte slice and returns a `Result` that contains either:
-    /// 
+    ///
     ///   - a.) A tuple with an instance of the implementer, as well as a byte
-    ///        slice that holds any remaining data (empty otherwise); or,
+    ///     slice that holds any remaining data (empty otherwise); or,
     ///   - b.) An instance of `RespParseError` if parsing failed
     fn from_byte_slice(slice: &[u8]) -> Result<(Self, &[u8]), RespParseError>
     where
         Self: Sized;
 }
 
+/// Accumulates the bytes that are read from a connection, and hands back each
+/// complete element once enough of it has arrived.
+///
+/// Elements are returned in the order that they were received, so a client may
+/// pipeline several commands in one write, or split a single command across as
+/// many writes as it likes.
+///
+/// Like Redis, requests are expected to be a flat array of bulk strings, which
+/// is read one argument at a time. The arguments that have already been read are
+/// kept between calls, so a large request that arrives over many reads is only
+/// parsed once, and anything other than a bulk string inside it (including a
+/// nested aggregate) is rejected rather than parsed or recursed into.
+#[derive(Debug, Default)]
+pub struct RespReader {
+    buffer: Vec<u8>,
+    position: usize,
+    /// The array that's currently being read, if its header has been read but
+    /// some of its elements haven't arrived yet
+    multibulk: Option<Multibulk>,
+}
+
+#[derive(Debug)]
+struct Multibulk {
+    length: usize,
+    elements: Vec<RespElement>,
+}
+
+impl RespReader {
+    pub fn new() -> RespReader {
+        RespReader::default()
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete element in the buffer, or `None` if the buffer
+    /// doesn't hold one yet. Any bytes belonging to a partially received
+    /// element are kept so that parsing can resume after the next read.
+    ///
+    /// Lines that don't start with a RESP type prefix are treated as inline
+    /// commands, and are returned as an array of bulk strings.
+    pub fn next_element(&mut self) -> Result<Option<RespElement>, RespParseError> {
+        loop {
+            let result = match self.multibulk.is_some() {
+                true => self.next_multibulk_element(),
+                false => self.next_request(),
+            };
+
+            match result {
+                Ok(Some(element)) => return Ok(Some(element)),
+                // part of the request was read, so there may be more to read
+                Ok(None) => {}
+                Err(RespParseError::Incomplete) => {
+                    self.compact();
+
+                    let pending = &self.buffer[.. self.buffer.len().min(RESP_MAX_LINE_LENGTH + 2)];
+
+                    if pending.len() > RESP_MAX_LINE_LENGTH && read_until_crlf(pending).is_none() {
+                        return Err(RespParseError::TooLarge);
+                    }
+
+                    return Ok(None);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Reads the start of the next request, which is either an inline command,
+    /// a single simple element, or the header of an array. Returns `None` once
+    /// an array's header has been read, or a blank inline line or an empty
+    /// array skipped.
+    fn next_request(&mut self) -> Result<Option<RespElement>, RespParseError> {
+        let pending = &self.buffer[self.position..];
+
+        let Some(first_byte) = pending.first() else {
+            return Err(RespParseError::Incomplete);
+        };
+
+        if !is_type_prefix(*first_byte) {
+            let (array, remaining_bytes) = parse_inline_command(pending)?;
+
+            self.position += pending.len() - remaining_bytes.len();
+
+            // blank inline lines are skipped, the same way Redis does
+            if array.elements.is_empty() {
+                return Ok(None);
+            }
+
+            return Ok(Some(RespElement::Array(array)));
+        }
+
+        if is_aggregate_prefix(*first_byte) && *first_byte != b'*' {
+            return Err(RespParseError::ExpectedBulkString(*first_byte));
+        }
+
+        if *first_byte != b'*' {
+            let (element, remaining_bytes) = RespElement::from_byte_slice(pending)?;
+
+            self.position += pending.len() - remaining_bytes.len();
+
+            return Ok(Some(element));
+        }
+
+        let Some((raw_length, remaining_bytes)) = read_until_crlf(&pending[1..]) else {
+            return Err(RespParseError::Incomplete);
+        };
+
+        let length = str::from_utf8(raw_length).ok()
+            .and_then(| s | s.parse::<i64>().ok())
+            .ok_or(RespParseError::InvalidElement)?;
+
+        if length > RESP_MAX_ARRAY_LENGTH as i64 {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        self.position += pending.len() - remaining_bytes.len();
+
+        // like Redis, empty and null arrays are skipped without a reply, so
+        // that a pipelining client's replies stay in step with its requests
+        if length <= 0 {
+            return Ok(None);
+        }
+
+        let length = length as usize;
+
+        // the length comes from the client, so don't trust it for preallocation
+        self.multibulk = Some(Multibulk { length, elements: Vec::with_capacity(length.min(1024)) });
+
+        Ok(None)
+    }
+
+    /// Reads as many of the current array's elements as have arrived, and
+    /// returns the array once all of them have
+    fn next_multibulk_element(&mut self) -> Result<Option<RespElement>, RespParseError> {
+        let Some(multibulk) = self.multibulk.as_mut() else {
+            return Ok(None);
+        };
+
+        while multibulk.elements.len() < multibulk.length {
+            let pending = &self.buffer[self.position..];
+
+            let Some(first_byte) = pending.first() else {
+                return Err(RespParseError::Incomplete);
+            };
+
+            // arguments are always sent as bulk strings, so anything else
+            // (e.g. an integer or a nested array) is a protocol error
+            if *first_byte != b'$' {
+                return Err(RespParseError::ExpectedBulkString(*first_byte));
+            }
+
+            let (element, remaining_bytes) = RespElement::from_byte_slice(pending)?;
+
+            self.position += pending.len() - remaining_bytes.len();
+
+            multibulk.elements.push(element);
+        }
+
+        let elements = self.multibulk.take().map(| m | m.elements).unwrap_or_default();
+
+        Ok(Some(RespElement::Array(RespArray::new(elements))))
+    }
+
+    /// Drops the bytes that have already been parsed, so the buffer only grows
+    /// as large as the data that's still waiting to be handled
+    fn compact(&mut self) {
+        if self.position > 0 {
+            self.buffer.drain(.. self.position);
+            self.position = 0;
+        }
+    }
+}
+
+fn is_type_prefix(byte: u8) -> bool {
+    matches!(byte, b'*' | b'+' | b'-' | b'$' | b':' | b'_' | b'#' | b',' | b'(' | b'!' | b'=' | b'%' | b'~' | b'|' | b'>')
+}
+
+fn is_aggregate_prefix(byte: u8) -> bool {
+    matches!(byte, b'*' | b'%' | b'~' | b'|' | b'>')
+}
+
 /// Reads the given byte slice until CRLF is found, and returns a tuple that
 /// contains:
-/// 
+///
 ///   - a.) The current line (from 0 until the first CRLF sequence), and
 ///   - b.) Any remaining data _after_ the first CRLF (or empty otherwise)
-/// 
-/// If no CRLF sequence was found in the given byte slice, then the tuple will
-/// contain the entirety of `input` in its first element, and an intentionally
-/// empty slice as its second.
+///
+/// If no CRLF sequence was found in the given byte slice, then `None` is
+/// returned, since the line may still be in flight.
 pub fn read_until_crlf(input: &[u8]) -> Option<(&[u8], &[u8])> {
     let position_of_crlf = input.windows(2)
         .position(| bytes | bytes == RESP_DELIMITER);
 
-    match position_of_crlf {
-        Some(index) => Some((&input[0..index], &input[(index + 2)..])),
-        None => Some((&input[0..], &input[input.len()..])),
-    }
+    position_of_crlf.map(| index | (&input[0..index], &input[(index + 2)..]))
 }
 
 pub fn get_length_of_current_element(input: &[u8]) -> Result<(usize, &[u8]), RespParseError> {
     let Some(first_line) = read_until_crlf(input) else {
-        return Err(RespParseError::InvalidElement);
+        return Err(RespParseError::Incomplete);
     };
 
     let (raw_length, remaining_bytes) = first_line;
 
-    let Ok(length_as_string) = String::from_utf8(raw_length.to_vec()) else {
+    let Ok(length_as_string) = str::from_utf8(raw_length) else {
         return Err(RespParseError::InvalidElement);
     };
 
@@ -56,9 +256,157 @@ pub fn get_length_of_current_element(input: &[u8]) -> Result<(usize, &[u8]), Res
         return Err(RespParseError::InvalidElement);
     };
 
-    if length == 0 {
-        return Err(RespParseError::UnexpectedEof);
+    Ok((length, remaining_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the arguments of a request, which must be an array of bulk strings
+    fn arguments(element: RespElement) -> Vec<Vec<u8>> {
+        let RespElement::Array(array) = element else {
+            panic!("expected an array, got {element:?}");
+        };
+
+        array.elements.into_iter()
+            .map(| e | match e {
+                RespElement::BulkString(b) => b.value.to_vec(),
+                other => panic!("expected a bulk string, got {other:?}"),
+            })
+            .collect()
     }
 
-    Ok((length, remaining_bytes))
-}
\ No newline at end of file
+    fn next_arguments(reader: &mut RespReader) -> Option<Vec<Vec<u8>>> {
+        reader.next_element().unwrap().map(arguments)
+    }
+
+    #[test]
+    fn resumes_a_command_split_across_reads() {
+        let request = b"*2\r\n$3\r\nGET\r\n$11\r\nhello world\r\n";
+        let mut reader = RespReader::new();
+
+        // split inside the array header (`*|2`), a bulk string's length
+        // (`$1|1`) and a bulk string's payload (`hel|lo wo|rld`)
+        for chunk in [&request[.. 2], &request[2 .. 15], &request[15 .. 21], &request[21 .. 26]] {
+            reader.extend(chunk);
+            assert!(reader.next_element().unwrap().is_none());
+        }
+
+        reader.extend(&request[26 ..]);
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"GET".to_vec(), b"hello world".to_vec()]));
+        assert!(reader.next_element().unwrap().is_none());
+    }
+
+    #[test]
+    fn resumes_a_command_fed_one_byte_at_a_time() {
+        let request = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$0\r\n\r\n";
+        let mut reader = RespReader::new();
+
+        for byte in &request[.. request.len() - 1] {
+            reader.extend(&[*byte]);
+            assert!(reader.next_element().unwrap().is_none());
+        }
+
+        reader.extend(&request[request.len() - 1 ..]);
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"SET".to_vec(), b"k".to_vec(), Vec::new()]));
+    }
+
+    #[test]
+    fn drains_pipelined_commands_in_order() {
+        let mut reader = RespReader::new();
+
+        reader.extend(b"*1\r\n$4\r\nPING\r\nECHO hi\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n*2\r\n$3\r\nGET");
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"PING".to_vec()]));
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"ECHO".to_vec(), b"hi".to_vec()]));
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"GET".to_vec(), b"a".to_vec()]));
+        assert_eq!(next_arguments(&mut reader), None);
+
+        reader.extend(b"\r\n$1\r\nb\r\n");
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"GET".to_vec(), b"b".to_vec()]));
+    }
+
+    #[test]
+    fn skips_empty_and_null_arrays_without_a_reply() {
+        let mut reader = RespReader::new();
+
+        reader.extend(b"*0\r\n*-1\r\n\r\n*1\r\n$4\r\nPING\r\n*0\r\n");
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"PING".to_vec()]));
+        assert_eq!(next_arguments(&mut reader), None);
+    }
+
+    #[test]
+    fn waits_for_incomplete_input() {
+        for partial in [&b"*"[..], b"*2", b"*2\r", b"*2\r\n$", b"*2\r\n$3\r\nGE", b"*2\r\n$3\r\nGET\r", b"$5\r\nhel", b"SET a"] {
+            let mut reader = RespReader::new();
+
+            reader.extend(partial);
+
+            assert!(matches!(reader.next_element(), Ok(None)), "{partial:?}");
+        }
+    }
+
+    fn parse_error(input: &[u8]) -> RespParseError {
+        let mut reader = RespReader::new();
+
+        reader.extend(input);
+
+        match reader.next_element() {
+            Err(e) => e,
+            Ok(element) => panic!("{input:?} gave {element:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(parse_error(b"*x\r\n"), RespParseError::InvalidElement));
+        assert!(matches!(parse_error(b"*2\r\n$x\r\n"), RespParseError::InvalidElement));
+        assert!(matches!(parse_error(b"*2\r\n$3\r\nGETX\r\n"), RespParseError::InvalidElement));
+        assert!(matches!(parse_error(b"SET a \"b\r\n"), RespParseError::UnbalancedQuotes));
+    }
+
+    #[test]
+    fn rejects_anything_but_bulk_strings_inside_a_request() {
+        assert!(matches!(parse_error(b"*1\r\n:-9223372036854775808\r\n"), RespParseError::ExpectedBulkString(b':')));
+        assert!(matches!(parse_error(b"*1\r\n+PING\r\n"), RespParseError::ExpectedBulkString(b'+')));
+        assert!(matches!(parse_error(b"*1\r\n*1\r\n$4\r\nPING\r\n"), RespParseError::ExpectedBulkString(b'*')));
+        assert!(matches!(parse_error(b"%1\r\n$1\r\na\r\n$1\r\nb\r\n"), RespParseError::ExpectedBulkString(b'%')));
+    }
+
+    #[test]
+    fn rejects_a_header_line_that_never_ends() {
+        let mut reader = RespReader::new();
+
+        reader.extend(b"*");
+        reader.extend(&vec![b'1'; RESP_MAX_LINE_LENGTH + 1]);
+
+        assert!(matches!(reader.next_element(), Err(RespParseError::TooLarge)));
+    }
+
+    #[test]
+    fn compacts_the_buffer_once_data_is_consumed() {
+        let mut reader = RespReader::new();
+
+        reader.extend(b"*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n$5\r\nhel");
+
+        assert!(reader.next_element().unwrap().is_some());
+        assert!(reader.next_element().unwrap().is_none());
+
+        // the finished request is dropped, and so are the parts of the
+        // unfinished one that have already been read
+        assert_eq!(reader.position, 0);
+        assert_eq!(reader.buffer, b"$5\r\nhel");
+        assert_eq!(reader.multibulk.as_ref().map(| m | m.elements.len()), Some(1));
+
+        reader.extend(b"lo\r\n");
+
+        assert_eq!(next_arguments(&mut reader), Some(vec![b"GET".to_vec(), b"hello".to_vec()]));
+        assert!(reader.next_element().unwrap().is_none());
+        assert!(reader.buffer.is_empty());
+    }
+}
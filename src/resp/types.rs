@@ -9,3 +9,36 @@ pub use bulk_string::RespBulkString;
 
 pub mod integer;
 pub use integer::RespInteger;
+
+pub mod simple_error;
+pub use simple_error::RespSimpleError;
+
+pub mod null;
+pub use null::RespNull;
+
+pub mod boolean;
+pub use boolean::RespBoolean;
+
+pub mod double;
+pub use double::RespDouble;
+
+pub mod big_number;
+pub use big_number::RespBigNumber;
+
+pub mod bulk_error;
+pub use bulk_error::RespBulkError;
+
+pub mod verbatim_string;
+pub use verbatim_string::RespVerbatimString;
+
+pub mod map;
+pub use map::RespMap;
+
+pub mod set;
+pub use set::RespSet;
+
+pub mod attribute;
+pub use attribute::RespAttribute;
+
+pub mod push;
+pub use push::RespPush;
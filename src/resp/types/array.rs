@@ -4,9 +4,10 @@ use crate::resp::parser::{
     RespDeserialize,
     RespParseError,
     get_length_of_current_element,
+    RESP_MAX_ARRAY_LENGTH,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RespArray {
     pub length: usize,
     pub elements: Vec<RespElement>,
@@ -40,7 +41,7 @@ impl RespSerialize for RespArray {
 
 impl RespDeserialize for RespArray {
     fn from_byte_slice(slice: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
-        if slice[0] != b'*' {
+        if slice.first() != Some(&b'*') {
             return Err(RespParseError::UnknownTypePrefix);
         }
 
@@ -48,7 +49,12 @@ impl RespDeserialize for RespArray {
 
         let (length, mut remaining_bytes) = get_length_of_current_element(packet)?;
 
-        let mut elements: Vec<RespElement> = Vec::with_capacity(length);
+        if length > RESP_MAX_ARRAY_LENGTH {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        // the length comes from the client, so don't trust it for preallocation
+        let mut elements: Vec<RespElement> = Vec::with_capacity(length.min(1024));
 
         for _ in 0 .. length {
             let (element, next_slice) = RespElement::from_byte_slice(remaining_bytes)?;
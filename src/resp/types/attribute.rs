@@ -0,0 +1,47 @@
+use crate::resp::{RespElement, RESP_DELIMITER};
+use crate::resp::types::map::{get_key_value_pairs, RespKeyValuePairs};
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+};
+
+/// Auxiliary key-value pairs that describe the element that follows them. The
+/// attributes and the element are kept together, since an attribute on its
+/// own isn't a complete reply.
+#[derive(Debug, Clone)]
+pub struct RespAttribute {
+    pub attributes: RespKeyValuePairs,
+    pub element: Box<RespElement>,
+}
+
+impl RespSerialize for RespAttribute {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'|'];
+
+        acc.extend(self.attributes.len().to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        for (key, value) in &self.attributes {
+            acc.extend(key.to_bytes());
+            acc.extend(value.to_bytes());
+        }
+
+        acc.extend(self.element.to_bytes());
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespAttribute {
+    fn from_byte_slice(slice: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if slice.first() != Some(&b'|') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        let (attributes, remaining_bytes) = get_key_value_pairs(&slice[1..])?;
+        let (element, remaining_bytes) = RespElement::from_byte_slice(remaining_bytes)?;
+
+        Ok((RespAttribute { attributes, element: Box::new(element) }, remaining_bytes))
+    }
+}
@@ -0,0 +1,51 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    read_until_crlf,
+};
+
+/// An integer that's too large to fit in a signed 64-bit integer, kept as its
+/// decimal representation
+#[derive(Debug, Clone)]
+pub struct RespBigNumber {
+    pub value: String,
+}
+
+impl RespSerialize for RespBigNumber {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'('];
+
+        acc.extend(self.value.as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespBigNumber {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'(') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        match read_until_crlf(&input[1..]) {
+            Some((data, remaining_bytes)) => {
+                let digits = match data.first() {
+                    Some(b'+') | Some(b'-') => &data[1..],
+                    _ => data,
+                };
+
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Err(RespParseError::InvalidElement);
+                }
+
+                let value = String::from_utf8_lossy(data).into_owned();
+
+                Ok((RespBigNumber { value }, remaining_bytes))
+            }
+            None => Err(RespParseError::Incomplete),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    read_until_crlf,
+};
+
+#[derive(Debug, Clone)]
+pub struct RespBoolean {
+    pub value: bool,
+}
+
+impl RespSerialize for RespBoolean {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'#'];
+
+        acc.push(if self.value { b't' } else { b'f' });
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespBoolean {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'#') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        match read_until_crlf(&input[1..]) {
+            Some((b"t", remaining_bytes)) => Ok((RespBoolean { value: true }, remaining_bytes)),
+            Some((b"f", remaining_bytes)) => Ok((RespBoolean { value: false }, remaining_bytes)),
+            Some(_) => Err(RespParseError::InvalidElement),
+            None => Err(RespParseError::Incomplete),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    get_length_of_current_element,
+    RESP_MAX_BULK_LENGTH,
+};
+
+/// A binary safe error, which (unlike a simple error) may contain CRLF
+#[derive(Debug, Clone)]
+pub struct RespBulkError {
+    pub length: usize,
+    pub value: Box<[u8]>,
+}
+
+impl RespSerialize for RespBulkError {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'!'];
+
+        acc.extend(self.length.to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc.extend_from_slice(&self.value);
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespBulkError {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'!') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        let (length, remaining_bytes) = get_length_of_current_element(&input[1..])?;
+
+        if length > RESP_MAX_BULK_LENGTH {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        if remaining_bytes.len() < length + 2 {
+            return Err(RespParseError::Incomplete);
+        }
+
+        if &remaining_bytes[length..(length + 2)] != RESP_DELIMITER {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        let value: Box<[u8]> = Box::from(&remaining_bytes[0..length]);
+
+        Ok((RespBulkError { length, value }, &remaining_bytes[(length + 2)..]))
+    }
+}
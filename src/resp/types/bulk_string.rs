@@ -4,11 +4,11 @@ use crate::resp::parser::{
     RespSerialize,
     RespDeserialize,
     RespParseError,
-    read_until_crlf,
     get_length_of_current_element,
+    RESP_MAX_BULK_LENGTH,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RespBulkString {
     pub length: usize,
     pub value: Box<[u8]>,
@@ -39,22 +39,30 @@ impl RespSerialize for RespBulkString {
 
 impl RespDeserialize for RespBulkString {
     fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
-        if input[0] != b'$' {
+        if input.first() != Some(&b'$') {
             return Err(RespParseError::UnknownTypePrefix);
         }
 
         let packet = &input[1..];
 
         let (length, remaining_bytes) = get_length_of_current_element(packet)?;
-        
-        let buffer: Box<[u8]> = Box::from(&remaining_bytes[0..length]);
 
-        let (remainder_of_line, next_line) = read_until_crlf(&remaining_bytes[length..]).unwrap();
+        if length > RESP_MAX_BULK_LENGTH {
+            return Err(RespParseError::InvalidElement);
+        }
 
-        if remainder_of_line.len() > 0 {
+        // the value and its trailing CRLF need to be fully buffered
+        if remaining_bytes.len() < length + 2 {
+            return Err(RespParseError::Incomplete);
+        }
+
+        if &remaining_bytes[length..(length + 2)] != RESP_DELIMITER {
             return Err(RespParseError::InvalidElement);
         }
 
+        let buffer: Box<[u8]> = Box::from(&remaining_bytes[0..length]);
+        let next_line = &remaining_bytes[(length + 2)..];
+
         Ok((RespBulkString { length, value: buffer }, next_line))
     }
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    read_until_crlf,
+};
+
+#[derive(Debug, Clone)]
+pub struct RespDouble {
+    pub value: f64,
+}
+
+impl RespSerialize for RespDouble {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b','];
+
+        acc.extend(format_double(self.value).as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespDouble {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b',') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        match read_until_crlf(&input[1..]) {
+            Some((data, remaining_bytes)) => {
+                let value = str::from_utf8(data).ok()
+                    .and_then(parse_double)
+                    .ok_or(RespParseError::InvalidElement)?;
+
+                Ok((RespDouble { value }, remaining_bytes))
+            }
+            None => Err(RespParseError::Incomplete),
+        }
+    }
+}
+
+/// Formats a double the way Redis does: `inf`, `-inf` and `nan` are spelled
+/// out, and very large or very small values use exponent notation
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".into();
+    }
+
+    if value.is_infinite() {
+        return if value > 0.0 { "inf".into() } else { "-inf".into() };
+    }
+
+    let magnitude = value.abs();
+
+    if magnitude != 0.0 && !(1e-5..1e17).contains(&magnitude) {
+        let formatted = format!("{value:e}");
+
+        // Rust omits the sign on positive exponents, e.g. `1e21` vs `1e+21`
+        return match formatted.split_once('e') {
+            Some((mantissa, exponent)) if !exponent.starts_with('-') => format!("{mantissa}e+{exponent}"),
+            _ => formatted,
+        };
+    }
+
+    format!("{value}")
+}
+
+/// Parses a double, accepting the same spellings of infinity that Redis does
+pub fn parse_double(value: &str) -> Option<f64> {
+    match value.to_ascii_lowercase().as_str() {
+        "inf" | "+inf" | "infinity" | "+infinity" => Some(f64::INFINITY),
+        "-inf" | "-infinity" => Some(f64::NEG_INFINITY),
+        "nan" | "+nan" | "-nan" => Some(f64::NAN),
+        // reject the forms Rust accepts but Redis doesn't, e.g. leading spaces
+        v if v.is_empty() || v.starts_with(char::is_whitespace) || v.ends_with(char::is_whitespace) => None,
+        v => v.parse::<f64>().ok(),
+    }
+}
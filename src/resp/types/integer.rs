@@ -1,3 +1,4 @@
+use crate::resp::RESP_DELIMITER;
 use crate::resp::parser::{
     RespSerialize,
     RespDeserialize,
@@ -5,7 +6,7 @@ use crate::resp::parser::{
     read_until_crlf,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RespInteger {
     pub value: isize,
 }
@@ -15,6 +16,7 @@ impl RespSerialize for RespInteger {
         let mut acc = vec![b':'];
 
         acc.extend_from_slice(self.value.to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
 
         acc
     }
@@ -22,36 +24,74 @@ impl RespSerialize for RespInteger {
 
 impl RespDeserialize for RespInteger {
     fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
-        if input[0] != b':' {
+        if input.first() != Some(&b':') {
             return Err(RespParseError::UnknownTypePrefix);
         }
 
-        let (start, multiplier): (usize, isize) = match input.get(1) {
-            Some(char) => {
-                match char {
-                    b'+' => (2, 1),
-                    b'-' => (2, -1),
-                    _ => (1, 1),
-                }
-            }
-            None => return Err(RespParseError::UnexpectedEof),
+        let Some((data, remaining_bytes)) = read_until_crlf(&input[1..]) else {
+            return Err(RespParseError::Incomplete);
         };
 
-        match read_until_crlf(&input[start..]) {
-            Some((data, remaining_bytes)) => {
-                let Ok(as_string) = String::from_utf8(data.to_vec()) else {
-                    return Err(RespParseError::InvalidElement);
-                };
+        let (is_negative, digits) = match data {
+            [b'-', rest @ ..] => (true, rest),
+            [b'+', rest @ ..] => (false, rest),
+            _ => (false, data),
+        };
 
-                let Ok(as_int) = as_string.parse::<usize>() else {
-                    return Err(RespParseError::InvalidElement);
-                };
+        if digits.is_empty() {
+            return Err(RespParseError::InvalidElement);
+        }
 
-                let value = (as_int as isize) * multiplier;
+        // negative values are accumulated downwards, so that `i64::MIN` (which
+        // has no positive counterpart) can still be read without overflowing
+        let value = digits.iter().try_fold(0i64, | acc, byte | {
+            let digit = (*byte as char).to_digit(10)? as i64;
+            let acc = acc.checked_mul(10)?;
 
-                Ok((RespInteger { value }, remaining_bytes))
+            match is_negative {
+                true => acc.checked_sub(digit),
+                false => acc.checked_add(digit),
             }
-            None => Err(RespParseError::InvalidElement),
+        });
+
+        let Some(value) = value.and_then(| v | isize::try_from(v).ok()) else {
+            return Err(RespParseError::InvalidElement);
+        };
+
+        Ok((RespInteger { value }, remaining_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[u8]) -> Result<isize, RespParseError> {
+        RespInteger::from_byte_slice(input).map(| (i, _) | i.value)
+    }
+
+    #[test]
+    fn parses_the_full_range_of_signed_integers() {
+        assert_eq!(parse(b":0\r\n").unwrap(), 0);
+        assert_eq!(parse(b":+12\r\n").unwrap(), 12);
+        assert_eq!(parse(b":-12\r\n").unwrap(), -12);
+        assert_eq!(parse(b":9223372036854775807\r\n").unwrap(), isize::MAX);
+        assert_eq!(parse(b":-9223372036854775808\r\n").unwrap(), isize::MIN);
+    }
+
+    #[test]
+    fn rejects_integers_that_overflow_instead_of_panicking() {
+        for input in [&b":9223372036854775808\r\n"[..], b":-9223372036854775809\r\n", b":99999999999999999999\r\n"] {
+            assert!(matches!(parse(input), Err(RespParseError::InvalidElement)), "{input:?}");
         }
     }
+
+    #[test]
+    fn rejects_malformed_integers() {
+        for input in [&b":\r\n"[..], b":-\r\n", b":1x\r\n", b":--1\r\n", b": 1\r\n"] {
+            assert!(matches!(parse(input), Err(RespParseError::InvalidElement)), "{input:?}");
+        }
+
+        assert!(matches!(parse(b":12"), Err(RespParseError::Incomplete)));
+    }
 }
@@ -0,0 +1,74 @@
+use crate::resp::{RespElement, RESP_DELIMITER};
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    get_length_of_current_element,
+    RESP_MAX_ARRAY_LENGTH,
+};
+
+pub type RespKeyValuePairs = Vec<(RespElement, RespElement)>;
+
+/// An ordered list of key-value pairs. The pairs are kept in a vector rather
+/// than a hash map, since the keys can be any element type.
+#[derive(Debug, Clone)]
+pub struct RespMap {
+    pub entries: RespKeyValuePairs,
+}
+
+impl RespMap {
+    pub fn new(entries: RespKeyValuePairs) -> RespMap {
+        RespMap { entries }
+    }
+}
+
+impl RespSerialize for RespMap {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'%'];
+
+        acc.extend(self.entries.len().to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        for (key, value) in &self.entries {
+            acc.extend(key.to_bytes());
+            acc.extend(value.to_bytes());
+        }
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespMap {
+    fn from_byte_slice(slice: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if slice.first() != Some(&b'%') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        let (entries, remaining_bytes) = get_key_value_pairs(&slice[1..])?;
+
+        Ok((RespMap { entries }, remaining_bytes))
+    }
+}
+
+/// Parses the length header and the key-value pairs that follow it, which are
+/// laid out the same way for both maps and attributes
+pub fn get_key_value_pairs(packet: &[u8]) -> Result<(RespKeyValuePairs, &[u8]), RespParseError> {
+    let (length, mut remaining_bytes) = get_length_of_current_element(packet)?;
+
+    if length > RESP_MAX_ARRAY_LENGTH {
+        return Err(RespParseError::InvalidElement);
+    }
+
+    let mut entries = Vec::with_capacity(length.min(1024));
+
+    for _ in 0 .. length {
+        let (key, next_slice) = RespElement::from_byte_slice(remaining_bytes)?;
+        let (value, next_slice) = RespElement::from_byte_slice(next_slice)?;
+
+        entries.push((key, value));
+
+        remaining_bytes = next_slice;
+    }
+
+    Ok((entries, remaining_bytes))
+}
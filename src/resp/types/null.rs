@@ -0,0 +1,34 @@
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    read_until_crlf,
+};
+
+/// The RESP3 null type, `_\r\n`. RESP2 has no standalone null, so it's sent
+/// to RESP2 clients as a null bulk string (`$-1\r\n`) instead, or as a null
+/// array (`*-1\r\n`) for replies where Redis would send one.
+#[derive(Debug, Clone, Default)]
+pub struct RespNull {
+    pub is_array: bool,
+}
+
+impl RespSerialize for RespNull {
+    fn to_bytes(&self) -> Vec<u8> {
+        b"_\r\n".to_vec()
+    }
+}
+
+impl RespDeserialize for RespNull {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'_') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        match read_until_crlf(&input[1..]) {
+            Some((b"", remaining_bytes)) => Ok((RespNull::default(), remaining_bytes)),
+            Some(_) => Err(RespParseError::InvalidElement),
+            None => Err(RespParseError::Incomplete),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::resp::{RespElement, RESP_DELIMITER};
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    get_length_of_current_element,
+    RESP_MAX_ARRAY_LENGTH,
+};
+
+/// An unordered collection of unique elements
+#[derive(Debug, Clone)]
+pub struct RespSet {
+    pub elements: Vec<RespElement>,
+}
+
+impl RespSerialize for RespSet {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'~'];
+
+        acc.extend(self.elements.len().to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc.extend(self.elements.iter().flat_map(| e | e.to_bytes()));
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespSet {
+    fn from_byte_slice(slice: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if slice.first() != Some(&b'~') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        let (length, mut remaining_bytes) = get_length_of_current_element(&slice[1..])?;
+
+        if length > RESP_MAX_ARRAY_LENGTH {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        let mut elements = Vec::with_capacity(length.min(1024));
+
+        for _ in 0 .. length {
+            let (element, next_slice) = RespElement::from_byte_slice(remaining_bytes)?;
+
+            elements.push(element);
+
+            remaining_bytes = next_slice;
+        }
+
+        Ok((RespSet { elements }, remaining_bytes))
+    }
+}
@@ -0,0 +1,58 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    read_until_crlf,
+};
+
+/// An error reply, e.g. `-ERR syntax error\r\n`. By convention the first word
+/// of the message is an uppercase error code (like `ERR` or `WRONGTYPE`) that
+/// clients can match on.
+#[derive(Debug, Clone)]
+pub struct RespSimpleError {
+    pub value: String,
+}
+
+impl RespSimpleError {
+    pub fn new(value: &str) -> RespSimpleError {
+        RespSimpleError {
+            value: value.into(),
+        }
+    }
+}
+
+impl RespSerialize for RespSimpleError {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'-'];
+
+        // CR and LF can't be represented in a simple error, so they're
+        // replaced rather than allowed to break the framing
+        acc.extend(self.value.bytes().map(| byte | match byte {
+            b'\r' | b'\n' => b' ',
+            _ => byte,
+        }));
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespSimpleError {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'-') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        match read_until_crlf(&input[1..]) {
+            Some((data, remaining_bytes)) => {
+                let Ok(value) = String::from_utf8(data.to_vec()) else {
+                    return Err(RespParseError::InvalidElement);
+                };
+
+                Ok((RespSimpleError { value }, remaining_bytes))
+            }
+            None => Err(RespParseError::Incomplete),
+        }
+    }
+}
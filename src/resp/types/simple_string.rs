@@ -1,3 +1,4 @@
+use crate::resp::RESP_DELIMITER;
 use crate::resp::parser::{
     RespSerialize,
     RespDeserialize,
@@ -5,7 +6,7 @@ use crate::resp::parser::{
     read_until_crlf,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RespSimpleString {
     pub value: String,
 }
@@ -15,6 +16,7 @@ impl RespSerialize for RespSimpleString {
         let mut acc = vec![b'+'];
 
         acc.extend(self.value.as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
 
         acc
     }
@@ -22,7 +24,7 @@ impl RespSerialize for RespSimpleString {
 
 impl RespDeserialize for RespSimpleString {
     fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
-        if input[0] != b'+' {
+        if input.first() != Some(&b'+') {
             return Err(RespParseError::UnknownTypePrefix);
         }
 
@@ -35,7 +37,7 @@ impl RespDeserialize for RespSimpleString {
 
                 Ok((RespSimpleString { value }, remaining_bytes))
             }
-            None => Err(RespParseError::InvalidElement),
+            None => Err(RespParseError::Incomplete),
         }
     }
 }
@@ -0,0 +1,60 @@
+use crate::resp::RESP_DELIMITER;
+use crate::resp::parser::{
+    RespSerialize,
+    RespDeserialize,
+    RespParseError,
+    get_length_of_current_element,
+    RESP_MAX_BULK_LENGTH,
+};
+
+/// A bulk string with a three character hint describing its format, e.g.
+/// `txt` for plain text or `mkd` for markdown
+#[derive(Debug, Clone)]
+pub struct RespVerbatimString {
+    pub format: [u8; 3],
+    pub value: Box<[u8]>,
+}
+
+impl RespSerialize for RespVerbatimString {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut acc = vec![b'='];
+
+        // the length includes the format and the colon that follows it
+        acc.extend((self.value.len() + 4).to_string().as_bytes());
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc.extend_from_slice(&self.format);
+        acc.push(b':');
+        acc.extend_from_slice(&self.value);
+        acc.extend_from_slice(RESP_DELIMITER);
+
+        acc
+    }
+}
+
+impl RespDeserialize for RespVerbatimString {
+    fn from_byte_slice(input: &[u8]) -> Result<(Self, &[u8]), RespParseError> {
+        if input.first() != Some(&b'=') {
+            return Err(RespParseError::UnknownTypePrefix);
+        }
+
+        let (length, remaining_bytes) = get_length_of_current_element(&input[1..])?;
+
+        if !(4..=RESP_MAX_BULK_LENGTH).contains(&length) {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        if remaining_bytes.len() < length + 2 {
+            return Err(RespParseError::Incomplete);
+        }
+
+        if remaining_bytes[3] != b':' || &remaining_bytes[length..(length + 2)] != RESP_DELIMITER {
+            return Err(RespParseError::InvalidElement);
+        }
+
+        let format = [remaining_bytes[0], remaining_bytes[1], remaining_bytes[2]];
+        let value: Box<[u8]> = Box::from(&remaining_bytes[4..length]);
+
+        Ok((RespVerbatimString { format, value }, &remaining_bytes[(length + 2)..]))
+    }
+}
@@ -1,85 +1,594 @@
-use std::collections::{BinaryHeap, HashMap};
-use std::time::{Instant, Duration};
+use std::collections::BinaryHeap;
+use std::time::Duration;
 use std::cmp::Reverse;
 
-type ExpiryHeap = BinaryHeap<Reverse<(Instant, usize, String)>>;
+use crate::clock::{Clock, SystemClock};
+use crate::store::dict::Dict;
+
+mod dict;
+mod geohash;
+mod hash;
+mod hyperloglog;
+mod intset;
+mod list;
+mod listpack;
+mod set;
+mod skiplist;
+mod sorted_set;
+mod stream;
+mod string;
+mod value;
+
+pub use list::{List, ListEnd};
+pub use geohash::{Coordinates, GeoShape};
+pub use hash::Hash;
+pub use hyperloglog::{HyperLogLog, HyperLogLogEncoding, HyperLogLogError, SparseOpcode};
+pub use set::Set;
+pub use sorted_set::{LexBound, LexRange, ScoreBound, ScoreRange, SortedSet};
+pub use stream::{
+    ClaimOptions,
+    ConsumerGroup,
+    STREAM_DEFAULT_TRIM_LIMIT,
+    Stream,
+    StreamFields,
+    StreamId,
+    TrimStrategy,
+};
+pub use string::StringValue;
+pub use value::{Value, WrongTypeError};
+
+/// Queued expiry times (in Unix milliseconds), along with the version of the
+/// entry they were queued for and its key
+type ExpiryHeap = BinaryHeap<Reverse<(u64, usize, Vec<u8>)>>;
+
+/// Queued times (in Unix milliseconds) that a hash at the given key has a
+/// field to remove at
+type FieldExpiryHeap = BinaryHeap<Reverse<(u64, Vec<u8>)>>;
+
+/// The expiry queue is only rebuilt once it holds at least this many entries,
+/// so that small queues aren't rebuilt over and over
+const EXPIRY_QUEUE_COMPACTION_THRESHOLD: usize = 1024;
+
+/// Controls whether `Database::set` writes a value, based on whether the key
+/// already exists (i.e. the `NX` and `XX` options for `SET`)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    IfMissing,
+    IfExists,
+}
+
+/// What should happen to a key's TTL when its value is written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// Remove any existing TTL, so that the key never expires
+    #[default]
+    Never,
+    /// Keep the key's existing TTL, if it has one
+    Keep,
+    /// Expire the key at the given Unix time, in milliseconds
+    At(u64),
+}
+
+/// Controls whether `Database::expire` replaces a key's TTL, based on the TTL
+/// it already has (i.e. the `NX`, `XX`, `GT` and `LT` options for `EXPIRE`).
+/// Keys without a TTL are treated as though their TTL is infinite, the same
+/// way Redis treats them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExpireCondition {
+    /// Only set the TTL if the key doesn't have one
+    pub if_none: bool,
+    /// Only set the TTL if the key already has one
+    pub if_some: bool,
+    /// Only set the TTL if it's later than the current one
+    pub if_later: bool,
+    /// Only set the TTL if it's earlier than the current one
+    pub if_earlier: bool,
+}
+
+impl ExpireCondition {
+    /// Whether a TTL of `when` should replace `current`, which is `None` when
+    /// there's no TTL
+    pub fn allows(&self, current: Option<u64>, when: u64) -> bool {
+        match current {
+            None => !self.if_some && !self.if_later,
+            Some(current) => {
+                !self.if_none
+                    && (!self.if_later || when > current)
+                    && (!self.if_earlier || when < current)
+            }
+        }
+    }
+}
+
+/// What happened when `Database::expire_field` was asked to set the TTL of a
+/// hash field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldExpireOutcome {
+    /// The field (or the hash) doesn't exist
+    NoSuchField,
+    /// The field's TTL didn't meet the `ExpireCondition`
+    ConditionNotMet,
+    /// The field's TTL was set
+    Set,
+    /// The time had already passed, so the field was deleted straight away
+    Deleted,
+}
+
+#[derive(Debug, Default)]
+pub struct SetOptions {
+    pub condition: SetCondition,
+    pub expiry: Expiry,
+}
+
+#[derive(Debug)]
+pub struct SetOutcome {
+    /// Whether the value was written, which is only false when the condition
+    /// in `SetOptions` wasn't met
+    pub written: bool,
+    /// The value that was stored at the key beforehand
+    pub previous: Option<Value>,
+}
 
 #[derive(Debug)]
 pub struct Entry {
-    pub value: Vec<u8>,
-    expires_at: Option<Instant>,
+    pub value: Value,
+    /// The Unix time that the key expires at, in milliseconds
+    expires_at: Option<u64>,
     version: usize,
 }
 
 #[derive(Debug)]
 pub struct Database {
-    store: HashMap<String, Entry>,
+    store: Dict<Entry>,
     expiry_queue: ExpiryHeap,
+    /// How many entries in the expiry queue belong to TTLs that have since
+    /// been changed or removed, and will be skipped when they're popped
+    stale_expiries: usize,
+    /// Hashes with fields that have TTLs, so that those fields are removed
+    /// once they run out even if the hash is never accessed again
+    field_expiry_queue: FieldExpiryHeap,
+    clock: Box<dyn Clock>,
 }
 
 impl Database {
     pub fn new() -> Self {
+        Self::with_clock(Box::new(SystemClock))
+    }
+
+    /// Creates a database that reads the current time from `clock` when it
+    /// decides whether keys have expired
+    pub fn with_clock(clock: Box<dyn Clock>) -> Self {
         Self {
-            store: HashMap::new(),
+            store: Dict::new(),
             expiry_queue: BinaryHeap::new(),
+            stale_expiries: 0,
+            field_expiry_queue: BinaryHeap::new(),
+            clock,
         }
     }
 
-    pub fn get(&mut self, key: &str) -> Option<&Entry> {
-        let expired = {
-            let Some(e) = self.store.get(key) else {
-                return None;
-            };
+    /// Returns the current Unix time in milliseconds, according to the
+    /// database's clock. Commands should use this when they work out absolute
+    /// expiry times, so that they agree with the database about what "now" is.
+    pub fn now_millis(&self) -> u64 {
+        self.clock.now_millis()
+    }
 
-            if let Some(expires_at) = e.expires_at {
-                expires_at <= Instant::now()
-            } else {
-                false
-            }
-        };
+    pub fn get(&mut self, key: &[u8]) -> Option<&Entry> {
+        self.expire_if_needed(key);
 
-        if expired {
-            None
-        } else {
-            self.store.get(key)
+        self.store.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut Entry> {
+        self.expire_if_needed(key);
+
+        self.store.get_mut(key)
+    }
+
+    /// Returns the entry stored at the key, first storing the value returned
+    /// by `default` (without a TTL) if the key doesn't exist
+    pub fn get_or_insert_with(&mut self, key: &[u8], default: impl FnOnce() -> Value) -> &mut Entry {
+        self.expire_if_needed(key);
+
+        self.store.get_or_insert_with(key, || Entry {
+            value: default(),
+            expires_at: None,
+            version: 0,
+        })
+    }
+
+    /// Deletes the key if it holds an empty collection, e.g. after the last
+    /// element of a list has been popped
+    pub fn delete_if_empty(&mut self, key: &[u8]) {
+        if self.store.get(key).is_some_and(| e | e.value.is_empty()) {
+            self.remove_entry(key);
         }
     }
 
-    pub fn set(&mut self, key: &str, value: &[u8], ttl: Option<Duration>) {
-        let entry = self.store.entry(key.into()).or_insert(Entry {
-            value: Vec::new(),
+    /// Returns the string stored at the key, or an error if the key holds a
+    /// different type of value
+    pub fn get_string(&mut self, key: &[u8]) -> Result<Option<&StringValue>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_string()).transpose()
+    }
+
+    pub fn get_list(&mut self, key: &[u8]) -> Result<Option<&List>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_list()).transpose()
+    }
+
+    pub fn get_list_mut(&mut self, key: &[u8]) -> Result<Option<&mut List>, WrongTypeError> {
+        self.get_mut(key).map(| e | e.value.as_list_mut()).transpose()
+    }
+
+    pub fn get_hash(&mut self, key: &[u8]) -> Result<Option<&Hash>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_hash()).transpose()
+    }
+
+    pub fn get_hash_mut(&mut self, key: &[u8]) -> Result<Option<&mut Hash>, WrongTypeError> {
+        self.get_mut(key).map(| e | e.value.as_hash_mut()).transpose()
+    }
+
+    pub fn get_set(&mut self, key: &[u8]) -> Result<Option<&Set>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_set()).transpose()
+    }
+
+    pub fn get_set_mut(&mut self, key: &[u8]) -> Result<Option<&mut Set>, WrongTypeError> {
+        self.get_mut(key).map(| e | e.value.as_set_mut()).transpose()
+    }
+
+    pub fn get_sorted_set(&mut self, key: &[u8]) -> Result<Option<&SortedSet>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_sorted_set()).transpose()
+    }
+
+    pub fn get_sorted_set_mut(&mut self, key: &[u8]) -> Result<Option<&mut SortedSet>, WrongTypeError> {
+        self.get_mut(key).map(| e | e.value.as_sorted_set_mut()).transpose()
+    }
+
+    pub fn get_stream(&mut self, key: &[u8]) -> Result<Option<&Stream>, WrongTypeError> {
+        self.get(key).map(| e | e.value.as_stream()).transpose()
+    }
+
+    pub fn get_stream_mut(&mut self, key: &[u8]) -> Result<Option<&mut Stream>, WrongTypeError> {
+        self.get_mut(key).map(| e | e.value.as_stream_mut()).transpose()
+    }
+
+    /// Stores `value` at the key, replacing whatever was there before
+    /// regardless of its type
+    pub fn set(&mut self, key: &[u8], value: Value, options: SetOptions) -> SetOutcome {
+        self.expire_if_needed(key);
+
+        let exists = self.store.contains_key(key);
+
+        let allowed = match options.condition {
+            SetCondition::Always => true,
+            SetCondition::IfMissing => !exists,
+            SetCondition::IfExists => exists,
+        };
+
+        if !allowed {
+            let previous = self.store.get(key).map(| e | e.value.clone());
+
+            return SetOutcome { written: false, previous };
+        }
+
+        let entry = self.store.get_or_insert_with(key, || Entry {
+            value: Value::String(StringValue::default()),
             version: 0,
             expires_at: None,
         });
 
-        let expires_at = match ttl {
-            Some(dur) => Instant::now().checked_add(dur),
-            None => None,
+        let expires_at = match options.expiry {
+            Expiry::Never => None,
+            Expiry::Keep => entry.expires_at,
+            Expiry::At(when) => Some(when),
+        };
+
+        let previous = std::mem::replace(&mut entry.value, value);
+
+        self.set_expiry(key, expires_at);
+
+        SetOutcome {
+            written: true,
+            previous: exists.then_some(previous),
+        }
+    }
+
+    /// Removes the key, and returns whether it existed
+    pub fn delete(&mut self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+
+        self.remove_entry(key).is_some()
+    }
+
+    pub fn exists(&mut self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of keys, which can include keys whose TTL has run
+    /// out but that haven't been removed yet
+    pub fn key_count(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Returns every key that `filter` accepts, leaving out (and deleting)
+    /// any whose TTL has run out
+    pub fn keys(&mut self, mut filter: impl FnMut(&[u8]) -> bool) -> Vec<Vec<u8>> {
+        let keys: Vec<Vec<u8>> = self.store.iter()
+            .filter(| (key, _) | filter(key))
+            .map(| (key, _) | key.to_vec())
+            .collect();
+
+        keys.into_iter()
+            .filter(| key | self.exists(key))
+            .collect()
+    }
+
+    /// Returns a random key, deleting any whose TTL has run out that are
+    /// picked along the way
+    pub fn random_key(&mut self) -> Option<Vec<u8>> {
+        loop {
+            let key = self.store.random_entry()?.0.to_vec();
+
+            if self.exists(&key) {
+                return Some(key);
+            }
+        }
+    }
+
+    /// Collects the keys in the buckets of the store after the cursor, until
+    /// there are at least `count` of them or `count * 10` buckets have been
+    /// visited, and returns the cursor to continue from (which is `0` once
+    /// the iteration is complete). Every key that exists for the whole of an
+    /// iteration is returned at least once, though some keys can be returned
+    /// more than once. Keys whose TTL has run out are left out (and deleted).
+    pub fn scan(&mut self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>) {
+        let mut keys = Vec::new();
+        let mut cursor = cursor;
+
+        for _ in 0 .. count.saturating_mul(10) {
+            cursor = self.store.scan(cursor, | key, _ | keys.push(key.to_vec()));
+
+            if cursor == 0 || keys.len() >= count {
+                break;
+            }
+        }
+
+        keys.retain(| key | self.exists(key));
+
+        (cursor, keys)
+    }
+
+    /// Moves the value stored at `from` (along with its TTL) to `to`. If `to`
+    /// already exists, it's only overwritten if `replace` is set. Returns
+    /// `None` if `from` doesn't exist, or otherwise whether it was moved.
+    pub fn rename(&mut self, from: &[u8], to: &[u8], replace: bool) -> Option<bool> {
+        if !self.exists(from) {
+            return None;
+        }
+
+        if from == to {
+            return Some(replace);
+        }
+
+        if self.exists(to) && !replace {
+            return Some(false);
+        }
+
+        let entry = self.remove_entry(from)?;
+
+        self.remove_entry(to);
+        self.insert_entry(to, entry.value, entry.expires_at);
+
+        Some(true)
+    }
+
+    /// Copies the value stored at `from` (along with its TTL) to `to`. If `to`
+    /// already exists, it's only overwritten if `replace` is set. Returns
+    /// whether the value was copied.
+    pub fn copy(&mut self, from: &[u8], to: &[u8], replace: bool) -> bool {
+        let Some(entry) = self.get(from) else {
+            return false;
+        };
+
+        let value = entry.value.clone();
+        let expires_at = entry.expires_at;
+
+        if self.exists(to) && !replace {
+            return false;
+        }
+
+        self.remove_entry(to);
+        self.insert_entry(to, value, expires_at);
+
+        true
+    }
+
+    /// Stores a new entry at `key`, which mustn't already exist
+    fn insert_entry(&mut self, key: &[u8], mut value: Value, expires_at: Option<u64>) {
+        // whatever was queued for a hash belongs to the key it came from
+        if let Value::Hash(hash) = &mut value {
+            hash.clear_queued_expiry();
+        }
+
+        self.store.insert(key.to_vec(), Entry { value, expires_at: None, version: 0 });
+        self.set_expiry(key, expires_at);
+        self.queue_field_expiry(key);
+    }
+
+    /// Removes an entry without checking whether it has expired, and keeps
+    /// track of any TTL that it leaves behind in the expiry queue
+    fn remove_entry(&mut self, key: &[u8]) -> Option<Entry> {
+        let entry = self.store.remove(key)?;
+
+        if entry.expires_at.is_some() {
+            self.mark_expiry_stale();
+        }
+
+        Some(entry)
+    }
+
+    /// Returns `None` if the key doesn't exist, or otherwise the Unix time (in
+    /// milliseconds) that it expires at, if it has a TTL
+    pub fn get_expiry(&mut self, key: &[u8]) -> Option<Option<u64>> {
+        self.get(key).map(| e | e.expires_at)
+    }
+
+    /// Sets the Unix time (in milliseconds) that the key expires at, if the key
+    /// exists and `condition` is met. A time that has already passed deletes
+    /// the key straight away. Returns whether the TTL was changed.
+    pub fn expire(&mut self, key: &[u8], when: u64, condition: ExpireCondition) -> bool {
+        let Some(current) = self.get_expiry(key) else {
+            return false;
+        };
+
+        if !condition.allows(current, when) {
+            return false;
+        }
+
+        if when <= self.now_millis() {
+            self.remove_entry(key);
+        } else {
+            self.set_expiry(key, Some(when));
+        }
+
+        true
+    }
+
+    /// Removes the key's TTL, and returns whether it had one
+    pub fn persist(&mut self, key: &[u8]) -> bool {
+        match self.get_expiry(key) {
+            Some(Some(_)) => {
+                self.set_expiry(key, None);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the Unix time (in milliseconds) that a field of the hash stored at
+    /// `key` expires at, if `condition` is met. A time that has already passed
+    /// deletes the field straight away, along with the hash if it was the
+    /// last field.
+    pub fn expire_field(&mut self, key: &[u8], field: &[u8], when: u64, condition: ExpireCondition) -> Result<FieldExpireOutcome, WrongTypeError> {
+        let now = self.now_millis();
+
+        let Some(hash) = self.get_hash_mut(key)? else {
+            return Ok(FieldExpireOutcome::NoSuchField);
         };
 
+        let Some(current) = hash.get_expiry(field) else {
+            return Ok(FieldExpireOutcome::NoSuchField);
+        };
+
+        if !condition.allows(current, when) {
+            return Ok(FieldExpireOutcome::ConditionNotMet);
+        }
+
+        if when <= now {
+            hash.remove(field);
+
+            self.delete_if_empty(key);
+
+            return Ok(FieldExpireOutcome::Deleted);
+        }
+
+        hash.set_expiry(field, when);
+
+        self.queue_field_expiry(key);
+
+        Ok(FieldExpireOutcome::Set)
+    }
+
+    /// Queues the hash stored at `key` to have its fields removed once their
+    /// TTLs run out, if it isn't already queued early enough
+    fn queue_field_expiry(&mut self, key: &[u8]) {
+        let Some(Entry { value: Value::Hash(hash), .. }) = self.store.get_mut(key) else {
+            return;
+        };
+
+        if let Some(when) = hash.next_expiry_to_queue() {
+            self.field_expiry_queue.push(Reverse((when, key.to_vec())));
+        }
+    }
+
+    /// Replaces the TTL of an existing key, and queues the new one (if any) so
+    /// that the key is removed once it runs out. The version is bumped so that
+    /// whatever was queued for the old TTL is recognised as stale.
+    fn set_expiry(&mut self, key: &[u8], expires_at: Option<u64>) {
+        let Some(entry) = self.store.get_mut(key) else {
+            return;
+        };
+
+        let had_expiry = entry.expires_at.is_some();
+
         entry.version = entry.version.wrapping_add(1);
-        entry.value = value.to_vec();
         entry.expires_at = expires_at;
 
-        if let Some(expires_at) = entry.expires_at {
-            self.expiry_queue.push(Reverse((expires_at, entry.version, key.into())));
+        if let Some(expires_at) = expires_at {
+            self.expiry_queue.push(Reverse((expires_at, entry.version, key.to_vec())));
+        }
+
+        if had_expiry {
+            self.mark_expiry_stale();
         }
     }
 
-    pub fn delete(&mut self, key: &str) {
-        self.store.remove(key);
+    /// Records that an entry in the expiry queue no longer applies. Stale
+    /// entries are normally skipped when they're popped, but if a lot of TTLs
+    /// are changed before they run out then the queue is rebuilt from the
+    /// store instead, so that it doesn't keep growing.
+    fn mark_expiry_stale(&mut self) {
+        self.stale_expiries += 1;
+
+        let queue_length = self.expiry_queue.len();
+
+        if queue_length >= EXPIRY_QUEUE_COMPACTION_THRESHOLD && self.stale_expiries > queue_length / 2 {
+            self.expiry_queue = self.store.iter()
+                .filter_map(| (key, entry) | {
+                    entry.expires_at.map(| when | Reverse((when, entry.version, key.to_vec())))
+                })
+                .collect();
+
+            self.stale_expiries = 0;
+        }
+    }
+
+    /// Deletes the key if its TTL has run out, so that it behaves as though it
+    /// doesn't exist even before the worker gets around to removing it
+    fn expire_if_needed(&mut self, key: &[u8]) {
+        let now = self.now_millis();
+
+        let expired = match self.store.get_mut(key) {
+            Some(Entry { expires_at: Some(when), .. }) if *when <= now => true,
+            // a hash whose fields have all run out is gone too
+            Some(Entry { value: Value::Hash(hash), .. }) => hash.remove_expired(now) > 0 && hash.is_empty(),
+            _ => false,
+        };
+
+        if expired {
+            self.remove_entry(key);
+        }
     }
 
     pub fn time_until_next_expiration(&self) -> Option<Duration> {
-        let now = Instant::now();
+        let now = self.now_millis();
 
-        self.expiry_queue.peek().map(| Reverse((when, _, _)) | {
-            when.saturating_duration_since(now)
-        })
+        let next_key = self.expiry_queue.peek().map(| Reverse((when, _, _)) | *when);
+        let next_field = self.field_expiry_queue.peek().map(| Reverse((when, _)) | *when);
+
+        let next = match (next_key, next_field) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        next.map(| when | Duration::from_millis(when.saturating_sub(now)))
     }
 
     pub fn delete_expired_keys(&mut self, budget: usize) {
-        let now = Instant::now();
+        let now = self.now_millis();
 
         for _ in 0 .. budget {
             match self.expiry_queue.peek().cloned() {
@@ -91,12 +600,449 @@ impl Database {
                         None => true,
                     };
 
-                    if !is_expiry_stale {
-                        self.delete(&key);
+                    if is_expiry_stale {
+                        self.stale_expiries = self.stale_expiries.saturating_sub(1);
+                    } else {
+                        self.store.remove(&key);
                     }
                 }
                 _ => break
             }
         }
+
+        self.delete_expired_fields(budget);
+    }
+
+    /// Removes the hash fields whose TTLs have run out, from up to `budget`
+    /// hashes, and deletes any hashes that are left empty
+    fn delete_expired_fields(&mut self, budget: usize) {
+        let now = self.now_millis();
+
+        for _ in 0 .. budget {
+            match self.field_expiry_queue.peek() {
+                Some(Reverse((when, _))) if *when <= now => {
+                    let Some(Reverse((when, key))) = self.field_expiry_queue.pop() else {
+                        break;
+                    };
+
+                    let Some(Entry { value: Value::Hash(hash), .. }) = self.store.get_mut(&key) else {
+                        continue;
+                    };
+
+                    // the hash has been queued again since, or was moved here
+                    // from another key
+                    if !hash.take_queued_expiry(when) {
+                        continue;
+                    }
+
+                    hash.remove_expired(now);
+
+                    if hash.is_empty() {
+                        self.remove_entry(&key);
+                    } else {
+                        self.queue_field_expiry(&key);
+                    }
+                }
+                _ => break
+            }
+        }
+    }
+}
+
+/// The longest that an `i64` can be when it's written out (`-9223372036854775808`),
+/// so longer strings aren't checked for whether they hold one
+const MAX_INTEGER_LENGTH: usize = 20;
+
+/// Parses an integer written the way Redis would write it (e.g. `12` but not
+/// `012`, `+12` or `-0`), so that it comes back out exactly as it went in.
+/// This decides both which arguments are accepted as integers and which
+/// values can be stored as integers.
+pub fn parse_integer(bytes: &[u8]) -> Option<i64> {
+    if bytes.len() > MAX_INTEGER_LENGTH {
+        return None;
+    }
+
+    let digits = bytes.strip_prefix(b"-").unwrap_or(bytes);
+
+    let is_canonical = match digits {
+        [] => false,
+        [b'0'] => bytes.len() == 1,
+        [first, ..] => (b'1'..=b'9').contains(first) && digits.iter().all(u8::is_ascii_digit),
+    };
+
+    if !is_canonical {
+        return None;
+    }
+
+    str::from_utf8(bytes).ok()?.parse::<i64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const START: u64 = 1_700_000_000_000;
+
+    fn database() -> (Database, ManualClock) {
+        let clock = ManualClock::new(START);
+
+        (Database::with_clock(Box::new(clock.clone())), clock)
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(StringValue::new(value.as_bytes().to_vec()))
+    }
+
+    fn set_with_expiry(db: &mut Database, key: &[u8], expiry: Expiry) {
+        db.set(key, string("value"), SetOptions { expiry, ..SetOptions::default() });
+    }
+
+    /// Stores a hash at `key` whose fields expire at the given times, if any
+    fn hash_with_fields(db: &mut Database, key: &[u8], fields: &[(&str, Option<u64>)]) {
+        for (field, _) in fields {
+            db.get_or_insert_with(key, || Value::Hash(Hash::new())).value.as_hash_mut().unwrap()
+                .insert(field.as_bytes().to_vec(), b"value".to_vec());
+        }
+
+        for (field, expiry) in fields {
+            if let Some(when) = expiry {
+                db.expire_field(key, field.as_bytes(), *when, ExpireCondition::default()).unwrap();
+            }
+        }
+    }
+
+    /// Returns the fields of the hash at `key` without expiring anything, so
+    /// that only what the worker has removed is gone
+    fn stored_fields(db: &Database, key: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let Some(Entry { value: Value::Hash(hash), .. }) = db.store.get(key) else {
+            return None;
+        };
+
+        let mut fields: Vec<Vec<u8>> = hash.keys().map(| f | f.to_vec()).collect();
+
+        fields.sort();
+
+        Some(fields)
+    }
+
+    #[test]
+    fn only_canonical_integers_are_parsed() {
+        assert_eq!(parse_integer(b"12"), Some(12));
+        assert_eq!(parse_integer(b"0"), Some(0));
+        assert_eq!(parse_integer(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_integer(b"012"), None);
+        assert_eq!(parse_integer(b"+12"), None);
+        assert_eq!(parse_integer(b"-0"), None);
+        assert_eq!(parse_integer(b" 1"), None);
+        assert_eq!(parse_integer(b"1e3"), None);
+        assert_eq!(parse_integer(b""), None);
+        assert_eq!(parse_integer(b"9223372036854775808"), None);
+    }
+
+    #[test]
+    fn expire_and_persist() {
+        let (mut db, clock) = database();
+
+        db.set(b"key", string("value"), SetOptions::default());
+
+        assert!(db.expire(b"key", START + 1000, ExpireCondition::default()));
+        assert_eq!(db.get_expiry(b"key"), Some(Some(START + 1000)));
+
+        clock.advance(999);
+        assert!(db.exists(b"key"));
+
+        assert!(db.persist(b"key"));
+        assert!(!db.persist(b"key"));
+        assert_eq!(db.get_expiry(b"key"), Some(None));
+
+        clock.advance(10_000);
+        assert!(db.exists(b"key"));
+
+        // a time that has already passed deletes the key straight away
+        assert!(db.expire(b"key", clock.now_millis(), ExpireCondition::default()));
+        assert!(!db.exists(b"key"));
+
+        assert!(!db.expire(b"missing", START + 1000, ExpireCondition::default()));
+        assert!(!db.persist(b"missing"));
+    }
+
+    #[test]
+    fn expire_conditions() {
+        let (mut db, _) = database();
+
+        db.set(b"key", string("value"), SetOptions::default());
+
+        let if_some = ExpireCondition { if_some: true, ..ExpireCondition::default() };
+        let if_later = ExpireCondition { if_later: true, ..ExpireCondition::default() };
+        let if_earlier = ExpireCondition { if_earlier: true, ..ExpireCondition::default() };
+        let if_none = ExpireCondition { if_none: true, ..ExpireCondition::default() };
+
+        // keys without a TTL are treated as though it's infinite
+        assert!(!db.expire(b"key", START + 1000, if_some));
+        assert!(!db.expire(b"key", START + 1000, if_later));
+        assert!(db.expire(b"key", START + 1000, if_earlier));
+
+        assert!(!db.expire(b"key", START + 2000, if_none));
+        assert!(!db.expire(b"key", START + 500, if_later));
+        assert!(db.expire(b"key", START + 2000, if_later));
+        assert_eq!(db.get_expiry(b"key"), Some(Some(START + 2000)));
+    }
+
+    #[test]
+    fn set_keeps_ttl_only_when_asked() {
+        let (mut db, _) = database();
+
+        set_with_expiry(&mut db, b"key", Expiry::At(START + 1000));
+        assert_eq!(db.get_expiry(b"key"), Some(Some(START + 1000)));
+
+        set_with_expiry(&mut db, b"key", Expiry::Keep);
+        assert_eq!(db.get_expiry(b"key"), Some(Some(START + 1000)));
+
+        set_with_expiry(&mut db, b"key", Expiry::Never);
+        assert_eq!(db.get_expiry(b"key"), Some(None));
+
+        // there's nothing to keep on a new key
+        set_with_expiry(&mut db, b"other", Expiry::Keep);
+        assert_eq!(db.get_expiry(b"other"), Some(None));
+    }
+
+    #[test]
+    fn expiry_times_are_absolute() {
+        let (mut db, clock) = database();
+
+        set_with_expiry(&mut db, b"key", Expiry::At(START + 5000));
+
+        clock.advance(3000);
+        assert_eq!(db.get_expiry(b"key"), Some(Some(START + 5000)));
+        assert_eq!(db.time_until_next_expiration(), Some(Duration::from_millis(2000)));
+
+        clock.set(START + 5000);
+        assert_eq!(db.get_expiry(b"key"), None);
+
+        // the queued TTL is left behind until the worker pops it
+        assert_eq!(db.time_until_next_expiration(), Some(Duration::ZERO));
+
+        db.delete_expired_keys(10);
+        assert_eq!(db.time_until_next_expiration(), None);
+    }
+
+    #[test]
+    fn lazy_expiry() {
+        let (mut db, clock) = database();
+
+        set_with_expiry(&mut db, b"key", Expiry::At(START + 1000));
+        clock.advance(1000);
+
+        // the key is still stored until something looks at it
+        assert_eq!(db.key_count(), 1);
+        assert!(db.get(b"key").is_none());
+        assert_eq!(db.key_count(), 0);
+    }
+
+    #[test]
+    fn active_expiry() {
+        let (mut db, clock) = database();
+
+        for i in 0 .. 10 {
+            set_with_expiry(&mut db, format!("key:{i}").as_bytes(), Expiry::At(START + 1000 + i));
+        }
+
+        db.set(b"forever", string("value"), SetOptions::default());
+
+        db.delete_expired_keys(10);
+        assert_eq!(db.key_count(), 11);
+
+        clock.advance(1004);
+
+        // only the keys that have run out are removed, and no more than the
+        // budget at a time
+        db.delete_expired_keys(3);
+        assert_eq!(db.key_count(), 8);
+
+        db.delete_expired_keys(10);
+        assert_eq!(db.key_count(), 6);
+        assert!(db.store.contains_key(b"key:5"));
+
+        clock.advance(1000);
+        db.delete_expired_keys(100);
+        assert_eq!(db.key_count(), 1);
+        assert!(db.exists(b"forever"));
+    }
+
+    #[test]
+    fn changed_ttls_are_skipped_by_active_expiry() {
+        let (mut db, clock) = database();
+
+        set_with_expiry(&mut db, b"key", Expiry::At(START + 1000));
+        assert!(db.expire(b"key", START + 5000, ExpireCondition::default()));
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+        assert!(db.exists(b"key"));
+        assert_eq!(db.expiry_queue.len(), 1);
+        assert_eq!(db.stale_expiries, 0);
+
+        clock.advance(4000);
+        db.delete_expired_keys(10);
+        assert_eq!(db.key_count(), 0);
+    }
+
+    #[test]
+    fn stale_expiries_compact_the_queue() {
+        let (mut db, _) = database();
+        let keys = EXPIRY_QUEUE_COMPACTION_THRESHOLD as u64;
+
+        for i in 0 .. keys {
+            set_with_expiry(&mut db, format!("key:{i}").as_bytes(), Expiry::At(START + 1000 + i));
+        }
+
+        assert_eq!(db.expiry_queue.len(), keys as usize);
+
+        // each new TTL queues another entry and leaves the old one stale,
+        // until more than half of the queue is stale and it's rebuilt
+        let mut longest = 0;
+
+        for round in 1 ..= 3 {
+            for i in 0 .. keys {
+                db.expire(format!("key:{i}").as_bytes(), START + round * 10_000 + i, ExpireCondition::default());
+
+                longest = longest.max(db.expiry_queue.len());
+            }
+        }
+
+        assert!(longest <= 2 * keys as usize + 1);
+        assert!(db.stale_expiries <= db.expiry_queue.len() / 2);
+
+        // removing the TTLs leaves nothing to rebuild the queue from
+        for i in 0 .. keys {
+            db.persist(format!("key:{i}").as_bytes());
+        }
+
+        assert!(db.expiry_queue.len() < keys as usize);
+    }
+
+    #[test]
+    fn expired_fields_are_removed_actively() {
+        let (mut db, clock) = database();
+
+        hash_with_fields(&mut db, b"hash", &[("a", Some(START + 1000)), ("b", Some(START + 2000)), ("c", None)]);
+
+        clock.advance(999);
+        db.delete_expired_keys(10);
+        assert_eq!(stored_fields(&db, b"hash").unwrap().len(), 3);
+        assert_eq!(db.time_until_next_expiration(), Some(Duration::from_millis(1)));
+
+        clock.advance(1);
+        db.delete_expired_keys(10);
+        assert_eq!(stored_fields(&db, b"hash"), Some(vec![b"b".to_vec(), b"c".to_vec()]));
+
+        // the hash was queued again for its next field
+        assert_eq!(db.field_expiry_queue.len(), 1);
+        assert_eq!(db.time_until_next_expiration(), Some(Duration::from_millis(1000)));
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+        assert_eq!(stored_fields(&db, b"hash"), Some(vec![b"c".to_vec()]));
+        assert!(db.field_expiry_queue.is_empty());
+    }
+
+    #[test]
+    fn field_expiry_queue_skips_duplicates() {
+        let (mut db, clock) = database();
+
+        let fields: Vec<(String, Option<u64>)> = (0 .. 100)
+            .map(| i | (format!("field:{i}"), Some(START + 1000 + i)))
+            .collect();
+        let fields: Vec<(&str, Option<u64>)> = fields.iter().map(| (f, e) | (f.as_str(), *e)).collect();
+
+        hash_with_fields(&mut db, b"hash", &fields);
+
+        // later TTLs don't queue the hash again, since it's already queued
+        // for its first field
+        assert_eq!(db.field_expiry_queue.len(), 1);
+
+        db.expire_field(b"hash", b"field:50", START + 500, ExpireCondition::default()).unwrap();
+        assert_eq!(db.field_expiry_queue.len(), 2);
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+
+        // the entry at 500 was handled, and the one at 1000 was skipped as
+        // stale, leaving the hash queued once for its next field
+        assert_eq!(stored_fields(&db, b"hash").unwrap().len(), 98);
+        assert_eq!(db.field_expiry_queue.len(), 1);
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+        assert!(db.field_expiry_queue.is_empty());
+    }
+
+    #[test]
+    fn field_expiries_follow_a_renamed_hash() {
+        let (mut db, clock) = database();
+
+        hash_with_fields(&mut db, b"old", &[("a", Some(START + 1000)), ("b", None)]);
+        assert_eq!(db.rename(b"old", b"new", false), Some(true));
+
+        // a new hash at the old key mustn't be cleaned up on the old schedule
+        hash_with_fields(&mut db, b"old", &[("x", Some(START + 5000))]);
+        assert_eq!(db.field_expiry_queue.len(), 3);
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+
+        assert_eq!(stored_fields(&db, b"new"), Some(vec![b"b".to_vec()]));
+        assert_eq!(stored_fields(&db, b"old"), Some(vec![b"x".to_vec()]));
+        assert_eq!(db.field_expiry_queue.len(), 1);
+
+        clock.advance(4000);
+        db.delete_expired_keys(10);
+        assert!(stored_fields(&db, b"old").is_none());
+    }
+
+    #[test]
+    fn field_expiries_are_copied_with_a_hash() {
+        let (mut db, clock) = database();
+
+        hash_with_fields(&mut db, b"source", &[("a", Some(START + 1000)), ("b", None)]);
+        assert!(db.copy(b"source", b"copy", false));
+
+        // the copy is queued on its own, and both are cleaned up
+        assert_eq!(db.field_expiry_queue.len(), 2);
+
+        clock.advance(1000);
+        db.delete_expired_keys(10);
+
+        assert_eq!(stored_fields(&db, b"source"), Some(vec![b"b".to_vec()]));
+        assert_eq!(stored_fields(&db, b"copy"), Some(vec![b"b".to_vec()]));
+        assert!(db.field_expiry_queue.is_empty());
+    }
+
+    #[test]
+    fn a_hash_is_deleted_with_its_last_field() {
+        let (mut db, clock) = database();
+
+        hash_with_fields(&mut db, b"active", &[("a", Some(START + 1000)), ("b", Some(START + 1000))]);
+        hash_with_fields(&mut db, b"lazy", &[("a", Some(START + 1000))]);
+
+        clock.advance(1000);
+
+        // accessing the hash removes it before the worker gets to it
+        assert!(db.get_hash(b"lazy").unwrap().is_none());
+        assert!(!db.store.contains_key(b"lazy"));
+
+        db.delete_expired_keys(10);
+        assert!(!db.store.contains_key(b"active"));
+        assert_eq!(db.key_count(), 0);
+        assert!(db.field_expiry_queue.is_empty());
+
+        // a TTL that has already passed deletes the field, and the hash, at once
+        hash_with_fields(&mut db, b"hash", &[("a", None)]);
+        assert!(matches!(
+            db.expire_field(b"hash", b"a", START, ExpireCondition::default()),
+            Ok(FieldExpireOutcome::Deleted),
+        ));
+        assert!(!db.exists(b"hash"));
     }
 }
@@ -0,0 +1,577 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::iter::Flatten;
+use std::{mem, slice};
+
+use crate::random::random_index;
+
+/// The number of buckets that a table starts out with, and never shrinks below
+const INITIAL_SIZE: usize = 4;
+/// Tables are shrunk once fewer than this percentage of their buckets would be
+/// in use if every key had its own, which is what Redis does
+const MIN_FILL_PERCENT: usize = 10;
+/// How many empty buckets a rehash step skips over before giving up, so that
+/// a step doesn't take too long when a table has just been shrunk
+const REHASH_EMPTY_VISITS: usize = 10;
+
+type Bucket<V> = Vec<(Vec<u8>, V)>;
+
+/// A hash table of byte string keys that's laid out like Redis's `dict`, so
+/// that it can be iterated with the same cursors as Redis's `SCAN`.
+///
+/// Keys are chained in buckets, of which there's always a power of two. When
+/// the table grows or shrinks, a second table of the new size is allocated,
+/// and the buckets of the first are moved over to it a few at a time on each
+/// write, rather than all at once. In the meantime, lookups check both tables.
+///
+/// `scan` visits the buckets in the order of their indices with the bits
+/// reversed, so that a bucket's keys can only move to buckets with the same
+/// low bits when the table is resized. Because of that, every key that's in
+/// the table for a whole iteration is visited at least once, however many
+/// times the table is resized along the way (though keys can be visited more
+/// than once).
+#[derive(Debug, Clone)]
+pub struct Dict<V> {
+    /// While rehashing, buckets are moved from the first table to the second,
+    /// which is otherwise empty
+    tables: [Vec<Bucket<V>>; 2],
+    len: usize,
+    /// The next bucket of the first table to move, while rehashing. The
+    /// buckets before it are all empty.
+    rehash_index: Option<usize>,
+    hasher: RandomState,
+}
+
+impl<V> Dict<V> {
+    pub fn new() -> Dict<V> {
+        Dict {
+            tables: [Vec::new(), Vec::new()],
+            len: 0,
+            rehash_index: None,
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let (table, bucket, position) = self.find(key)?;
+
+        Some(&self.tables[table][bucket][position].1)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let (table, bucket, position) = self.find(key)?;
+
+        Some(&mut self.tables[table][bucket][position].1)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Returns the value stored at the key, first storing the one returned by
+    /// `default` if the key doesn't exist
+    pub fn get_or_insert_with(&mut self, key: &[u8], default: impl FnOnce() -> V) -> &mut V {
+        self.rehash_step();
+
+        let (table, bucket, position) = match self.find(key) {
+            Some(location) => location,
+            None => self.push(key.to_vec(), default()),
+        };
+
+        &mut self.tables[table][bucket][position].1
+    }
+
+    /// Stores the value at the key, and returns the one it replaced
+    pub fn insert(&mut self, key: Vec<u8>, value: V) -> Option<V> {
+        self.rehash_step();
+
+        if let Some((table, bucket, position)) = self.find(&key) {
+            return Some(mem::replace(&mut self.tables[table][bucket][position].1, value));
+        }
+
+        self.push(key, value);
+
+        None
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        self.rehash_step();
+
+        let (table, bucket, position) = self.find(key)?;
+        let (_, value) = self.tables[table][bucket].swap_remove(position);
+
+        self.len -= 1;
+        self.shrink_if_needed();
+
+        Some(value)
+    }
+
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            buckets: self.tables.iter().flatten().flatten(),
+            remaining: self.len,
+        }
+    }
+
+    /// Calls `f` with the keys in the next bucket (or few buckets, while
+    /// rehashing) after the cursor, and returns the cursor to continue from,
+    /// which is `0` once every bucket has been visited.
+    ///
+    /// While rehashing, the bucket of the smaller table is visited along with
+    /// every bucket of the larger table that its keys could be moved to.
+    pub fn scan(&self, cursor: u64, mut f: impl FnMut(&[u8], &V)) -> u64 {
+        if self.len == 0 {
+            return 0;
+        }
+
+        let mut visit = | bucket: &Bucket<V> | bucket.iter().for_each(| (k, v) | f(k, v));
+        let mut cursor = cursor;
+
+        if self.rehash_index.is_none() {
+            let table = &self.tables[0];
+            let mask = table.len() as u64 - 1;
+
+            visit(&table[(cursor & mask) as usize]);
+
+            return next_cursor(cursor, mask);
+        }
+
+        let (small, large) = match self.tables[0].len() <= self.tables[1].len() {
+            true => (&self.tables[0], &self.tables[1]),
+            false => (&self.tables[1], &self.tables[0]),
+        };
+
+        let small_mask = small.len() as u64 - 1;
+        let large_mask = large.len() as u64 - 1;
+
+        visit(&small[(cursor & small_mask) as usize]);
+
+        // the buckets of the larger table whose low bits are the same as the
+        // smaller one's index
+        loop {
+            visit(&large[(cursor & large_mask) as usize]);
+
+            cursor = next_cursor(cursor, large_mask);
+
+            if cursor & (small_mask ^ large_mask) == 0 {
+                break;
+            }
+        }
+
+        cursor
+    }
+
+    /// Returns a random entry, by picking random buckets until one isn't
+    /// empty, and then a random key from its chain. Keys in shorter chains are
+    /// more likely to be picked, like in Redis.
+    pub fn random_entry(&self) -> Option<(&[u8], &V)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        // the buckets of the first table that have already been rehashed are
+        // empty, so they're skipped
+        let skipped = self.rehash_index.unwrap_or(0);
+        let total = self.tables[0].len() + self.tables[1].len() - skipped;
+
+        loop {
+            let index = skipped + random_index(total);
+
+            let bucket = match index.checked_sub(self.tables[0].len()) {
+                Some(index) => &self.tables[1][index],
+                None => &self.tables[0][index],
+            };
+
+            if !bucket.is_empty() {
+                let (key, value) = &bucket[random_index(bucket.len())];
+
+                return Some((key, value));
+            }
+        }
+    }
+
+    fn hash(&self, key: &[u8]) -> usize {
+        self.hasher.hash_one(key) as usize
+    }
+
+    /// Returns the table, bucket and position in the bucket of the key
+    fn find(&self, key: &[u8]) -> Option<(usize, usize, usize)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let hash = self.hash(key);
+
+        self.tables.iter().enumerate()
+            .filter(| (_, table) | !table.is_empty())
+            .find_map(| (t, table) | {
+                let bucket = hash & (table.len() - 1);
+                let position = table[bucket].iter().position(| (k, _) | k == key)?;
+
+                Some((t, bucket, position))
+            })
+    }
+
+    /// Adds a key that isn't in the table yet, and returns where it was put
+    fn push(&mut self, key: Vec<u8>, value: V) -> (usize, usize, usize) {
+        self.expand_if_needed();
+
+        // while rehashing, new keys go straight into the new table
+        let table = if self.rehash_index.is_some() { 1 } else { 0 };
+        let bucket = self.hash(&key) & (self.tables[table].len() - 1);
+
+        self.tables[table][bucket].push((key, value));
+        self.len += 1;
+
+        (table, bucket, self.tables[table][bucket].len() - 1)
+    }
+
+    /// Starts growing the table once there are as many keys as buckets
+    fn expand_if_needed(&mut self) {
+        if self.rehash_index.is_some() {
+            return;
+        }
+
+        if self.tables[0].is_empty() {
+            self.tables[0] = new_table(INITIAL_SIZE);
+        } else if self.len >= self.tables[0].len() {
+            self.resize((self.len + 1).next_power_of_two());
+        }
+    }
+
+    fn shrink_if_needed(&mut self) {
+        let size = self.tables[0].len();
+
+        if self.rehash_index.is_none() && size > INITIAL_SIZE && self.len * 100 < size * MIN_FILL_PERCENT {
+            self.resize(self.len.max(INITIAL_SIZE).next_power_of_two());
+        }
+    }
+
+    fn resize(&mut self, size: usize) {
+        // there's nothing to move, so the new table can be used straight away
+        if self.len == 0 {
+            self.tables[0] = new_table(size);
+
+            return;
+        }
+
+        self.tables[1] = new_table(size);
+        self.rehash_index = Some(0);
+    }
+
+    /// Moves the next bucket of the first table to the second, and finishes
+    /// rehashing once the first table is empty
+    fn rehash_step(&mut self) {
+        let Some(mut index) = self.rehash_index else {
+            return;
+        };
+
+        let size = self.tables[0].len();
+        let mut empty_visits = REHASH_EMPTY_VISITS;
+
+        while index < size && self.tables[0][index].is_empty() {
+            index += 1;
+            empty_visits -= 1;
+
+            if empty_visits == 0 {
+                self.rehash_index = Some(index);
+
+                return;
+            }
+        }
+
+        if index < size {
+            let mask = self.tables[1].len() - 1;
+
+            for (key, value) in mem::take(&mut self.tables[0][index]) {
+                let bucket = self.hash(&key) & mask;
+
+                self.tables[1][bucket].push((key, value));
+            }
+
+            index += 1;
+        }
+
+        if index < size {
+            self.rehash_index = Some(index);
+        } else {
+            self.tables[0] = mem::take(&mut self.tables[1]);
+            self.rehash_index = None;
+        }
+    }
+}
+
+impl<V> Default for Dict<V> {
+    fn default() -> Dict<V> {
+        Dict::new()
+    }
+}
+
+/// Iterates over the keys and values of a `Dict` in the order of its buckets
+#[derive(Debug, Clone)]
+pub struct Iter<'a, V> {
+    buckets: Flatten<Flatten<slice::Iter<'a, Vec<Bucket<V>>>>>,
+    remaining: usize,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<(&'a [u8], &'a V)> {
+        let (key, value) = self.buckets.next()?;
+
+        self.remaining -= 1;
+
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<V> ExactSizeIterator for Iter<'_, V> {}
+
+fn new_table<V>(size: usize) -> Vec<Bucket<V>> {
+    (0 .. size).map(| _ | Vec::new()).collect()
+}
+
+/// Increments the bits of the cursor that are covered by the mask, starting
+/// from the highest one rather than the lowest. Growing the table adds a bit
+/// to the top of the mask, and shrinking it takes one away, so visiting the
+/// buckets in this order means that the ones that were already visited map
+/// onto ones that will be skipped, and the rest onto ones that won't.
+fn next_cursor(cursor: u64, mask: u64) -> u64 {
+    (cursor | !mask).reverse_bits().wrapping_add(1).reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn key(i: usize) -> Vec<u8> {
+        format!("key:{i}").into_bytes()
+    }
+
+    /// Scans the whole dict, calling `between` after each call to `scan`,
+    /// and returns every key that was visited
+    fn scan_all(dict: &mut Dict<usize>, mut between: impl FnMut(&mut Dict<usize>, usize)) -> HashSet<Vec<u8>> {
+        let mut visited = HashSet::new();
+        let mut cursor = 0;
+        let mut calls = 0;
+
+        loop {
+            cursor = dict.scan(cursor, | key, _ | { visited.insert(key.to_vec()); });
+            calls += 1;
+
+            if cursor == 0 {
+                return visited;
+            }
+
+            between(dict, calls);
+        }
+    }
+
+    #[test]
+    fn insert_get_and_remove() {
+        let mut dict = Dict::new();
+
+        assert_eq!(dict.insert(key(1), 1), None);
+        assert_eq!(dict.insert(key(1), 2), Some(1));
+        assert_eq!(dict.get(&key(1)), Some(&2));
+        assert_eq!(dict.len(), 1);
+
+        *dict.get_or_insert_with(&key(2), || 0) += 5;
+        *dict.get_or_insert_with(&key(2), || 0) += 5;
+        assert_eq!(dict.get(&key(2)), Some(&10));
+
+        assert_eq!(dict.remove(&key(1)), Some(2));
+        assert_eq!(dict.remove(&key(1)), None);
+        assert!(!dict.contains_key(&key(1)));
+        assert_eq!(dict.len(), 1);
+    }
+
+    #[test]
+    fn rehashes_incrementally_while_growing() {
+        let mut dict = Dict::new();
+        let mut saw_rehash = false;
+
+        for i in 0 .. 1000 {
+            dict.insert(key(i), i);
+
+            if dict.rehash_index.is_some() {
+                saw_rehash = true;
+
+                // both tables are in use, and every key is still found
+                assert!(!dict.tables[1].is_empty());
+                assert!((0 ..= i).all(| j | dict.get(&key(j)) == Some(&j)));
+            }
+        }
+
+        assert!(saw_rehash);
+        assert_eq!(dict.len(), 1000);
+        assert_eq!(dict.iter().count(), 1000);
+
+        // lookups don't move buckets, but writes do until rehashing finishes
+        while dict.rehash_index.is_some() {
+            dict.insert(key(0), 0);
+        }
+
+        assert!(dict.tables[1].is_empty());
+        assert!(dict.tables[0].len() >= dict.len());
+        assert!(dict.tables[0].len().is_power_of_two());
+    }
+
+    #[test]
+    fn shrinks_once_mostly_empty() {
+        let mut dict = Dict::new();
+
+        for i in 0 .. 1000 {
+            dict.insert(key(i), i);
+        }
+
+        for i in 0 .. 990 {
+            assert_eq!(dict.remove(&key(i)), Some(i));
+        }
+
+        let finish_rehashing = | dict: &mut Dict<usize> | {
+            while dict.rehash_index.is_some() {
+                dict.insert(key(999), 999);
+            }
+        };
+
+        finish_rehashing(&mut dict);
+
+        assert_eq!(dict.len(), 10);
+        assert!(dict.tables[0].len() < 1024);
+        assert!((990 .. 1000).all(| i | dict.get(&key(i)) == Some(&i)));
+
+        // a table can only be shrunk again once the last resize has finished
+        dict.remove(&key(990));
+        finish_rehashing(&mut dict);
+
+        assert_eq!(dict.tables[0].len(), 16);
+
+        for i in 991 .. 1000 {
+            dict.remove(&key(i));
+        }
+
+        assert_eq!(dict.len(), 0);
+        assert_eq!(dict.iter().count(), 0);
+        assert_eq!(dict.scan(0, | _, _ | panic!("the dict is empty")), 0);
+    }
+
+    #[test]
+    fn scan_visits_every_key() {
+        let mut dict = Dict::new();
+
+        for i in 0 .. 500 {
+            dict.insert(key(i), i);
+        }
+
+        let visited = scan_all(&mut dict, | _, _ | {});
+
+        assert_eq!(visited.len(), 500);
+    }
+
+    #[test]
+    fn scan_visits_stable_keys_while_growing() {
+        let mut dict = Dict::new();
+
+        for i in 0 .. 100 {
+            dict.insert(key(i), i);
+        }
+
+        // keys are added faster than buckets are visited, until there are
+        // enough of them for the table to have grown a few times
+        let visited = scan_all(&mut dict, | dict, calls | {
+            for j in 0 .. 20 {
+                if dict.len() < 2000 {
+                    dict.insert(key(1000 + calls * 20 + j), 0);
+                }
+            }
+        });
+
+        assert!(dict.len() > 1000);
+        assert!((0 .. 100).all(| i | visited.contains(&key(i))));
+    }
+
+    #[test]
+    fn scan_visits_stable_keys_while_shrinking() {
+        let mut dict = Dict::new();
+
+        for i in 0 .. 5000 {
+            dict.insert(key(i), i);
+        }
+
+        // the first 100 keys stay, and the rest are removed a few at a time
+        let mut next = 100;
+        let mut shrank = false;
+
+        let visited = scan_all(&mut dict, | dict, _ | {
+            for _ in 0 .. 50 {
+                if next < 5000 {
+                    dict.remove(&key(next));
+                    next += 1;
+                }
+            }
+
+            shrank |= dict.rehash_index.is_some() && dict.tables[1].len() < dict.tables[0].len();
+        });
+
+        assert!(shrank);
+        assert!((0 .. 100).all(| i | visited.contains(&key(i))));
+    }
+
+    #[test]
+    fn next_cursor_reverses_the_bits() {
+        // with 8 buckets, the order is 0, 4, 2, 6, 1, 5, 3, 7
+        let mut order = vec![0];
+
+        loop {
+            let cursor = next_cursor(*order.last().unwrap(), 7);
+
+            if cursor == 0 {
+                break;
+            }
+
+            order.push(cursor);
+        }
+
+        assert_eq!(order, [0, 4, 2, 6, 1, 5, 3, 7]);
+    }
+
+    #[test]
+    fn random_entry_only_returns_stored_keys() {
+        let mut dict = Dict::new();
+
+        assert!(dict.random_entry().is_none());
+
+        for i in 0 .. 100 {
+            dict.insert(key(i), i);
+        }
+
+        for i in 0 .. 90 {
+            dict.remove(&key(i));
+        }
+
+        let mut seen = HashSet::new();
+
+        for _ in 0 .. 1000 {
+            let (k, v) = dict.random_entry().unwrap();
+
+            assert_eq!(k, key(*v));
+            assert!(*v >= 90);
+
+            seen.insert(*v);
+        }
+
+        assert_eq!(seen.len(), 10);
+    }
+}
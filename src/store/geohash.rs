@@ -0,0 +1,346 @@
+use std::ops::Range;
+
+/// The number of bits that the longitude and the latitude are each encoded
+/// in, which makes for a 52 bit score that a double can hold exactly
+const STEP_MAX: u8 = 26;
+
+pub const LONGITUDE_MIN: f64 = -180.0;
+pub const LONGITUDE_MAX: f64 = 180.0;
+/// The latitudes that the Web Mercator projection is limited to, which (like
+/// in Redis) are the only ones that can be indexed
+pub const LATITUDE_MIN: f64 = -85.05112878;
+pub const LATITUDE_MAX: f64 = 85.05112878;
+
+/// The latitudes that standard geohash strings are encoded with
+const STANDARD_LATITUDES: (f64, f64) = (-90.0, 90.0);
+const MERCATOR_LATITUDES: (f64, f64) = (LATITUDE_MIN, LATITUDE_MAX);
+
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+/// Half of the Earth's circumference in the Web Mercator projection
+const MERCATOR_MAX: f64 = 20037726.37;
+
+/// The bits of a geohash that hold the latitude and the longitude
+const LATITUDE_BITS: u64 = 0x5555_5555_5555_5555;
+const LONGITUDE_BITS: u64 = 0xaaaa_aaaa_aaaa_aaaa;
+
+const BASE32_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+const GEOHASH_LENGTH: usize = 11;
+
+/// A point on the map, in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+/// The area that a search covers around its center, in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+/// A cell of the grid that the map is divided into, whose `bits` hold `step`
+/// bits of the latitude (in the even bits) interleaved with `step` bits of
+/// the longitude (in the odd bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GeoHash {
+    bits: u64,
+    step: u8,
+}
+
+/// The bounds of a rectangle on the map, in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    longitude_min: f64,
+    longitude_max: f64,
+    latitude_min: f64,
+    latitude_max: f64,
+}
+
+impl Coordinates {
+    /// Returns `None` if the coordinates are outside of the range that can be
+    /// indexed
+    pub fn new(longitude: f64, latitude: f64) -> Option<Coordinates> {
+        let is_valid = (LONGITUDE_MIN..=LONGITUDE_MAX).contains(&longitude)
+            && (LATITUDE_MIN..=LATITUDE_MAX).contains(&latitude);
+
+        is_valid.then_some(Coordinates { longitude, latitude })
+    }
+
+    /// Decodes the coordinates from a sorted set score, which gives the center
+    /// of the cell that they were encoded into
+    pub fn from_score(score: f64) -> Coordinates {
+        let area = GeoHash { bits: score as u64, step: STEP_MAX }.area();
+
+        Coordinates {
+            longitude: ((area.longitude_min + area.longitude_max) / 2.0).clamp(LONGITUDE_MIN, LONGITUDE_MAX),
+            latitude: ((area.latitude_min + area.latitude_max) / 2.0).clamp(LATITUDE_MIN, LATITUDE_MAX),
+        }
+    }
+
+    /// Encodes the coordinates as a 52 bit geohash, which is stored as their
+    /// score in a sorted set so that nearby points have similar scores
+    pub fn score(&self) -> f64 {
+        GeoHash::encode(*self, MERCATOR_LATITUDES, STEP_MAX).bits as f64
+    }
+
+    /// Encodes the coordinates as an 11 character geohash string. Since it
+    /// uses the standard range of latitudes, rather than the one that scores
+    /// are encoded with, it can be used with other geohash libraries.
+    pub fn geohash(&self) -> String {
+        let bits = GeoHash::encode(*self, STANDARD_LATITUDES, STEP_MAX).bits;
+
+        (0..GEOHASH_LENGTH)
+            .map(| i | match i {
+                // there are only 52 bits, so the last character is padding
+                10 => BASE32_ALPHABET[0],
+                _ => BASE32_ALPHABET[((bits >> (52 - (i + 1) * 5)) & 0x1f) as usize],
+            })
+            .map(char::from)
+            .collect()
+    }
+
+    /// Returns the distance to the other point in meters, using the haversine
+    /// formula
+    pub fn distance_to(&self, other: Coordinates) -> f64 {
+        let v = ((other.longitude.to_radians() - self.longitude.to_radians()) / 2.0).sin();
+
+        // the points are on the same meridian, so the rest can be skipped
+        if v == 0.0 {
+            return latitude_distance(self.latitude, other.latitude);
+        }
+
+        let latitude1 = self.latitude.to_radians();
+        let latitude2 = other.latitude.to_radians();
+        let u = ((latitude2 - latitude1) / 2.0).sin();
+        let a = u * u + latitude1.cos() * latitude2.cos() * v * v;
+
+        2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+    }
+}
+
+impl GeoShape {
+    /// Returns the distance from the center to the point if it's within the
+    /// shape
+    pub fn distance_within(&self, center: Coordinates, point: Coordinates) -> Option<f64> {
+        if let GeoShape::Box { width, height } = *self {
+            // the latitude distance is cheaper to compute, so it's checked first
+            if latitude_distance(point.latitude, center.latitude) > height / 2.0 {
+                return None;
+            }
+
+            let parallel = Coordinates { longitude: center.longitude, latitude: point.latitude };
+
+            if point.distance_to(parallel) > width / 2.0 {
+                return None;
+            }
+        }
+
+        let distance = center.distance_to(point);
+
+        match *self {
+            GeoShape::Radius(radius) if distance > radius => None,
+            _ => Some(distance),
+        }
+    }
+
+    /// Returns the ranges of scores that cover the shape around the center,
+    /// the same way that Redis does: the cell that the center is in and its
+    /// eight neighbors, at a step where they're at least as big as the shape,
+    /// minus the neighbors that the shape doesn't reach. Every point within
+    /// the shape has a score in one of the ranges, but not every point in the
+    /// ranges is within the shape.
+    pub fn score_ranges(&self, center: Coordinates) -> Vec<Range<u64>> {
+        let bounds = self.bounding_box(center);
+
+        let mut step = estimate_step(self.reach(), center.latitude);
+        let mut hash = GeoHash::encode(center, MERCATOR_LATITUDES, step);
+
+        // when the center is near the edge of its cell, the neighbors at the
+        // estimated step may not reach the edge of the shape
+        let is_too_fine = hash.neighbor(0, 1).area().latitude_max < bounds.latitude_max
+            || hash.neighbor(0, -1).area().latitude_min > bounds.latitude_min
+            || hash.neighbor(1, 0).area().longitude_max < bounds.longitude_max
+            || hash.neighbor(-1, 0).area().longitude_min > bounds.longitude_min;
+
+        if step > 1 && is_too_fine {
+            step -= 1;
+            hash = GeoHash::encode(center, MERCATOR_LATITUDES, step);
+        }
+
+        // the neighbors on any side where the center's cell already reaches
+        // past the shape aren't needed, unless the cells are so big that
+        // there are only four of them
+        let area = hash.area();
+        let is_coarse = step < 2;
+
+        let north = is_coarse || area.latitude_max <= bounds.latitude_max;
+        let south = is_coarse || area.latitude_min >= bounds.latitude_min;
+        let east = is_coarse || area.longitude_max <= bounds.longitude_max;
+        let west = is_coarse || area.longitude_min >= bounds.longitude_min;
+
+        let mut cells = [(0, 0), (0, 1), (0, -1), (1, 0), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)]
+            .into_iter()
+            .filter(| &(x, y) | (x <= 0 || east) && (x >= 0 || west) && (y <= 0 || north) && (y >= 0 || south))
+            .map(| (x, y) | hash.neighbor(x, y))
+            .collect::<Vec<_>>();
+
+        // with a large enough shape, the neighbors wrap around the map and
+        // can be the same cell
+        cells.dedup();
+
+        cells.iter().map(GeoHash::scores).collect()
+    }
+
+    /// The distance from the center to the furthest point of the shape
+    fn reach(&self) -> f64 {
+        match *self {
+            GeoShape::Radius(radius) => radius,
+            GeoShape::Box { width, height } => ((width / 2.0).powi(2) + (height / 2.0).powi(2)).sqrt(),
+        }
+    }
+
+    fn bounding_box(&self, center: Coordinates) -> Area {
+        let (width, height) = match *self {
+            GeoShape::Radius(radius) => (radius, radius),
+            GeoShape::Box { width, height } => (width / 2.0, height / 2.0),
+        };
+
+        let latitude_delta = (height / EARTH_RADIUS_IN_METERS).to_degrees();
+
+        // the parallels get shorter towards the poles, so the widest edge of
+        // the box is the one nearest to the equator
+        let edge_latitude = match center.latitude < 0.0 {
+            true => center.latitude - latitude_delta,
+            false => center.latitude + latitude_delta,
+        };
+
+        let longitude_delta = (width / EARTH_RADIUS_IN_METERS / edge_latitude.to_radians().cos()).to_degrees();
+
+        Area {
+            longitude_min: center.longitude - longitude_delta,
+            longitude_max: center.longitude + longitude_delta,
+            latitude_min: center.latitude - latitude_delta,
+            latitude_max: center.latitude + latitude_delta,
+        }
+    }
+}
+
+impl GeoHash {
+    fn encode(coordinates: Coordinates, (latitude_min, latitude_max): (f64, f64), step: u8) -> GeoHash {
+        let scale = (1u64 << step) as f64;
+
+        let latitude = (coordinates.latitude - latitude_min) / (latitude_max - latitude_min) * scale;
+        let longitude = (coordinates.longitude - LONGITUDE_MIN) / (LONGITUDE_MAX - LONGITUDE_MIN) * scale;
+
+        GeoHash { bits: spread(latitude as u32) | (spread(longitude as u32) << 1), step }
+    }
+
+    /// Decodes the bounds of the cell, assuming that it was encoded with the
+    /// Web Mercator latitudes
+    fn area(&self) -> Area {
+        let latitude = squash(self.bits) as f64;
+        let longitude = squash(self.bits >> 1) as f64;
+
+        let scale = (1u64 << self.step) as f64;
+        let latitude_scale = LATITUDE_MAX - LATITUDE_MIN;
+        let longitude_scale = LONGITUDE_MAX - LONGITUDE_MIN;
+
+        Area {
+            longitude_min: LONGITUDE_MIN + (longitude / scale) * longitude_scale,
+            longitude_max: LONGITUDE_MIN + ((longitude + 1.0) / scale) * longitude_scale,
+            latitude_min: LATITUDE_MIN + (latitude / scale) * latitude_scale,
+            latitude_max: LATITUDE_MIN + ((latitude + 1.0) / scale) * latitude_scale,
+        }
+    }
+
+    /// Returns the cell that's `x` cells east and `y` cells north of this
+    /// one (where each is -1, 0 or 1), wrapping around the edges of the map
+    fn neighbor(&self, x: i8, y: i8) -> GeoHash {
+        let unused = 64 - u32::from(self.step) * 2;
+
+        let move_bits = | bits: u64, mask: u64, d: i8 | {
+            let gaps = !mask >> unused;
+
+            let moved = match d {
+                1 => bits.wrapping_add(gaps + 1),
+                -1 => (bits | gaps).wrapping_sub(gaps + 1),
+                _ => bits,
+            };
+
+            moved & (mask >> unused)
+        };
+
+        let longitude = move_bits(self.bits & LONGITUDE_BITS, LONGITUDE_BITS, x);
+        let latitude = move_bits(self.bits & LATITUDE_BITS, LATITUDE_BITS, y);
+
+        GeoHash { bits: longitude | latitude, step: self.step }
+    }
+
+    /// The range of the scores of the points within the cell
+    fn scores(&self) -> Range<u64> {
+        let shift = u32::from(STEP_MAX - self.step) * 2;
+
+        (self.bits << shift)..((self.bits + 1) << shift)
+    }
+}
+
+/// Picks the step for cells that are at least as big as the distance, which
+/// is where it's the smallest number of cells that cover a circle with that
+/// radius
+fn estimate_step(distance: f64, latitude: f64) -> u8 {
+    if distance == 0.0 {
+        return STEP_MAX;
+    }
+
+    let mut step: i32 = 1;
+    let mut range = distance;
+
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+
+    // make sure that the distance is covered in most cases
+    step -= 2;
+
+    // the cells are narrower towards the poles
+    if latitude.abs() > 66.0 {
+        step -= 1;
+
+        if latitude.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+
+    step.clamp(1, i32::from(STEP_MAX)) as u8
+}
+
+fn latitude_distance(latitude1: f64, latitude2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (latitude2.to_radians() - latitude1.to_radians()).abs()
+}
+
+/// Spreads the bits of the value out into the even bits of the result
+fn spread(value: u32) -> u64 {
+    let mut bits = u64::from(value);
+
+    bits = (bits | (bits << 16)) & 0x0000_ffff_0000_ffff;
+    bits = (bits | (bits << 8)) & 0x00ff_00ff_00ff_00ff;
+    bits = (bits | (bits << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    bits = (bits | (bits << 2)) & 0x3333_3333_3333_3333;
+
+    (bits | (bits << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Gathers the even bits of the value back together, undoing `spread`
+fn squash(value: u64) -> u32 {
+    let mut bits = value & 0x5555_5555_5555_5555;
+
+    bits = (bits | (bits >> 1)) & 0x3333_3333_3333_3333;
+    bits = (bits | (bits >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    bits = (bits | (bits >> 4)) & 0x00ff_00ff_00ff_00ff;
+    bits = (bits | (bits >> 8)) & 0x0000_ffff_0000_ffff;
+
+    ((bits | (bits >> 16)) & 0x0000_0000_ffff_ffff) as u32
+}
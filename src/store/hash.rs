@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use crate::store::dict::Dict;
+
+/// A map of fields to values, any of which can be given its own TTL (the
+/// field-level expiration that Redis added in 7.4). The fields are kept in a
+/// `Dict`, so that `HSCAN` can use the same cursors as `SCAN`.
+///
+/// Fields that have run out are removed by the `Database` that owns the hash,
+/// either when the hash is next accessed or when the worker gets around to
+/// them, so they never show up in replies.
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: Dict<Vec<u8>>,
+    /// The Unix times (in milliseconds) that fields with a TTL expire at
+    expiries: HashMap<Vec<u8>, u64>,
+    /// The earliest time in `expiries`, which makes checking whether any field
+    /// has run out cheap
+    next_expiry: Option<u64>,
+    /// The time that the hash is queued at in the database's field expiry
+    /// queue, if it's queued at all. The hash is only queued again when one of
+    /// its fields is given an earlier TTL, so that it isn't queued many times
+    /// over.
+    queued_expiry: Option<u64>,
+}
+
+impl Hash {
+    pub fn new() -> Hash {
+        Hash::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.len() == 0
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Vec<u8>> {
+        self.fields.get(field)
+    }
+
+    pub fn contains_key(&self, field: &[u8]) -> bool {
+        self.fields.contains_key(field)
+    }
+
+    /// Sets the field's value, removing any TTL it had, and returns the value
+    /// it had before
+    pub fn insert(&mut self, field: Vec<u8>, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.remove_expiry(&field);
+
+        self.fields.insert(field, value)
+    }
+
+    /// Sets the value of a field that may already exist without touching its
+    /// TTL, e.g. for `HINCRBY`
+    pub fn update(&mut self, field: Vec<u8>, value: Vec<u8>) {
+        self.fields.insert(field, value);
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> Option<Vec<u8>> {
+        self.remove_expiry(field);
+
+        self.fields.remove(field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &Vec<u8>)> {
+        self.fields.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.fields.iter().map(| (field, _) | field)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.fields.iter().map(| (_, value) | value)
+    }
+
+    /// Returns a random field and its value, or `None` if the hash is empty
+    pub fn random_entry(&self) -> Option<(&[u8], &Vec<u8>)> {
+        self.fields.random_entry()
+    }
+
+    /// Calls `f` with the fields and values in the next bucket after the
+    /// cursor, and returns the cursor to continue from (see `Dict::scan`)
+    pub fn scan(&self, cursor: u64, f: impl FnMut(&[u8], &Vec<u8>)) -> u64 {
+        self.fields.scan(cursor, f)
+    }
+
+    /// Returns `None` if the field doesn't exist, or otherwise the Unix time
+    /// (in milliseconds) that it expires at, if it has a TTL
+    pub fn get_expiry(&self, field: &[u8]) -> Option<Option<u64>> {
+        self.fields.contains_key(field).then(|| self.expiries.get(field).copied())
+    }
+
+    /// Sets the Unix time (in milliseconds) that the field expires at. Returns
+    /// false if the field doesn't exist.
+    pub fn set_expiry(&mut self, field: &[u8], when: u64) -> bool {
+        if !self.fields.contains_key(field) {
+            return false;
+        }
+
+        self.remove_expiry(field);
+        self.expiries.insert(field.to_vec(), when);
+
+        self.next_expiry = Some(self.next_expiry.map_or(when, | next | next.min(when)));
+
+        true
+    }
+
+    /// Removes the field's TTL. Returns `None` if the field doesn't exist, or
+    /// otherwise whether it had a TTL.
+    pub fn persist(&mut self, field: &[u8]) -> Option<bool> {
+        if !self.fields.contains_key(field) {
+            return None;
+        }
+
+        Some(self.remove_expiry(field))
+    }
+
+    /// Removes the fields whose TTLs have run out by `now`, and returns how
+    /// many were removed
+    pub fn remove_expired(&mut self, now: u64) -> usize {
+        if self.next_expiry.is_none_or(| next | next > now) {
+            return 0;
+        }
+
+        let expired: Vec<Vec<u8>> = self.expiries.iter()
+            .filter(| (_, when) | **when <= now)
+            .map(| (field, _) | field.clone())
+            .collect();
+
+        for field in &expired {
+            self.expiries.remove(field);
+            self.fields.remove(field);
+        }
+
+        self.next_expiry = self.expiries.values().min().copied();
+
+        expired.len()
+    }
+
+    /// Returns the time that the hash needs to be queued at so that its next
+    /// field is removed on time, unless it's already queued at or before then,
+    /// and records that it has been
+    pub fn next_expiry_to_queue(&mut self) -> Option<u64> {
+        let next = self.next_expiry?;
+
+        if self.queued_expiry.is_some_and(| queued | queued <= next) {
+            return None;
+        }
+
+        self.queued_expiry = Some(next);
+
+        Some(next)
+    }
+
+    /// Returns whether the hash was queued at `when`, and if it was, records
+    /// that it no longer is. Anything else that was queued for the hash is
+    /// stale, e.g. because the hash was copied from another key.
+    pub fn take_queued_expiry(&mut self, when: u64) -> bool {
+        if self.queued_expiry != Some(when) {
+            return false;
+        }
+
+        self.queued_expiry = None;
+
+        true
+    }
+
+    /// Forgets where the hash is queued, for when it's moved to another key
+    pub fn clear_queued_expiry(&mut self) {
+        self.queued_expiry = None;
+    }
+
+    /// Removes the field's TTL, and returns whether it had one
+    fn remove_expiry(&mut self, field: &[u8]) -> bool {
+        let Some(when) = self.expiries.remove(field) else {
+            return false;
+        };
+
+        if self.next_expiry == Some(when) {
+            self.next_expiry = self.expiries.values().min().copied();
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(fields: &[(&str, Option<u64>)]) -> Hash {
+        let mut hash = Hash::new();
+
+        for (field, expiry) in fields {
+            hash.insert(field.as_bytes().to_vec(), b"value".to_vec());
+
+            if let Some(when) = expiry {
+                hash.set_expiry(field.as_bytes(), *when);
+            }
+        }
+
+        hash
+    }
+
+    #[test]
+    fn removes_fields_once_their_ttls_run_out() {
+        let mut hash = hash(&[("a", Some(1000)), ("b", Some(2000)), ("c", None)]);
+
+        assert_eq!(hash.remove_expired(999), 0);
+        assert_eq!(hash.len(), 3);
+
+        assert_eq!(hash.remove_expired(1000), 1);
+        assert!(!hash.contains_key(b"a"));
+        assert_eq!(hash.get_expiry(b"b"), Some(Some(2000)));
+
+        assert_eq!(hash.remove_expired(5000), 1);
+        assert_eq!(hash.len(), 1);
+        assert_eq!(hash.get_expiry(b"c"), Some(None));
+        assert_eq!(hash.remove_expired(u64::MAX), 0);
+    }
+
+    #[test]
+    fn setting_or_removing_a_field_clears_its_ttl() {
+        let mut hash = hash(&[("a", Some(1000)), ("b", Some(2000))]);
+
+        hash.insert(b"a".to_vec(), b"new".to_vec());
+        assert_eq!(hash.get_expiry(b"a"), Some(None));
+
+        hash.update(b"b".to_vec(), b"new".to_vec());
+        assert_eq!(hash.get_expiry(b"b"), Some(Some(2000)));
+        assert_eq!(hash.persist(b"b"), Some(true));
+        assert_eq!(hash.persist(b"b"), Some(false));
+        assert_eq!(hash.persist(b"missing"), None);
+
+        assert_eq!(hash.remove_expired(u64::MAX), 0);
+        assert_eq!(hash.len(), 2);
+    }
+
+    #[test]
+    fn is_only_queued_again_for_an_earlier_expiry() {
+        let mut hash = hash(&[("a", Some(2000))]);
+
+        assert_eq!(hash.next_expiry_to_queue(), Some(2000));
+        assert_eq!(hash.next_expiry_to_queue(), None);
+
+        // a later TTL is picked up when the hash is dequeued at 2000
+        hash.insert(b"b".to_vec(), b"value".to_vec());
+        assert!(hash.set_expiry(b"b", 3000));
+        assert_eq!(hash.next_expiry_to_queue(), None);
+
+        hash.set_expiry(b"a", 1000);
+        assert_eq!(hash.next_expiry_to_queue(), Some(1000));
+        assert_eq!(hash.next_expiry_to_queue(), None);
+    }
+
+    #[test]
+    fn only_the_latest_queued_expiry_is_taken() {
+        let mut hash = hash(&[("a", Some(2000))]);
+
+        hash.next_expiry_to_queue();
+        hash.set_expiry(b"a", 1000);
+        hash.next_expiry_to_queue();
+
+        // the entry queued at 2000 was superseded by the one at 1000
+        assert!(!hash.take_queued_expiry(2000));
+        assert!(hash.take_queued_expiry(1000));
+        assert!(!hash.take_queued_expiry(1000));
+
+        hash.next_expiry_to_queue();
+        hash.clear_queued_expiry();
+        assert!(!hash.take_queued_expiry(1000));
+        assert_eq!(hash.next_expiry_to_queue(), Some(1000));
+    }
+}
@@ -0,0 +1,676 @@
+/// The number of bits of an element's hash that pick its register
+const P: u32 = 14;
+/// The number of bits of the hash left over to count leading zeros in
+const Q: u32 = 64 - P;
+const REGISTERS: usize = 1 << P;
+const REGISTER_BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+
+const MAGIC: &[u8] = b"HYLL";
+const HEADER_SIZE: usize = 16;
+const ENCODING_OFFSET: usize = 4;
+const CACHE_OFFSET: usize = 8;
+const DENSE_SIZE: usize = HEADER_SIZE + (REGISTERS * REGISTER_BITS).div_ceil(8);
+
+/// The top bit of the cached cardinality, which is set when it's stale
+const CACHE_STALE: u64 = 1 << 63;
+
+/// Sparse representations that would be longer than this are converted to
+/// the dense one, which is what Redis's `hll-sparse-max-bytes` defaults to
+const SPARSE_MAX_BYTES: usize = 3000;
+const SPARSE_VAL_MAX_VALUE: u8 = 32;
+const SPARSE_VAL_MAX_LEN: usize = 4;
+const SPARSE_ZERO_MAX_LEN: usize = 64;
+const SPARSE_XZERO_MAX_LEN: usize = 16384;
+
+const HASH_SEED: u64 = 0xadc83b19;
+
+/// The constant `0.5 / ln(2)`, which the estimate is scaled by
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HyperLogLogEncoding {
+    /// Runs of registers, which is compact while most of them are zero
+    Sparse = 1,
+    /// Every register packed into 6 bits
+    Dense = 0,
+}
+
+impl HyperLogLogEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HyperLogLogEncoding::Sparse => "sparse",
+            HyperLogLogEncoding::Dense => "dense",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HyperLogLogError {
+    /// The string doesn't start with a valid HyperLogLog header
+    NotAHyperLogLog,
+    /// The header is valid, but the sparse representation doesn't describe
+    /// every register
+    Corrupt,
+}
+
+/// One of the opcodes that make up the sparse representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseOpcode {
+    /// `00xxxxxx`: a run of up to 64 registers that are zero
+    Zero(usize),
+    /// `01xxxxxx yyyyyyyy`: a run of up to 16384 registers that are zero
+    XZero(usize),
+    /// `1vvvvvxx`: a run of up to 4 registers that hold the same value, from
+    /// 1 to 32
+    Val(u8, usize),
+}
+
+impl SparseOpcode {
+    fn len(&self) -> usize {
+        match *self {
+            SparseOpcode::Zero(len) | SparseOpcode::XZero(len) | SparseOpcode::Val(_, len) => len,
+        }
+    }
+}
+
+/// A HyperLogLog, stored in the same layout that Redis uses for its string
+/// values so that they can be copied between servers with `GET` and `SET`:
+///
+/// ```text
+/// "HYLL" <encoding> <unused:3> <cached cardinality:8> <registers>
+/// ```
+///
+/// There are 16384 registers, each of which holds the longest run of
+/// trailing zeros (plus one) seen in the hashes of the elements that were
+/// mapped to it. They're either stored densely, as 6 bits apiece, or as a
+/// sparse run-length encoding while they're mostly zero.
+///
+/// The cardinality is cached in the header (as a little endian integer)
+/// after it's counted, until the registers change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    bytes: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// The number of registers in every HyperLogLog
+    pub const REGISTERS: usize = REGISTERS;
+
+    /// Creates an empty HyperLogLog, using the sparse representation
+    pub fn new() -> HyperLogLog {
+        let mut bytes = header(HyperLogLogEncoding::Sparse);
+        bytes.extend(encode_sparse(&[0; REGISTERS]).unwrap_or_default());
+
+        HyperLogLog { bytes }
+    }
+
+    /// Checks that the bytes start with a valid header. The registers are
+    /// only checked once they're read.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<HyperLogLog, HyperLogLogError> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(MAGIC) {
+            return Err(HyperLogLogError::NotAHyperLogLog);
+        }
+
+        match bytes[ENCODING_OFFSET] {
+            0 if bytes.len() == DENSE_SIZE => Ok(HyperLogLog { bytes }),
+            1 => Ok(HyperLogLog { bytes }),
+            _ => Err(HyperLogLogError::NotAHyperLogLog),
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn encoding(&self) -> HyperLogLogEncoding {
+        match self.bytes[ENCODING_OFFSET] {
+            0 => HyperLogLogEncoding::Dense,
+            _ => HyperLogLogEncoding::Sparse,
+        }
+    }
+
+    /// Returns the value of every register
+    pub fn registers(&self) -> Result<Vec<u8>, HyperLogLogError> {
+        let body = &self.bytes[HEADER_SIZE ..];
+
+        match self.encoding() {
+            HyperLogLogEncoding::Dense => Ok((0 .. REGISTERS).map(| i | get_dense_register(body, i)).collect()),
+            HyperLogLogEncoding::Sparse => decode_sparse(body),
+        }
+    }
+
+    /// Returns the opcodes of the sparse representation, or `None` if the
+    /// registers are stored densely
+    pub fn sparse_opcodes(&self) -> Option<Result<Vec<SparseOpcode>, HyperLogLogError>> {
+        match self.encoding() {
+            HyperLogLogEncoding::Dense => None,
+            HyperLogLogEncoding::Sparse => Some(read_sparse_opcodes(&self.bytes[HEADER_SIZE ..])),
+        }
+    }
+
+    /// Adds the elements, and returns whether any of the registers changed
+    /// (in which case the cached cardinality is stale)
+    pub fn add<'a>(&mut self, elements: impl IntoIterator<Item = &'a [u8]>) -> Result<bool, HyperLogLogError> {
+        let mut registers = self.registers()?;
+        let mut changed = false;
+
+        for element in elements {
+            let (index, count) = get_pattern(element);
+
+            if registers[index] < count {
+                registers[index] = count;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.set_registers(&registers, self.encoding());
+        }
+
+        Ok(changed)
+    }
+
+    /// Raises each register to the value of the corresponding one in `max`,
+    /// if it's lower
+    pub fn merge_into(&self, max: &mut [u8]) -> Result<(), HyperLogLogError> {
+        for (max, register) in max.iter_mut().zip(self.registers()?) {
+            *max = (*max).max(register);
+        }
+
+        Ok(())
+    }
+
+    /// Overwrites the registers, storing them densely if `encoding` says to
+    /// (or if they've been stored densely already), and marks the cached
+    /// cardinality as stale
+    pub fn set_registers(&mut self, registers: &[u8], encoding: HyperLogLogEncoding) {
+        let sparse = match (encoding, self.encoding()) {
+            (HyperLogLogEncoding::Sparse, HyperLogLogEncoding::Sparse) => encode_sparse(registers),
+            _ => None,
+        };
+
+        self.bytes.truncate(HEADER_SIZE);
+
+        match sparse {
+            Some(body) => self.bytes.extend(body),
+            None => {
+                self.bytes[ENCODING_OFFSET] = HyperLogLogEncoding::Dense as u8;
+                self.bytes.extend(encode_dense(registers));
+            }
+        }
+
+        self.bytes[HEADER_SIZE - 1] |= (CACHE_STALE >> 56) as u8;
+    }
+
+    /// Converts the registers to the dense representation, and returns
+    /// whether they weren't already stored that way. The cached cardinality
+    /// is kept, since the registers don't change.
+    pub fn convert_to_dense(&mut self) -> Result<bool, HyperLogLogError> {
+        if self.encoding() == HyperLogLogEncoding::Dense {
+            return Ok(false);
+        }
+
+        let registers = self.registers()?;
+
+        self.bytes.truncate(HEADER_SIZE);
+        self.bytes[ENCODING_OFFSET] = HyperLogLogEncoding::Dense as u8;
+        self.bytes.extend(encode_dense(&registers));
+
+        Ok(true)
+    }
+
+    /// Returns whether the cardinality has been cached since the registers
+    /// last changed
+    pub fn is_count_cached(&self) -> bool {
+        self.cached_count() & CACHE_STALE == 0
+    }
+
+    fn cached_count(&self) -> u64 {
+        u64::from_le_bytes(self.bytes[CACHE_OFFSET .. HEADER_SIZE].try_into().unwrap_or_default())
+    }
+
+    /// Returns the estimated cardinality, from the header if it's been cached
+    /// since the registers last changed, or otherwise by counting it and then
+    /// caching it
+    pub fn count(&mut self) -> Result<u64, HyperLogLogError> {
+        if self.is_count_cached() {
+            return Ok(self.cached_count());
+        }
+
+        let count = HyperLogLog::estimate(&self.registers()?);
+
+        self.bytes[CACHE_OFFSET .. HEADER_SIZE].copy_from_slice(&count.to_le_bytes());
+
+        Ok(count)
+    }
+
+    /// Estimates the cardinality of a set of registers with the estimator from
+    /// Otmar Ertl's "New cardinality estimation algorithms for HyperLogLog
+    /// sketches", which is what Redis uses
+    pub fn estimate(registers: &[u8]) -> u64 {
+        let mut histogram = [0u32; Q as usize + 2];
+
+        for &register in registers {
+            histogram[register as usize] += 1;
+        }
+
+        let m = REGISTERS as f64;
+        let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+
+        for &count in histogram[1 ..= Q as usize].iter().rev() {
+            z += count as f64;
+            z *= 0.5;
+        }
+
+        z += m * sigma(histogram[0] as f64 / m);
+
+        // when every register is zero, `z` is infinite, so the estimate is 0
+        (ALPHA_INF * m * m / z).round() as u64
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog::new()
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut y = 1.0;
+    let mut z = x;
+
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+fn header(encoding: HyperLogLogEncoding) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(DENSE_SIZE);
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&[encoding as u8, 0, 0, 0]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    bytes
+}
+
+/// Hashes the element, and returns the index of the register it maps to,
+/// along with the position of the first set bit in the rest of the hash
+/// (counting from 1)
+fn get_pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash_64a(element, HASH_SEED);
+    let index = hash as usize & (REGISTERS - 1);
+
+    // the extra bit makes sure that the count is at most `Q + 1`
+    let count = ((hash >> P) | (1 << Q)).trailing_zeros() + 1;
+
+    (index, count as u8)
+}
+
+/// The 64-bit version of MurmurHash2, reading the input as little endian so
+/// that every platform maps elements to the same registers as Redis does
+fn murmur_hash_64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let rest = chunks.remainder();
+
+    if !rest.is_empty() {
+        for (i, &byte) in rest.iter().enumerate() {
+            h ^= (byte as u64) << (8 * i);
+        }
+
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+
+    h
+}
+
+/// Reads a register from the dense representation, where registers are
+/// packed from the least significant bit of each byte
+fn get_dense_register(body: &[u8], index: usize) -> u8 {
+    let byte = index * REGISTER_BITS / 8;
+    let shift = index * REGISTER_BITS % 8;
+
+    let low = body[byte] as u16;
+    let high = body.get(byte + 1).copied().unwrap_or(0) as u16;
+
+    (((low | (high << 8)) >> shift) as u8) & REGISTER_MAX
+}
+
+fn encode_dense(registers: &[u8]) -> Vec<u8> {
+    let mut body = vec![0; DENSE_SIZE - HEADER_SIZE];
+
+    for (index, &value) in registers.iter().enumerate() {
+        let byte = index * REGISTER_BITS / 8;
+        let shift = index * REGISTER_BITS % 8;
+        let value = (value.min(REGISTER_MAX) as u16) << shift;
+
+        body[byte] |= value as u8;
+
+        if let Some(next) = body.get_mut(byte + 1) {
+            *next |= (value >> 8) as u8;
+        }
+    }
+
+    body
+}
+
+fn read_sparse_opcodes(body: &[u8]) -> Result<Vec<SparseOpcode>, HyperLogLogError> {
+    let mut opcodes = Vec::new();
+    let mut i = 0;
+
+    while i < body.len() {
+        let byte = body[i];
+
+        let opcode = match byte >> 6 {
+            0b00 => SparseOpcode::Zero((byte & 0x3f) as usize + 1),
+            0b01 => {
+                let next = *body.get(i + 1).ok_or(HyperLogLogError::Corrupt)?;
+                i += 1;
+
+                SparseOpcode::XZero(((((byte & 0x3f) as usize) << 8) | next as usize) + 1)
+            }
+            _ => SparseOpcode::Val(((byte >> 2) & 0x1f) + 1, (byte & 0x03) as usize + 1),
+        };
+
+        opcodes.push(opcode);
+        i += 1;
+    }
+
+    Ok(opcodes)
+}
+
+fn decode_sparse(body: &[u8]) -> Result<Vec<u8>, HyperLogLogError> {
+    let mut registers = Vec::with_capacity(REGISTERS);
+
+    for opcode in read_sparse_opcodes(body)? {
+        if registers.len() + opcode.len() > REGISTERS {
+            return Err(HyperLogLogError::Corrupt);
+        }
+
+        let value = match opcode {
+            SparseOpcode::Val(value, _) => value,
+            _ => 0,
+        };
+
+        registers.resize(registers.len() + opcode.len(), value);
+    }
+
+    if registers.len() != REGISTERS {
+        return Err(HyperLogLogError::Corrupt);
+    }
+
+    Ok(registers)
+}
+
+/// Encodes the registers as runs, or returns `None` if they can't be stored
+/// sparsely, either because one of them is too large or because the runs
+/// would take up too much space
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut i = 0;
+
+    while i < registers.len() {
+        let value = registers[i];
+        let run = registers[i ..].iter().take_while(| &&r | r == value).count();
+
+        if value > SPARSE_VAL_MAX_VALUE {
+            return None;
+        }
+
+        let mut remaining = run;
+
+        while remaining > 0 {
+            if value != 0 {
+                let len = remaining.min(SPARSE_VAL_MAX_LEN);
+                body.push(0x80 | ((value - 1) << 2) | (len - 1) as u8);
+                remaining -= len;
+            } else if remaining > SPARSE_ZERO_MAX_LEN {
+                let len = remaining.min(SPARSE_XZERO_MAX_LEN) - 1;
+                body.extend_from_slice(&[0x40 | (len >> 8) as u8, len as u8]);
+                remaining -= len + 1;
+            } else {
+                body.push((remaining - 1) as u8);
+                remaining = 0;
+            }
+        }
+
+        if body.len() > SPARSE_MAX_BYTES {
+            return None;
+        }
+
+        i += run;
+    }
+
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(range: std::ops::Range<usize>) -> Vec<Vec<u8>> {
+        range.map(| i | format!("element:{i}").into_bytes()).collect()
+    }
+
+    fn from_elements(elements: &[Vec<u8>]) -> HyperLogLog {
+        let mut hll = HyperLogLog::new();
+
+        hll.add(elements.iter().map(| e | e.as_slice())).unwrap();
+        hll
+    }
+
+    fn assert_close(count: u64, expected: usize) {
+        let error = (count as f64 - expected as f64).abs() / expected as f64;
+
+        assert!(error < 0.03, "estimated {count} for {expected} elements");
+    }
+
+    #[test]
+    fn starts_out_sparse_and_empty() {
+        let mut hll = HyperLogLog::new();
+
+        assert_eq!(hll.encoding(), HyperLogLogEncoding::Sparse);
+        assert_eq!(hll.sparse_opcodes(), Some(Ok(vec![SparseOpcode::XZero(REGISTERS)])));
+        assert_eq!(hll.clone().into_bytes().len(), HEADER_SIZE + 2);
+        assert_eq!(hll.count(), Ok(0));
+    }
+
+    #[test]
+    fn sparse_runs_round_trip() {
+        let mut registers = vec![0; REGISTERS];
+
+        registers[0] = 1;
+        registers[1 .. 6].fill(32);
+        registers[100] = 7;
+        registers[REGISTERS - 1] = 3;
+
+        let body = encode_sparse(&registers).unwrap();
+
+        assert_eq!(decode_sparse(&body), Ok(registers.clone()));
+        assert_eq!(read_sparse_opcodes(&body).unwrap()[.. 4], [
+            SparseOpcode::Val(1, 1),
+            SparseOpcode::Val(32, 4),
+            SparseOpcode::Val(32, 1),
+            SparseOpcode::XZero(94),
+        ]);
+
+        // values above 32 can only be stored densely
+        registers[50] = 33;
+        assert_eq!(encode_sparse(&registers), None);
+    }
+
+    #[test]
+    fn converts_to_dense_as_it_fills_up() {
+        let mut hll = HyperLogLog::new();
+        let mut added = 0;
+
+        while hll.encoding() == HyperLogLogEncoding::Sparse {
+            let sparse = hll.clone();
+            let batch = elements(added .. added + 100);
+
+            hll.add(batch.iter().map(| e | e.as_slice())).unwrap();
+            added += 100;
+
+            // the registers are the same whichever way they're stored
+            let mut dense = sparse.clone();
+
+            assert_eq!(dense.convert_to_dense(), Ok(true));
+            assert_eq!(dense.convert_to_dense(), Ok(false));
+            assert_eq!(dense.registers(), sparse.registers());
+            assert_eq!(dense.into_bytes().len(), DENSE_SIZE);
+        }
+
+        assert!(added > 1000);
+        assert_eq!(hll.sparse_opcodes(), None);
+        assert_close(hll.count().unwrap(), added);
+
+        // and once it's dense, it stays that way
+        let registers = hll.registers().unwrap();
+
+        hll.set_registers(&registers, HyperLogLogEncoding::Sparse);
+        assert_eq!(hll.encoding(), HyperLogLogEncoding::Dense);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let hll = from_elements(&elements(0 .. 500));
+        let copy = HyperLogLog::from_bytes(hll.clone().into_bytes()).unwrap();
+
+        assert_eq!(copy, hll);
+
+        let mut dense = hll.clone();
+
+        dense.convert_to_dense().unwrap();
+
+        let copy = HyperLogLog::from_bytes(dense.clone().into_bytes()).unwrap();
+
+        assert_eq!(copy.registers(), hll.registers());
+        assert_eq!(copy.encoding(), HyperLogLogEncoding::Dense);
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        assert_eq!(HyperLogLog::from_bytes(b"HYLL".to_vec()), Err(HyperLogLogError::NotAHyperLogLog));
+        assert_eq!(HyperLogLog::from_bytes(vec![0; DENSE_SIZE]), Err(HyperLogLogError::NotAHyperLogLog));
+
+        // a dense HyperLogLog has to have every register
+        let mut bytes = header(HyperLogLogEncoding::Dense);
+
+        bytes.extend([0; 10]);
+        assert_eq!(HyperLogLog::from_bytes(bytes), Err(HyperLogLogError::NotAHyperLogLog));
+
+        // a sparse one is only checked once its registers are read
+        let mut bytes = header(HyperLogLogEncoding::Sparse);
+
+        bytes.push(0x3f);
+
+        let hll = HyperLogLog::from_bytes(bytes).unwrap();
+
+        assert_eq!(hll.registers(), Err(HyperLogLogError::Corrupt));
+    }
+
+    #[test]
+    fn caches_the_count_until_it_changes() {
+        let mut hll = from_elements(&elements(0 .. 100));
+
+        assert!(!hll.is_count_cached());
+
+        let count = hll.count().unwrap();
+
+        assert!(hll.is_count_cached());
+        assert_close(count, 100);
+
+        // adding elements that are already counted doesn't change anything
+        assert_eq!(hll.add([b"element:5".as_slice()]), Ok(false));
+        assert!(hll.is_count_cached());
+
+        assert_eq!(hll.add([b"something new".as_slice()]), Ok(true));
+        assert!(!hll.is_count_cached());
+    }
+
+    #[test]
+    fn merging_gives_the_union() {
+        let a = from_elements(&elements(0 .. 3000));
+        let b = from_elements(&elements(2000 .. 6000));
+        let union = from_elements(&elements(0 .. 6000));
+
+        let mut registers = vec![0; REGISTERS];
+
+        a.merge_into(&mut registers).unwrap();
+        b.merge_into(&mut registers).unwrap();
+
+        assert_eq!(registers, union.registers().unwrap());
+        assert_close(HyperLogLog::estimate(&registers), 6000);
+
+        // storing the merged registers, the way `PFMERGE` does
+        let mut merged = HyperLogLog::new();
+
+        merged.set_registers(&registers, a.encoding());
+
+        assert_eq!(merged.registers(), union.registers());
+        assert_eq!(merged.count(), union.clone().count());
+    }
+
+    #[test]
+    fn estimates_large_cardinalities() {
+        let mut hll = HyperLogLog::new();
+
+        for chunk in elements(0 .. 100_000).chunks(10_000) {
+            hll.add(chunk.iter().map(| e | e.as_slice())).unwrap();
+        }
+
+        assert_close(hll.count().unwrap(), 100_000);
+    }
+}
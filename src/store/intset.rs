@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+
+/// A sorted set of integers packed into a single buffer, modelled on the
+/// intset encoding that Redis uses for small sets of integers.
+///
+/// Every integer is stored with the same width (2, 4 or 8 bytes, little
+/// endian), which is the smallest that fits all of them. Adding an integer
+/// that doesn't fit widens every entry, and the width never shrinks again.
+/// Lookups are a binary search, and inserts and removals shift the entries
+/// after them, so this is only meant for small sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Intset {
+    /// The number of bytes that each integer takes up
+    width: usize,
+    contents: Vec<u8>,
+}
+
+impl Intset {
+    pub fn new() -> Intset {
+        Intset { width: 2, contents: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len() / self.width
+    }
+
+    pub fn get(&self, index: usize) -> Option<i64> {
+        (index < self.len()).then(|| self.read(index))
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        width_of(value) <= self.width && self.search(value).is_ok()
+    }
+
+    /// Adds the value, and returns whether it wasn't already there
+    pub fn insert(&mut self, value: i64) -> bool {
+        if width_of(value) > self.width {
+            self.widen(width_of(value));
+        }
+
+        let Err(index) = self.search(value) else {
+            return false;
+        };
+
+        let offset = index * self.width;
+
+        self.contents.splice(offset .. offset, encode(value, self.width));
+
+        true
+    }
+
+    /// Removes the value, and returns whether it was there
+    pub fn remove(&mut self, value: i64) -> bool {
+        if width_of(value) > self.width {
+            return false;
+        }
+
+        let Ok(index) = self.search(value) else {
+            return false;
+        };
+
+        let offset = index * self.width;
+
+        self.contents.drain(offset .. offset + self.width);
+
+        true
+    }
+
+    /// Iterates over the values in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        (0 .. self.len()).map(| i | self.read(i))
+    }
+
+    fn read(&self, index: usize) -> i64 {
+        let offset = index * self.width;
+        let bytes = &self.contents[offset .. offset + self.width];
+
+        match self.width {
+            2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i64,
+            4 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+            _ => i64::from_le_bytes(bytes.try_into().expect("intset entries are 8 bytes wide")),
+        }
+    }
+
+    /// Finds the value's index, or the index it would be inserted at
+    fn search(&self, value: i64) -> Result<usize, usize> {
+        let (mut low, mut high) = (0, self.len());
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+
+            match self.read(middle).cmp(&value) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(middle),
+            }
+        }
+
+        Err(low)
+    }
+
+    /// Re-encodes every entry with a larger width
+    fn widen(&mut self, width: usize) {
+        let contents = self.iter().flat_map(| v | encode(v, width)).collect();
+
+        self.contents = contents;
+        self.width = width;
+    }
+}
+
+impl Default for Intset {
+    fn default() -> Intset {
+        Intset::new()
+    }
+}
+
+/// The smallest width that can hold the value
+fn width_of(value: i64) -> usize {
+    if i16::try_from(value).is_ok() {
+        2
+    } else if i32::try_from(value).is_ok() {
+        4
+    } else {
+        8
+    }
+}
+
+fn encode(value: i64, width: usize) -> Vec<u8> {
+    value.to_le_bytes()[.. width].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_values_sorted_and_unique() {
+        let mut intset = Intset::new();
+
+        for value in [5, -3, 12, 5, 0, -3] {
+            intset.insert(value);
+        }
+
+        assert!(intset.iter().eq([-3, 0, 5, 12]));
+        assert!(!intset.insert(12));
+        assert!(intset.contains(0));
+        assert!(!intset.contains(1));
+
+        assert!(intset.remove(0));
+        assert!(!intset.remove(0));
+        assert!(intset.iter().eq([-3, 5, 12]));
+        assert_eq!(intset.get(2), Some(12));
+        assert_eq!(intset.get(3), None);
+    }
+
+    #[test]
+    fn widens_to_fit_larger_values() {
+        let mut intset = Intset::new();
+
+        intset.insert(i16::MIN as i64);
+        intset.insert(i16::MAX as i64);
+        assert_eq!(intset.width, 2);
+
+        intset.insert(i16::MAX as i64 + 1);
+        assert_eq!(intset.width, 4);
+        assert_eq!(intset.contents.len(), 12);
+
+        intset.insert(i32::MIN as i64 - 1);
+        assert_eq!(intset.width, 8);
+
+        intset.insert(i64::MAX);
+        intset.insert(i64::MIN);
+
+        let expected = [i64::MIN, i32::MIN as i64 - 1, i16::MIN as i64, i16::MAX as i64, i16::MAX as i64 + 1, i64::MAX];
+
+        assert!(intset.iter().eq(expected));
+        assert!(expected.iter().all(| v | intset.contains(*v)));
+    }
+
+    #[test]
+    fn never_narrows_again() {
+        let mut intset = Intset::new();
+
+        intset.insert(1);
+        intset.insert(1 << 40);
+        intset.remove(1 << 40);
+
+        assert_eq!(intset.width, 8);
+        assert!(intset.iter().eq([1]));
+
+        // values that are wider than the entries can't be in the set
+        let mut narrow = Intset::new();
+
+        narrow.insert(1);
+
+        assert!(!narrow.contains(1 << 40));
+        assert!(!narrow.remove(1 << 40));
+        assert_eq!(narrow.width, 2);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{vec_deque, VecDeque};
+use std::ops::Range;
+
+use crate::store::listpack::{Listpack, ListpackIter};
+
+/// Lists are kept in a listpack until they have more than this many elements
+const LIST_MAX_LISTPACK_ENTRIES: usize = 128;
+
+/// Lists are kept in a listpack until their elements take up more than this
+/// many bytes (the same as Redis's default `list-max-listpack-size` of -2)
+const LIST_MAX_LISTPACK_BYTES: usize = 8 * 1024;
+
+/// Which end of a list to push to or pop from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// An ordered list of byte strings, which can be pushed to and popped from at
+/// either end.
+///
+/// Small lists are packed into a single `Listpack` to save memory, and are
+/// converted to a `VecDeque` once they grow past `LIST_MAX_LISTPACK_ENTRIES`
+/// or `LIST_MAX_LISTPACK_BYTES`. They're only converted back once they've
+/// shrunk to half of those limits, so that a list which hovers around a limit
+/// isn't converted back and forth.
+#[derive(Debug, Clone)]
+pub struct List {
+    encoding: ListEncoding,
+}
+
+#[derive(Debug, Clone)]
+enum ListEncoding {
+    Listpack(Listpack),
+    Deque(VecDeque<Vec<u8>>),
+}
+
+impl List {
+    pub fn new() -> List {
+        List { encoding: ListEncoding::Listpack(Listpack::new()) }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.encoding {
+            ListEncoding::Listpack(l) => l.len(),
+            ListEncoding::Deque(d) => d.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match &self.encoding {
+            ListEncoding::Listpack(l) => l.is_empty(),
+            ListEncoding::Deque(d) => d.is_empty(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        match &self.encoding {
+            ListEncoding::Listpack(l) => l.get(index),
+            ListEncoding::Deque(d) => d.get(index).map(| v | v.as_slice()),
+        }
+    }
+
+    /// Replaces the element at `index`, and returns whether there was one
+    pub fn set(&mut self, index: usize, value: Vec<u8>) -> bool {
+        let replaced = match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.replace(index, &value),
+            ListEncoding::Deque(d) => match d.get_mut(index) {
+                Some(element) => {
+                    *element = value;
+
+                    true
+                }
+                None => false,
+            },
+        };
+
+        self.convert_if_needed();
+
+        replaced
+    }
+
+    pub fn push_front(&mut self, value: Vec<u8>) {
+        match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.push_front(&value),
+            ListEncoding::Deque(d) => d.push_front(value),
+        }
+
+        self.convert_if_needed();
+    }
+
+    pub fn push_back(&mut self, value: Vec<u8>) {
+        match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.push_back(&value),
+            ListEncoding::Deque(d) => d.push_back(value),
+        }
+
+        self.convert_if_needed();
+    }
+
+    pub fn push(&mut self, end: ListEnd, value: Vec<u8>) {
+        match end {
+            ListEnd::Left => self.push_front(value),
+            ListEnd::Right => self.push_back(value),
+        }
+    }
+
+    pub fn pop(&mut self, end: ListEnd) -> Option<Vec<u8>> {
+        match end {
+            ListEnd::Left => self.pop_front(),
+            ListEnd::Right => self.pop_back(),
+        }
+    }
+
+    /// Inserts the value so that it ends up at `index`. Panics if `index` is
+    /// greater than the length.
+    pub fn insert(&mut self, index: usize, value: Vec<u8>) {
+        match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.insert(index, &value),
+            ListEncoding::Deque(d) => d.insert(index, value),
+        }
+
+        self.convert_if_needed();
+    }
+
+    pub fn pop_front(&mut self) -> Option<Vec<u8>> {
+        let value = match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.pop_front(),
+            ListEncoding::Deque(d) => d.pop_front(),
+        };
+
+        self.convert_if_needed();
+
+        value
+    }
+
+    pub fn pop_back(&mut self) -> Option<Vec<u8>> {
+        let value = match &mut self.encoding {
+            ListEncoding::Listpack(l) => l.pop_back(),
+            ListEncoding::Deque(d) => d.pop_back(),
+        };
+
+        self.convert_if_needed();
+
+        value
+    }
+
+    /// Keeps only the elements whose indices are in `range`
+    pub fn trim(&mut self, range: Range<usize>) {
+        match &mut self.encoding {
+            ListEncoding::Listpack(l) => {
+                let range = range.start.min(l.len()) .. range.end.min(l.len());
+
+                *l = l.iter_range(range).collect();
+            }
+            ListEncoding::Deque(d) => {
+                d.truncate(range.end);
+                d.drain(.. range.start.min(d.len()));
+            }
+        }
+
+        self.convert_if_needed();
+    }
+
+    /// Keeps only the elements for which `keep` returns true, given each
+    /// element's index
+    pub fn retain_indices(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        match &mut self.encoding {
+            ListEncoding::Listpack(l) => {
+                *l = l.iter().enumerate()
+                    .filter(| (i, _) | keep(*i))
+                    .map(| (_, v) | v)
+                    .collect();
+            }
+            ListEncoding::Deque(d) => {
+                let mut index = 0;
+
+                d.retain(| _ | {
+                    index += 1;
+
+                    keep(index - 1)
+                });
+            }
+        }
+
+        self.convert_if_needed();
+    }
+
+    pub fn iter(&self) -> ListIter<'_> {
+        self.iter_range(0 .. self.len())
+    }
+
+    /// Iterates over the elements whose indices are in `range`, which must be
+    /// within the list
+    pub fn iter_range(&self, range: Range<usize>) -> ListIter<'_> {
+        match &self.encoding {
+            ListEncoding::Listpack(l) => ListIter::Listpack(l.iter_range(range)),
+            ListEncoding::Deque(d) => ListIter::Deque(d.range(range)),
+        }
+    }
+
+    /// Switches to whichever encoding suits the list's current size
+    fn convert_if_needed(&mut self) {
+        match &self.encoding {
+            ListEncoding::Listpack(l) if l.len() > LIST_MAX_LISTPACK_ENTRIES || l.byte_size() > LIST_MAX_LISTPACK_BYTES => {
+                let deque = l.iter().map(| v | v.to_vec()).collect();
+
+                self.encoding = ListEncoding::Deque(deque);
+            }
+            ListEncoding::Deque(d) if d.len() <= LIST_MAX_LISTPACK_ENTRIES / 2 => {
+                let size: usize = d.iter().map(| v | v.len()).sum();
+
+                if size <= LIST_MAX_LISTPACK_BYTES / 2 {
+                    let listpack = d.iter().map(| v | v.as_slice()).collect();
+
+                    self.encoding = ListEncoding::Listpack(listpack);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for List {
+    fn default() -> List {
+        List::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ListIter<'a> {
+    Listpack(ListpackIter<'a>),
+    Deque(vec_deque::Iter<'a, Vec<u8>>),
+}
+
+impl<'a> Iterator for ListIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        match self {
+            ListIter::Listpack(i) => i.next(),
+            ListIter::Deque(i) => i.next().map(| v | v.as_slice()),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            ListIter::Listpack(i) => i.size_hint(),
+            ListIter::Deque(i) => i.size_hint(),
+        }
+    }
+}
+
+impl DoubleEndedIterator for ListIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            ListIter::Listpack(i) => i.next_back(),
+            ListIter::Deque(i) => i.next_back().map(| v | v.as_slice()),
+        }
+    }
+}
+
+impl ExactSizeIterator for ListIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_listpack(list: &List) -> bool {
+        matches!(list.encoding, ListEncoding::Listpack(_))
+    }
+
+    #[test]
+    fn converts_once_there_are_too_many_elements() {
+        let mut list = List::new();
+
+        for i in 0 .. LIST_MAX_LISTPACK_ENTRIES {
+            list.push_back(i.to_string().into_bytes());
+        }
+
+        assert!(is_listpack(&list));
+
+        list.push_front(b"one too many".to_vec());
+
+        assert!(!is_listpack(&list));
+        assert_eq!(list.len(), LIST_MAX_LISTPACK_ENTRIES + 1);
+        assert_eq!(list.get(0), Some(b"one too many".as_slice()));
+        assert_eq!(list.get(1), Some(b"0".as_slice()));
+
+        // it's only converted back once it's down to half of the limit
+        while list.len() > LIST_MAX_LISTPACK_ENTRIES / 2 + 1 {
+            list.pop_front();
+        }
+
+        assert!(!is_listpack(&list));
+
+        list.pop_front();
+
+        assert!(is_listpack(&list));
+        assert!(list.iter().map(| v | v.to_vec()).eq((64 .. 128).map(| i: usize | i.to_string().into_bytes())));
+    }
+
+    #[test]
+    fn converts_once_the_elements_are_too_big() {
+        let mut list = List::new();
+
+        list.push_back(vec![b'x'; LIST_MAX_LISTPACK_BYTES / 4]);
+        list.push_back(vec![b'y'; LIST_MAX_LISTPACK_BYTES / 2]);
+        assert!(is_listpack(&list));
+
+        list.push_back(vec![b'z'; LIST_MAX_LISTPACK_BYTES / 4]);
+        assert!(!is_listpack(&list));
+
+        // it's only converted back once it's down to half of the limit
+        list.set(2, b"small".to_vec());
+        assert!(!is_listpack(&list));
+
+        list.set(1, b"small".to_vec());
+        assert!(is_listpack(&list));
+
+        list.set(1, vec![b'z'; LIST_MAX_LISTPACK_BYTES]);
+        assert!(!is_listpack(&list));
+        assert_eq!(list.get(1).map(| v | v.len()), Some(LIST_MAX_LISTPACK_BYTES));
+    }
+
+    #[test]
+    fn encodings_behave_the_same() {
+        let mut small = List::new();
+        let mut large = List::new();
+
+        for i in 0 .. 200 {
+            large.push_back(i.to_string().into_bytes());
+        }
+
+        large.trim(0 .. 10);
+
+        for i in 0 .. 10 {
+            small.push_back(i.to_string().into_bytes());
+        }
+
+        assert!(is_listpack(&small));
+
+        for list in [&mut small, &mut large] {
+            list.insert(5, b"inserted".to_vec());
+            list.retain_indices(| i | i % 2 == 0);
+            list.push(ListEnd::Left, b"left".to_vec());
+        }
+
+        assert!(small.iter().eq(large.iter()));
+        assert!(small.iter().rev().eq(large.iter().rev()));
+        assert_eq!(small.pop(ListEnd::Right), large.pop(ListEnd::Right));
+    }
+}
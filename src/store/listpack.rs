@@ -0,0 +1,371 @@
+use std::ops::Range;
+
+/// A sequence of byte strings packed into a single buffer, modelled on the
+/// listpack encoding that Redis uses for small collections. Each entry is laid
+/// out as:
+///
+/// ```text
+/// <length> <data> <backlen>
+/// ```
+///
+/// where `length` is the size of `data` as a variable-length integer, and
+/// `backlen` is the combined size of `length` and `data`, encoded so that it
+/// can be read from right to left. That lets the entries be walked in either
+/// direction without allocating anything per entry.
+///
+/// Finding an entry by index is linear, so this is only meant for small
+/// collections, which are converted to a different representation as they grow.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listpack {
+    buffer: Vec<u8>,
+    length: usize,
+}
+
+impl Listpack {
+    pub fn new() -> Listpack {
+        Listpack::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The number of bytes that the entries take up, including their headers
+    pub fn byte_size(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.length {
+            return None;
+        }
+
+        let (data, _) = read_entry(&self.buffer, self.offset_of(index));
+
+        Some(&self.buffer[data])
+    }
+
+    pub fn push_back(&mut self, value: &[u8]) {
+        self.buffer.extend(encode_entry(value));
+        self.length += 1;
+    }
+
+    pub fn push_front(&mut self, value: &[u8]) {
+        self.insert(0, value);
+    }
+
+    /// Inserts the value so that it ends up at `index`, shifting the entries
+    /// after it along. Panics if `index` is greater than the length.
+    pub fn insert(&mut self, index: usize, value: &[u8]) {
+        assert!(index <= self.length, "listpack index out of bounds");
+
+        let offset = self.offset_of(index);
+
+        self.buffer.splice(offset .. offset, encode_entry(value));
+        self.length += 1;
+    }
+
+    /// Replaces the value at `index`, and returns whether there was one
+    pub fn replace(&mut self, index: usize, value: &[u8]) -> bool {
+        if index >= self.length {
+            return false;
+        }
+
+        let offset = self.offset_of(index);
+        let (_, next) = read_entry(&self.buffer, offset);
+
+        self.buffer.splice(offset .. next, encode_entry(value));
+
+        true
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Vec<u8>> {
+        if index >= self.length {
+            return None;
+        }
+
+        let offset = self.offset_of(index);
+        let (data, next) = read_entry(&self.buffer, offset);
+
+        let value = self.buffer[data].to_vec();
+
+        self.buffer.drain(offset .. next);
+        self.length -= 1;
+
+        Some(value)
+    }
+
+    pub fn pop_front(&mut self) -> Option<Vec<u8>> {
+        self.remove(0)
+    }
+
+    pub fn pop_back(&mut self) -> Option<Vec<u8>> {
+        self.remove(self.length.checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> ListpackIter<'_> {
+        self.iter_range(0 .. self.length)
+    }
+
+    /// Iterates over the entries whose indices are in `range`, which must be
+    /// within the listpack
+    pub fn iter_range(&self, range: Range<usize>) -> ListpackIter<'_> {
+        let front = self.offset_of(range.start);
+        let back = self.offset_of(range.end);
+
+        ListpackIter {
+            buffer: &self.buffer,
+            front,
+            back,
+            remaining: range.len(),
+        }
+    }
+
+    /// Returns the offset in the buffer that the entry at `index` starts at,
+    /// walking from whichever end of the buffer is closest. An index equal to
+    /// the length gives the end of the buffer.
+    fn offset_of(&self, index: usize) -> usize {
+        if index <= self.length / 2 {
+            (0 .. index).fold(0, | offset, _ | read_entry(&self.buffer, offset).1)
+        } else {
+            (index .. self.length).fold(self.buffer.len(), | end, _ | read_entry_before(&self.buffer, end).0.start)
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a [u8]> for Listpack {
+    fn from_iter<I: IntoIterator<Item = &'a [u8]>>(iter: I) -> Listpack {
+        let mut listpack = Listpack::new();
+
+        for value in iter {
+            listpack.push_back(value);
+        }
+
+        listpack
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListpackIter<'a> {
+    buffer: &'a [u8],
+    front: usize,
+    back: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for ListpackIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (data, next) = read_entry(self.buffer, self.front);
+
+        self.front = next;
+        self.remaining -= 1;
+
+        Some(&self.buffer[data])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for ListpackIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (entry, data) = read_entry_before(self.buffer, self.back);
+
+        self.back = entry.start;
+        self.remaining -= 1;
+
+        Some(&self.buffer[data])
+    }
+}
+
+impl ExactSizeIterator for ListpackIter<'_> {}
+
+fn encode_entry(value: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(value.len() + 8);
+
+    write_varint(&mut entry, value.len());
+    entry.extend_from_slice(value);
+
+    let backlen = entry.len();
+
+    write_backlen(&mut entry, backlen);
+
+    entry
+}
+
+/// Reads the entry that starts at `offset`, and returns the range of its data
+/// along with the offset of the entry after it
+fn read_entry(buffer: &[u8], offset: usize) -> (Range<usize>, usize) {
+    let (length, header_size) = read_varint(&buffer[offset ..]);
+
+    let start = offset + header_size;
+    let end = start + length;
+
+    (start .. end, end + varint_size(header_size + length))
+}
+
+/// Reads the entry that ends at `end`, and returns its full range along with
+/// the range of its data
+fn read_entry_before(buffer: &[u8], end: usize) -> (Range<usize>, Range<usize>) {
+    let (backlen, backlen_size) = read_backlen(&buffer[.. end]);
+
+    let start = end - backlen_size - backlen;
+    let (data, _) = read_entry(buffer, start);
+
+    (start .. end, data)
+}
+
+/// Writes `value` as an unsigned LEB128 integer: seven bits per byte, lowest
+/// bits first, with the top bit set on every byte but the last
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    output.push(value as u8);
+}
+
+fn read_varint(input: &[u8]) -> (usize, usize) {
+    let mut value = 0;
+
+    for (i, byte) in input.iter().enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+
+    unreachable!("listpack entries always end with a complete header")
+}
+
+fn varint_size(mut value: usize) -> usize {
+    let mut size = 1;
+
+    while value >= 0x80 {
+        value >>= 7;
+        size += 1;
+    }
+
+    size
+}
+
+/// Writes `value` as a LEB128 integer in reverse, so that it can be read by
+/// starting from the last byte and working backwards
+fn write_backlen(output: &mut Vec<u8>, value: usize) {
+    let start = output.len();
+
+    write_varint(output, value);
+
+    output[start ..].reverse();
+}
+
+/// Reads a backlen that ends at the end of `input`
+fn read_backlen(input: &[u8]) -> (usize, usize) {
+    let mut value = 0;
+
+    for (i, byte) in input.iter().rev().enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+
+    unreachable!("listpack entries always end with a complete backlen")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_grow_with_the_value() {
+        // values up to 127 bytes have one byte headers, up to 16383 have two,
+        // and so on, with the backlen growing to match
+        for (length, header) in [(0, 1), (1, 1), (127, 1), (128, 2), (16383, 2), (16384, 3), (100_000, 3)] {
+            let value = vec![b'x'; length];
+            let entry = encode_entry(&value);
+
+            assert_eq!(read_varint(&entry), (length, header));
+            assert_eq!(read_backlen(&entry), (header + length, varint_size(header + length)));
+            assert_eq!(entry.len(), header + length + varint_size(header + length));
+        }
+    }
+
+    #[test]
+    fn mixed_widths_can_be_walked_both_ways() {
+        let values: Vec<Vec<u8>> = [0, 5, 127, 128, 300, 16383, 16384, 1, 20_000]
+            .iter()
+            .enumerate()
+            .map(| (i, &length) | vec![b'a' + i as u8; length])
+            .collect();
+
+        let listpack: Listpack = values.iter().map(| v | v.as_slice()).collect();
+
+        assert_eq!(listpack.len(), values.len());
+        assert!(listpack.iter().eq(values.iter().map(| v | v.as_slice())));
+        assert!(listpack.iter().rev().eq(values.iter().rev().map(| v | v.as_slice())));
+        assert!(listpack.iter_range(2 .. 7).eq(values[2 .. 7].iter().map(| v | v.as_slice())));
+
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(listpack.get(i), Some(value.as_slice()));
+        }
+
+        assert_eq!(listpack.get(values.len()), None);
+    }
+
+    #[test]
+    fn entries_can_change_width_in_place() {
+        let mut listpack: Listpack = [b"a".as_slice(), b"b", b"c"].into_iter().collect();
+
+        // replacing the middle entry with a wider one (and back) shifts the
+        // entries after it without corrupting them
+        assert!(listpack.replace(1, &[b'x'; 200]));
+        assert_eq!(listpack.get(1), Some([b'x'; 200].as_slice()));
+        assert_eq!(listpack.get(2), Some(b"c".as_slice()));
+        assert_eq!(listpack.iter().next_back(), Some(b"c".as_slice()));
+
+        assert!(listpack.replace(1, b"y"));
+        assert_eq!(listpack.byte_size(), 9);
+        assert!(!listpack.replace(3, b"z"));
+
+        listpack.insert(1, &[b'z'; 20_000]);
+        listpack.push_front(b"first");
+
+        assert_eq!(listpack.remove(2).map(| v | v.len()), Some(20_000));
+        assert!(listpack.iter().eq([b"first".as_slice(), b"a", b"y", b"c"]));
+    }
+
+    #[test]
+    fn push_and_pop_at_both_ends() {
+        let mut listpack = Listpack::new();
+
+        listpack.push_back(b"b");
+        listpack.push_front(b"a");
+        listpack.push_back(b"c");
+
+        assert_eq!(listpack.pop_front(), Some(b"a".to_vec()));
+        assert_eq!(listpack.pop_back(), Some(b"c".to_vec()));
+        assert_eq!(listpack.pop_back(), Some(b"b".to_vec()));
+        assert_eq!(listpack.pop_back(), None);
+        assert!(listpack.is_empty());
+        assert_eq!(listpack.byte_size(), 0);
+    }
+}
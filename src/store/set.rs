@@ -0,0 +1,241 @@
+use std::borrow::Cow;
+
+use crate::random;
+use crate::store::dict::{self, Dict};
+use crate::store::intset::Intset;
+use crate::store::parse_integer;
+
+/// Sets are kept in an intset until they have more than this many members
+/// (the same as Redis's default `set-max-intset-entries`)
+const SET_MAX_INTSET_ENTRIES: usize = 512;
+
+/// An unordered collection of unique byte strings.
+///
+/// Sets whose members are all integers are packed into an `Intset` to save
+/// memory, and are converted to a `Dict` (without values) as soon as a member
+/// that isn't an integer is added, or once they grow past
+/// `SET_MAX_INTSET_ENTRIES`. Like in Redis, they're never converted back.
+#[derive(Debug, Clone)]
+pub struct Set {
+    encoding: SetEncoding,
+}
+
+#[derive(Debug, Clone)]
+enum SetEncoding {
+    Intset(Intset),
+    Hash(Dict<()>),
+}
+
+impl Set {
+    pub fn new() -> Set {
+        Set { encoding: SetEncoding::Intset(Intset::new()) }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.encoding {
+            SetEncoding::Intset(i) => i.len(),
+            SetEncoding::Hash(h) => h.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match &self.encoding {
+            SetEncoding::Intset(i) => parse_integer(member).is_some_and(| v | i.contains(v)),
+            SetEncoding::Hash(h) => h.contains_key(member),
+        }
+    }
+
+    /// Adds the member, and returns whether it wasn't already there
+    pub fn insert(&mut self, member: Vec<u8>) -> bool {
+        if let SetEncoding::Intset(intset) = &mut self.encoding {
+            match parse_integer(&member) {
+                Some(value) if intset.contains(value) => return false,
+                Some(value) if intset.len() < SET_MAX_INTSET_ENTRIES => return intset.insert(value),
+                _ => self.convert_to_hash(),
+            }
+        }
+
+        match &mut self.encoding {
+            SetEncoding::Hash(h) => h.insert(member, ()).is_none(),
+            SetEncoding::Intset(_) => unreachable!("the set was converted to a hash set"),
+        }
+    }
+
+    /// Removes the member, and returns whether it was there
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match &mut self.encoding {
+            SetEncoding::Intset(i) => parse_integer(member).is_some_and(| v | i.remove(v)),
+            SetEncoding::Hash(h) => h.remove(member).is_some(),
+        }
+    }
+
+    /// Returns a random member, or `None` if the set is empty
+    pub fn random_member(&self) -> Option<Cow<'_, [u8]>> {
+        match &self.encoding {
+            SetEncoding::Intset(i) if i.len() == 0 => None,
+            SetEncoding::Intset(i) => i.get(random::random_index(i.len())).map(format_member),
+            SetEncoding::Hash(h) => h.random_entry().map(| (m, _) | Cow::Borrowed(m)),
+        }
+    }
+
+    /// Iterates over the members, in no particular order (although sets of
+    /// integers happen to be in ascending order)
+    pub fn iter(&self) -> SetIter<'_> {
+        match &self.encoding {
+            SetEncoding::Intset(i) => SetIter::Intset(i, 0),
+            SetEncoding::Hash(h) => SetIter::Hash(h.iter()),
+        }
+    }
+
+    fn convert_to_hash(&mut self) {
+        if let SetEncoding::Intset(intset) = &self.encoding {
+            let mut members = Dict::new();
+
+            for value in intset.iter() {
+                members.insert(value.to_string().into_bytes(), ());
+            }
+
+            self.encoding = SetEncoding::Hash(members);
+        }
+    }
+}
+
+impl Default for Set {
+    fn default() -> Set {
+        Set::new()
+    }
+}
+
+impl FromIterator<Vec<u8>> for Set {
+    fn from_iter<I: IntoIterator<Item = Vec<u8>>>(iter: I) -> Set {
+        let mut set = Set::new();
+
+        for member in iter {
+            set.insert(member);
+        }
+
+        set
+    }
+}
+
+pub enum SetIter<'a> {
+    /// The intset, and the index of the next member in it
+    Intset(&'a Intset, usize),
+    Hash(dict::Iter<'a, ()>),
+}
+
+impl<'a> Iterator for SetIter<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u8]>> {
+        match self {
+            SetIter::Intset(intset, index) => {
+                let value = intset.get(*index)?;
+
+                *index += 1;
+
+                Some(format_member(value))
+            }
+            SetIter::Hash(i) => i.next().map(| (m, _) | Cow::Borrowed(m)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SetIter::Intset(intset, index) => {
+                let remaining = intset.len() - index;
+
+                (remaining, Some(remaining))
+            }
+            SetIter::Hash(i) => i.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for SetIter<'_> {}
+
+fn format_member<'a>(value: i64) -> Cow<'a, [u8]> {
+    Cow::Owned(value.to_string().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_intset(set: &Set) -> bool {
+        matches!(set.encoding, SetEncoding::Intset(_))
+    }
+
+    fn members(set: &Set) -> Vec<Vec<u8>> {
+        let mut members: Vec<Vec<u8>> = set.iter().map(Cow::into_owned).collect();
+
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn converts_when_a_member_isnt_an_integer() {
+        let mut set: Set = [b"3".to_vec(), b"1".to_vec(), b"2".to_vec()].into_iter().collect();
+
+        assert!(is_intset(&set));
+        assert!(!set.insert(b"2".to_vec()));
+
+        // integers that wouldn't come back out the same way aren't stored as
+        // integers either
+        assert!(set.insert(b"02".to_vec()));
+        assert!(!is_intset(&set));
+
+        assert_eq!(members(&set), [b"02".to_vec(), b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+        assert!(set.contains(b"1"));
+        assert!(!set.insert(b"1".to_vec()));
+        assert!(set.remove(b"1"));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn converts_once_there_are_too_many_members() {
+        let mut set = Set::new();
+
+        for i in 0 .. SET_MAX_INTSET_ENTRIES as i64 {
+            set.insert(i.to_string().into_bytes());
+        }
+
+        assert!(is_intset(&set));
+
+        set.insert(b"-1".to_vec());
+
+        assert!(!is_intset(&set));
+        assert_eq!(set.len(), SET_MAX_INTSET_ENTRIES + 1);
+        assert!((-1 .. SET_MAX_INTSET_ENTRIES as i64).all(| i | set.contains(i.to_string().as_bytes())));
+
+        // like in Redis, it isn't converted back
+        for i in 0 .. SET_MAX_INTSET_ENTRIES as i64 {
+            set.remove(i.to_string().as_bytes());
+        }
+
+        assert!(!is_intset(&set));
+        assert_eq!(members(&set), [b"-1".to_vec()]);
+    }
+
+    #[test]
+    fn random_members_come_from_the_set() {
+        let mut set = Set::new();
+
+        assert!(set.random_member().is_none());
+
+        set.insert(b"7".to_vec());
+        assert_eq!(set.random_member().as_deref(), Some(b"7".as_slice()));
+
+        set.insert(b"eight".to_vec());
+
+        for _ in 0 .. 100 {
+            let member = set.random_member().unwrap();
+
+            assert!(set.contains(&member));
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::cmp::Ordering;
+
+use crate::random;
+
+/// The most levels that a node can have, which is plenty for 2^64 elements
+const SKIPLIST_MAX_LEVEL: usize = 32;
+
+/// The index of the header node, which comes before every element
+const HEAD: usize = 0;
+
+/// A skiplist of members ordered by their scores (and then by the members
+/// themselves when scores are equal), modelled on the one that Redis uses for
+/// sorted sets.
+///
+/// Each link between two nodes records how many elements it skips over (its
+/// span), which makes finding an element's rank, or the element at a given
+/// rank, O(log n) rather than O(n). Nodes are kept in an arena and link to
+/// each other by index, and the slots of removed nodes are reused.
+#[derive(Debug, Clone)]
+pub struct Skiplist {
+    nodes: Vec<Node>,
+    /// The indices of the nodes that have been removed, and can be reused
+    free: Vec<usize>,
+    length: usize,
+    /// The number of levels in use, which is the height of the tallest node
+    level: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Vec<u8>,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    forward: Option<usize>,
+    /// The number of elements between this node and `forward`, counting
+    /// `forward` itself
+    span: usize,
+}
+
+impl Node {
+    fn compare(&self, score: f64, member: &[u8]) -> Ordering {
+        self.score.partial_cmp(&score)
+            .expect("scores are never NaN")
+            .then_with(|| self.member.as_slice().cmp(member))
+    }
+}
+
+impl Skiplist {
+    pub fn new() -> Skiplist {
+        let head = Node {
+            member: Vec::new(),
+            score: 0.0,
+            backward: None,
+            levels: vec![Level::default(); SKIPLIST_MAX_LEVEL],
+        };
+
+        Skiplist { nodes: vec![head], free: Vec::new(), length: 0, level: 1 }
+    }
+
+    /// Adds a member, which mustn't already be in the list
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) {
+        let mut update = [HEAD; SKIPLIST_MAX_LEVEL];
+        let mut rank = [0; SKIPLIST_MAX_LEVEL];
+        let mut x = HEAD;
+
+        for i in (0 .. self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].compare(score, &member) != Ordering::Less {
+                    break;
+                }
+
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+
+            update[i] = x;
+        }
+
+        let level = random_level();
+
+        if level > self.level {
+            for i in self.level .. level {
+                self.nodes[HEAD].levels[i].span = self.length;
+            }
+
+            self.level = level;
+        }
+
+        let node = self.allocate(Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            levels: vec![Level::default(); level],
+        });
+
+        for i in 0 .. level {
+            let previous = update[i];
+            let skipped = rank[0] - rank[i];
+
+            self.nodes[node].levels[i] = Level {
+                forward: self.nodes[previous].levels[i].forward,
+                span: self.nodes[previous].levels[i].span - skipped,
+            };
+
+            self.nodes[previous].levels[i] = Level { forward: Some(node), span: skipped + 1 };
+        }
+
+        for (i, &previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[previous].levels[i].span += 1;
+        }
+
+        if let Some(next) = self.nodes[node].levels[0].forward {
+            self.nodes[next].backward = Some(node);
+        }
+
+        self.length += 1;
+    }
+
+    /// Removes a member with the given score, and returns whether it was there
+    pub fn remove(&mut self, member: &[u8], score: f64) -> bool {
+        let mut update = [HEAD; SKIPLIST_MAX_LEVEL];
+        let mut x = HEAD;
+
+        for i in (0 .. self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].compare(score, member) != Ordering::Less {
+                    break;
+                }
+
+                x = next;
+            }
+
+            update[i] = x;
+        }
+
+        match self.nodes[x].levels[0].forward {
+            Some(node) if self.nodes[node].compare(score, member) == Ordering::Equal => {
+                self.unlink(node, &update);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the member's rank (its 0-based position in the list), or
+    /// `None` if it isn't in the list with the given score
+    pub fn rank(&self, member: &[u8], score: f64) -> Option<usize> {
+        let mut rank = 0;
+        let mut x = HEAD;
+
+        for i in (0 .. self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if self.nodes[next].compare(score, member) == Ordering::Greater {
+                    break;
+                }
+
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+
+            if x != HEAD && self.nodes[x].member == member {
+                return Some(rank - 1);
+            }
+        }
+
+        None
+    }
+
+    /// Counts the members at the start of the list for which `before` returns
+    /// true. `before` has to hold for every member up to some point in the
+    /// list, and for none of them after it (e.g. "the score is below 10"), so
+    /// this is the rank of the first member that it doesn't hold for.
+    pub fn count_before(&self, before: impl Fn(&[u8], f64) -> bool) -> usize {
+        let mut rank = 0;
+        let mut x = HEAD;
+
+        for i in (0 .. self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if !before(&self.nodes[next].member, self.nodes[next].score) {
+                    break;
+                }
+
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+
+        rank
+    }
+
+    /// Iterates over the members starting from the given rank, towards the
+    /// end of the list or, if `reverse` is set, towards the start of it
+    pub fn iter_from(&self, rank: usize, reverse: bool) -> SkiplistIter<'_> {
+        SkiplistIter { list: self, next: self.get_by_rank(rank), reverse }
+    }
+
+    fn get_by_rank(&self, rank: usize) -> Option<usize> {
+        // ranks are 0-based, but spans count the node that they lead to
+        let target = rank.checked_add(1)?;
+        let mut traversed = 0;
+        let mut x = HEAD;
+
+        for i in (0 .. self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+
+            if traversed == target {
+                return Some(x);
+            }
+        }
+
+        None
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+
+                index
+            }
+            None => {
+                self.nodes.push(node);
+
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Unlinks a node, given the last node before it on each level
+    fn unlink(&mut self, node: usize, update: &[usize]) {
+        for (i, &previous) in update.iter().enumerate().take(self.level) {
+            if self.nodes[previous].levels[i].forward == Some(node) {
+                self.nodes[previous].levels[i] = Level {
+                    forward: self.nodes[node].levels[i].forward,
+                    span: self.nodes[previous].levels[i].span + self.nodes[node].levels[i].span - 1,
+                };
+            } else {
+                self.nodes[previous].levels[i].span -= 1;
+            }
+        }
+
+        if let Some(next) = self.nodes[node].levels[0].forward {
+            self.nodes[next].backward = self.nodes[node].backward;
+        }
+
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+
+        self.length -= 1;
+
+        // the node's memory is released, but its slot is kept for reuse
+        self.nodes[node].member = Vec::new();
+        self.nodes[node].levels = Vec::new();
+        self.free.push(node);
+    }
+}
+
+impl Default for Skiplist {
+    fn default() -> Skiplist {
+        Skiplist::new()
+    }
+}
+
+pub struct SkiplistIter<'a> {
+    list: &'a Skiplist,
+    next: Option<usize>,
+    reverse: bool,
+}
+
+impl<'a> Iterator for SkiplistIter<'a> {
+    type Item = (&'a [u8], f64);
+
+    fn next(&mut self) -> Option<(&'a [u8], f64)> {
+        let node = &self.list.nodes[self.next?];
+
+        self.next = if self.reverse { node.backward } else { node.levels[0].forward };
+
+        Some((&node.member, node.score))
+    }
+}
+
+/// Picks how many levels a new node has, where each level is a quarter as
+/// likely as the one below it (the same odds as Redis)
+fn random_level() -> usize {
+    let mut level = 1;
+
+    while level < SKIPLIST_MAX_LEVEL && random::next_u64().is_multiple_of(4) {
+        level += 1;
+    }
+
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that every link's span matches the number of elements it skips
+    /// over, and that the backward links and the length agree with the
+    /// bottom level
+    fn check_links(list: &Skiplist) {
+        let mut positions = std::collections::HashMap::from([(HEAD, 0)]);
+        let mut x = HEAD;
+        let mut previous = None;
+
+        while let Some(next) = list.nodes[x].levels[0].forward {
+            assert_eq!(list.nodes[next].backward, previous);
+            assert_eq!(list.nodes[x].levels[0].span, 1);
+
+            positions.insert(next, positions[&x] + 1);
+            previous = Some(next);
+            x = next;
+        }
+
+        assert_eq!(positions.len(), list.length + 1);
+
+        for i in 0 .. list.level {
+            let mut x = HEAD;
+
+            while let Some(next) = list.nodes[x].levels[i].forward {
+                assert_eq!(list.nodes[x].levels[i].span, positions[&next] - positions[&x]);
+
+                x = next;
+            }
+        }
+
+        assert!(list.nodes[HEAD].levels[list.level..].iter().all(| l | l.forward.is_none()));
+    }
+
+    fn members(list: &Skiplist) -> Vec<(Vec<u8>, f64)> {
+        list.iter_from(0, false).map(| (m, s) | (m.to_vec(), s)).collect()
+    }
+
+    #[test]
+    fn orders_by_score_and_then_member() {
+        let mut list = Skiplist::new();
+
+        list.insert(b"b".to_vec(), 2.0);
+        list.insert(b"a".to_vec(), 2.0);
+        list.insert(b"c".to_vec(), 1.0);
+        list.insert(b"d".to_vec(), f64::NEG_INFINITY);
+
+        assert_eq!(members(&list), [
+            (b"d".to_vec(), f64::NEG_INFINITY),
+            (b"c".to_vec(), 1.0),
+            (b"a".to_vec(), 2.0),
+            (b"b".to_vec(), 2.0),
+        ]);
+
+        let reversed: Vec<_> = list.iter_from(3, true).map(| (m, _) | m.to_vec()).collect();
+
+        assert_eq!(reversed, [b"b".to_vec(), b"a".to_vec(), b"c".to_vec(), b"d".to_vec()]);
+        assert_eq!(list.iter_from(4, false).next(), None);
+
+        check_links(&list);
+    }
+
+    #[test]
+    fn ranks_follow_inserts_and_removals() {
+        let mut list = Skiplist::new();
+        let mut expected: Vec<(Vec<u8>, f64)> = Vec::new();
+
+        for i in 0 .. 2000u64 {
+            // scores repeat, so that ties are broken by member
+            let score = (random::next_u64() % 100) as f64;
+            let member = format!("member:{i}").into_bytes();
+
+            list.insert(member.clone(), score);
+            expected.push((member, score));
+
+            // every third element is removed again
+            if i % 3 == 2 {
+                let (member, score) = expected.swap_remove((random::next_u64() % expected.len() as u64) as usize);
+
+                assert!(list.remove(&member, score));
+                assert!(!list.remove(&member, score));
+            }
+
+            if i % 250 == 0 {
+                check_links(&list);
+            }
+        }
+
+        expected.sort_by(| a, b | a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        check_links(&list);
+        assert_eq!(list.length, expected.len());
+        assert_eq!(members(&list), expected);
+
+        for (rank, (member, score)) in expected.iter().enumerate() {
+            assert_eq!(list.rank(member, *score), Some(rank));
+            assert_eq!(list.iter_from(rank, false).next(), Some((member.as_slice(), *score)));
+        }
+
+        assert_eq!(list.rank(b"member:0", 1000.0), None);
+        assert_eq!(list.count_before(| _, score | score < 50.0), expected.iter().filter(| (_, s) | *s < 50.0).count());
+    }
+
+    #[test]
+    fn removing_everything_resets_the_levels() {
+        let mut list = Skiplist::new();
+
+        for i in 0 .. 500 {
+            list.insert(i.to_string().into_bytes(), i as f64);
+        }
+
+        let slots = list.nodes.len();
+
+        for i in 0 .. 500 {
+            assert!(list.remove(i.to_string().as_bytes(), i as f64));
+        }
+
+        assert_eq!(list.length, 0);
+        assert_eq!(list.level, 1);
+        assert_eq!(list.iter_from(0, false).next(), None);
+        check_links(&list);
+
+        // the slots of removed nodes are reused
+        for i in 0 .. 500 {
+            list.insert(i.to_string().into_bytes(), -(i as f64));
+        }
+
+        assert_eq!(list.nodes.len(), slots);
+        assert_eq!(list.rank(b"499", -499.0), Some(0));
+        check_links(&list);
+    }
+}
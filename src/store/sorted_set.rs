@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::store::skiplist::{Skiplist, SkiplistIter};
+
+/// A set of unique members, each with a score that they're ordered by.
+///
+/// Like in Redis, the scores are kept in a hash map so that looking up a
+/// member's score is O(1), and the members are also kept in a `Skiplist`
+/// ordered by score, which makes rank and range queries O(log n).
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    list: Skiplist,
+}
+
+/// One end of a range of scores, e.g. `(1.5` for "greater than 1.5"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: ScoreBound,
+    pub max: ScoreBound,
+}
+
+/// One end of a range of members, which are compared byte by byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    /// Before every member (`-`)
+    Start,
+    /// After every member (`+`)
+    End,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+/// A range of members, for sorted sets whose members all have the same score
+/// (since the range is only meaningful when they're ordered by member alone)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl ScoreRange {
+    fn is_below_min(&self, score: f64) -> bool {
+        score < self.min.value || (self.min.exclusive && score == self.min.value)
+    }
+
+    fn is_within_max(&self, score: f64) -> bool {
+        score < self.max.value || (!self.max.exclusive && score == self.max.value)
+    }
+}
+
+impl LexRange {
+    fn is_below_min(&self, member: &[u8]) -> bool {
+        match &self.min {
+            LexBound::Start => false,
+            LexBound::End => true,
+            LexBound::Inclusive(min) => member < min.as_slice(),
+            LexBound::Exclusive(min) => member <= min.as_slice(),
+        }
+    }
+
+    fn is_within_max(&self, member: &[u8]) -> bool {
+        match &self.max {
+            LexBound::Start => false,
+            LexBound::End => true,
+            LexBound::Inclusive(max) => member <= max.as_slice(),
+            LexBound::Exclusive(max) => member < max.as_slice(),
+        }
+    }
+}
+
+impl SortedSet {
+    pub fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Sets the member's score, adding it if it isn't already there, and
+    /// returns the score it had before. The score mustn't be NaN.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        match self.scores.get_mut(&member) {
+            Some(current) => {
+                let previous = *current;
+
+                if previous != score {
+                    *current = score;
+
+                    self.list.remove(&member, previous);
+                    self.list.insert(member, score);
+                }
+
+                Some(previous)
+            }
+            None => {
+                self.list.insert(member.clone(), score);
+                self.scores.insert(member, score);
+
+                None
+            }
+        }
+    }
+
+    /// Removes the member, and returns the score it had
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+
+        self.list.remove(member, score);
+
+        Some(score)
+    }
+
+    /// Returns the member's 0-based position when the set is ordered from the
+    /// lowest score to the highest
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        self.list.rank(member, self.score(member)?)
+    }
+
+    /// Returns the ranks of the members whose scores are in the range
+    pub fn ranks_by_score(&self, range: &ScoreRange) -> Range<usize> {
+        let start = self.list.count_before(| _, score | range.is_below_min(score));
+        let end = self.list.count_before(| _, score | range.is_within_max(score));
+
+        start .. end.max(start)
+    }
+
+    /// Returns the ranks of the members that are in the range
+    pub fn ranks_by_lex(&self, range: &LexRange) -> Range<usize> {
+        let start = self.list.count_before(| member, _ | range.is_below_min(member));
+        let end = self.list.count_before(| member, _ | range.is_within_max(member));
+
+        start .. end.max(start)
+    }
+
+    /// Iterates over the members and their scores from the lowest score to the
+    /// highest, starting at the given rank, or from the highest to the lowest
+    /// if `reverse` is set
+    pub fn iter_from(&self, rank: usize, reverse: bool) -> SkiplistIter<'_> {
+        self.list.iter_from(rank, reverse)
+    }
+
+    /// Iterates over the members and their scores from the lowest score to the
+    /// highest
+    pub fn iter(&self) -> SkiplistIter<'_> {
+        self.list.iter_from(0, false)
+    }
+}
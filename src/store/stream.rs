@@ -0,0 +1,560 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::fmt;
+use std::ops::Bound;
+
+mod consumer_group;
+
+pub use consumer_group::{AutoClaim, ClaimOptions, ConsumerGroup};
+
+/// How many entries a stream's trimming treats as a block when it's allowed to
+/// be approximate (`~`), the same as Redis's default `stream-node-max-entries`
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// The default for `LIMIT` when a stream is trimmed approximately, which is
+/// the same as Redis's (100 times `stream-node-max-entries`)
+pub const STREAM_DEFAULT_TRIM_LIMIT: usize = 100 * STREAM_NODE_MAX_ENTRIES;
+
+/// The ID of a stream entry: the Unix time in milliseconds that it was added,
+/// and a sequence number for entries that were added in the same millisecond
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The fields of a stream entry and their values, in the order they were given
+pub type StreamFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Which entries are removed when a stream is trimmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    /// Remove the oldest entries until there are at most this many
+    MaxLen(usize),
+    /// Remove the entries whose IDs are lower than this one
+    MinId(StreamId),
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId { ms: u64::MAX, seq: u64::MAX };
+
+    pub fn new(ms: u64, seq: u64) -> StreamId {
+        StreamId { ms, seq }
+    }
+
+    /// Parses an ID in the form `<ms>-<seq>`, or just `<ms>`, in which case
+    /// the sequence number is `default_seq`
+    pub fn parse(s: &str, default_seq: u64) -> Option<StreamId> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(StreamId::new(s.parse().ok()?, default_seq)),
+        }
+    }
+
+    /// The ID that comes straight after this one, if there is one
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The ID that comes straight before this one, if there is one
+    pub fn previous(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// An append-only log of entries, ordered by their IDs.
+///
+/// The entries are kept in a B-tree, which keeps range queries by ID cheap.
+/// The stream also remembers the last ID it handed out, since new entries
+/// have to have a greater ID than every entry that was ever added, even ones
+/// that have since been deleted.
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    last_id: StreamId,
+    /// How many entries have ever been added to the stream
+    entries_added: u64,
+    /// The greatest ID of an entry that was deleted with `XDEL`, or 0-0. An
+    /// entry deleted from the middle of the stream means the number of
+    /// entries before an ID can no longer be worked out from its position.
+    max_deleted_id: StreamId,
+    groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+}
+
+impl Stream {
+    pub fn new() -> Stream {
+        Stream::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The ID of the last entry that was added, even if it has been deleted
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    /// The ID of the first entry in the stream, or 0-0 if it's empty
+    pub fn first_id(&self) -> StreamId {
+        self.entries.first_key_value().map_or(StreamId::MIN, | (id, _) | *id)
+    }
+
+    pub fn first_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.first_key_value()
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.last_key_value()
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&StreamFields> {
+        self.entries.get(&id)
+    }
+
+    /// Generates the ID for an entry that's added at `now` (a Unix time in
+    /// milliseconds). If the clock has gone backwards, or an entry has already
+    /// been added this millisecond, the last ID's sequence number is
+    /// incremented instead. Returns `None` once every ID has been used.
+    pub fn next_id(&self, now: u64) -> Option<StreamId> {
+        if now > self.last_id.ms {
+            Some(StreamId::new(now, 0))
+        } else {
+            self.last_id.next()
+        }
+    }
+
+    /// Generates the ID for an entry that's added with the given time and an
+    /// automatic sequence number (i.e. `<ms>-*`), if one is left that's
+    /// greater than the last ID
+    pub fn next_id_at(&self, ms: u64) -> Option<StreamId> {
+        if ms > self.last_id.ms {
+            // 0-0 is never a valid ID, so the first entry at 0 starts at 0-1
+            Some(StreamId::new(ms, if ms == 0 { 1 } else { 0 }))
+        } else if ms == self.last_id.ms {
+            Some(StreamId::new(ms, self.last_id.seq.checked_add(1)?))
+        } else {
+            None
+        }
+    }
+
+    /// Adds an entry, whose ID has to be greater than `last_id`
+    pub fn insert(&mut self, id: StreamId, fields: StreamFields) {
+        debug_assert!(id > self.last_id, "stream IDs only ever increase");
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    /// Removes an entry, and returns whether it was there
+    pub fn remove(&mut self, id: StreamId) -> bool {
+        if self.entries.remove(&id).is_none() {
+            return false;
+        }
+
+        self.max_deleted_id = self.max_deleted_id.max(id);
+
+        true
+    }
+
+    /// Iterates over the entries whose IDs are between `start` and `end`,
+    /// inclusive, in order
+    pub fn range(&self, start: StreamId, end: StreamId) -> btree_map::Range<'_, StreamId, StreamFields> {
+        if start > end {
+            return self.entries.range((Bound::Included(start), Bound::Excluded(start)));
+        }
+
+        self.entries.range(start ..= end)
+    }
+
+    /// Removes the oldest entries according to `strategy`, and returns how
+    /// many were removed.
+    ///
+    /// When `approximate` is set, entries are only removed in whole blocks of
+    /// `STREAM_NODE_MAX_ENTRIES`, which mirrors how Redis only removes whole
+    /// nodes of its radix tree, so the stream can be left a bit longer than
+    /// asked for. `limit` caps how many entries are removed, and is only
+    /// given along with `approximate`.
+    pub fn trim(&mut self, strategy: TrimStrategy, approximate: bool, limit: Option<usize>) -> usize {
+        let mut count = match strategy {
+            TrimStrategy::MaxLen(max_len) => self.entries.len().saturating_sub(max_len),
+            TrimStrategy::MinId(min_id) => self.entries.range(.. min_id).count(),
+        };
+
+        if approximate {
+            count -= count % STREAM_NODE_MAX_ENTRIES;
+
+            if let Some(limit) = limit {
+                count = count.min(limit - limit % STREAM_NODE_MAX_ENTRIES);
+            }
+        }
+
+        for _ in 0 .. count {
+            self.entries.pop_first();
+        }
+
+        count
+    }
+
+    /// Iterates over the consumer groups, ordered by name
+    pub fn groups(&self) -> btree_map::Iter<'_, Vec<u8>, ConsumerGroup> {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &[u8]) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &[u8]) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// Adds a consumer group, and returns whether there wasn't already one
+    /// with the same name
+    pub fn create_group(&mut self, name: &[u8], group: ConsumerGroup) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+
+        self.groups.insert(name.to_vec(), group);
+
+        true
+    }
+
+    /// Removes a consumer group, and returns whether it existed
+    pub fn destroy_group(&mut self, name: &[u8]) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Delivers up to `count` entries that haven't been delivered to the group
+    /// yet to `consumer`, and returns their IDs. Unless `no_ack` is set, the
+    /// entries are added to the group's pending entries list until they're
+    /// acknowledged. Returns `None` if the group doesn't exist.
+    pub fn read_group(&mut self, name: &[u8], consumer: &[u8], count: usize, no_ack: bool, now: u64) -> Option<Vec<StreamId>> {
+        let group = self.groups.get(name)?;
+
+        let ids: Vec<StreamId> = match group.last_delivered_id().next() {
+            Some(start) => self.range(start, StreamId::MAX).take(count).map(| (id, _) | *id).collect(),
+            None => Vec::new(),
+        };
+
+        // the group's count of entries read can only be carried on while
+        // nothing has been deleted after the entries it reads
+        let mut entries_read = group.entries_read();
+
+        for id in &ids {
+            entries_read = match entries_read {
+                Some(read) if !self.has_tombstones_from(*id) => Some(read + 1),
+                _ => self.estimate_entries_read(*id),
+            };
+        }
+
+        let group = self.groups.get_mut(name)?;
+        let consumer_state = group.touch_consumer(consumer, now);
+
+        let Some(last) = ids.last() else {
+            return Some(ids);
+        };
+
+        consumer_state.active_time = Some(now);
+        group.set_last_delivered_id(*last, entries_read);
+
+        if !no_ack {
+            for id in &ids {
+                group.deliver(*id, consumer, now);
+            }
+        }
+
+        Some(ids)
+    }
+
+    /// Delivers the consumer's pending entries after `after` again, and
+    /// returns their IDs, including ones that have since been deleted from
+    /// the stream. Returns `None` if the group doesn't exist.
+    pub fn read_group_history(&mut self, name: &[u8], consumer: &[u8], after: StreamId, count: usize, now: u64) -> Option<Vec<StreamId>> {
+        Some(self.groups.get_mut(name)?.read_history(consumer, after, count, now, &self.entries))
+    }
+
+    /// Claims the given pending entries for `consumer` (i.e. `XCLAIM`), and
+    /// returns the IDs of the ones that were claimed. Returns `None` if the
+    /// group doesn't exist.
+    pub fn claim(&mut self, name: &[u8], consumer: &[u8], ids: &[StreamId], options: &ClaimOptions, now: u64) -> Option<Vec<StreamId>> {
+        Some(self.groups.get_mut(name)?.claim(consumer, ids, options, now, &self.entries))
+    }
+
+    /// Claims pending entries for `consumer` that have been idle for long
+    /// enough, starting from `start` (i.e. `XAUTOCLAIM`). Returns `None` if
+    /// the group doesn't exist.
+    #[allow(clippy::too_many_arguments)]
+    pub fn auto_claim(
+        &mut self,
+        name: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+        now: u64,
+    ) -> Option<AutoClaim> {
+        Some(self.groups.get_mut(name)?.auto_claim(consumer, min_idle, start, count, just_id, now, &self.entries))
+    }
+
+    /// How many entries the group has yet to read, if that can be worked out
+    pub fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+
+        let entries_read = match group.entries_read() {
+            Some(read) if !self.has_tombstones_from(group.last_delivered_id()) => read,
+            _ => self.estimate_entries_read(group.last_delivered_id())?,
+        };
+
+        Some(self.entries_added.saturating_sub(entries_read))
+    }
+
+    /// Whether an entry with an ID at or after `id` has been deleted, which
+    /// means that counting the entries up to an ID isn't reliable
+    fn has_tombstones_from(&self, id: StreamId) -> bool {
+        !self.entries.is_empty() && self.max_deleted_id != StreamId::MIN && id <= self.max_deleted_id
+    }
+
+    /// Works out how many entries were added up to and including `id`, if
+    /// that's possible without counting them, in the same way as Redis
+    fn estimate_entries_read(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+
+        if self.entries.is_empty() && id <= self.last_id {
+            return Some(self.entries_added);
+        }
+
+        if id == self.last_id {
+            return Some(self.entries_added);
+        }
+
+        if id > self.last_id {
+            return None;
+        }
+
+        let first_id = self.first_id();
+
+        // without deletions after the first entry, every entry from the first
+        // one onwards is still in the stream
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first_id {
+            let before_first = self.entries_added - self.entries.len() as u64;
+
+            if id < first_id {
+                return Some(before_first);
+            }
+
+            if id == first_id {
+                return Some(before_first + 1);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(ms: u64, seq: u64) -> StreamId {
+        StreamId::new(ms, seq)
+    }
+
+    fn fields() -> StreamFields {
+        vec![(b"field".to_vec(), b"value".to_vec())]
+    }
+
+    /// A stream with entries at `1-0`, `2-0` and so on, up to `length-0`
+    fn numbered_stream(length: u64) -> Stream {
+        let mut stream = Stream::new();
+
+        for ms in 1 ..= length {
+            stream.insert(id(ms, 0), fields());
+        }
+
+        stream
+    }
+
+    fn ids<'a>(range: impl Iterator<Item = (&'a StreamId, &'a StreamFields)>) -> Vec<StreamId> {
+        range.map(| (id, _) | *id).collect()
+    }
+
+    #[test]
+    fn parses_and_formats_ids() {
+        assert_eq!(StreamId::parse("5-3", 0), Some(id(5, 3)));
+        assert_eq!(StreamId::parse("5", 7), Some(id(5, 7)));
+        assert_eq!(StreamId::parse("18446744073709551615-18446744073709551615", 0), Some(StreamId::MAX));
+        assert_eq!(StreamId::parse("5-", 0), None);
+        assert_eq!(StreamId::parse("-5", 0), None);
+        assert_eq!(StreamId::parse("5-3-1", 0), None);
+        assert_eq!(StreamId::parse("18446744073709551616", 0), None);
+        assert_eq!(id(5, 3).to_string(), "5-3");
+    }
+
+    #[test]
+    fn generates_ids_from_the_clock() {
+        let mut stream = Stream::new();
+
+        assert_eq!(stream.next_id(1000), Some(id(1000, 0)));
+        stream.insert(id(1000, 0), fields());
+
+        // a second entry in the same millisecond, or after the clock has gone
+        // backwards, carries on from the last ID
+        assert_eq!(stream.next_id(1000), Some(id(1000, 1)));
+        assert_eq!(stream.next_id(999), Some(id(1000, 1)));
+        assert_eq!(stream.next_id(1001), Some(id(1001, 0)));
+    }
+
+    #[test]
+    fn generates_sequence_numbers_for_a_given_time() {
+        let mut stream = Stream::new();
+
+        assert_eq!(stream.next_id_at(0), Some(id(0, 1)));
+        assert_eq!(stream.next_id_at(5), Some(id(5, 0)));
+
+        stream.insert(id(5, 3), fields());
+
+        assert_eq!(stream.next_id_at(5), Some(id(5, 4)));
+        assert_eq!(stream.next_id_at(6), Some(id(6, 0)));
+
+        // a time before the last ID can't be moved forward from
+        assert_eq!(stream.next_id_at(4), None);
+    }
+
+    #[test]
+    fn sequence_numbers_overflow_into_the_next_millisecond() {
+        let mut stream = Stream::new();
+
+        stream.insert(id(5, u64::MAX), fields());
+
+        assert_eq!(stream.next_id_at(5), None);
+        assert_eq!(stream.next_id(5), Some(id(6, 0)));
+
+        stream.insert(StreamId::MAX, fields());
+
+        assert_eq!(stream.next_id(u64::MAX), None);
+        assert_eq!(stream.next_id_at(u64::MAX), None);
+    }
+
+    #[test]
+    fn the_last_id_survives_deletion() {
+        let mut stream = numbered_stream(3);
+
+        assert!(stream.remove(id(3, 0)));
+        assert!(!stream.remove(id(3, 0)));
+
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream.last_id(), id(3, 0));
+        assert_eq!(stream.max_deleted_id(), id(3, 0));
+        assert_eq!(stream.next_id(2), Some(id(3, 1)));
+        assert_eq!(stream.entries_added(), 3);
+    }
+
+    #[test]
+    fn trims_to_a_maximum_length() {
+        let mut stream = numbered_stream(10);
+
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(20), false, None), 0);
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(3), false, None), 7);
+        assert_eq!(ids(stream.range(StreamId::MIN, StreamId::MAX)), [id(8, 0), id(9, 0), id(10, 0)]);
+
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(0), false, None), 3);
+        assert_eq!(stream.len(), 0);
+        assert_eq!(stream.last_id(), id(10, 0));
+    }
+
+    #[test]
+    fn trims_entries_below_a_minimum_id() {
+        let mut stream = numbered_stream(10);
+
+        assert_eq!(stream.trim(TrimStrategy::MinId(id(1, 0)), false, None), 0);
+        assert_eq!(stream.trim(TrimStrategy::MinId(id(4, 1)), false, None), 4);
+        assert_eq!(stream.first_id(), id(5, 0));
+        assert_eq!(stream.trim(TrimStrategy::MinId(StreamId::MAX), false, None), 6);
+    }
+
+    #[test]
+    fn approximate_trims_remove_whole_blocks() {
+        let mut stream = numbered_stream(250);
+
+        // 200 entries would have to go, which is exactly two blocks
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(50), true, None), 200);
+
+        let mut stream = numbered_stream(250);
+
+        // 170 entries would have to go, so only one block is removed
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(80), true, None), 100);
+        assert_eq!(stream.trim(TrimStrategy::MinId(id(240, 0)), true, None), 100);
+
+        // too few entries for a whole block leaves the stream as it is
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(0), true, None), 0);
+        assert_eq!(stream.len(), 50);
+    }
+
+    #[test]
+    fn approximate_trims_stop_at_the_limit() {
+        let mut stream = numbered_stream(1000);
+
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(0), true, Some(250)), 200);
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(0), true, Some(50)), 0);
+        assert_eq!(stream.trim(TrimStrategy::MaxLen(0), true, Some(STREAM_DEFAULT_TRIM_LIMIT)), 800);
+    }
+
+    #[test]
+    fn ranges_are_inclusive() {
+        let stream = numbered_stream(5);
+
+        assert_eq!(ids(stream.range(id(2, 0), id(4, 0))), [id(2, 0), id(3, 0), id(4, 0)]);
+        assert_eq!(ids(stream.range(id(3, 0), id(3, 0))), [id(3, 0)]);
+        assert_eq!(ids(stream.range(id(6, 0), StreamId::MAX)), []);
+        assert_eq!(ids(stream.range(id(4, 0), id(2, 0))), []);
+        assert_eq!(stream.range(StreamId::MIN, StreamId::MAX).next_back().map(| (id, _) | *id), Some(id(5, 0)));
+    }
+
+    #[test]
+    fn exclusive_bounds_are_the_ids_next_to_them() {
+        let stream = numbered_stream(5);
+
+        // `(2-0` and `(4-0` as the start and end of a range
+        let (start, end) = (id(2, 0).next().unwrap(), id(4, 0).previous().unwrap());
+
+        assert_eq!((start, end), (id(2, 1), id(3, u64::MAX)));
+        assert_eq!(ids(stream.range(start, end)), [id(3, 0)]);
+
+        // an exclusive range around a single ID is empty
+        assert_eq!(ids(stream.range(id(3, 0).next().unwrap(), id(3, 0).previous().unwrap())), []);
+
+        assert_eq!(id(3, u64::MAX).next(), Some(id(4, 0)));
+        assert_eq!(id(3, 0).previous(), Some(id(2, u64::MAX)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::MIN.previous(), None);
+    }
+}
@@ -0,0 +1,620 @@
+use std::collections::{BTreeMap, BTreeSet, btree_map};
+use std::ops::Bound;
+
+use crate::store::stream::{StreamFields, StreamId};
+
+/// A group of consumers that share the entries of a stream between them, so
+/// that each entry is only delivered to one of them.
+///
+/// Entries that have been delivered but not yet acknowledged are kept in the
+/// group's pending entries list (PEL), along with the consumer they were
+/// delivered to, so that they can be read again or claimed by another
+/// consumer if the first one goes away.
+#[derive(Debug, Clone)]
+pub struct ConsumerGroup {
+    /// The ID of the last entry that was delivered to the group
+    last_delivered_id: StreamId,
+    /// How many entries the group has read, if it's known, which is used to
+    /// work out how far behind the stream it is
+    entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+/// An entry that was delivered to a consumer, and hasn't been acknowledged
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub consumer: Vec<u8>,
+    /// The Unix time in milliseconds that the entry was last delivered
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Consumer {
+    /// The Unix time in milliseconds that the consumer last did anything
+    pub seen_time: u64,
+    /// The Unix time in milliseconds that the consumer last read or claimed an
+    /// entry, if it ever has
+    pub active_time: Option<u64>,
+    /// The IDs of the entries that are pending for this consumer, which are
+    /// also in the group's pending entries list
+    pending: BTreeSet<StreamId>,
+}
+
+/// The options given to `XCLAIM`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaimOptions {
+    /// How long an entry has to have been pending for, in milliseconds, for
+    /// it to be claimed
+    pub min_idle: u64,
+    /// The delivery time that claimed entries are given
+    pub delivery_time: u64,
+    /// The delivery count that claimed entries are given, rather than it
+    /// being incremented
+    pub retry_count: Option<u64>,
+    /// Whether entries that aren't pending are claimed anyway, as long as
+    /// they're in the stream
+    pub force: bool,
+    /// Whether only the IDs are returned, in which case the delivery count
+    /// isn't incremented
+    pub just_id: bool,
+    /// An ID to move the group's last delivered ID up to
+    pub last_id: Option<StreamId>,
+}
+
+/// The result of `ConsumerGroup::auto_claim`
+#[derive(Debug, Clone, Default)]
+pub struct AutoClaim {
+    /// The ID to continue scanning from, or 0-0 once the whole pending entries
+    /// list has been scanned
+    pub next: StreamId,
+    pub claimed: Vec<StreamId>,
+    /// Pending entries that were removed because they're no longer in the
+    /// stream
+    pub deleted: Vec<StreamId>,
+}
+
+impl Consumer {
+    fn new(now: u64) -> Consumer {
+        Consumer { seen_time: now, active_time: None, pending: BTreeSet::new() }
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Iterates over the IDs of the consumer's pending entries, in order
+    pub fn pending(&self) -> impl DoubleEndedIterator<Item = StreamId> + '_ {
+        self.pending.iter().copied()
+    }
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered_id: StreamId, entries_read: Option<u64>) -> ConsumerGroup {
+        ConsumerGroup {
+            last_delivered_id,
+            entries_read,
+            pending: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+        }
+    }
+
+    pub fn last_delivered_id(&self) -> StreamId {
+        self.last_delivered_id
+    }
+
+    pub fn entries_read(&self) -> Option<u64> {
+        self.entries_read
+    }
+
+    /// Moves the group's last delivered ID (e.g. with `XGROUP SETID`), which
+    /// can be backwards to deliver entries again
+    pub fn set_last_delivered_id(&mut self, id: StreamId, entries_read: Option<u64>) {
+        self.last_delivered_id = id;
+        self.entries_read = entries_read;
+    }
+
+    /// Iterates over the consumers, ordered by name
+    pub fn consumers(&self) -> btree_map::Iter<'_, Vec<u8>, Consumer> {
+        self.consumers.iter()
+    }
+
+    pub fn consumer(&self, name: &[u8]) -> Option<&Consumer> {
+        self.consumers.get(name)
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Iterates over the pending entries whose IDs are between `start` and
+    /// `end`, inclusive, in order
+    pub fn pending_range(&self, start: StreamId, end: StreamId) -> btree_map::Range<'_, StreamId, PendingEntry> {
+        if start > end {
+            return self.pending.range((Bound::Included(start), Bound::Excluded(start)));
+        }
+
+        self.pending.range(start ..= end)
+    }
+
+    pub fn pending_entry(&self, id: StreamId) -> Option<&PendingEntry> {
+        self.pending.get(&id)
+    }
+
+    /// Adds a consumer, and returns whether it didn't already exist
+    pub fn create_consumer(&mut self, name: &[u8], now: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+
+        self.consumers.insert(name.to_vec(), Consumer::new(now));
+
+        true
+    }
+
+    /// Returns the consumer, creating it if it doesn't exist yet, and marks it
+    /// as having been seen
+    pub fn touch_consumer(&mut self, name: &[u8], now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_vec()).or_insert_with(|| Consumer::new(now));
+
+        consumer.seen_time = now;
+
+        consumer
+    }
+
+    /// Removes a consumer along with its pending entries, and returns how
+    /// many pending entries it had
+    pub fn delete_consumer(&mut self, name: &[u8]) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+
+        Some(consumer.pending.len())
+    }
+
+    /// Removes an entry from the pending entries list, and returns whether it
+    /// was there
+    pub fn acknowledge(&mut self, id: StreamId) -> bool {
+        let Some(entry) = self.pending.remove(&id) else {
+            return false;
+        };
+
+        if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+            consumer.pending.remove(&id);
+        }
+
+        true
+    }
+
+    /// Adds an entry that's being delivered to `consumer` to the pending
+    /// entries list. The entry can already be pending if the group's last ID
+    /// was moved back, in which case it's handed over to the consumer.
+    pub(super) fn deliver(&mut self, id: StreamId, consumer: &[u8], now: u64) {
+        let entry = self.assign(id, consumer, now);
+
+        entry.delivery_time = now;
+        entry.delivery_count = 1;
+    }
+
+    /// Delivers the consumer's pending entries with IDs after `after` again,
+    /// up to `count` of them, and returns their IDs. Entries that are no
+    /// longer in the stream are still returned, but their delivery isn't
+    /// counted.
+    pub(super) fn read_history(
+        &mut self,
+        consumer: &[u8],
+        after: StreamId,
+        count: usize,
+        now: u64,
+        entries: &BTreeMap<StreamId, StreamFields>,
+    ) -> Vec<StreamId> {
+        let consumer = self.touch_consumer(consumer, now);
+
+        let ids: Vec<StreamId> = match after.next() {
+            Some(start) => consumer.pending.range(start ..).take(count).copied().collect(),
+            None => Vec::new(),
+        };
+
+        for id in &ids {
+            if let Some(entry) = self.pending.get_mut(id).filter(|_| entries.contains_key(id)) {
+                entry.delivery_time = now;
+                entry.delivery_count += 1;
+            }
+        }
+
+        ids
+    }
+
+    /// Hands the given pending entries over to `consumer` if they've been
+    /// pending for long enough, and returns the IDs of the ones that were.
+    /// Entries that are no longer in the stream are removed from the pending
+    /// entries list instead.
+    pub(super) fn claim(
+        &mut self,
+        consumer: &[u8],
+        ids: &[StreamId],
+        options: &ClaimOptions,
+        now: u64,
+        entries: &BTreeMap<StreamId, StreamFields>,
+    ) -> Vec<StreamId> {
+        self.touch_consumer(consumer, now);
+
+        if let Some(last_id) = options.last_id.filter(| id | *id > self.last_delivered_id) {
+            self.last_delivered_id = last_id;
+        }
+
+        let mut claimed = Vec::new();
+
+        for &id in ids {
+            // entries that are forced into the pending entries list haven't
+            // been pending for any time, so the idle time isn't checked
+            let forced = !self.pending.contains_key(&id);
+
+            if forced && !(options.force && entries.contains_key(&id)) {
+                continue;
+            }
+
+            if !forced && now.saturating_sub(self.pending[&id].delivery_time) < options.min_idle {
+                continue;
+            }
+
+            if !entries.contains_key(&id) {
+                self.acknowledge(id);
+                continue;
+            }
+
+            let entry = self.assign(id, consumer, now);
+
+            if forced {
+                entry.delivery_count = 1;
+            }
+
+            entry.delivery_time = options.delivery_time;
+
+            match options.retry_count {
+                Some(retry_count) => entry.delivery_count = retry_count,
+                None if !options.just_id => entry.delivery_count += 1,
+                None => {}
+            }
+
+            claimed.push(id);
+        }
+
+        if !claimed.is_empty() {
+            self.touch_consumer(consumer, now).active_time = Some(now);
+        }
+
+        claimed
+    }
+
+    /// Scans the pending entries list from `start`, and hands entries that
+    /// have been pending for at least `min_idle` milliseconds over to
+    /// `consumer`, until `count` entries have been claimed. At most 10 times
+    /// `count` entries are looked at, the same as Redis, so that a single call
+    /// can't take too long.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn auto_claim(
+        &mut self,
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+        now: u64,
+        entries: &BTreeMap<StreamId, StreamFields>,
+    ) -> AutoClaim {
+        self.touch_consumer(consumer, now);
+
+        let mut attempts = count.saturating_mul(10);
+        let mut result = AutoClaim::default();
+
+        let candidates: Vec<(StreamId, u64)> = self.pending.range(start ..)
+            .take(attempts.saturating_add(1))
+            .map(| (id, entry) | (*id, entry.delivery_time))
+            .collect();
+
+        for (id, delivery_time) in candidates {
+            if attempts == 0 || result.claimed.len() == count {
+                result.next = id;
+                break;
+            }
+
+            attempts -= 1;
+
+            if now.saturating_sub(delivery_time) < min_idle {
+                continue;
+            }
+
+            if !entries.contains_key(&id) {
+                self.acknowledge(id);
+                result.deleted.push(id);
+                continue;
+            }
+
+            let entry = self.assign(id, consumer, now);
+
+            entry.delivery_time = now;
+
+            if !just_id {
+                entry.delivery_count += 1;
+            }
+
+            result.claimed.push(id);
+        }
+
+        if !result.claimed.is_empty() {
+            self.touch_consumer(consumer, now).active_time = Some(now);
+        }
+
+        result
+    }
+
+    /// Makes `consumer` the owner of a pending entry, adding the entry if it
+    /// isn't pending yet, and returns it so its delivery can be updated
+    fn assign(&mut self, id: StreamId, consumer: &[u8], now: u64) -> &mut PendingEntry {
+        let previous = self.pending.get(&id).map(| e | e.consumer.clone());
+
+        if let Some(previous) = previous.and_then(| name | self.consumers.get_mut(&name)) {
+            previous.pending.remove(&id);
+        }
+
+        self.consumers.entry(consumer.to_vec())
+            .or_insert_with(|| Consumer::new(now))
+            .pending
+            .insert(id);
+
+        let entry = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: Vec::new(),
+            delivery_time: now,
+            delivery_count: 0,
+        });
+
+        entry.consumer = consumer.to_vec();
+
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::stream::{Stream, TrimStrategy};
+
+    fn id(ms: u64) -> StreamId {
+        StreamId::new(ms, 0)
+    }
+
+    /// A stream with entries at `1-0` up to `length-0`, and a group called
+    /// `group` that hasn't read any of them
+    fn stream_with_group(length: u64) -> Stream {
+        let mut stream = Stream::new();
+
+        for ms in 1 ..= length {
+            stream.insert(id(ms), vec![(b"field".to_vec(), b"value".to_vec())]);
+        }
+
+        stream.create_group(b"group", ConsumerGroup::new(StreamId::MIN, Some(0)));
+
+        stream
+    }
+
+    fn group(stream: &Stream) -> &ConsumerGroup {
+        stream.group(b"group").unwrap()
+    }
+
+    fn pending_of(stream: &Stream, consumer: &[u8]) -> Vec<StreamId> {
+        group(stream).consumer(consumer).map_or_else(Vec::new, | c | c.pending().collect())
+    }
+
+    fn claim_options(min_idle: u64, now: u64) -> ClaimOptions {
+        ClaimOptions { min_idle, delivery_time: now, ..ClaimOptions::default() }
+    }
+
+    #[test]
+    fn delivered_entries_are_pending_until_acknowledged() {
+        let mut stream = stream_with_group(5);
+
+        assert_eq!(stream.read_group(b"group", b"alice", 2, false, 100), Some(vec![id(1), id(2)]));
+        assert_eq!(stream.read_group(b"group", b"bob", 2, false, 100), Some(vec![id(3), id(4)]));
+        assert_eq!(stream.read_group(b"missing", b"bob", 2, false, 100), None);
+
+        assert_eq!(group(&stream).pending_len(), 4);
+        assert_eq!(group(&stream).last_delivered_id(), id(4));
+        assert_eq!(pending_of(&stream, b"alice"), [id(1), id(2)]);
+        assert_eq!(group(&stream).pending_entry(id(3)).map(| e | e.consumer.clone()), Some(b"bob".to_vec()));
+
+        let group_mut = stream.group_mut(b"group").unwrap();
+
+        assert!(group_mut.acknowledge(id(1)));
+        assert!(!group_mut.acknowledge(id(1)));
+        assert!(!group_mut.acknowledge(id(5)));
+
+        assert_eq!(pending_of(&stream, b"alice"), [id(2)]);
+        assert_eq!(group(&stream).pending_len(), 3);
+
+        // entries read with NOACK are delivered without being pending
+        assert_eq!(stream.read_group(b"group", b"alice", 10, true, 100), Some(vec![id(5)]));
+        assert_eq!(group(&stream).pending_len(), 3);
+        assert_eq!(stream.read_group(b"group", b"alice", 10, false, 100), Some(Vec::new()));
+
+        // deleting a consumer drops its pending entries too
+        assert_eq!(stream.group_mut(b"group").unwrap().delete_consumer(b"bob"), Some(2));
+        assert_eq!(group(&stream).pending_len(), 1);
+        assert!(group(&stream).pending_entry(id(3)).is_none());
+    }
+
+    #[test]
+    fn reading_history_delivers_pending_entries_again() {
+        let mut stream = stream_with_group(3);
+
+        stream.read_group(b"group", b"alice", 3, false, 100);
+        stream.remove(id(2));
+
+        assert_eq!(stream.read_group_history(b"group", b"alice", StreamId::MIN, 10, 200), Some(vec![id(1), id(2), id(3)]));
+        assert_eq!(stream.read_group_history(b"group", b"alice", id(1), 1, 200), Some(vec![id(2)]));
+        assert_eq!(stream.read_group_history(b"group", b"bob", StreamId::MIN, 10, 200), Some(Vec::new()));
+
+        let delivery = | ms | group(&stream).pending_entry(id(ms)).map(| e | (e.delivery_time, e.delivery_count));
+
+        // a deleted entry is still returned, but its delivery isn't counted
+        assert_eq!(delivery(1), Some((200, 2)));
+        assert_eq!(delivery(2), Some((100, 1)));
+        assert_eq!(delivery(3), Some((200, 2)));
+    }
+
+    #[test]
+    fn claims_only_entries_that_have_been_idle_for_long_enough() {
+        let mut stream = stream_with_group(3);
+
+        stream.read_group(b"group", b"alice", 3, false, 1000);
+
+        assert_eq!(stream.claim(b"group", b"bob", &[id(1)], &claim_options(500, 1499), 1499), Some(Vec::new()));
+        assert_eq!(stream.claim(b"group", b"bob", &[id(1), id(2)], &claim_options(500, 1500), 1500), Some(vec![id(1), id(2)]));
+
+        assert_eq!(pending_of(&stream, b"alice"), [id(3)]);
+        assert_eq!(pending_of(&stream, b"bob"), [id(1), id(2)]);
+
+        let entry = group(&stream).pending_entry(id(1)).unwrap();
+        assert_eq!((entry.delivery_time, entry.delivery_count), (1500, 2));
+
+        // claiming resets the idle time, so it can't be claimed straight back
+        assert_eq!(stream.claim(b"group", b"alice", &[id(1)], &claim_options(500, 1600), 1600), Some(Vec::new()));
+    }
+
+    #[test]
+    fn claim_options_change_the_delivery() {
+        let mut stream = stream_with_group(5);
+
+        stream.read_group(b"group", b"alice", 2, false, 1000);
+
+        let just_id = ClaimOptions { just_id: true, ..claim_options(0, 2000) };
+        let retry_count = ClaimOptions { retry_count: Some(7), ..claim_options(0, 2000) };
+
+        stream.claim(b"group", b"bob", &[id(1)], &just_id, 2000);
+        stream.claim(b"group", b"bob", &[id(2)], &retry_count, 2000);
+
+        assert_eq!(group(&stream).pending_entry(id(1)).unwrap().delivery_count, 1);
+        assert_eq!(group(&stream).pending_entry(id(2)).unwrap().delivery_count, 7);
+
+        // entries that aren't pending are only claimed with FORCE, and only
+        // if they're in the stream
+        let force = ClaimOptions { force: true, ..claim_options(1_000_000, 2000) };
+
+        assert_eq!(stream.claim(b"group", b"bob", &[id(3)], &claim_options(0, 2000), 2000), Some(Vec::new()));
+        assert_eq!(stream.claim(b"group", b"bob", &[id(3), id(9)], &force, 2000), Some(vec![id(3)]));
+
+        // like Redis, a forced entry starts out delivered once, and the claim
+        // counts as another delivery
+        assert_eq!(group(&stream).pending_entry(id(3)).unwrap().delivery_count, 2);
+
+        let last_id = ClaimOptions { last_id: Some(id(4)), ..claim_options(0, 2000) };
+
+        stream.claim(b"group", b"bob", &[], &last_id, 2000);
+        assert_eq!(group(&stream).last_delivered_id(), id(4));
+    }
+
+    #[test]
+    fn claiming_a_deleted_entry_removes_it_from_the_pending_list() {
+        let mut stream = stream_with_group(2);
+
+        stream.read_group(b"group", b"alice", 2, false, 1000);
+        stream.remove(id(1));
+
+        assert_eq!(stream.claim(b"group", b"bob", &[id(1), id(2)], &claim_options(0, 1000), 1000), Some(vec![id(2)]));
+        assert!(group(&stream).pending_entry(id(1)).is_none());
+        assert!(pending_of(&stream, b"alice").is_empty());
+    }
+
+    #[test]
+    fn auto_claim_scans_the_pending_list() {
+        let mut stream = stream_with_group(6);
+
+        stream.read_group(b"group", b"alice", 3, false, 1000);
+        stream.read_group(b"group", b"alice", 3, false, 2000);
+        stream.remove(id(2));
+
+        // only the entries delivered at 1000 are idle for long enough
+        let result = stream.auto_claim(b"group", b"bob", 1500, StreamId::MIN, 10, false, 2500).unwrap();
+
+        assert_eq!(result.claimed, [id(1), id(3)]);
+        assert_eq!(result.deleted, [id(2)]);
+        assert_eq!(result.next, StreamId::MIN);
+        assert_eq!(group(&stream).pending_entry(id(1)).map(| e | e.delivery_count), Some(2));
+
+        // `count` stops the scan, and the cursor picks up where it left off
+        let result = stream.auto_claim(b"group", b"carol", 0, StreamId::MIN, 2, true, 5000).unwrap();
+
+        assert_eq!(result.claimed, [id(1), id(3)]);
+        assert_eq!(result.next, id(4));
+        assert_eq!(group(&stream).pending_entry(id(1)).map(| e | e.delivery_count), Some(2));
+
+        let result = stream.auto_claim(b"group", b"carol", 0, result.next, 2, false, 5000).unwrap();
+
+        assert_eq!(result.claimed, [id(4), id(5)]);
+        assert_eq!(result.next, id(6));
+        assert_eq!(pending_of(&stream, b"carol"), [id(1), id(3), id(4), id(5)]);
+    }
+
+    #[test]
+    fn auto_claim_looks_at_no_more_than_ten_times_count() {
+        let mut stream = stream_with_group(30);
+
+        stream.read_group(b"group", b"alice", 30, false, 1000);
+
+        // nothing has been idle for long enough, so the scan gives up early
+        let result = stream.auto_claim(b"group", b"bob", 10_000, StreamId::MIN, 2, false, 2000).unwrap();
+
+        assert!(result.claimed.is_empty());
+        assert_eq!(result.next, id(21));
+    }
+
+    #[test]
+    fn lag_follows_the_entries_read() {
+        let mut stream = stream_with_group(5);
+
+        assert_eq!(stream.lag(group(&stream)), Some(5));
+
+        stream.read_group(b"group", b"alice", 2, true, 100);
+
+        assert_eq!(group(&stream).entries_read(), Some(2));
+        assert_eq!(stream.lag(group(&stream)), Some(3));
+
+        // deleting an entry that was already read doesn't affect the count
+        stream.remove(id(1));
+        assert_eq!(stream.lag(group(&stream)), Some(3));
+
+        // but one that hasn't been read yet means it can't be worked out
+        stream.remove(id(4));
+        assert_eq!(stream.lag(group(&stream)), None);
+
+        stream.read_group(b"group", b"alice", 1, true, 100);
+        assert_eq!(group(&stream).entries_read(), None);
+
+        // reading up to the last entry makes the count exact again
+        stream.read_group(b"group", b"alice", 10, true, 100);
+        assert_eq!(group(&stream).entries_read(), Some(5));
+        assert_eq!(stream.lag(group(&stream)), Some(0));
+    }
+
+    #[test]
+    fn lag_is_estimated_after_trimming() {
+        let mut stream = stream_with_group(10);
+
+        stream.trim(TrimStrategy::MaxLen(4), false, None);
+
+        // a group that doesn't know how much it has read is behind by every
+        // entry that's left, since everything before them was trimmed
+        stream.create_group(b"late", ConsumerGroup::new(StreamId::MIN, None));
+        assert_eq!(stream.lag(stream.group(b"late").unwrap()), Some(4));
+
+        stream.read_group(b"late", b"alice", 1, true, 100);
+        assert_eq!(stream.group(b"late").unwrap().entries_read(), Some(7));
+        assert_eq!(stream.lag(stream.group(b"late").unwrap()), Some(3));
+
+        // the original group's count carries on from where it was
+        assert_eq!(stream.lag(group(&stream)), Some(10));
+    }
+}
@@ -0,0 +1,148 @@
+use std::borrow::Cow;
+
+use crate::store::parse_integer;
+
+/// A binary-safe string.
+///
+/// Strings that hold an integer written the way Redis would write it (e.g.
+/// `12` but not `012` or `+12`) are kept as an `i64`, like Redis's `int`
+/// encoding, so that counters can be incremented without being parsed and
+/// formatted every time. Since only integers that come back out exactly as
+/// they went in are encoded this way, the encoding can't be observed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringValue {
+    encoding: StringEncoding,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StringEncoding {
+    Int(i64),
+    Raw(Vec<u8>),
+}
+
+impl StringValue {
+    pub fn new(bytes: Vec<u8>) -> StringValue {
+        match parse_integer(&bytes) {
+            Some(value) => StringValue::from_integer(value),
+            None => StringValue { encoding: StringEncoding::Raw(bytes) },
+        }
+    }
+
+    pub fn from_integer(value: i64) -> StringValue {
+        StringValue { encoding: StringEncoding::Int(value) }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.encoding {
+            StringEncoding::Int(value) => integer_length(*value),
+            StringEncoding::Raw(bytes) => bytes.len(),
+        }
+    }
+
+    /// Returns the integer that the string holds, if it holds one
+    pub fn as_integer(&self) -> Option<i64> {
+        match &self.encoding {
+            StringEncoding::Int(value) => Some(*value),
+            StringEncoding::Raw(_) => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Cow<'_, [u8]> {
+        match &self.encoding {
+            StringEncoding::Int(value) => Cow::Owned(value.to_string().into_bytes()),
+            StringEncoding::Raw(bytes) => Cow::Borrowed(bytes),
+        }
+    }
+
+    /// Adds `bytes` to the end of the string, and returns its new length
+    pub fn append(&mut self, bytes: &[u8]) -> usize {
+        self.update(| raw | raw.extend_from_slice(bytes));
+
+        self.len()
+    }
+
+    /// Overwrites the string with `bytes` from `offset`, padding it with zero
+    /// bytes first if it's shorter than `offset`, and returns its new length
+    pub fn set_range(&mut self, offset: usize, bytes: &[u8]) -> usize {
+        self.update(| raw | {
+            let end = offset + bytes.len();
+
+            if raw.len() < end {
+                raw.resize(end, 0);
+            }
+
+            raw[offset .. end].copy_from_slice(bytes);
+        });
+
+        self.len()
+    }
+
+    /// Pads the string with zero bytes until it's at least `length` bytes long
+    pub fn grow(&mut self, length: usize) {
+        if self.len() < length {
+            self.update(| raw | raw.resize(length, 0));
+        }
+    }
+
+    /// Returns `bits` bits (at most 64) of the string from the bit at
+    /// `offset`, where bit 0 is the most significant bit of the first byte.
+    /// Bits past the end of the string are read as 0.
+    pub fn get_bits(&self, offset: usize, bits: u32) -> u64 {
+        let bytes = self.as_bytes();
+
+        (offset .. offset + bits as usize).fold(0, | value, position | {
+            let bit = bytes.get(position / 8).map_or(0, | byte | (byte >> (7 - position % 8)) & 1);
+
+            (value << 1) | bit as u64
+        })
+    }
+
+    /// Overwrites `bits` bits (at most 64) of the string from the bit at
+    /// `offset` with the low bits of `value`, padding the string with zero
+    /// bytes first if it's too short
+    pub fn set_bits(&mut self, offset: usize, bits: u32, value: u64) {
+        self.update(| raw | {
+            let end = offset + bits as usize;
+
+            if raw.len() * 8 < end {
+                raw.resize(end.div_ceil(8), 0);
+            }
+
+            for (i, position) in (offset .. end).enumerate() {
+                let mask = 1 << (7 - position % 8);
+
+                if (value >> (bits as usize - 1 - i)) & 1 == 1 {
+                    raw[position / 8] |= mask;
+                } else {
+                    raw[position / 8] &= !mask;
+                }
+            }
+        });
+    }
+
+    /// Changes the string's bytes in place, and then checks whether it can
+    /// be kept as an integer
+    fn update(&mut self, change: impl FnOnce(&mut Vec<u8>)) {
+        let mut bytes = match std::mem::take(&mut self.encoding) {
+            StringEncoding::Int(value) => value.to_string().into_bytes(),
+            StringEncoding::Raw(bytes) => bytes,
+        };
+
+        change(&mut bytes);
+
+        *self = StringValue::new(bytes);
+    }
+}
+
+impl Default for StringEncoding {
+    fn default() -> StringEncoding {
+        StringEncoding::Raw(Vec::new())
+    }
+}
+
+/// The number of bytes that an integer takes up when it's written out
+fn integer_length(value: i64) -> usize {
+    let digits = value.unsigned_abs().checked_ilog10().map_or(1, | log | log as usize + 1);
+
+    if value < 0 { digits + 1 } else { digits }
+}
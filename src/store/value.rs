@@ -0,0 +1,138 @@
+use crate::store::hash::Hash;
+use crate::store::list::List;
+use crate::store::set::Set;
+use crate::store::sorted_set::SortedSet;
+use crate::store::stream::Stream;
+use crate::store::string::StringValue;
+
+/// A value stored in the database. Each variant is one of the data types that
+/// Redis supports, and commands only operate on the type they were made for.
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(StringValue),
+    List(List),
+    Hash(Hash),
+    Set(Set),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+/// The error returned when a key holds a different type of value from the one
+/// that was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrongTypeError;
+
+impl Value {
+    /// Returns the name of the value's type, as reported by `TYPE`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Whether the value is a collection with nothing in it. Redis never keeps
+    /// empty collections around, so keys holding one should be deleted.
+    /// Strings are never considered empty, since an empty string is a value
+    /// in its own right.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(sorted_set) => sorted_set.is_empty(),
+            // like Redis, streams are kept around once their last entry is
+            // removed, since they also remember the last ID they handed out
+            Value::Stream(_) => false,
+        }
+    }
+
+    pub fn as_string(&self) -> Result<&StringValue, WrongTypeError> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Result<&mut StringValue, WrongTypeError> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&List, WrongTypeError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut List, WrongTypeError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_hash(&self) -> Result<&Hash, WrongTypeError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Result<&mut Hash, WrongTypeError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_set(&self) -> Result<&Set, WrongTypeError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Result<&mut Set, WrongTypeError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_sorted_set(&self) -> Result<&SortedSet, WrongTypeError> {
+        match self {
+            Value::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_sorted_set_mut(&mut self) -> Result<&mut SortedSet, WrongTypeError> {
+        match self {
+            Value::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_stream(&self) -> Result<&Stream, WrongTypeError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Result<&mut Stream, WrongTypeError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WrongTypeError),
+        }
+    }
+}
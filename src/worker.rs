@@ -1,16 +1,34 @@
+use std::collections::VecDeque;
 use std::sync::mpsc::{channel, Sender, RecvTimeoutError};
 use std::thread;
 use std::time::Duration;
 
-use crate::resp::parser::RespSerialize;
-use crate::resp::types::RespBulkString;
-use crate::resp::commands::{RespCommand, RespCommandError};
-use crate::resp::{RESP_EMPTY_STRING, RESP_OK};
+use crate::resp::commands::{
+    get_argument_bytes,
+    CommandContext,
+    CommandFlag,
+    CommandHandler,
+    CommandResult,
+    RespCommand,
+    RespCommandError,
+};
+use crate::resp::RespProtocol;
 use crate::store::Database;
+use crate::worker::waiters::{BlockedClient, Waiters};
 
-pub struct WorkerMessage {
-    pub op: RespCommand,
-    pub reply: Sender<WorkerResponse>,
+mod waiters;
+
+pub enum WorkerMessage {
+    /// A command to run against the store, whose reply is sent back on `reply`
+    Command {
+        client_id: usize,
+        op: RespCommand,
+        protocol: RespProtocol,
+        reply: Sender<WorkerResponse>,
+    },
+    /// The client has disconnected, so it shouldn't be served anything that it
+    /// was blocked waiting for
+    Disconnected(usize),
 }
 
 pub type WorkerResponse = Result<Option<Vec<u8>>, RespCommandError>;
@@ -20,9 +38,18 @@ pub fn spawn_worker() -> Sender<WorkerMessage> {
 
     thread::spawn(move || {
         let mut db = Database::new();
+        let mut waiters = Waiters::new();
 
         loop {
-            let timeout = db.time_until_next_expiration();
+            let now = db.now_millis();
+
+            let next_deadline = waiters.next_deadline()
+                .map(| deadline | Duration::from_millis(deadline.saturating_sub(now)));
+
+            let timeout = match (db.time_until_next_expiration(), next_deadline) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
 
             let cmd = match timeout {
                 Some(dur) if dur > Duration::from_millis(0) =>
@@ -34,49 +61,306 @@ pub fn spawn_worker() -> Sender<WorkerMessage> {
             };
 
             match cmd {
-                Ok(WorkerMessage { op, reply }) => {
-                    let response = match op {
-                        RespCommand::Ping => {
-                            Some("+PONG\r\n".into())
-                        },
-                        RespCommand::Echo(e) => {
-                            let response = RespBulkString::new(e.value.as_bytes());
-                            
-                            Some(response.to_bytes())
-                        }
-                        RespCommand::Set(s) => {
-                            db.set(s.key.as_str(), &s.value, s.ttl);
-
-                            Some(RESP_OK.to_vec())
-                        }
-                        RespCommand::Get(g) => {
-                            if let Some(entry) = db.get(g.key.as_str()) {
-                                let response = RespBulkString::new(&entry.value);
-
-                                Some(response.to_bytes())
-                            } else {
-                                Some(RESP_EMPTY_STRING.to_vec())
-                            }
-                        }
-                    };
-
-                    if let Err(e) = reply.send(Ok(response)) {
-                        eprintln!("Unable to send response back to main thread");
-                        dbg!(e);
-                    }
+                Ok(WorkerMessage::Command { client_id, op, protocol, reply }) => {
+                    run_command(&mut db, &mut waiters, client_id, op, protocol, reply);
+                }
+                Ok(WorkerMessage::Disconnected(client_id)) => {
+                    waiters.remove(client_id);
                 }
                 Err(RecvTimeoutError::Timeout) => {
                     // to do: make the budget configurable
                     db.delete_expired_keys(1_000);
+
+                    for client in waiters.remove_timed_out(db.now_millis()) {
+                        send_response(&client.reply, Ok(Some(client.timeout_reply)));
+                    }
                 }
                 Err(_) => break
             };
-
-            thread::sleep(Duration::from_millis(5));
         }
 
         println!("Closing worker thread...");
     });
 
     worker_tx
-}
\ No newline at end of file
+}
+
+fn run_command(
+    db: &mut Database,
+    waiters: &mut Waiters,
+    client_id: usize,
+    op: RespCommand,
+    protocol: RespProtocol,
+    reply: Sender<WorkerResponse>,
+) {
+    // the keys are found before the command runs, since it takes ownership of
+    // its arguments
+    let written_keys = get_written_keys(waiters, &op);
+
+    // blocking commands keep a copy of their arguments, in case they have to
+    // be run again once they're woken up
+    let retry = op.spec.has_flag(CommandFlag::Blocking).then(|| op.clone());
+
+    let mut ctx = CommandContext::new(db, protocol);
+
+    let response = run_handler(&mut ctx, op);
+    let block = ctx.block.take();
+
+    match (block, retry, response) {
+        (Some(block), Some(mut op), Ok(timeout_reply)) => {
+            if let Some(args) = block.retry_args {
+                op.args = args;
+            }
+
+            waiters.add(BlockedClient {
+                client_id,
+                op,
+                protocol,
+                reply,
+                keys: block.keys,
+                value_type: block.value_type,
+                deadline: block.deadline,
+                timeout_reply: timeout_reply.to_bytes_for(protocol),
+            });
+        }
+        (_, _, response) => {
+            send_response(&reply, response.map(| element | Some(element.to_bytes_for(protocol))));
+
+            serve_blocked_clients(db, waiters, written_keys);
+        }
+    }
+}
+
+fn run_handler(ctx: &mut CommandContext, op: RespCommand) -> CommandResult {
+    let CommandHandler::Store(handler) = op.spec.handler else {
+        unreachable!("connection commands are handled by the connection that sent them")
+    };
+
+    handler(ctx, op.args)
+}
+
+/// Returns the keys that a write command was given which have clients waiting
+/// on them, since those are the only ones that could need to be served after
+fn get_written_keys(waiters: &Waiters, op: &RespCommand) -> VecDeque<Vec<u8>> {
+    if waiters.is_empty() || !op.spec.has_flag(CommandFlag::Write) {
+        return VecDeque::new();
+    }
+
+    let Ok(positions) = op.spec.get_key_positions(&op.args) else {
+        return VecDeque::new();
+    };
+
+    positions.into_iter()
+        .filter_map(| p | op.args.elements.get(p))
+        .filter_map(| element | get_argument_bytes(element).ok())
+        .filter(| key | waiters.is_waiting_on(key))
+        .collect()
+}
+
+/// Runs the commands of the clients that are blocked on the given keys again,
+/// in the order that they blocked, for as long as the keys hold something for
+/// them. Each command that's served can write to keys of its own (e.g. the
+/// destination of `BLMOVE`), so those are served in turn.
+fn serve_blocked_clients(db: &mut Database, waiters: &mut Waiters, mut ready_keys: VecDeque<Vec<u8>>) {
+    while let Some(key) = ready_keys.pop_front() {
+        for client_id in waiters.waiting_on(&key) {
+            // the key may have been deleted, in which case its clients carry
+            // on waiting
+            let Some(value_type) = db.get(&key).map(| entry | entry.value.type_name()) else {
+                break;
+            };
+
+            // clients waiting for another type are skipped, so e.g. a `BLPOP`
+            // doesn't hold up a `BZPOPMIN` on the same key
+            let Some(client) = waiters.get(client_id).filter(| c | c.value_type == value_type) else {
+                continue;
+            };
+
+            let op = client.op.clone();
+            let protocol = client.protocol;
+            let written_keys = get_written_keys(waiters, &op);
+
+            let mut ctx = CommandContext::new(db, protocol);
+
+            let response = run_handler(&mut ctx, op);
+
+            // the command can still have nothing to do (e.g. an `XREAD` for
+            // entries after the ones that were added), in which case the
+            // client carries on waiting until its original deadline
+            if ctx.block.is_some() {
+                continue;
+            }
+
+            let Some(client) = waiters.remove(client_id) else {
+                continue;
+            };
+
+            send_response(&client.reply, response.map(| element | Some(element.to_bytes_for(client.protocol))));
+
+            ready_keys.extend(written_keys);
+        }
+    }
+}
+
+fn send_response(reply: &Sender<WorkerResponse>, response: WorkerResponse) {
+    if let Err(e) = reply.send(response) {
+        eprintln!("Unable to send response back to main thread");
+        dbg!(e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Receiver;
+
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::resp::commands::RespCommandConstructor;
+    use crate::resp::types::RespArray;
+    use crate::resp::RespElement;
+
+    fn command(args: &[&str]) -> RespCommand {
+        let elements = args.iter().map(| a | RespElement::new_bulk_string(a.as_bytes())).collect();
+
+        RespCommand::from_array(RespArray::new(elements)).unwrap()
+    }
+
+    /// Runs a command for the client, and returns where its reply is sent
+    fn run(db: &mut Database, waiters: &mut Waiters, client_id: usize, args: &[&str]) -> Receiver<WorkerResponse> {
+        let (reply, receiver) = channel();
+
+        run_command(db, waiters, client_id, command(args), RespProtocol::default(), reply);
+
+        receiver
+    }
+
+    /// Returns the reply that has been sent to the client so far, if any
+    fn reply(receiver: &Receiver<WorkerResponse>) -> Option<String> {
+        receiver.try_recv().ok().map(| r | String::from_utf8(r.unwrap().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn a_client_blocked_on_several_keys_is_served_once() {
+        let (mut db, mut waiters) = (Database::new(), Waiters::new());
+
+        let blocked = run(&mut db, &mut waiters, 1, &["BLPOP", "a", "b", "0"]);
+        assert_eq!(reply(&blocked), None);
+
+        // both keys are written before the blocked client is looked at, as
+        // though a single command had pushed to both of them
+        run(&mut db, &mut Waiters::new(), 2, &["RPUSH", "a", "from-a"]);
+        run(&mut db, &mut Waiters::new(), 2, &["RPUSH", "b", "from-b"]);
+
+        serve_blocked_clients(&mut db, &mut waiters, VecDeque::from([b"a".to_vec(), b"b".to_vec()]));
+
+        assert_eq!(reply(&blocked).as_deref(), Some("*2\r\n$1\r\na\r\n$6\r\nfrom-a\r\n"));
+        assert_eq!(reply(&blocked), None);
+        assert!(waiters.is_empty());
+
+        assert!(!db.exists(b"a"));
+        assert_eq!(db.get_list(b"b").unwrap().map(| l | l.len()), Some(1));
+    }
+
+    #[test]
+    fn a_blocked_sorted_set_pop_is_run_again_once_the_key_is_ready() {
+        let (mut db, mut waiters) = (Database::new(), Waiters::new());
+
+        let first = run(&mut db, &mut waiters, 1, &["BZPOPMIN", "jobs", "0"]);
+        let second = run(&mut db, &mut waiters, 2, &["BZPOPMAX", "jobs", "0"]);
+        assert_eq!(reply(&first), None);
+
+        // the oldest waiter is served first, and the next one once there's
+        // something left for it
+        run(&mut db, &mut waiters, 3, &["ZADD", "jobs", "1", "a"]);
+        assert_eq!(reply(&first).as_deref(), Some("*3\r\n$4\r\njobs\r\n$1\r\na\r\n$1\r\n1\r\n"));
+        assert_eq!(reply(&second), None);
+
+        run(&mut db, &mut waiters, 3, &["ZADD", "jobs", "2", "b", "3", "c"]);
+        assert_eq!(reply(&second).as_deref(), Some("*3\r\n$4\r\njobs\r\n$1\r\nc\r\n$1\r\n3\r\n"));
+        assert!(waiters.is_empty());
+    }
+
+    #[test]
+    fn blocked_clients_wait_for_the_type_they_need() {
+        let (mut db, mut waiters) = (Database::new(), Waiters::new());
+
+        let zset = run(&mut db, &mut waiters, 1, &["BZPOPMIN", "key", "0"]);
+        let list = run(&mut db, &mut waiters, 2, &["BLPOP", "key", "0"]);
+
+        // a key of the wrong type doesn't wake anyone, and doesn't give the
+        // waiting clients a WRONGTYPE error either
+        run(&mut db, &mut waiters, 3, &["SET", "key", "value"]);
+        assert_eq!(reply(&zset), None);
+        assert_eq!(reply(&list), None);
+
+        run(&mut db, &mut waiters, 3, &["DEL", "key"]);
+        run(&mut db, &mut waiters, 3, &["RPUSH", "key", "item"]);
+        assert_eq!(reply(&zset), None);
+        assert_eq!(reply(&list).as_deref(), Some("*2\r\n$3\r\nkey\r\n$4\r\nitem\r\n"));
+
+        run(&mut db, &mut waiters, 3, &["ZADD", "key", "1", "member"]);
+        assert_eq!(reply(&zset).as_deref(), Some("*3\r\n$3\r\nkey\r\n$6\r\nmember\r\n$1\r\n1\r\n"));
+    }
+
+    #[test]
+    fn a_blocked_move_serves_the_clients_waiting_on_its_destination() {
+        let (mut db, mut waiters) = (Database::new(), Waiters::new());
+
+        let mover = run(&mut db, &mut waiters, 1, &["BLMOVE", "source", "middle", "LEFT", "RIGHT", "0"]);
+        let popper = run(&mut db, &mut waiters, 2, &["BLPOP", "middle", "0"]);
+
+        run(&mut db, &mut waiters, 3, &["LPUSH", "source", "item"]);
+
+        assert_eq!(reply(&mover).as_deref(), Some("$4\r\nitem\r\n"));
+        assert_eq!(reply(&popper).as_deref(), Some("*2\r\n$6\r\nmiddle\r\n$4\r\nitem\r\n"));
+        assert!(!db.exists(b"source"));
+        assert!(!db.exists(b"middle"));
+    }
+
+    #[test]
+    fn a_blocked_read_is_run_again_with_its_retry_arguments() {
+        let (mut db, mut waiters) = (Database::new(), Waiters::new());
+
+        run(&mut db, &mut waiters, 1, &["XADD", "events", "1-1", "field", "old"]);
+
+        // `$` is replaced with the last ID when the client blocks, so the
+        // entry that was already there isn't returned when it's woken up
+        let reader = run(&mut db, &mut waiters, 2, &["XREAD", "BLOCK", "0", "STREAMS", "events", "$"]);
+        assert_eq!(reply(&reader), None);
+
+        let retry_id = waiters.get(2).and_then(| c | c.op.args.elements.last()).map(| e | get_argument_bytes(e).unwrap());
+        assert_eq!(retry_id.as_deref(), Some(&b"1-1"[..]));
+
+        run(&mut db, &mut waiters, 1, &["XADD", "events", "2-1", "field", "new"]);
+
+        let served = reply(&reader).unwrap();
+
+        assert!(served.contains("2-1") && served.contains("new"), "{served}");
+        assert!(!served.contains("old"), "{served}");
+    }
+
+    #[test]
+    fn blocked_clients_time_out_with_their_timeout_reply() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = Database::with_clock(Box::new(clock.clone()));
+        let mut waiters = Waiters::new();
+
+        let blocked = run(&mut db, &mut waiters, 1, &["BZPOPMIN", "jobs", "0.25"]);
+        assert_eq!(waiters.next_deadline(), Some(1_000_250));
+
+        clock.advance(249);
+        assert!(waiters.remove_timed_out(db.now_millis()).is_empty());
+
+        clock.advance(1);
+
+        for client in waiters.remove_timed_out(db.now_millis()) {
+            send_response(&client.reply, Ok(Some(client.timeout_reply)));
+        }
+
+        assert_eq!(reply(&blocked).as_deref(), Some("*-1\r\n"));
+
+        // a write after the timeout has nobody left to serve
+        run(&mut db, &mut waiters, 2, &["ZADD", "jobs", "1", "a"]);
+        assert_eq!(reply(&blocked), None);
+    }
+}
@@ -0,0 +1,260 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::mpsc::Sender;
+
+use crate::resp::commands::RespCommand;
+use crate::resp::RespProtocol;
+use crate::worker::WorkerResponse;
+
+/// A client that's waiting on a blocking command such as `BLPOP`
+pub struct BlockedClient {
+    pub client_id: usize,
+    pub op: RespCommand,
+    pub protocol: RespProtocol,
+    pub reply: Sender<WorkerResponse>,
+    pub keys: Vec<Vec<u8>>,
+    /// The type that one of the keys has to hold for the command to be run again
+    pub value_type: &'static str,
+    /// The Unix time in milliseconds that the client gives up at, if any
+    pub deadline: Option<u64>,
+    /// The reply that's sent if the deadline passes, already serialized for
+    /// the client's protocol
+    pub timeout_reply: Vec<u8>,
+}
+
+/// The clients that are blocked, grouped by the keys they're waiting on.
+///
+/// Clients are identified by their connection ID, since the connection stops
+/// sending commands once it has one that's blocked. The clients waiting on a
+/// key are kept in the order they blocked, so that they're served first come,
+/// first served, the same as in Redis.
+#[derive(Default)]
+pub struct Waiters {
+    clients: HashMap<usize, BlockedClient>,
+    by_key: HashMap<Vec<u8>, VecDeque<usize>>,
+    /// The deadlines of the blocked clients, soonest first. Entries aren't
+    /// removed when a client is unblocked early, so they're checked against
+    /// the client's deadline when they're popped.
+    deadlines: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl Waiters {
+    pub fn new() -> Waiters {
+        Waiters::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    pub fn is_waiting_on(&self, key: &[u8]) -> bool {
+        self.by_key.contains_key(key)
+    }
+
+    pub fn add(&mut self, client: BlockedClient) {
+        for key in &client.keys {
+            let queue = self.by_key.entry(key.clone()).or_default();
+
+            // a key that's given more than once only needs to be waited on once
+            if !queue.contains(&client.client_id) {
+                queue.push_back(client.client_id);
+            }
+        }
+
+        if let Some(deadline) = client.deadline {
+            self.deadlines.push(Reverse((deadline, client.client_id)));
+        }
+
+        self.clients.insert(client.client_id, client);
+    }
+
+    /// Returns the IDs of the clients waiting on the key, in the order they
+    /// started waiting
+    pub fn waiting_on(&self, key: &[u8]) -> Vec<usize> {
+        self.by_key.get(key).map_or_else(Vec::new, | queue | queue.iter().copied().collect())
+    }
+
+    pub fn get(&self, client_id: usize) -> Option<&BlockedClient> {
+        self.clients.get(&client_id)
+    }
+
+    pub fn remove(&mut self, client_id: usize) -> Option<BlockedClient> {
+        let client = self.clients.remove(&client_id)?;
+
+        for key in &client.keys {
+            if let Some(queue) = self.by_key.get_mut(key) {
+                queue.retain(| id | *id != client_id);
+
+                if queue.is_empty() {
+                    self.by_key.remove(key);
+                }
+            }
+        }
+
+        Some(client)
+    }
+
+    /// The soonest that a blocked client will time out
+    pub fn next_deadline(&mut self) -> Option<u64> {
+        while let Some(Reverse((deadline, client_id))) = self.deadlines.peek() {
+            if self.clients.get(client_id).is_some_and(| c | c.deadline == Some(*deadline)) {
+                return Some(*deadline);
+            }
+
+            self.deadlines.pop();
+        }
+
+        None
+    }
+
+    /// Removes and returns the clients whose deadlines are at or before `now`
+    pub fn remove_timed_out(&mut self, now: u64) -> Vec<BlockedClient> {
+        let mut timed_out = Vec::new();
+
+        while let Some(deadline) = self.next_deadline() {
+            if deadline > now {
+                break;
+            }
+
+            let Some(Reverse((_, client_id))) = self.deadlines.pop() else {
+                break;
+            };
+
+            timed_out.extend(self.remove(client_id));
+        }
+
+        timed_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Receiver};
+
+    use super::*;
+    use crate::resp::commands::RespCommandConstructor;
+    use crate::resp::types::RespArray;
+    use crate::resp::RespElement;
+
+    fn blocked(client_id: usize, keys: &[&str], deadline: Option<u64>) -> (BlockedClient, Receiver<WorkerResponse>) {
+        let mut args = vec![RespElement::new_bulk_string(b"BLPOP")];
+
+        args.extend(keys.iter().map(| k | RespElement::new_bulk_string(k.as_bytes())));
+        args.push(RespElement::new_bulk_string(b"0"));
+
+        let (reply, receiver) = channel();
+
+        let client = BlockedClient {
+            client_id,
+            op: RespCommand::from_array(RespArray::new(args)).unwrap(),
+            protocol: RespProtocol::default(),
+            reply,
+            keys: keys.iter().map(| k | k.as_bytes().to_vec()).collect(),
+            value_type: "list",
+            deadline,
+            timeout_reply: b"*-1\r\n".to_vec(),
+        };
+
+        (client, receiver)
+    }
+
+    fn add(waiters: &mut Waiters, client_id: usize, keys: &[&str], deadline: Option<u64>) {
+        waiters.add(blocked(client_id, keys, deadline).0);
+    }
+
+    fn ids(clients: Vec<BlockedClient>) -> Vec<usize> {
+        clients.iter().map(| c | c.client_id).collect()
+    }
+
+    #[test]
+    fn clients_are_served_in_the_order_they_blocked() {
+        let mut waiters = Waiters::new();
+
+        add(&mut waiters, 3, &["key"], None);
+        add(&mut waiters, 1, &["key"], None);
+        add(&mut waiters, 2, &["other", "key"], None);
+
+        assert_eq!(waiters.waiting_on(b"key"), [3, 1, 2]);
+        assert_eq!(waiters.waiting_on(b"other"), [2]);
+        assert!(waiters.waiting_on(b"missing").is_empty());
+
+        waiters.remove(1);
+        add(&mut waiters, 1, &["key"], None);
+
+        assert_eq!(waiters.waiting_on(b"key"), [3, 2, 1]);
+    }
+
+    #[test]
+    fn a_key_given_twice_is_only_waited_on_once() {
+        let mut waiters = Waiters::new();
+
+        add(&mut waiters, 1, &["key", "key"], None);
+
+        assert_eq!(waiters.waiting_on(b"key"), [1]);
+
+        waiters.remove(1);
+        assert!(!waiters.is_waiting_on(b"key"));
+    }
+
+    #[test]
+    fn deadlines_expire_soonest_first() {
+        let mut waiters = Waiters::new();
+
+        add(&mut waiters, 1, &["key"], Some(1000));
+        add(&mut waiters, 2, &["key"], Some(500));
+        add(&mut waiters, 3, &["key"], None);
+
+        assert_eq!(waiters.next_deadline(), Some(500));
+        assert!(waiters.remove_timed_out(499).is_empty());
+
+        assert_eq!(ids(waiters.remove_timed_out(500)), [2]);
+        assert_eq!(waiters.next_deadline(), Some(1000));
+
+        assert_eq!(ids(waiters.remove_timed_out(u64::MAX)), [1]);
+        assert_eq!(waiters.next_deadline(), None);
+
+        // the client without a deadline waits for as long as it takes
+        assert_eq!(waiters.waiting_on(b"key"), [3]);
+    }
+
+    #[test]
+    fn deadlines_of_unblocked_clients_are_skipped() {
+        let mut waiters = Waiters::new();
+
+        add(&mut waiters, 1, &["key"], Some(500));
+        add(&mut waiters, 2, &["key"], Some(1000));
+
+        waiters.remove(1);
+
+        assert_eq!(waiters.next_deadline(), Some(1000));
+
+        // a client that blocks again with the same ID has a new deadline, and
+        // the old one doesn't time it out
+        add(&mut waiters, 1, &["key"], Some(2000));
+        waiters.remove(2);
+        add(&mut waiters, 2, &["key"], Some(3000));
+
+        assert!(waiters.remove_timed_out(1000).is_empty());
+        assert_eq!(ids(waiters.remove_timed_out(3000)), [1, 2]);
+        assert!(waiters.is_empty());
+    }
+
+    #[test]
+    fn disconnected_clients_are_removed_from_every_key() {
+        let mut waiters = Waiters::new();
+
+        add(&mut waiters, 1, &["a", "b", "c"], Some(1000));
+        add(&mut waiters, 2, &["b"], None);
+
+        let client = waiters.remove(1).unwrap();
+
+        assert_eq!(client.keys.len(), 3);
+        assert!(!waiters.is_waiting_on(b"a"));
+        assert!(!waiters.is_waiting_on(b"c"));
+        assert_eq!(waiters.waiting_on(b"b"), [2]);
+        assert!(waiters.get(1).is_none());
+        assert!(waiters.remove(1).is_none());
+
+        assert!(waiters.remove_timed_out(u64::MAX).is_empty());
+    }
+}